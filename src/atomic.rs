@@ -0,0 +1,92 @@
+//! Writing files under a temporary name and renaming them into place at the end.
+//!
+//! A program watching the output directory (a test runner, an upload job) would otherwise see
+//! `data.txt` appear and start reading it while it's still being written. Instead everything
+//! goes to `data.txt.tmp` first, and once the last byte is out it's renamed to `data.txt`.
+//! A rename within one directory is atomic, like rename() in C, so the file is either not
+//! there yet or all there.
+//!
+//! A run that crashed leaves its `.tmp` behind, and the next run with the same output name
+//! deletes it before starting - unless it's resuming, since then the `.tmp` is what it carries on.
+
+use std::fs;
+use std::io;
+
+use log::debug;
+
+use crate::error;
+
+/// Name a file is written under until it's complete: data.txt -> data.txt.tmp
+pub fn temp_name(filename: &str) -> String {
+    format!("{}.tmp", filename)
+}
+
+/// Deletes the .tmp an earlier run of `filename` left behind, if there is one
+pub(crate) fn remove_stale_temp(filename: &str) -> error::Result<()> {
+    let temp = temp_name(filename);
+    match fs::remove_file(&temp) {
+        Ok(()) => debug!("Removed {} left over from an earlier run", temp),
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        Err(_) => {},
+    }
+    Ok(())
+}
+
+/// Renames the finished `<filename>.tmp` to `filename`. Without `replace`, a file that turned up
+/// under that name while we were writing is left alone and this fails instead
+pub(crate) fn publish(filename: &str, replace: bool) -> error::Result<()> {
+    let temp = temp_name(filename);
+    if replace {
+        return Ok(fs::rename(&temp, filename)?);
+    }
+    // rename() would quietly replace it, but a hard link fails if the name is taken, and
+    // it's just as atomic. Filesystems without hard links (FAT) get the rename after all
+    match fs::hard_link(&temp, filename) {
+        Ok(()) => Ok(fs::remove_file(&temp)?),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(io::Error::new(
+            e.kind(),
+            format!("{} appeared while it was being written, so the new one was left as {}", filename, temp),
+        ).into()),
+        Err(_) => Ok(fs::rename(&temp, filename)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_only_appear_once_they_are_complete() {
+        let path = std::env::temp_dir().join(format!("ruststf_test_{}_atomic.txt", std::process::id()));
+        let name = path.to_string_lossy().into_owned();
+        fs::write(temp_name(&name), "old").unwrap();
+        remove_stale_temp(&name).unwrap();
+        assert!(fs::metadata(temp_name(&name)).is_err());
+
+        fs::write(temp_name(&name), "first").unwrap();
+        publish(&name, false).unwrap();
+        assert_eq!(fs::read_to_string(&name).unwrap(), "first");
+        // Something is there now, so only a replace goes through
+        fs::write(temp_name(&name), "second").unwrap();
+        assert!(publish(&name, false).is_err());
+        assert_eq!(fs::read_to_string(&name).unwrap(), "first");
+        publish(&name, true).unwrap();
+        assert_eq!(fs::read_to_string(&name).unwrap(), "second");
+        fs::remove_file(&name).unwrap();
+    }
+
+    #[test]
+    fn other_temp_files_are_left_alone() {
+        let dir = std::env::temp_dir().join(format!("ruststf_test_{}_unrelated", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        // Someone else's .tmp, and one that only looks like ours with a different name
+        for name in ["thesis.tmp", "data.csv.tmp", "data.txt.tmp"] {
+            fs::write(dir.join(name), "keep").unwrap();
+        }
+        remove_stale_temp(&dir.join("data.txt").to_string_lossy()).unwrap();
+        assert!(!dir.join("data.txt.tmp").exists());
+        assert!(dir.join("thesis.tmp").exists() && dir.join("data.csv.tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
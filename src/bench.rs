@@ -0,0 +1,242 @@
+//! Timing how fast values are generated and written, to help pick settings for big files,
+//! and estimating how big and slow a run will be before starting it.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::time::Instant; // A stopwatch, like clock_gettime(CLOCK_MONOTONIC) in C
+
+use crate::blob::{blob_bytes, writes_blob_files};
+use crate::checkpoint::{checkpoint_name, Checkpoints};
+use crate::error;
+use crate::generator::{make_rng, value_source, Value};
+use crate::mapped::MappedFile;
+use crate::stats::Collector;
+use crate::writer::{write_body, write_data, Blanks, IfExists, OutputFile};
+use crate::{invalid_input, Options};
+
+/// Values generated to estimate a full run from - enough to smooth out start-up costs,
+/// few enough to take well under a second
+pub const ESTIMATE_SAMPLE: u32 = 100_000;
+
+/// How long one size took - generating and writing are timed separately
+#[derive(Debug, Clone, Copy)]
+pub struct BenchResult {
+    pub count: u64, // Values, not rows, so CSV and matrix runs compare fairly
+    pub generate_secs: f64,
+    pub write_secs: f64,
+    pub bytes: u64, // Size of the file that got written
+}
+
+impl BenchResult {
+    pub fn generate_rate(&self) -> f64 {
+        rate(self.count as f64, self.generate_secs)
+    }
+
+    pub fn write_rate(&self) -> f64 {
+        rate(self.count as f64, self.write_secs)
+    }
+
+    /// Megabytes (1,000,000 bytes) written per second
+    pub fn write_mb_per_sec(&self) -> f64 {
+        rate(self.bytes as f64 / 1_000_000.0, self.write_secs)
+    }
+}
+
+// A tiny run can finish in well under a microsecond, so don't divide by zero
+fn rate(amount: f64, seconds: f64) -> f64 {
+    if seconds > 0.0 { amount / seconds } else { 0.0 }
+}
+
+/// Generates options.count rows into memory, then writes them to options.filename,
+/// timing each half. The file is deleted afterwards since only the timings matter
+/// Everything is held in memory between the two steps, so very large counts need a lot of RAM
+pub fn bench(options: &Options) -> error::Result<BenchResult> {
+    options.validate()?;
+    // Stats would add their own time to the write, and nobody wants them here
+    let options = Options { stats: false, append_stats: false, histogram: false, progress: false, ..options.clone() };
+
+    let started = Instant::now();
+    let rng = &mut make_rng(options.seed);
+    let generated: Vec<Value> = value_source(rng, &options)?.collect();
+    let generate_secs = started.elapsed().as_secs_f64();
+
+    let started = Instant::now();
+    let blanks = &mut Blanks::new(&options);
+    // Where the file can't be mapped this times the buffer instead, the same as write_data would
+    let mapped = if options.mmap {
+        MappedFile::create(&options.filename, &options)?
+    } else {
+        None
+    };
+    if let Some(mut file) = mapped {
+        let mut rest = file.bytes();
+        write_body(&mut rest, generated.into_iter(), options.count, options.seed, &options, &mut Collector::Off, blanks, &mut Checkpoints::off())?;
+        file.finish()?;
+    } else {
+        let file = OutputFile::new(Box::new(File::create(&options.filename)?), &options);
+        let mut writer = BufWriter::with_capacity(options.buffer_size, file);
+        write_body(&mut writer, generated.into_iter(), options.count, options.seed, &options, &mut Collector::Off, blanks, &mut Checkpoints::off())?;
+        writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+    }
+    let write_secs = started.elapsed().as_secs_f64();
+
+    let bytes = fs::metadata(&options.filename)?.len();
+    fs::remove_file(&options.filename)?;
+    Ok(BenchResult {
+        count: options.count as u64 * options.values_per_row() as u64,
+        generate_secs,
+        write_secs,
+        bytes,
+    })
+}
+
+/// What a full run should come to, scaled up from a small sample of it
+#[derive(Debug, Clone, Copy)]
+pub struct Estimate {
+    pub count: u64,  // Values in the full run
+    pub sample: u64, // Values actually generated to make the guess
+    pub bytes: u64,  // Expected size of the output
+    pub secs: f64,   // Expected time to generate and write it
+}
+
+impl Estimate {
+    pub fn bytes_per_value(&self) -> f64 {
+        rate(self.bytes as f64, self.count as f64)
+    }
+}
+
+/// Writes the first few rows of the run to a scratch file and scales the size and time up to the full count.
+/// The guess assumes every value costs the same, so sorting (which slows down as files grow)
+/// will take a bit longer than this says, and a slower disk than the temp directory's will too
+pub fn estimate(options: &Options) -> error::Result<Estimate> {
+    options.validate()?;
+    let values_per_row = options.values_per_row().max(1);
+    let rows = (ESTIMATE_SAMPLE / values_per_row).clamp(1, options.count.max(1));
+    let scratch = env::temp_dir().join(format!("ruststf_estimate_{}.tmp", std::process::id()));
+    let sample = Options {
+        count: rows,
+        // The start of a permutation is that many different numbers from the whole range,
+        // which is exactly what unique values are
+        unique: options.unique || options.permutation,
+        permutation: false,
+        // Only the writing itself is being measured, so nothing else goes along with it
+        stats: false,
+        append_stats: false,
+        histogram: false,
+        progress: false,
+        rate: None, // A sample paced like the real run would take just as long
+        if_exists: IfExists::Overwrite,
+        resume: false,
+        space_check: false,
+        shards: 1,
+        checksum: false,
+        filename: scratch.to_string_lossy().into_owned(),
+        ..options.clone()
+    };
+
+    let started = Instant::now();
+    write_data(&sample)?;
+    let secs = started.elapsed().as_secs_f64();
+    // Raw blobs come out as a directory of files, which all count
+    let bytes = if writes_blob_files(&sample) {
+        let bytes = blob_bytes(&sample.filename)?;
+        fs::remove_dir_all(&sample.filename)?;
+        bytes
+    } else {
+        let bytes = fs::metadata(&sample.filename)?.len();
+        fs::remove_file(&sample.filename)?;
+        bytes
+    };
+    let _ = fs::remove_file(checkpoint_name(&sample.filename));
+
+    let sample_values = rows as u64 * values_per_row as u64;
+    let count = options.count as u64 * values_per_row as u64;
+    let scale = count as f64 / sample_values as f64;
+    Ok(Estimate { count, sample: sample_values, bytes: (bytes as f64 * scale) as u64, secs: secs * scale })
+}
+
+/// How close count_for_size has to get: within 1% of the size asked for
+pub const SIZE_TOLERANCE: f64 = 0.01;
+
+// Tries at a file small enough to write whole - each one measures the last count for real
+const SIZE_ATTEMPTS: usize = 5;
+
+/// The number of rows that makes options' file come out at about `target` bytes
+/// Text values aren't all the same length, so this can't just divide: it writes a sample,
+/// scales the count by how far off that came out, and for files no bigger than the sample
+/// (where a header is a real part of the size) measures the new count and corrects it again
+pub fn count_for_size(options: &Options, target: u64) -> error::Result<u32> {
+    let values_per_row = options.values_per_row().max(1);
+    let mut count = (ESTIMATE_SAMPLE / values_per_row).max(1);
+    for _ in 0..SIZE_ATTEMPTS {
+        let estimate = estimate(&Options { count, ..options.clone() })?;
+        let off = estimate.bytes as f64 / target as f64;
+        if (off - 1.0).abs() <= SIZE_TOLERANCE {
+            break;
+        }
+        // Even one row is too much, so no count can do it
+        if count == 1 && off > 1.0 {
+            return Err(invalid_input(format!(
+                "The smallest file these settings make is {} bytes, more than --target-size allows", estimate.bytes
+            )));
+        }
+        let scaled = (count as f64 / off).round().max(1.0);
+        if scaled > u32::MAX as f64 {
+            return Err(invalid_input(format!("A file that big would need more than {} rows", u32::MAX)));
+        }
+        count = scaled as u32;
+        // Past the sample, the next estimate would only scale the same sample up again
+        if count as u64 * values_per_row as u64 > estimate.sample {
+            break;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::tests::options;
+    use crate::{DataType, Distribution};
+
+    #[test]
+    fn estimates_scale_with_the_count() {
+        let mut options = options(DataType::Integer, 0.0, 999.0, Distribution::Uniform);
+        options.seed = Some(3);
+        options.count = ESTIMATE_SAMPLE;
+        options.filename = env::temp_dir().join(format!("ruststf_test_{}_estimate.txt", std::process::id()))
+            .to_string_lossy().into_owned();
+        // When the whole run fits in the sample, the estimate is the real thing
+        let small = estimate(&options).unwrap();
+        write_data(&options).unwrap();
+        assert_eq!(small.bytes, fs::metadata(&options.filename).unwrap().len());
+        assert_eq!(small.sample, small.count);
+        fs::remove_file(&options.filename).unwrap();
+
+        options.count = ESTIMATE_SAMPLE * 50;
+        let big = estimate(&options).unwrap();
+        assert_eq!(big.sample, ESTIMATE_SAMPLE as u64);
+        assert_eq!(big.count, 50 * ESTIMATE_SAMPLE as u64);
+        assert!((big.bytes_per_value() - small.bytes_per_value()).abs() < 0.01);
+        assert!(!std::path::Path::new(&options.filename).exists());
+    }
+
+    #[test]
+    fn target_sizes_come_out_close() {
+        let mut options = options(DataType::Double, -1000.0, 1000.0, Distribution::Uniform);
+        options.seed = Some(5);
+        options.filename = env::temp_dir().join(format!("ruststf_test_{}_target.txt", std::process::id()))
+            .to_string_lossy().into_owned();
+        // Bigger than the sample, so scaled up from it, and smaller, so measured for real
+        for target in [3_000_000, 20_000] {
+            options.count = count_for_size(&options, target).unwrap();
+            write_data(&options).unwrap();
+            let bytes = fs::metadata(&options.filename).unwrap().len();
+            assert!((bytes as f64 / target as f64 - 1.0).abs() <= SIZE_TOLERANCE, "{} for {}", bytes, target);
+            fs::remove_file(&options.filename).unwrap();
+        }
+        // A header on its own is bigger than this
+        assert!(count_for_size(&options, 5).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! The header at the start of binary files, and the byte order of the numbers after it.
+//!
+//! A binary file starts with
+//!
+//! ```text
+//! 0x89 'R' 'S' 'F'   magic number, so readers can tell it's one of ours
+//! 1                  format version
+//! 0 or 1             byte order of the numbers: 0 = little-endian, 1 = big-endian
+//! tag                data type, see DataType::binary_tag
+//! count              4-byte value count, in the file's byte order
+//! ```
+//!
+//! followed by the raw values. The 0x89 up front is the same trick PNG uses: it isn't valid
+//! ASCII or UTF-8, so no text file can start the same way. Files from before the header had
+//! a magic number (just the tag and a little-endian count) are still read.
+
+use std::io::Write;
+
+use crate::error;
+use crate::generator::DataType;
+use crate::invalid_data;
+
+/// First four bytes of every binary file
+pub const BINARY_MAGIC: [u8; 4] = [0x89, b'R', b'S', b'F'];
+
+/// Version written into new files - bumped whenever the layout changes
+pub const BINARY_VERSION: u8 = 1;
+
+/// Magic number + version + byte order + type tag + 4-byte count
+pub const BINARY_HEADER_LEN: u64 = 11;
+
+// Files from before the magic number: 1 byte type tag + 4 byte little-endian count
+const OLD_HEADER_LEN: u64 = 5;
+
+/// Which end of a number goes first in binary files
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Endianness {
+    #[default]
+    Little, // Least significant byte first, like x86 and ARM store them in memory
+    Big,    // Most significant byte first, like network protocols and older Motorola/PowerPC machines
+}
+
+impl Endianness {
+    pub fn name(self) -> &'static str {
+        match self {
+            Endianness::Little => "little",
+            Endianness::Big => "big",
+        }
+    }
+
+    /// Turns little-endian bytes into this byte order, or back again - swapping the bytes
+    /// round works both ways, like htonl() and ntohl() being the same thing in C
+    pub fn arrange<const N: usize>(self, mut bytes: [u8; N]) -> [u8; N] {
+        if self == Endianness::Big {
+            bytes.reverse();
+        }
+        bytes
+    }
+
+    /// One value's bytes from a file in this byte order, as a little-endian array ready for from_le_bytes
+    pub fn little<const N: usize>(self, chunk: &[u8]) -> [u8; N] {
+        // The callers slice exactly N bytes off, so try_into can't fail
+        self.arrange(chunk.try_into().unwrap())
+    }
+}
+
+/// What the header of a binary file says
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BinaryHeader {
+    pub version: u8, // 0 for files from before the header had a version
+    pub endianness: Endianness,
+    pub data_type: DataType,
+    pub count: u32,
+}
+
+impl BinaryHeader {
+    /// Bytes the header takes up, so the values start right after it
+    pub fn size(&self) -> u64 {
+        if self.version == 0 { OLD_HEADER_LEN } else { BINARY_HEADER_LEN }
+    }
+}
+
+/// Whether a file looks like one of our binary files, judging by its first bytes
+pub fn is_binary(bytes: &[u8]) -> bool {
+    // Old files start with a small tag byte, which never shows up at the start of a text file
+    bytes.starts_with(&BINARY_MAGIC) || bytes.first().and_then(|&tag| DataType::from_binary_tag(tag)).is_some()
+}
+
+/// Writes the header for `count` values of a type that has a binary tag
+pub fn write_header(writer: &mut impl Write, tag: u8, count: u32, endianness: Endianness) -> error::Result<()> {
+    writer.write_all(&BINARY_MAGIC)?;
+    writer.write_all(&[BINARY_VERSION, endianness as u8, tag])?;
+    Ok(writer.write_all(&endianness.arrange(count.to_le_bytes()))?)
+}
+
+/// Reads and checks the header at the start of a binary file
+pub fn read_header(bytes: &[u8]) -> error::Result<BinaryHeader> {
+    let too_short = || invalid_data("File is too short to hold the binary header");
+    if !bytes.starts_with(&BINARY_MAGIC) {
+        let header = bytes.get(..OLD_HEADER_LEN as usize).ok_or_else(too_short)?;
+        let data_type = DataType::from_binary_tag(header[0])
+            .ok_or_else(|| invalid_data("Not a binary data file (no magic number)"))?;
+        let count = u32::from_le_bytes(header[1..5].try_into().unwrap());
+        return Ok(BinaryHeader { version: 0, endianness: Endianness::Little, data_type, count });
+    }
+    let header = bytes.get(..BINARY_HEADER_LEN as usize).ok_or_else(too_short)?;
+    let version = header[4];
+    if version != BINARY_VERSION {
+        return Err(invalid_data(format!(
+            "Binary file version {} isn't supported (this program reads version {})", version, BINARY_VERSION
+        )));
+    }
+    let endianness = match header[5] {
+        0 => Endianness::Little,
+        1 => Endianness::Big,
+        byte => return Err(invalid_data(format!("Unknown byte order {} in the binary header", byte))),
+    };
+    let data_type = DataType::from_binary_tag(header[6])
+        .ok_or_else(|| invalid_data(format!("Unknown type tag {}", header[6])))?;
+    let count = u32::from_le_bytes(endianness.little(&header[7..11]));
+    Ok(BinaryHeader { version, endianness, data_type, count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_round_trip_in_both_byte_orders() {
+        for endianness in [Endianness::Little, Endianness::Big] {
+            let mut bytes = Vec::new();
+            write_header(&mut bytes, DataType::Double.binary_tag().unwrap(), 258, endianness).unwrap();
+            assert_eq!(bytes.len() as u64, BINARY_HEADER_LEN);
+            let header = read_header(&bytes).unwrap();
+            assert_eq!(header, BinaryHeader { version: 1, endianness, data_type: DataType::Double, count: 258 });
+        }
+        let mut big = Vec::new();
+        write_header(&mut big, 0, 258, Endianness::Big).unwrap();
+        assert_eq!(big[7..], [0, 0, 1, 2]);
+        assert_eq!(Endianness::Big.little::<2>(&[1, 2]), [2, 1]);
+
+        // An old file: tag 0 (int) and a little-endian count, with no magic number
+        let old = read_header(&[0, 3, 0, 0, 0]).unwrap();
+        assert_eq!((old.version, old.data_type, old.count, old.size()), (0, DataType::Integer, 3, 5));
+        let mut newer = big.clone();
+        newer[4] = 2;
+        assert!(read_header(&newer).is_err());
+        assert!(read_header(&big[..8]).is_err());
+    }
+}
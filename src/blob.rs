@@ -0,0 +1,197 @@
+//! Random bytes for the bytes type: blobs of any size, for testing binary file handling and hashing.
+//!
+//! Each value is a run of random bytes, like a uint8_t buffer filled from /dev/urandom in C,
+//! and the range is how many bytes long each one is (the same way it's a string's length).
+//! In text, CSV and JSON a blob is written as lowercase hex, two digits a byte, so every one
+//! fits on a line. Binary output writes the bytes themselves instead - one file per blob,
+//! since there's no way to tell where one raw blob ends and the next begins. The output name
+//! is then a directory, filled with 1.bin, 2.bin... (padded with zeros so they sort by name).
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::atomic::temp_name;
+use crate::error;
+use crate::generator::{make_rng, value_source, Value};
+use crate::interrupt::{check_interrupt, Writing};
+use crate::stats::{Collector, Stats};
+use crate::throttle::Throttle;
+use crate::writer::{IfExists, Progress};
+use crate::{invalid_input, unsupported_format, Options, OutputFormat};
+
+/// Biggest blob we'll generate - 16 MB each, which is already 32 MB of hex on one line
+pub const MAX_BLOB_SIZE: f64 = 16_777_216.0;
+
+/// Between min and max random bytes (both ends included)
+pub fn random_blob(rng: &mut StdRng, min: f64, max: f64) -> Vec<u8> {
+    let mut blob = vec![0u8; rng.gen_range(min as usize..=max as usize)];
+    // fill() asks for the whole buffer at once, rather than a call per byte
+    rng.fill(&mut blob[..]);
+    blob
+}
+
+/// Two lowercase hex digits per byte, like printing each one with %02x in C
+pub fn format_hex(blob: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut text = String::with_capacity(blob.len() * 2);
+    for &byte in blob {
+        text.push(DIGITS[(byte >> 4) as usize] as char);
+        text.push(DIGITS[(byte & 0xf) as usize] as char);
+    }
+    text
+}
+
+/// Reads hex back into bytes (either case), or None if it isn't an even number of hex digits
+pub fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim();
+    if text.is_empty() || !text.len().is_multiple_of(2) || !text.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    // Every character is a hex digit, so slicing two at a time can't split one in half
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok()).collect()
+}
+
+/// Whether this run writes each blob to a file of its own
+pub fn writes_blob_files(options: &Options) -> bool {
+    options.data_type.is_bytes() && options.schema.is_empty() && options.format == OutputFormat::Binary
+}
+
+/// A directory of blobs is nothing like one file, so the settings that work on one are refused
+pub(crate) fn validate_blob_files(options: &Options) -> error::Result<()> {
+    if options.writes_to_stdout() || options.socket().is_some() || options.writes_to_fifo() {
+        return Err(unsupported_format("Raw blobs go into a directory, one file each, so they can't be sent anywhere else"));
+    }
+    if options.columns != 1 || options.shards > 1 || options.mmap || options.compress || options.framed {
+        return Err(unsupported_format("Raw blobs are written one file each, so columns, shards, --mmap, gzip and framing don't apply"));
+    }
+    if options.if_exists == IfExists::Append || options.resume || options.checksum || options.append_stats {
+        return Err(invalid_input("A directory of blobs can't be appended to, resumed, checksummed or given statistics"));
+    }
+    if options.missing != 0.0 {
+        return Err(invalid_input("A missing blob would just be a file that isn't there, so --missing doesn't apply"));
+    }
+    Ok(())
+}
+
+/// Name of one blob in the directory: blobs, 7 of 1000 -> blobs/0007.bin
+pub fn blob_name(directory: &str, number: u32, count: u32) -> String {
+    let digits = count.to_string().len();
+    Path::new(directory).join(format!("{:0width$}.bin", number, width = digits)).to_string_lossy().into_owned()
+}
+
+/// Bytes in every blob in a directory of them, for estimates
+pub fn blob_bytes(directory: &str) -> io::Result<u64> {
+    fs::read_dir(directory)?.map(|entry| Ok(entry?.metadata()?.len())).sum()
+}
+
+/// Writes every blob to a file of its own, in a directory named after the output
+/// The directory is filled under a .tmp name and renamed at the end, like a single file is
+pub(crate) fn write_blob_files(options: &Options) -> error::Result<Option<Stats>> {
+    let directory = &options.filename;
+    if fs::metadata(directory).is_ok() {
+        clear_old_blobs(directory, options.if_exists)?;
+    }
+    let temp = temp_name(directory);
+    if fs::metadata(&temp).is_ok() {
+        fs::remove_dir_all(&temp)?;
+    }
+    fs::create_dir_all(&temp)?;
+    let _writing = Writing::start();
+    let written = write_blobs(&temp, options);
+    if written.is_err() {
+        // Stopped or failed part way, so nothing half-made is left behind
+        let _ = fs::remove_dir_all(&temp);
+        return written;
+    }
+    fs::rename(&temp, directory)?;
+    written
+}
+
+fn write_blobs(temp: &str, options: &Options) -> error::Result<Option<Stats>> {
+    let rng = &mut make_rng(options.seed);
+    let values = Throttle::new(value_source(rng, options)?, options.rate);
+    let source = Progress::new(values, options.count as u64, options.progress);
+    let mut collector = Collector::new(options);
+    for (value, number) in source.zip(1..) {
+        check_interrupt()?;
+        // validate() only lets the bytes type through, so everything here is a blob
+        let Value::Bytes(blob) = value else { unreachable!("only blobs are written as files") };
+        File::create(blob_name(temp, number, options.count))?.write_all(&blob)?;
+        collector.push(blob.len() as f64);
+    }
+    Ok(collector.stats())
+}
+
+// An earlier directory of blobs is only replaced when that's been asked for, and only if all
+// it holds is .bin files - anything else in there belongs to somebody and stays put
+fn clear_old_blobs(directory: &str, if_exists: IfExists) -> error::Result<()> {
+    if if_exists != IfExists::Overwrite {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists (choose overwrite to replace the blobs in it)", directory),
+        ).into());
+    }
+    let only_blobs = fs::read_dir(directory)?
+        .collect::<io::Result<Vec<_>>>()?
+        .iter()
+        .all(|entry| entry.path().extension().is_some_and(|extension| extension == "bin"));
+    if !only_blobs {
+        return Err(invalid_input(format!("{} holds more than .bin files, so it wasn't replaced", directory)));
+    }
+    Ok(fs::remove_dir_all(directory)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::tests::options;
+    use crate::{DataType, Distribution};
+
+    #[test]
+    fn hex_reads_back_as_the_same_bytes() {
+        let mut rng = make_rng(Some(5));
+        for _ in 0..100 {
+            let blob = random_blob(&mut rng, 1.0, 64.0);
+            assert!((1..=64).contains(&blob.len()));
+            let text = format_hex(&blob);
+            assert_eq!(text.len(), blob.len() * 2);
+            assert_eq!(parse_hex(&text), Some(blob.clone()));
+            assert_eq!(parse_hex(&text.to_uppercase()), Some(blob));
+        }
+        assert_eq!(format_hex(&[0, 0x0f, 0xa0, 0xff]), "000fa0ff");
+        for bad in ["", "abc", "0g", "12 34"] {
+            assert_eq!(parse_hex(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn binary_output_is_a_file_per_blob() {
+        let directory = std::env::temp_dir().join(format!("ruststf_test_{}_blobs", std::process::id()));
+        let directory = directory.to_string_lossy().into_owned();
+        let mut options = options(DataType::Bytes, 10.0, 20.0, Distribution::Uniform);
+        (options.count, options.format, options.filename) = (12, OutputFormat::Binary, directory.clone());
+        options.if_exists = IfExists::Abort;
+        crate::write_data(&options).unwrap();
+        let mut names: Vec<String> = fs::read_dir(&directory).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names.first().map(String::as_str), Some("01.bin"));
+        assert_eq!(names.len(), 12);
+        for name in &names {
+            let size = fs::metadata(Path::new(&directory).join(name)).unwrap().len();
+            assert!((10..=20).contains(&size));
+        }
+        // Without overwrite the directory is left alone, and with it only .bin files get replaced
+        assert!(crate::write_data(&options).is_err());
+        options.if_exists = IfExists::Overwrite;
+        options.count = 3;
+        crate::write_data(&options).unwrap();
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 3);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
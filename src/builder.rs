@@ -0,0 +1,213 @@
+//! A chainable way to set up a run from code, for programs that use the crate as a library.
+//!
+//! ```no_run
+//! use ruststf::{DataType, Generator};
+//!
+//! let stats = Generator::new()
+//!     .data_type(DataType::Float)
+//!     .count(1_000)
+//!     .range(-5.0..5.0)
+//!     .seed(42)
+//!     .stats(true)
+//!     .write_to("floats.txt")?;
+//! # Ok::<(), ruststf::GeneratorError>(())
+//! ```
+//!
+//! Each method takes the builder by value and hands it back, so the calls chain without any
+//! `mut` variables. Nothing is checked until the end: build(), write_to(), write_into() and
+//! values() all run Options::validate first and hand back a GeneratorError if something's off.
+
+use std::io::Write;
+use std::ops::Range;
+use std::path::Path;
+
+use crate::error::GeneratorError;
+use crate::generator::{make_rng, value_source, DataType, Distribution, SortOrder, Value};
+use crate::schema::Field;
+use crate::stats::Stats;
+use crate::writer::{write_data, write_to, OutputFormat};
+use crate::Options;
+
+/// Builds up the Options for a run one setting at a time
+/// Anything without a method of its own can still be reached through configure()
+#[derive(Debug, Clone)]
+pub struct Generator {
+    options: Options,
+    range: Option<(f64, f64)>, // None until range() is called, so the type's default range is used
+}
+
+// Default lets Generator::default() and ..Default::default() work too
+impl Default for Generator {
+    fn default() -> Self {
+        Generator::new()
+    }
+}
+
+impl Generator {
+    /// 100 integers in the type's default range, as text, with a fresh seed every run
+    pub fn new() -> Generator {
+        Generator {
+            options: Options::default(),
+            range: None,
+        }
+    }
+
+    pub fn data_type(mut self, data_type: DataType) -> Self {
+        self.options.data_type = data_type;
+        self
+    }
+
+    /// Rows to generate - values for plain files, records with a schema
+    pub fn count(mut self, count: u32) -> Self {
+        self.options.count = count;
+        self
+    }
+
+    /// Smallest and largest value, like --min and --max - the end is included, so
+    /// range(1.0..6.0) on integers rolls a die (for strings it's the length, for bytes the size)
+    pub fn range(mut self, range: Range<f64>) -> Self {
+        self.range = Some((range.start, range.end));
+        self
+    }
+
+    pub fn distribution(mut self, distribution: Distribution) -> Self {
+        self.options.distribution = distribution;
+        self
+    }
+
+    pub fn order(mut self, order: SortOrder) -> Self {
+        self.options.order = order;
+        self
+    }
+
+    pub fn unique(mut self, unique: bool) -> Self {
+        self.options.unique = unique;
+        self
+    }
+
+    /// Every number from min to max once, shuffled - set the count to the range's size as well
+    pub fn permutation(mut self, permutation: bool) -> Self {
+        self.options.permutation = permutation;
+        self
+    }
+
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.options.format = format;
+        self
+    }
+
+    /// Values per row for CSV, matrix, Parquet, SQLite and fixed-width files
+    pub fn columns(mut self, columns: u32) -> Self {
+        self.options.columns = columns;
+        self
+    }
+
+    /// Records instead of single values, one field per column
+    pub fn schema(mut self, fields: Vec<Field>) -> Self {
+        self.options.schema = fields;
+        self
+    }
+
+    /// The same seed gives the same file every time
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.options.seed = Some(seed);
+        self
+    }
+
+    /// Digits after the point for floats and doubles
+    pub fn precision(mut self, digits: usize) -> Self {
+        self.options.precision = Some(digits);
+        self
+    }
+
+    /// Let values out at this many a second, like a live feed, instead of as fast as possible
+    pub fn rate(mut self, per_second: f64) -> Self {
+        self.options.rate = Some(per_second);
+        self
+    }
+
+    /// Generate on this many threads, 0 for one per core
+    pub fn threads(mut self, threads: u32) -> Self {
+        self.options.threads = threads;
+        self
+    }
+
+    /// Work out min, max, mean, median and standard deviation, for write_to() to hand back
+    pub fn stats(mut self, stats: bool) -> Self {
+        self.options.stats = stats;
+        self
+    }
+
+    /// gzip the output
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.options.compress = compress;
+        self
+    }
+
+    /// Send the output in length-prefixed frames with a CRC each (see framing.rs)
+    pub fn framed(mut self, framed: bool) -> Self {
+        self.options.framed = framed;
+        self
+    }
+
+    /// Changes anything else on the Options directly, e.g.
+    /// `.configure(|options| options.missing = 5.0)`
+    pub fn configure(mut self, change: impl FnOnce(&mut Options)) -> Self {
+        change(&mut self.options);
+        self
+    }
+
+    /// The finished Options, checked the same way write_data checks them
+    pub fn build(&self) -> Result<Options, GeneratorError> {
+        let mut options = self.options.clone();
+        // Without range() the range follows whatever type was picked in the end
+        (options.min, options.max) = self.range.unwrap_or_else(|| options.data_type.default_range());
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Writes the file, handing back the statistics if stats(true) asked for them
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<Option<Stats>, GeneratorError> {
+        let mut options = self.build()?;
+        options.filename = path.as_ref().to_string_lossy().into_owned();
+        write_data(&options)
+    }
+
+    /// Writes the same thing into any writer instead of a file - a socket, a pipe or a Vec<u8>
+    pub fn write_into(&self, writer: impl Write + Send + 'static) -> Result<Option<Stats>, GeneratorError> {
+        write_to(writer, &self.build()?)
+    }
+
+    /// The values themselves, without writing them anywhere - every field of every record
+    /// in turn when there's a schema
+    pub fn values(&self) -> Result<Vec<Value>, GeneratorError> {
+        let options = self.build()?;
+        let mut rng = make_rng(options.seed);
+        let values = value_source(&mut rng, &options)?.collect();
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_chains_and_checks() {
+        let generator = Generator::new().data_type(DataType::Float).count(1_000).range(-5.0..5.0).seed(42);
+        let values = generator.values().unwrap();
+        assert_eq!(values.len(), 1_000);
+        assert!(values.iter().all(|value| matches!(value, Value::Float(num) if (-5.0..=5.0).contains(num))));
+        assert_eq!(generator.values().unwrap(), values);
+
+        // Without range() the type picked last decides it
+        let options = Generator::new().data_type(DataType::Short).build().unwrap();
+        assert_eq!((options.min, options.max), DataType::Short.default_range());
+
+        let backwards = Generator::new().range(10.0..1.0).write_to("never_written.txt");
+        assert!(matches!(backwards, Err(GeneratorError::RangeError(_))));
+        assert!(!Path::new("never_written.txt").exists());
+        let missing_dir = Generator::new().write_to("/no/such/dir/data.txt");
+        assert!(matches!(missing_dir, Err(GeneratorError::Io(_))));
+    }
+}
@@ -0,0 +1,300 @@
+//! Checkpoints for long runs, so an interrupted file can be finished instead of started over.
+//!
+//! Every CHECKPOINT_EVERY rows the buffer is flushed and `<filename>.checkpoint` records how
+//! many rows are safely in the file and how many bytes they take up. The file being written is
+//! still `<filename>.tmp` at that point (see atomic.rs), so that's the one resuming picks up. It cuts off
+//! anything written after that (a half-written row, say) and carries on from there. A seeded
+//! run fast-forwards its generator past the rows already written, so the finished file is
+//! the same as one that was never interrupted. A run without --seed was given a random one, which
+//! the checkpoint keeps so the command line can use it again when resuming; without any seed
+//! (from the library) the rest is just new random values.
+
+use std::fs;
+use std::io::{self, Write};
+
+use crate::atomic::temp_name;
+use crate::error;
+use crate::generator::SortOrder;
+use crate::interrupt::{check_interrupt, OnInterrupt};
+use crate::{invalid_data, invalid_input, IfExists, Options, OutputFormat, DEFAULT_BUFFER_SIZE};
+
+/// Rows between checkpoints - flushing this rarely costs nothing next to writing the rows
+pub const CHECKPOINT_EVERY: u32 = 1_000_000;
+
+/// How far an interrupted run got
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub rows: u32,        // Rows (values, for one column) completely written
+    pub bytes: u64,       // Length of the file at that point
+    pub settings: String, // The run's options, so resuming with different ones can be refused
+    pub seed: Option<u64>, // The run's seed, so a resume can pick up the random one it was given
+}
+
+/// Name of the checkpoint file for a data file: data.txt -> data.txt.checkpoint
+pub fn checkpoint_name(filename: &str) -> String {
+    format!("{}.checkpoint", filename)
+}
+
+/// Why a run can't be checkpointed, or None if it can
+/// Only files that are written front to back in one pass can be picked up part way through
+pub fn resume_blocker(options: &Options) -> Option<&'static str> {
+    if options.writes_to_stdout() {
+        Some("output sent to stdout")
+    } else if options.compress {
+        Some("a gzipped file")
+    } else if options.framed {
+        Some("a framed file")
+    } else if options.shards > 1 {
+        Some("a sharded dataset")
+    } else if options.graph.is_some() || !options.schema.is_empty() {
+        Some("a graph or records")
+    } else if !matches!(options.format, OutputFormat::Text | OutputFormat::Csv | OutputFormat::Matrix | OutputFormat::Binary) {
+        Some("JSON or source code, which have to be closed off at the end")
+    } else if options.unique || options.pool.is_some() || !matches!(options.order, SortOrder::Random) {
+        // These generate every value before writing the first, so there's no halfway point
+        Some("sorted, unique or sampled values")
+    } else if options.stats || options.append_stats {
+        Some("statistics, which need every value")
+    } else if options.if_exists == IfExists::Append {
+        Some("appending")
+    } else if !options.line_layout.is_plain() {
+        Some("a custom delimiter, prefix or suffix")
+    } else {
+        None
+    }
+}
+
+/// The settings line a checkpoint is matched against
+/// Options that don't change the file's contents are left out, so e.g. --progress can differ
+pub fn settings(options: &Options) -> String {
+    let contents = Options {
+        resume: false,
+        on_interrupt: OnInterrupt::Finish,
+        space_check: true,
+        progress: false,
+        rate: None,
+        checksum: false,
+        if_exists: IfExists::Overwrite,
+        buffer_size: DEFAULT_BUFFER_SIZE,
+        mmap: false,
+        ..options.clone()
+    };
+    // Debug prints every field on one line, which is all we need to compare two runs
+    format!("{:?}", contents)
+}
+
+/// The seed the run that left `filename`'s checkpoint was using, if it had one and there is a
+/// checkpoint - a run without --seed still got a random one, which resuming has to use again
+pub fn checkpoint_seed(filename: &str) -> Option<u64> {
+    let text = fs::read_to_string(checkpoint_name(filename)).ok()?;
+    parse_checkpoint(&text)?.seed
+}
+
+/// Reads the checkpoint for options.filename and checks it belongs to this run
+pub fn load_checkpoint(options: &Options) -> error::Result<Checkpoint> {
+    let path = checkpoint_name(&options.filename);
+    let text = fs::read_to_string(&path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => io::Error::new(
+            e.kind(),
+            format!("No checkpoint for {} - it either finished or stopped before the first one", options.filename),
+        ),
+        _ => e,
+    })?;
+    let checkpoint = parse_checkpoint(&text).ok_or_else(|| invalid_data(format!("{} is damaged", path)))?;
+    if checkpoint.settings != settings(options) {
+        return Err(invalid_input(format!(
+            "{} was started with different settings - run the same command again with --resume", options.filename
+        )));
+    }
+    Ok(checkpoint)
+}
+
+// "Name: value" lines, the same style as the headers in text files
+fn checkpoint_text(rows: u32, bytes: u64, settings: &str, seed: Option<u64>) -> String {
+    let mut text = format!("Rows: {}\nBytes: {}\nSettings: {}\n", rows, bytes, settings);
+    if let Some(seed) = seed {
+        text.push_str(&format!("Seed: {}\n", seed));
+    }
+    text
+}
+
+// Checkpoints from before the Seed line, or from unseeded runs, just don't have one
+fn parse_checkpoint(text: &str) -> Option<Checkpoint> {
+    let mut lines = text.lines();
+    let rows = lines.next()?.strip_prefix("Rows: ")?.parse().ok()?;
+    let bytes = lines.next()?.strip_prefix("Bytes: ")?.parse().ok()?;
+    let settings = lines.next()?.strip_prefix("Settings: ")?.to_string();
+    let seed = match lines.next() {
+        Some(line) => Some(line.strip_prefix("Seed: ")?.parse().ok()?),
+        None => None,
+    };
+    Some(Checkpoint { rows, bytes, settings, seed })
+}
+
+/// Writes checkpoints as rows go past - or does nothing, for runs that can't be resumed
+/// Either way it's where the writers stop between rows when Ctrl-C has been pressed
+pub(crate) struct Checkpoints {
+    path: Option<String>, // Where to save them; None turns them off
+    filename: String, // The .tmp the rows are going into
+    settings: String,
+    seed: Option<u64>,
+    start: u32, // Rows that were already in the file when this run began
+    per_row: u64, // Values in a row
+    rows: u32, // Rows in the file so far, counting the ones that were there already
+}
+
+impl Checkpoints {
+    pub(crate) fn off() -> Checkpoints {
+        Checkpoints { path: None, filename: String::new(), settings: String::new(), seed: None, start: 0, per_row: 1, rows: 0 }
+    }
+
+    /// Checkpoints for options.filename, carrying on after `start` rows
+    pub(crate) fn new(options: &Options, start: u32) -> Checkpoints {
+        Checkpoints {
+            path: Some(checkpoint_name(&options.filename)),
+            filename: temp_name(&options.filename),
+            settings: settings(options),
+            seed: options.seed,
+            start,
+            per_row: options.values_per_row() as u64,
+            rows: start,
+        }
+    }
+
+    /// No checkpoints, but still counting whole rows, so Ctrl-C stops at the end of one
+    pub(crate) fn counting(options: &Options) -> Checkpoints {
+        Checkpoints { path: None, ..Checkpoints::new(options, 0) }
+    }
+
+    /// Rows already in the file before this run, which the writer skips
+    pub(crate) fn start(&self) -> u32 {
+        self.start
+    }
+
+    /// Values already in the file, for the formats that write one value at a time
+    pub(crate) fn start_value(&self) -> u64 {
+        self.start as u64 * self.per_row
+    }
+
+    /// A resumed file already has its header, so it mustn't get another one
+    pub(crate) fn resuming(&self) -> bool {
+        self.start > 0
+    }
+
+    /// Complete rows written so far, for finishing a file off after Ctrl-C
+    pub(crate) fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Called after each complete row, with the number of rows in the file so far
+    pub(crate) fn row_written(&mut self, writer: &mut impl Write, rows: u32) -> error::Result<()> {
+        self.rows = rows;
+        check_interrupt()?;
+        let path = match &self.path {
+            Some(path) if rows.is_multiple_of(CHECKPOINT_EVERY) => path,
+            _ => return Ok(()),
+        };
+        // Everything up to this row has to be in the file before we claim it is
+        writer.flush()?;
+        let bytes = fs::metadata(&self.filename)?.len();
+        // Written next door and renamed over the old one, so a crash mid-write leaves
+        // the previous checkpoint rather than half of this one
+        let temp = format!("{}.tmp", path);
+        fs::write(&temp, checkpoint_text(rows, bytes, &self.settings, self.seed))?;
+        Ok(fs::rename(&temp, path)?)
+    }
+
+    /// Text and binary files write their values one after another, even with several columns,
+    /// so they say how many values are done and a checkpoint only lands on the end of a row
+    pub(crate) fn value_written(&mut self, writer: &mut impl Write, values: u64) -> error::Result<()> {
+        if values.is_multiple_of(self.per_row) {
+            self.row_written(writer, (values / self.per_row) as u32)?;
+        }
+        Ok(())
+    }
+
+    /// The file is complete, so there's nothing left to resume
+    pub(crate) fn finish(self) -> error::Result<()> {
+        match self.path {
+            Some(path) => remove_checkpoint(&path),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Deletes a checkpoint file if there is one
+pub(crate) fn remove_checkpoint(path: &str) -> error::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::binary::BINARY_HEADER_LEN;
+    use crate::generator::tests::options;
+    use crate::{write_data, DataType, Distribution};
+
+    // Pretends a run was killed after `rows` rows: the .tmp is cut off part way through the
+    // next row, with a checkpoint saying where the last complete one ended
+    // Shared with the command line's tests, which interrupt runs of their own
+    pub(crate) fn interrupt(options: &Options, rows: u32, bytes: u64) {
+        let full = fs::read(&options.filename).unwrap();
+        fs::write(temp_name(&options.filename), &full[..bytes as usize + 3]).unwrap();
+        let text = checkpoint_text(rows, bytes, &settings(options), options.seed);
+        fs::write(checkpoint_name(&options.filename), text).unwrap();
+    }
+
+    // Bytes up to and including the nth newline
+    pub(crate) fn end_of_line(bytes: &[u8], n: usize) -> u64 {
+        bytes.iter().enumerate().filter(|(_, &b)| b == b'\n').nth(n - 1).unwrap().0 as u64 + 1
+    }
+
+    #[test]
+    fn resumed_file_matches_an_uninterrupted_one() {
+        for (format, columns) in [(OutputFormat::Text, 1), (OutputFormat::Csv, 3), (OutputFormat::Binary, 2)] {
+            let mut options = options(DataType::Integer, -1000.0, 1000.0, Distribution::Uniform);
+            options.format = format;
+            options.columns = columns;
+            let path = std::env::temp_dir().join(format!("ruststf_test_{}_resume_{:?}", std::process::id(), format));
+            options.filename = path.to_string_lossy().into_owned();
+            write_data(&options).unwrap();
+            let expected = fs::read(&options.filename).unwrap();
+
+            // Where row 400 ends - binary rows are a fixed size, text ones end at a newline
+            // (after the Count and Seed lines in text files)
+            let bytes = match format {
+                OutputFormat::Binary => BINARY_HEADER_LEN + 400 * columns as u64 * 4,
+                OutputFormat::Text => end_of_line(&expected, 402),
+                _ => end_of_line(&expected, 400),
+            };
+            interrupt(&options, 400, bytes);
+            options.resume = true;
+            write_data(&options).unwrap();
+            assert_eq!(fs::read(&options.filename).unwrap(), expected, "{:?}", format);
+            // Finishing cleans the checkpoint up, so a second resume has nothing to go on
+            assert!(write_data(&options).is_err());
+            fs::remove_file(&options.filename).unwrap();
+        }
+    }
+
+    #[test]
+    fn different_settings_are_refused() {
+        let mut options = options(DataType::Integer, 0.0, 100.0, Distribution::Uniform);
+        let path = std::env::temp_dir().join(format!("ruststf_test_{}_resume_settings", std::process::id()));
+        options.filename = path.to_string_lossy().into_owned();
+        write_data(&options).unwrap();
+        interrupt(&options, 10, 40);
+        options.resume = true;
+        options.max = 200.0;
+        assert!(load_checkpoint(&options).is_err());
+        options.max = 100.0;
+        options.progress = true; // Doesn't change what's in the file, so it's allowed to differ
+        assert_eq!(load_checkpoint(&options).unwrap().rows, 10);
+        let _ = fs::remove_file(&options.filename);
+        let _ = fs::remove_file(checkpoint_name(&options.filename));
+        let _ = fs::remove_file(temp_name(&options.filename));
+    }
+}
@@ -0,0 +1,70 @@
+//! SHA-256 checksums written next to the data file, so a copy can be checked after it's moved around.
+//!
+//! The sidecar uses the same layout as the `sha256sum` tool (`<hash>  <name>`), so
+//! `sha256sum -c data.txt.sha256` works on it too.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::error;
+use crate::invalid_data;
+
+/// What verify_checksum found - the two hashes match when the file is intact
+#[derive(Debug)]
+pub struct ChecksumCheck {
+    pub expected: String, // From the .sha256 file
+    pub actual: String,   // Worked out from the file as it is now
+}
+
+impl ChecksumCheck {
+    pub fn is_ok(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+/// Name of the sidecar file for a data file: data.txt -> data.txt.sha256
+pub fn checksum_name(filename: &str) -> String {
+    format!("{}.sha256", filename)
+}
+
+/// SHA-256 of a file's bytes as lowercase hex
+/// Gzipped files are hashed as they sit on disk, since that's what gets copied around
+pub fn checksum_file(filename: &str) -> error::Result<String> {
+    let mut file = File::open(filename).map_err(|e| io::Error::new(e.kind(), format!("Can't read {}: {}", filename, e)))?;
+    let mut hasher = Sha256::new();
+    // Read a chunk at a time like fread() in a loop, so huge files don't have to fit in memory
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Hashes a file and writes the result to <filename>.sha256, handing back the hash
+pub fn write_checksum(filename: &str) -> error::Result<String> {
+    let hash = checksum_file(filename)?;
+    // Only the bare name goes in the sidecar, so the pair still works after being moved together
+    let name = Path::new(filename).file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    std::fs::write(checksum_name(filename), format!("{}  {}\n", hash, name))?;
+    Ok(hash)
+}
+
+/// Re-hashes a file and compares it with what its .sha256 file says
+pub fn verify_checksum(filename: &str) -> error::Result<ChecksumCheck> {
+    let sidecar = checksum_name(filename);
+    let text = std::fs::read_to_string(&sidecar)
+        .map_err(|e| io::Error::new(e.kind(), format!("Can't read {}: {}", sidecar, e)))?;
+    // The hash is the first word - sha256sum puts the filename after it
+    let expected = text.split_whitespace().next().unwrap_or_default().to_lowercase();
+    if expected.len() != 64 || !expected.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid_data(format!("{} doesn't hold a SHA-256 checksum", sidecar)));
+    }
+    Ok(ChecksumCheck { expected, actual: checksum_file(filename)? })
+}
@@ -0,0 +1,3893 @@
+//! The interactive menu, prompts and command-line flags.
+
+use std::collections::VecDeque; // A queue, for answers to give again after going back
+use std::env; // For command-line arguments, like argc/argv in C
+use std::io::{self, BufRead, IsTerminal, Write}; // How we handle I/O, like stdio.h in C
+use std::net::TcpListener; // A listening socket, like socket() + bind() + listen() in C
+use std::path::Path; // Filename helpers, like stat() for checking a file is there
+use std::time::{Duration, Instant}; // A stopwatch for timing the run, and --follow's gap between rows
+
+use log::{debug, warn, LevelFilter};
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use crate::bench::{bench, count_for_size, estimate, Estimate, SIZE_TOLERANCE};
+use crate::binary::Endianness;
+use crate::blob::writes_blob_files;
+use crate::checkpoint::{checkpoint_name, checkpoint_seed, resume_blocker};
+use crate::checksum::{checksum_name, verify_checksum};
+use crate::code::Language;
+use crate::config::{env_key, is_config_key, prepare_dir, validate_filename, Config, ENV_PREFIX};
+use crate::convert::convert_options;
+use crate::error::{self, GeneratorError};
+use crate::fifo::is_fifo;
+use crate::follow::{check_follow, follow};
+use crate::generator::{self, parse_bound, parse_data_type, Category, DataType, Distribution, SortOrder};
+use crate::graph::{self, Graph};
+use crate::histogram::Histogram;
+use crate::history::{History, Recipe, HISTORY_FILE};
+use crate::interrupt::{install_handler, OnInterrupt, INTERRUPTED_EXIT_CODE};
+use crate::manifest::Manifest;
+use crate::merge::{merge_files, merge_inputs, MergeOrder};
+use crate::mixture::{self, parse_clusters, Mixture};
+use crate::network::{parse_cidr, Cidr};
+use crate::points::{self, Points, Shape};
+use crate::pool::{Pool, Sampling};
+use crate::preset::{self, Presets, PRESETS_FILE};
+use crate::reader::load_file;
+use crate::schema::parse_schema;
+use crate::series::TimeSeries;
+use crate::server::{serve, DEFAULT_PORT, GENERATE_PATH};
+use crate::shard::{output_files, shard_manifest_name, MAX_SHARDS};
+use crate::shuffle::shuffle_file;
+use crate::socket::Socket;
+use crate::space::size_text;
+use crate::special::Special;
+use crate::split::{split_file, SplitSize};
+use crate::sqlite::{table_exists, table_name, DEFAULT_TABLE};
+use crate::stats::{compute_stats, Stats};
+use crate::timestamp::format_iso;
+use crate::units::{parse_count, parse_suffixed};
+use crate::writer::{
+    write_data, Align, BoolFormat, FieldWidth, HeaderStyle, IfExists, IntFormat, LineLayout, MissingToken, NumberFormat,
+    OutputFormat, TimeFormat,
+};
+use crate::verify::verify_file;
+use crate::{invalid_data, invalid_input, usage_error, Options, DEFAULT_BUFFER_SIZE, MAX_BUFFER_SIZE, MAX_PRECISION, STDOUT_NAME};
+
+// Menu runs with at least this many values get asked about using every core
+const PARALLEL_PROMPT_AT: u64 = 1_000_000;
+
+// Goes at the type, count and filename questions before the menu gives up on a file
+const MAX_ATTEMPTS: u32 = 3;
+
+// What --bench runs when no --sizes are given, and the scratch file it writes in the temp directory
+const BENCH_SIZES: &str = "1K,100K,10M";
+// Buffer sizes --bench compares at its largest size (the chosen --buffer-size gets added if it's not here)
+const BENCH_BUFFERS: [usize; 3] = [8 << 10, 64 << 10, 1 << 20];
+const BENCH_FILE: &str = "ruststf_bench.tmp";
+
+// generate's on/off flags, which a DATAGEN_ variable turns on with 1 and leaves off with 0
+const ENV_SWITCHES: [&str; 22] = [
+    "no_replacement", "unique", "header", "no_header", "comment_header", "weighted", "time_series", "labels", "stats", "append_stats",
+    "histogram", "progress", "stream", "gzip", "scientific", "checksum", "mmap", "force", "resume", "dry_run", "quiet", "verbose",
+];
+
+/// Where the menu reads its answers and writes its questions: stdin and stdout normally,
+/// but anything that reads lines and anything that takes bytes will do, so tests can feed
+/// in a string of answers and check what was printed back
+pub struct Console<R, W> {
+    input: R,  // BufRead - read_line() needs a buffer to find where each line ends
+    output: W, // Write - the same trait files and stdout use
+    prompts: bool, // False for an answer file: no menu or questions, and the first mistake stops the run
+    answers: Vec<String>,      // What's been typed so far for the current menu option, for going back
+    replay: VecDeque<String>,  // Answers to give again, without asking, after going back
+    back: bool,                // Set when the last answer was "back" rather than "cancel"
+    b_answers: bool,           // The question being asked has a "b for ..." answer of its own
+}
+
+impl Console<io::StdinLock<'static>, io::Stdout> {
+    /// The real keyboard and screen - or, when stdin is a pipe or a file rather than a terminal
+    /// (isatty() in C), an answer file, read without printing the questions it answers
+    pub fn stdio() -> Self {
+        Console::new(io::stdin().lock(), io::stdout()).with_prompts(io::stdin().is_terminal())
+    }
+}
+
+impl<R: BufRead, W: Write> Console<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Console { input, output, prompts: true, answers: Vec::new(), replay: VecDeque::new(), back: false, b_answers: false }
+    }
+
+    /// Turns the menu and questions off (or back on), for answers coming from a script
+    pub fn with_prompts(mut self, prompts: bool) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    // Explanations that go with a question, left out along with the question for an answer file
+    fn explain(&mut self, text: &str) -> error::Result<()> {
+        if self.prompts && self.replay.is_empty() {
+            writeln!(self.output, "{}", text)?;
+        }
+        Ok(())
+    }
+
+    /// Hands back the output, e.g. the Vec<u8> a test collected everything printed into
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    // Helper for getting input - &str is like const char* in C
+    // but it can't be null and Rust knows its length
+    // At the keyboard, q or cancel gives up on the current menu option, and b or back goes back a question
+    fn read_line(&mut self, prompt: &str) -> error::Result<String> {
+        // Going back starts the option over, and the answers already given are handed straight back
+        if let Some(answer) = self.replay.pop_front() {
+            self.answers.push(answer.clone());
+            return Ok(answer);
+        }
+        if self.prompts {
+            write!(self.output, "{}", prompt)?;
+            self.output.flush()?;
+        }
+        let mut input = String::new();
+        // 0 bytes means the input is finished - without this check an empty answer
+        // would be "pressed enter" forever, and the menu would loop without end
+        if self.input.read_line(&mut input)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Input ended").into());
+        }
+        let input = input.trim().to_string();
+        // An answer file has no way to change its mind, so there q and b are just answers
+        if self.prompts {
+            match input.to_lowercase().as_str() {
+                "q" | "cancel" => return Err(GeneratorError::Cancelled),
+                "b" if self.b_answers => {},
+                "b" | "back" => {
+                    self.go_back();
+                    return Err(GeneratorError::Cancelled);
+                },
+                _ => {},
+            }
+        }
+        self.answers.push(input.clone());
+        Ok(input)
+    }
+
+    // The last answer is the one to change, and everything before it gets replayed
+    // Back at the very first question just leaves, like cancel
+    fn go_back(&mut self) {
+        self.back = self.answers.pop().is_some();
+        self.replay = self.answers.drain(..).collect();
+    }
+
+    // Asks a question where b already means something (boolean, big-endian, binary),
+    // so only the whole word "back" goes back
+    fn with_b_answer<T>(&mut self, ask: impl FnOnce(&mut Self) -> T) -> T {
+        self.b_answers = true;
+        let answer = ask(self);
+        self.b_answers = false;
+        answer
+    }
+
+    // Whether the option was left by going back, so it should start over - resets it for next time
+    fn take_back(&mut self) -> bool {
+        std::mem::take(&mut self.back)
+    }
+
+    // At the keyboard a typo just gets the question asked again, up to MAX_ATTEMPTS times,
+    // instead of throwing away every answer before it.
+    // An answer file gets no second go, since its next line is the answer to the next question
+    fn read_valid<T>(&mut self, prompt: &str, parse: impl Fn(&str) -> error::Result<T>) -> error::Result<T> {
+        let mut attempts = 1;
+        loop {
+            let input = self.read_line(prompt)?;
+            match parse(&input) {
+                // I/O errors (like the input running out) won't get better by asking again
+                Err(e) if self.prompts && e.io_kind().is_none() && attempts < MAX_ATTEMPTS => {
+                    writeln!(self.output, "{} - try again, or type cancel to go back to the menu", e)?;
+                    // Only the answer that worked is kept for going back to
+                    self.answers.pop();
+                    attempts += 1;
+                },
+                result => return result,
+            }
+        }
+    }
+}
+
+// Printing to a console prints to its output, so write!/writeln! work on it directly
+impl<R, W: Write> Write for Console<R, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+// What the command line asked us to do
+enum Command {
+    Generate(Options),
+    Batch(Options, Vec<BatchSize>), // The Options filename is the template here
+    Display(String),
+    Verify(String),
+    VerifyChecksum(String),
+    Clean,
+    Bench(Options, Vec<BatchSize>), // The Options filename is the scratch file here
+    Serve(String), // The address to listen on, like 127.0.0.1:8080
+    Shuffle(String, String, Option<u64>, bool), // The file, where the shuffled copy goes, the seed and --force
+    Merge(Vec<String>, String, MergeOrder, bool), // The files, where they go, how and --force
+    Split(String, String, SplitSize, bool), // The file, the name the parts are numbered after, their size and --force
+    Convert(String, Options), // The file, and Options with the new format and name - the rest comes from the file
+    SavePreset(String, Vec<String>), // The name and the flags to save under it
+    ToSize(Options, u64), // Generate, with the count worked out to make a file of about this many bytes
+    Follow(Options, Duration), // Add a row this often until Ctrl-C (or the count, if one was given)
+    DryRun(Box<Command>), // Estimate a Generate, ToSize or Batch, then ask before running it
+    Menu,
+}
+
+// One file in a batch: the size as the user typed it (for the filename) and as a number
+struct BatchSize {
+    label: String,
+    count: u32,
+}
+
+/// Runs the program: flags if there are any, otherwise the interactive menu
+pub fn run() -> error::Result<()> {
+    // In C we'd loop over argv; here args() gives us an iterator of Strings
+    // The first one is the program name, so we skip it
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        print_usage();
+        return Ok(());
+    }
+    // Settings can come from DATAGEN_ variables too, for CI jobs that can't answer prompts
+    let vars: Vec<(String, String)> = env::vars().filter(|(name, _)| name.starts_with(ENV_PREFIX)).collect();
+    let args = match with_env_args(args, &vars) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    // -q and -v are for the whole program rather than one command, so they come out first
+    let (args, level) = take_log_level(args);
+    init_logging(level);
+    // Ctrl-C while a file is being written finishes or deletes it instead of leaving half of it
+    if let Err(e) = install_handler() {
+        warn!("Ctrl-C will stop the program without tidying up ({})", e);
+    }
+    if !vars.is_empty() {
+        let names: Vec<&str> = vars.iter().map(|(name, _)| name.as_str()).collect();
+        debug!("Settings from the environment: {}", names.join(", "));
+    }
+    // A broken config file would quietly give the wrong defaults, so stop and say so
+    let config = match load_config(&args, &vars) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    // Every file we write goes on this list, and into the manifest file if there is one
+    let mut manifest = match load_manifest(&args, &config) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if !args.is_empty() {
+        let command = match parse_args(&args, &config) {
+            Ok(Command::Menu) => return menu_on_stdio(&config, &mut manifest),
+            Ok(command) => command,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                // A flag we don't know, one missing its value or a word too many gets the usage
+                // text after it. Anything else is a setting that's wrong, and the message already
+                // says what, so the usage text would only bury it
+                if matches!(e, GeneratorError::Usage(_)) {
+                    print_usage();
+                }
+                std::process::exit(2);
+            }
+        };
+        // Quiet runs keep stdout for the data alone - the exit code says how it went
+        let mut out: Box<dyn Write> = if level == LevelFilter::Error { Box::new(io::sink()) } else { Box::new(io::stdout()) };
+        let result = match command {
+            Command::Generate(options) => generate(&mut out, &options, &mut manifest),
+            Command::ToSize(options, target) => generate_to_size(&mut out, &options, target, &mut manifest),
+            Command::Follow(options, every) => follow_file(&mut out, &options, every, &mut manifest),
+            Command::Batch(options, sizes) => generate_batch(&mut out, &options, &sizes, &mut manifest),
+            Command::Display(filename) => display_file(&mut out, &filename),
+            Command::Verify(filename) => verify(&mut out, &filename),
+            Command::VerifyChecksum(filename) => check_checksum(&mut out, &filename),
+            Command::Clean => clean_all(&mut out, &mut manifest),
+            Command::Bench(options, sizes) => run_bench(&mut out, &options, &sizes),
+            Command::Serve(address) => run_server(&mut out, &address, &config),
+            Command::Shuffle(input, output, seed, force) => shuffle(&mut out, &input, &output, seed, force),
+            Command::Merge(inputs, output, order, force) => merge(&mut out, &inputs, &output, order, force),
+            Command::Split(input, output, size, force) => split(&mut out, &input, &output, size, force),
+            Command::Convert(input, options) => convert(&mut out, &input, &options),
+            Command::SavePreset(name, args) => save_preset(&mut out, &config, &name, &args),
+            Command::DryRun(command) => dry_run(&mut out, *command, &mut manifest),
+            Command::Menu => unreachable!("the menu was started above"),
+        };
+        // Scripts check the exit code, so failures need to return non-zero like a C program would
+        // A broken pipe just means whoever was reading stdout stopped early (like `| head`),
+        // which is how pipelines normally end, so that one isn't worth complaining about
+        if let Err(e) = result {
+            if e.io_kind() == Some(io::ErrorKind::BrokenPipe) {
+                return Ok(());
+            }
+            eprintln!("Error: {}", e);
+            // The code a shell gives a program killed by Ctrl-C, so scripts see the same either way
+            let code = if matches!(e, GeneratorError::Interrupted(_)) { INTERRUPTED_EXIT_CODE } else { 1 };
+            std::process::exit(code);
+        }
+        // Anything that got written goes in the history, along with the flags that wrote it
+        if let Err(e) = manifest.history_mut().record(Recipe::Flags(history_args(&args, &config))) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    menu_on_stdio(&config, &mut manifest)
+}
+
+// The menu on the real console - an answer file that goes wrong exits non-zero, like bad flags do
+fn menu_on_stdio(config: &Config, manifest: &mut Manifest) -> error::Result<()> {
+    if let Err(e) = run_menu(&mut Console::stdio(), config, manifest) {
+        // The menu's own errors already say what went wrong ("Error creating file: ...")
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// The interactive menu, reading answers from the console until Exit (or the input runs out)
+pub fn run_menu(console: &mut Console<impl BufRead, impl Write>, config: &Config, manifest: &mut Manifest) -> error::Result<()> {
+    loop {
+        display_menu(console)?;
+        // Running out of input (Ctrl-D, or the end of a file piped in) counts as choosing Exit,
+        // and so does q here, since there's nothing further back to go to
+        let choice = match get_choice(console) {
+            Ok(choice) => choice,
+            Err(e) if e.io_kind() == Some(io::ErrorKind::UnexpectedEof) => break,
+            Err(GeneratorError::Cancelled) => break,
+            Err(e) => return Err(e),
+        };
+        // match is like switch in C but needs to handle all cases
+        match choice {
+            1 => run_option(console, "Error creating file", |console| create_file(console, config, manifest))?,
+            2 => run_option(console, "Error creating files", |console| create_batch(console, config, manifest))?,
+            3 => run_option(console, "Error reading file", |console| {
+                get_filename(console).and_then(|filename| display_file(console, &filename))
+            })?,
+            4 => run_option(console, "Error verifying file", |console| {
+                get_filename(console).and_then(|filename| verify(console, &filename))
+            })?,
+            5 => run_option(console, "Error verifying checksum", |console| {
+                get_filename(console).and_then(|filename| check_checksum(console, &filename))
+            })?,
+            6 => run_option(console, "Error creating file", |console| create_records(console, config, manifest))?,
+            7 => run_option(console, "Error creating file", |console| create_graph(console, config, manifest))?,
+            8 => run_option(console, "Error deleting files", |console| clean_up(console, manifest))?,
+            9 => run_option(console, "Error creating file", |console| create_from_preset(console, config, manifest))?,
+            10 => run_option(console, "Error creating file", |console| regenerate(console, config, manifest))?,
+            11 => break,
+            _ if console.prompts => writeln!(console, "Invalid choice!")?,
+            _ => return Err(invalid_input("Error: invalid menu choice (expected a number from 1 to 11)")),
+        }
+        // Whatever the option wrote is logged with the answers that got it there
+        manifest.history_mut().record(Recipe::Menu(choice as u32, console.answers.clone()))?;
+    }
+    
+    writeln!(console, "Program terminated.")?;
+    Ok(()) // Like return 0 in C, but wrapped in Ok() to show success
+}
+
+// Runs one menu option, starting it over each time someone types back - the answers before
+// the one they're changing are replayed, which lands them on the previous question
+fn run_option<R: BufRead, W: Write>(
+    console: &mut Console<R, W>,
+    what: &str,
+    mut option: impl FnMut(&mut Console<R, W>) -> error::Result<()>,
+) -> error::Result<()> {
+    console.answers.clear();
+    loop {
+        let result = option(console);
+        if !(matches!(result, Err(GeneratorError::Cancelled)) && console.take_back()) {
+            return report(console, what, result);
+        }
+    }
+}
+
+// At the keyboard a mistake is reported and the menu carries on, but in an answer file every
+// answer after it would land on the wrong question, so the run stops there instead
+fn report(console: &mut Console<impl BufRead, impl Write>, what: &str, result: error::Result<()>) -> error::Result<()> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(GeneratorError::Cancelled) => Ok(writeln!(console, "Cancelled.")?),
+        Err(e) if console.prompts => Ok(writeln!(console, "{}: {}", what, e)?),
+        Err(e) => Err(e.context(what)),
+    }
+}
+
+// Simple menu display - writeln! is like fprintf in C, but it checks the types for us
+fn display_menu(console: &mut Console<impl BufRead, impl Write>) -> error::Result<()> {
+    if !console.prompts {
+        return Ok(());
+    }
+    writeln!(console, "\n1. Create new data file")?;
+    writeln!(console, "2. Create a batch of files")?;
+    writeln!(console, "3. Read and display file")?;
+    writeln!(console, "4. Verify a file")?;
+    writeln!(console, "5. Verify a checksum")?;
+    writeln!(console, "6. Create records from a schema")?;
+    writeln!(console, "7. Create a random graph")?;
+    writeln!(console, "8. Delete generated files")?;
+    writeln!(console, "9. Create from a saved preset")?;
+    writeln!(console, "10. Generate a past file again")?;
+    writeln!(console, "11. Exit")?;
+    write!(console, "Enter your choice: ")?;
+    Ok(console.flush()?)
+}
+
+
+// Usage text for the non-interactive mode, printed for --help or flags it can't make sense of
+fn print_usage() {
+    println!("Usage: ruststf generate --type <type> --count <n> --output <file>");
+    println!("       ruststf generate --type <type> --sizes <list> --output <template>");
+    println!("       ruststf generate --schema <fields> --count <n> --format csv|jsonl|parquet|sqlite --output <file>");
+    println!("       ruststf generate --time-series [--trend <v>] [--amplitude <v>] [--noise <v>] --count <n> --output <file>");
+    println!("       ruststf generate --mixture <clusters> [--labels] --count <n> --output <file>");
+    println!("       ruststf generate --points box|circle|sphere [--dimensions 2|3] [--radius <r>] --count <n> --output <file>");
+    println!("       ruststf generate --graph <nodes> --edges <n>|--density <p> [--weighted] --output <file>");
+    println!("       ruststf inspect <file>");
+    println!("       ruststf verify [--checksum] <file>");
+    println!("       ruststf shuffle <file> --output <file> [--seed <n>]");
+    println!("       ruststf merge <file>... --output <file> [--interleave | --order asc|desc]");
+    println!("       ruststf split <file> --parts <k> | --rows <m> [--output <name>]");
+    println!("       ruststf convert <file> --format <format> --output <file> [--columns <n>] [--precision <n>]");
+    println!("       ruststf bench [--type <type>] [--format <format>] [--sizes <list>]");
+    println!("       ruststf serve [--port <n>] [--bind <address>]");
+    println!("       ruststf menu");
+    println!("       ruststf --clean --manifest <file>");
+    println!();
+    println!("Commands:");
+    println!("  generate    Write a file of random data (the default when the flags start right away)");
+    println!("  inspect     Read a data file back and print a summary and histogram");
+    println!("  verify      Check a data file is well formed, or with --checksum that it still matches");
+    println!("              its .sha256 (exit code 1 if not)");
+    println!("  shuffle     Copy a data file with its rows in a random order and its header kept on");
+    println!("              top (--force replaces an existing output; naming the file itself shuffles it in place)");
+    println!("  merge       Join files (or the shards a _manifest.txt lists) into one under a single");
+    println!("              header, one after another, a row from each in turn, or sorted");
+    println!("  split       Cut a file into k even parts, or parts of at most m rows, each with its own");
+    println!("              header (named like shards, with a _manifest.txt that merge can read)");
+    println!("  convert     Rewrite a data file in another format, keeping its type, rows and seed");
+    println!("              (also --header, --endianness, --language, --gzip and --force, which work as for generate)");
+    println!("  bench       Time generating and writing {} (or --sizes) values", BENCH_SIZES);
+    println!("  serve       Serve generated data over HTTP");
+    println!("  menu        The interactive menu, also shown when there are no arguments at all");
+    println!();
+    println!("Options for generate and bench:");
+    println!("  -t, --type <type>             Type of data: int, float, short (i16), long (i64),");
+    println!("                                unsigned (u32), double (f64), string, bytes, timestamp, bool,");
+    println!("                                uuid, ipv4, ipv6, first_name, last_name, email or phone");
+    println!("  -n, --count <n>               Number of elements to generate, e.g. 5000, 10k, 2.5M or 1G");
+    println!("      --min <value>             Smallest value to generate (default depends on type)");
+    println!("      --max <value>             Largest value to generate (default depends on type)");
+    println!("      --exclude <list>          Values that must never appear, e.g. 0 or -10..10,99 (ends of a");
+    println!("                                range are left out too; can be given more than once)");
+    println!("                                Timestamps also take dates, e.g. 2024-01-31T12:00:00");
+    println!("      --length <n>|<min>-<max>  String length, fixed or a range (default 8) - for bytes, the");
+    println!("                                size of each blob (default 16), written as hex, or as a");
+    println!("                                directory with a file per blob for --format binary");
+    println!("      --sample-from <file>      Pick the values from a file instead of making them up (any file");
+    println!("                                this program writes, or a CSV); the type comes from the file and");
+    println!("                                --count defaults to its size, which resamples it for a bootstrap");
+    println!("      --no-replacement          With --sample-from, never pick the same value twice");
+    println!("      --dist <name>             Distribution: uniform, normal, exponential, poisson, zipf");
+    println!("                                or categorical (default uniform); for integers, primes,");
+    println!("                                squares or powers-of-two picks only those from the range");
+    println!("      --mean <value>            Mean for --dist normal (default middle of the range)");
+    println!("      --std-dev <value>         Standard deviation for --dist normal (default range / 6)");
+    println!("      --lambda <value>          Rate for --dist exponential or poisson (default 1)");
+    println!("      --exponent <value>        Skew for --dist zipf: the smallest value is the most common,");
+    println!("                                and bigger exponents make it more so (default 1)");
+    println!("      --categories <list>       Labels to pick from with weights, e.g. \"A:0.5,B:0.3,C:0.2\"");
+    println!("                                (string type; implies --dist categorical)");
+    println!("      --cidr <block>            Keep IP addresses inside a network, e.g. 10.0.0.0/8 or");
+    println!("                                2001:db8::/32 (implies --type ipv4/ipv6 when none is given)");
+    println!("      --order <order>           Value order: random, asc, desc or nearly (default random)");
+    println!("      --disorder <percent>      Percentage of values shuffled for --order nearly (default 5)");
+    println!("      --unique                  Never repeat a value (integer types, uniform only)");
+    println!("      --permutation <n>         Every number from 0 to n-1 exactly once, shuffled (--min 1 for");
+    println!("                                1 to n); sets the count and range itself");
+    println!("      --outliers <percent>      Swap this share of values for ones far outside the range, for");
+    println!("                                testing anomaly detection (number types)");
+    println!("      --outlier-scale <n>       How far out outliers go, in half-widths of the range from its");
+    println!("                                middle: between n and 2n (default 10)");
+    println!("      --missing <percent>       Leave this share of values missing, for testing null handling");
+    println!("                                (CSV, JSON and JSON lines)");
+    println!("      --missing-as <token>      What a missing value is written as: empty, null (NULL in CSV,");
+    println!("                                null in JSON) or nan (default empty)");
+    println!("      --duplicates <percent>    Make this share of values repeats of earlier ones, for");
+    println!("                                testing deduplication (integer types)");
+    println!("  -f, --format <format>         Output format: text, csv, json, binary, matrix, jsonl");
+    println!("                                (JSON lines, records only), parquet, sqlite or fixed");
+    println!("                                (fixed-width fields), or code (an array in --language)");
+    println!("                                (default text)");
+    println!("      --language <lang>         The source code --format code writes: c, cpp, rust or python");
+    println!("                                (default c), named after the output file");
+    println!("      --columns <n>             Number of CSV, matrix, Parquet, SQLite, fixed-width or code columns per row");
+    println!("                                (default 1)");
+    println!("      --table <name>            SQLite table to fill (default data)");
+    println!("      --header                  Write a column header line in CSV mode");
+    println!("      --no-header               Leave the Count:/Matrix: header out of text and matrix files");
+    println!("      --comment-header          Write that header as # comment lines instead");
+    println!("      --header-template <text>  Write one header line of your own, with {{count}}, {{columns}},");
+    println!("                                {{type}}, {{seed}} and {{timestamp}} filled in");
+    println!("      --delimiter <d>           Between values in text files: newline (the default), comma,");
+    println!("                                space, tab or any text, e.g. \", \" (other than newline puts");
+    println!("                                them all on one line)");
+    println!("      --prefix <text>           Put this at the start of each line of a text file");
+    println!("      --suffix <text>           Put this at the end of each line, e.g. --prefix \"int data[] = {{\"");
+    println!("                                --suffix \"}};\" --delimiter \", \" --no-header for a C array");
+    println!("      --separator <sep>         Matrix value separator: space or comma (default space)");
+    println!("      --width <n>               Characters per field for --format fixed (default 12)");
+    println!("      --fill <char>             Padding for fixed-width fields, e.g. 0, or space (default)");
+    println!("      --align <side>            Fixed-width values sit on the left or right (default right)");
+    println!("      --endianness <order>      Byte order of binary numbers: little or big (default little)");
+    println!("      --schema <fields>         Write records instead of single values, one per line as");
+    println!("                                CSV, jsonl, parquet or sqlite, e.g. \"id:int(1..1000), score:float, ok:bool\"");
+    println!("      --time-series             Write timestamped readings as time,value records (CSV unless");
+    println!("                                --format says otherwise); --type is for the values (default double)");
+    println!("      --from <time>             Time of the first reading (default 2024-01-01)");
+    println!("      --interval <time>         Time between readings: seconds, or 30s, 15m, 6h, 1d (default 1h)");
+    println!("      --base <value>            Value at the first reading (default 0)");
+    println!("      --trend <value>           Added to the value every reading (default 0)");
+    println!("      --amplitude <value>       Height of the seasonal sine wave (default 0)");
+    println!("      --period <n>              Readings in one full wave (default 24)");
+    println!("      --noise <value>           Standard deviation of the random noise (default 1)");
+    println!("      --mixture <clusters>      Write points drawn from a Gaussian mixture as x(,y,z) records,");
+    println!("                                each cluster mean:variance:weight, e.g. \"0,0:1:0.7; 5,5:2:0.3\"");
+    println!("                                (CSV unless --format says otherwise; --type as for --time-series)");
+    println!("      --labels                  Add a cluster field with the index of each point's cluster");
+    println!("      --points <shape>          Write x,y(,z) points: box (evenly between --min and --max on");
+    println!("                                every axis), circle or sphere (on the outline), or mixture");
+    println!("                                (the same as --mixture, which takes 3D means too)");
+    println!("      --dimensions <n>          Axes for --points box: 2 or 3 (default 2)");
+    println!("      --radius <value>          Radius of --points circle or sphere, centred on 0 (default 1)");
+    println!("      --graph <nodes>           Write a random graph as an edge list (\"u v\" per line)");
+    println!("      --edges <n>               Number of edges in the graph");
+    println!("      --density <p>             Share of all possible edges to use instead, 0 to 1");
+    println!("      --weighted                Add a weight to each edge (\"u v w\"); --type, --min, --max");
+    println!("                                and --dist describe the weights (default int 1 to 100)");
+    println!("      --precision <n>           Digits after the point for float/double (0-15, default 3/6)");
+    println!("      --scientific              Write float/double in scientific notation, e.g. 1.234e5");
+    println!("      --number-style <style>    How numbers look in text, CSV and matrix files: plain");
+    println!("                                (1234567.89), comma (1,234,567.89), european");
+    println!("                                (1.234.567,89), space (1 234 567,89) or swiss (1'234'567.89)");
+    println!("      --int-format <base>       Write integers as decimal, hex (0x3FF) or binary (0b1010)");
+    println!("                                in text, CSV, matrix and fixed-width files (default decimal)");
+    println!("      --decimal <sep>           Decimal separator: point or comma (default point)");
+    println!("      --thousands <sep>         Between groups of three digits: comma, point, space,");
+    println!("                                apostrophe, underscore or none (default none)");
+    println!("                                CSV files use ; between columns when numbers have commas");
+    println!("      --time-format <format>    Write timestamps as iso (2024-01-31T12:00:00Z) or epoch");
+    println!("                                seconds (default iso)");
+    println!("      --true-probability <p>    Chance of each boolean being true, 0 to 1 (default 0.5)");
+    println!("      --bool-format <format>    Write booleans as words (true/false) or digits (1/0)");
+    println!("                                (default words)");
+    println!("      --seed <n>                Seed the RNG so the same file comes out every run");
+    println!("      --stats                   Print min/max/mean/median/std dev when done");
+    println!("      --append-stats            Same as --stats, and also write them into the file");
+    println!("      --histogram               Same as --stats, and also draw a histogram of the values");
+    println!("      --progress                Show progress and throughput on stderr while writing");
+    println!("      --follow <every>          Keep adding a row every 250ms, 2s, ... until Ctrl+C, like a");
+    println!("                                growing log (text or CSV; appends unless --if-exists says not to)");
+    println!("      --rate <n>/<unit>         Let values out at a steady rate, e.g. 100/s, 30/m or 500/h,");
+    println!("                                to feed another program like a live source");
+    println!("      --threads <n>             Generate on n threads, 0 = one per core (default 1)");
+    println!("      --stream                  Keep memory use bounded however big the file: no sorting,");
+    println!("                                --unique or appending, and --stats estimates the median");
+    println!("      --sizes <list>            Write one file per size, e.g. 1K,10K,100K (instead of --count)");
+    println!("      --target-size <size>      Write as many values as make a file of about this size,");
+    println!("                                e.g. 500MB or 1G (instead of --count)");
+    println!("      --shards <k>              Split the rows across k files, data_part0001.txt and so on,");
+    println!("                                listed with their row counts in data_manifest.txt");
+    println!("      --gzip                    Compress the output (implied by an -o name ending in .gz)");
+    println!("      --framed                  Send the output in frames with a length and CRC each, so a");
+    println!("                                reader can tell a stream was cut short or damaged");
+    println!("      --checksum                Also write a SHA-256 checksum to <file>.sha256");
+    println!("      --buffer-size <size>      Write buffer size, e.g. 64K or 1M (default 8K)");
+    println!("      --mmap                    Write binary files through a memory map instead of the buffer");
+    println!("                                (falls back to the buffer where files can't be mapped)");
+    println!("      --if-exists <action>      If the output file exists: overwrite, append or abort");
+    println!("                                (default: ask, or abort when not run from a terminal)");
+    println!("      --force                   Overwrite existing files without asking");
+    println!("      --resume                  Finish a file that was interrupted, from its last");
+    println!("                                checkpoint (run the same command again with --resume)");
+    println!("      --on-interrupt <action>   What Ctrl-C does to the file: finish (keep the rows so far");
+    println!("                                and fix the count, the default), delete, or keep it as it is");
+    println!("                                for --resume");
+    println!("      --no-space-check          Start even if the file looks too big for the free disk space");
+    println!("  -o, --output <file>           File to write the data to, or - for stdout");
+    println!("                                tcp://host:port or udp://host:port sends it over the network,");
+    println!("                                and kafka://brokers/topic publishes it (--features kafka builds)");
+    println!("      --dry-run                 Estimate the file size and time from a quick sample, then");
+    println!("                                ask before generating (only the estimate without a terminal)");
+    println!("      --output-dir <dir>        Put relative output names in this directory (created if");
+    println!("                                missing, and checked for write access up front)");
+    println!("                                ({{size}} is replaced in batch mode)");
+    println!("      --read <file>             Same as inspect <file>");
+    println!("      --verify <file>           Same as verify <file>");
+    println!("      --verify-checksum <file>  Same as verify --checksum <file>");
+    println!("      --bench                   Same as bench");
+    println!("      --config <file>           Read defaults from this file instead of generator.toml");
+    println!("      --manifest <file>         Record every generated file in this list (or set manifest");
+    println!("                                in generator.toml)");
+    println!("      --clean                   Delete every file listed in the manifest, then empty it");
+    println!("      --preset <name>           Use the flags saved under this name (flags after it win)");
+    println!("      --save-preset <name>      Save the other flags under this name in {} instead of", PRESETS_FILE);
+    println!("                                generating (or set presets in generator.toml)");
+    println!("  -q, --quiet                   Print nothing but errors");
+    println!("  -v, --verbose                 Also print the seed, buffer sizes and how long each step took");
+    println!("  -h, --help                    Show this help");
+    println!();
+    println!("Any generate setting can also come from a DATAGEN_ variable named after its flag, like");
+    println!("DATAGEN_COUNT=1000, DATAGEN_FORMAT=csv, DATAGEN_UNIQUE=1 or DATAGEN_OUTDIR=data. The");
+    println!("type, count, seed, range and output directory fill in the menu's answers too. Flags win.");
+    println!();
+    println!("serve answers GET {}?type=int&count=100 with freshly generated data.", GENERATE_PATH);
+    println!("The query takes the generating options above without their dashes.");
+    println!("      --port <n>                Port to listen on (default {})", DEFAULT_PORT);
+    println!("      --bind <address>          Address to listen on (default 127.0.0.1, this machine only;");
+    println!("                                0.0.0.0 for every network)");
+}
+
+// --config (or DATAGEN_CONFIG) picks the file by name; otherwise generator.toml is used if it's there
+// This runs before parse_args since the config fills in defaults parse_args needs
+// DATAGEN_ variables beat the file, and flags beat both
+fn load_config(args: &[String], vars: &[(String, String)]) -> error::Result<Config> {
+    let named = vars.iter().find(|(name, _)| env_key(name).as_deref() == Some("config")).map(|(_, path)| path);
+    let mut config = match args.iter().position(|arg| arg == "--config") {
+        Some(i) => {
+            let path = args.get(i + 1).ok_or_else(|| usage_error("Missing value for --config"))?;
+            Config::load(path)?
+        },
+        None => match named {
+            Some(path) => Config::load(path)?,
+            None => Config::load_default()?,
+        },
+    };
+    config.apply_env(vars)?;
+    // --output-dir beats the config file's output_dir, like every other flag
+    if let Some(i) = args.iter().position(|arg| arg == "--output-dir") {
+        let dir = args.get(i + 1).ok_or_else(|| usage_error("Missing value for --output-dir"))?;
+        config.output_dir = Some(dir.clone());
+    }
+    Ok(config)
+}
+
+// -q/--quiet leaves only errors, -v/--verbose adds the debug lines (seed, buffer, timings),
+// and in between are errors, warnings and info (serve's line per request). The last one given
+// wins, like any other flag
+fn take_log_level(args: Vec<String>) -> (Vec<String>, LevelFilter) {
+    let mut level = LevelFilter::Info;
+    let args = args.into_iter().filter(|arg| match arg.as_str() {
+        "-q" | "--quiet" => {
+            level = LevelFilter::Error;
+            false
+        },
+        "-v" | "--verbose" => {
+            level = LevelFilter::Debug;
+            false
+        },
+        _ => true,
+    }).collect();
+    (args, level)
+}
+
+// Log lines go to stderr as "[debug] ...", so they never mix with data sent to stdout
+// Only our own - Parquet and SQLite have chatter of their own that nobody asked for
+fn init_logging(level: LevelFilter) {
+    env_logger::Builder::new()
+        .filter_level(LevelFilter::Error)
+        .filter_module("ruststf", level)
+        .format(|buf, record| writeln!(buf, "[{}] {}", record.level().as_str().to_lowercase(), record.args()))
+        .init();
+}
+
+// generate's settings from DATAGEN_ variables, as the flags they stand for (DATAGEN_FORMAT=csv
+// is --format csv), put in front of the real flags so those win. The ones the config file has
+// (type, count, seed, ...) go through Config instead, which reaches the menu's prompts too
+fn with_env_args(mut args: Vec<String>, vars: &[(String, String)]) -> error::Result<Vec<String>> {
+    let at = match args.first().map(String::as_str) {
+        Some("generate" | "bench") => 1,
+        // The menu and the other commands have flags of their own, or none
+        None | Some("inspect" | "verify" | "serve" | "shuffle" | "merge" | "split" | "convert" | "menu") => return Ok(args),
+        Some(_) => 0,
+    };
+    let mut settings: Vec<(String, &String, &String)> = vars.iter()
+        .filter_map(|(name, value)| Some((env_key(name)?, name, value)))
+        .filter(|(key, ..)| !is_config_key(key) && key != "config")
+        .collect();
+    // The environment comes in no particular order, so sort it to get the same flags every time
+    settings.sort();
+    let mut flags = Vec::new();
+    for (key, name, value) in settings {
+        let flag = format!("--{}", key.replace('_', "-"));
+        if !ENV_SWITCHES.contains(&key.as_str()) {
+            flags.extend([flag, value.clone()]);
+            continue;
+        }
+        match value.trim().to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => flags.push(flag),
+            "0" | "false" | "no" | "off" | "" => {},
+            _ => return Err(invalid_input(format!("{} is an on/off switch, so it has to be 1 or 0", name))),
+        }
+    }
+    args.splice(at..at, flags);
+    Ok(args)
+}
+
+// --manifest wins over the config file; with neither, the list only lasts for this run
+// Like --config, this has to be known before the other flags are parsed
+fn load_manifest(args: &[String], config: &Config) -> error::Result<Manifest> {
+    let path = match args.iter().position(|arg| arg == "--manifest") {
+        Some(i) => Some(args.get(i + 1).ok_or_else(|| usage_error("Missing value for --manifest"))?.clone()),
+        None => config.manifest.clone(),
+    };
+    let manifest = match path {
+        Some(path) => Manifest::load(&path)?,
+        None => Manifest::session(),
+    };
+    Ok(manifest.with_history(History::load(&history_path(config))?))
+}
+
+// Where past runs are logged: the config file can name a file, otherwise history.log here
+fn history_path(config: &Config) -> String {
+    config.history.clone().unwrap_or_else(|| HISTORY_FILE.to_string())
+}
+
+// The flags to log for a run, in a form that does the same thing again later - presets are
+// filled in (the preset might change in the meantime), and a dry run's question was already answered
+fn history_args(args: &[String], config: &Config) -> Vec<String> {
+    let args = expand_presets(args, config).unwrap_or_else(|_| args.to_vec());
+    let args = args.strip_prefix(&[String::from("generate")]).unwrap_or(&args);
+    args.iter().filter(|arg| *arg != "--dry-run").cloned().collect()
+}
+
+// Puts a relative filename in the configured output directory, creating the directory if needed
+fn in_output_dir(config: &Config, filename: &str) -> error::Result<String> {
+    if filename == STDOUT_NAME || Socket::parse(filename).is_some() {
+        return Ok(filename.to_string());
+    }
+    validate_filename(filename)?;
+    let path = config.output_path(filename)?;
+    // Whatever directory the file lands in gets created and checked before anything is generated
+    // A batch template can have {size} in the directory part, which isn't a real directory yet
+    let dir = Path::new(&path).parent().filter(|dir| !dir.as_os_str().is_empty());
+    if let Some(dir) = dir.filter(|dir| !dir.to_string_lossy().contains("{size}")) {
+        prepare_dir(dir)?;
+    }
+    Ok(path)
+}
+
+// Subcommands come first, like git's: generate, inspect, verify, bench, serve or menu
+// With none, the flags are for generate, so scripts written before subcommands still work
+fn parse_args(args: &[String], config: &Config) -> error::Result<Command> {
+    let rest = &args[1.min(args.len())..];
+    match args.first().map(String::as_str) {
+        Some("generate") => parse_generate_args(rest, config, false),
+        Some("bench") => parse_generate_args(rest, config, true),
+        Some("inspect") => Ok(Command::Display(needs_file("inspect", parse_file_args("inspect", rest)?.0)?)),
+        Some("verify") => match parse_file_args("verify", rest)? {
+            (filename, true) => Ok(Command::VerifyChecksum(needs_file("verify", filename)?)),
+            (filename, false) => Ok(Command::Verify(needs_file("verify", filename)?)),
+        },
+        Some("serve") => parse_serve_args(rest),
+        Some("shuffle") => parse_shuffle_args(rest, config),
+        Some("merge") => parse_merge_args(rest, config),
+        Some("split") => parse_split_args(rest, config),
+        Some("convert") => parse_convert_args(rest, config),
+        Some("menu") => parse_file_args("menu", rest).map(|_| Command::Menu),
+        _ => parse_generate_args(args, config, false),
+    }
+}
+
+// The arguments of inspect <file>, verify [--checksum] <file> and menu (which takes no file)
+// Returns the file, if there was one, and whether --checksum was given
+fn parse_file_args(command: &str, args: &[String]) -> error::Result<(Option<String>, bool)> {
+    let mut filename = None;
+    let mut checksum = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--checksum" if command == "verify" => checksum = true,
+            // Already handled by load_config and load_manifest, so just step over the value
+            "--config" | "--manifest" | "--output-dir" => {
+                next_value(&mut iter, arg)?;
+            },
+            _ if arg.starts_with('-') => return Err(usage_error(format!("Unknown option for {}: {}", command, arg))),
+            _ if filename.is_some() || command == "menu" => {
+                return Err(usage_error(format!("Too many arguments for {}: {}", command, arg)));
+            },
+            _ => filename = Some(arg.clone()),
+        }
+    }
+    Ok((filename, checksum))
+}
+
+fn needs_file(command: &str, filename: Option<String>) -> error::Result<String> {
+    filename.ok_or_else(|| invalid_input(format!("{} needs a file, e.g. ruststf {} data.txt", command, command)))
+}
+
+// Walks the argument list by hand - no need for a library for a handful of flags
+// bench is set for the bench subcommand, the same as giving --bench
+fn parse_generate_args(args: &[String], config: &Config, mut bench: bool) -> error::Result<Command> {
+    // --preset <name> is swapped for the flags it was saved with, so flags after it can override them
+    let args = &expand_presets(args, config)?;
+    let mut data_type = None;
+    let mut count = None;
+    let mut sizes = None;
+    let mut target_size = None;
+    let mut follow = None;
+    let mut min = None;
+    let mut max = None;
+    let mut exclude = Vec::new();
+    let mut length = None;
+    let mut sample_from = None;
+    let mut replace = true;
+    let mut dist_name = String::from("uniform");
+    let mut categories = None;
+    let mut mean = None;
+    let mut std_dev = None;
+    let mut lambda = None;
+    let mut exponent = None;
+    let mut start = None;
+    let mut step = None;
+    let mut jitter = None;
+    let mut order_name = String::from("random");
+    let mut disorder = 5.0;
+    let mut unique = false;
+    let mut permutation = None;
+    let mut duplicates = 0.0;
+    let mut outliers = 0.0;
+    let mut outlier_scale = generator::DEFAULT_OUTLIER_SCALE;
+    let mut missing = 0.0;
+    let mut missing_token = MissingToken::Empty;
+    let mut format = OutputFormat::Text;
+    let mut columns = 1;
+    let mut csv_header = false;
+    let mut header_style = HeaderStyle::Plain;
+    let mut line_layout = LineLayout::default();
+    let mut separator = ' ';
+    let mut schema = None;
+    let mut graph_nodes = None;
+    let mut series = TimeSeries::default();
+    let mut time_series = false;
+    let mut clusters = None;
+    let mut labels = false;
+    let mut shape = None;
+    let mut dimensions = None;
+    let mut radius = None;
+    let mut density = None;
+    let mut weighted = false;
+    let mut seed = None;
+    let mut stats = false;
+    let mut append_stats = false;
+    let mut histogram = false;
+    let mut progress = false;
+    let mut rate = None;
+    let mut threads = 1;
+    let mut streaming = false;
+    let mut if_exists = None;
+    let mut resume = false;
+    let mut on_interrupt = OnInterrupt::Finish;
+    let mut space_check = true;
+    let mut shards = 1;
+    let mut force = false;
+    let mut compress = false;
+    let mut framed = false;
+    let mut precision = None;
+    let mut scientific = false;
+    let mut number_format = NumberFormat::default();
+    let mut int_format = IntFormat::Decimal;
+    let mut time_format = TimeFormat::Iso;
+    let mut true_probability = None;
+    let mut bool_format = BoolFormat::Words;
+    let mut field_width = FieldWidth::default();
+    let mut endianness = Endianness::Little;
+    let mut language = None;
+    let mut cidr = None;
+    let mut checksum = false;
+    let mut buffer_size = DEFAULT_BUFFER_SIZE;
+    let mut mmap = false;
+    let mut table = None;
+    let mut filename = None;
+    let mut save_preset = None;
+    let mut dry_run = false;
+
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        match flag.as_str() {
+            "-t" | "--type" => data_type = Some(parse_data_type(next_value(&mut iter, flag)?)?),
+            "-n" | "--count" => count = Some(parse_count(next_value(&mut iter, flag)?)?),
+            "--sizes" => sizes = Some(parse_sizes(next_value(&mut iter, flag)?)?),
+            "--target-size" => target_size = Some(parse_target_size(next_value(&mut iter, flag)?)?),
+            "--follow" => follow = Some(parse_every(next_value(&mut iter, flag)?)?),
+            "--min" => min = Some(parse_bound(next_value(&mut iter, flag)?)?),
+            "--max" => max = Some(parse_bound(next_value(&mut iter, flag)?)?),
+            "--exclude" => exclude.extend(generator::parse_exclusions(next_value(&mut iter, flag)?)?),
+            "--length" => length = Some(parse_length(next_value(&mut iter, flag)?)?),
+            "--sample-from" => sample_from = Some(next_value(&mut iter, flag)?.clone()),
+            "--no-replacement" => replace = false,
+            "--dist" => dist_name = next_value(&mut iter, flag)?.clone(),
+            "--categories" => categories = Some(parse_categories(next_value(&mut iter, flag)?)?),
+            "--mean" => mean = Some(parse_param(next_value(&mut iter, flag)?)?),
+            "--std-dev" => std_dev = Some(parse_param(next_value(&mut iter, flag)?)?),
+            "--lambda" => lambda = Some(parse_param(next_value(&mut iter, flag)?)?),
+            "--exponent" => exponent = Some(parse_param(next_value(&mut iter, flag)?)?),
+            "--start" => start = Some(parse_bound(next_value(&mut iter, flag)?)?),
+            "--step" => step = Some(parse_param(next_value(&mut iter, flag)?)?),
+            "--jitter" => jitter = Some(parse_param(next_value(&mut iter, flag)?)?),
+            "--order" => order_name = next_value(&mut iter, flag)?.clone(),
+            "--disorder" => disorder = parse_param(next_value(&mut iter, flag)?)?,
+            "--unique" => unique = true,
+            "--permutation" => permutation = Some(parse_count(next_value(&mut iter, flag)?)?),
+            "--duplicates" => duplicates = parse_param(next_value(&mut iter, flag)?)?,
+            "--outliers" => outliers = parse_param(next_value(&mut iter, flag)?)?,
+            "--outlier-scale" => outlier_scale = parse_param(next_value(&mut iter, flag)?)?,
+            "--missing" => missing = parse_param(next_value(&mut iter, flag)?)?,
+            "--missing-as" => missing_token = parse_missing_token(next_value(&mut iter, flag)?)?,
+            "-f" | "--format" => format = parse_format(next_value(&mut iter, flag)?)?,
+            "--columns" => columns = parse_columns(next_value(&mut iter, flag)?)?,
+            "--header" => csv_header = true,
+            "--no-header" => header_style = HeaderStyle::Omit,
+            "--comment-header" => header_style = HeaderStyle::Comment,
+            "--header-template" => header_style = HeaderStyle::Template(next_value(&mut iter, flag)?.clone()),
+            "--delimiter" => line_layout.delimiter = parse_delimiter(next_value(&mut iter, flag)?),
+            "--prefix" => line_layout.prefix = next_value(&mut iter, flag)?.clone(),
+            "--suffix" => line_layout.suffix = next_value(&mut iter, flag)?.clone(),
+            "--separator" => separator = parse_separator(next_value(&mut iter, flag)?)?,
+            "--width" => field_width.width = parse_width(next_value(&mut iter, flag)?)?,
+            "--fill" => field_width.fill = parse_fill(next_value(&mut iter, flag)?)?,
+            "--align" => field_width.align = parse_align(next_value(&mut iter, flag)?)?,
+            "--endianness" | "--endian" => endianness = parse_endianness(next_value(&mut iter, flag)?)?,
+            "--language" => language = Some(parse_language(next_value(&mut iter, flag)?)?),
+            "--schema" => schema = Some(parse_schema(next_value(&mut iter, flag)?)?),
+            "--graph" => graph_nodes = Some(parse_count(next_value(&mut iter, flag)?)?),
+            // A graph's count is its edges, so --edges is just the clearer name for it
+            "--edges" => count = Some(parse_count(next_value(&mut iter, flag)?)?),
+            "--density" => density = Some(parse_param(next_value(&mut iter, flag)?)?),
+            "--weighted" => weighted = true,
+            "--time-series" => time_series = true,
+            // Each setting of a series is enough to ask for one, like --density is for a graph
+            "--from" => {
+                series.start = parse_bound(next_value(&mut iter, flag)?)? as i64;
+                time_series = true;
+            },
+            "--interval" => {
+                series.interval = parse_interval(next_value(&mut iter, flag)?)?;
+                time_series = true;
+            },
+            "--base" | "--trend" | "--amplitude" | "--period" | "--noise" => {
+                let value = parse_param(next_value(&mut iter, flag)?)?;
+                match flag.as_str() {
+                    "--base" => series.base = value,
+                    "--trend" => series.trend = value,
+                    "--amplitude" => series.amplitude = value,
+                    "--period" => series.period = value,
+                    _ => series.noise = value,
+                }
+                time_series = true;
+            },
+            "--mixture" => clusters = Some(parse_clusters(next_value(&mut iter, flag)?)?),
+            "--labels" => labels = true,
+            "--points" => shape = Some(next_value(&mut iter, flag)?.to_lowercase()),
+            "--dimensions" | "--dims" => dimensions = Some(parse_dimensions(next_value(&mut iter, flag)?)?),
+            "--radius" => radius = Some(parse_param(next_value(&mut iter, flag)?)?),
+            "--seed" => seed = Some(parse_seed(next_value(&mut iter, flag)?)?),
+            "--stats" => stats = true,
+            "--append-stats" => {
+                stats = true;
+                append_stats = true;
+            },
+            "--histogram" => {
+                stats = true;
+                histogram = true;
+            },
+            "--progress" => progress = true,
+            "--rate" => rate = Some(parse_rate(next_value(&mut iter, flag)?)?),
+            "--threads" => threads = parse_threads(next_value(&mut iter, flag)?)?,
+            "--stream" => streaming = true,
+            "--gzip" => compress = true,
+            "--framed" => framed = true,
+            "--precision" => precision = Some(parse_precision(next_value(&mut iter, flag)?)?),
+            "--scientific" => scientific = true,
+            "--number-style" => number_format = parse_number_style(next_value(&mut iter, flag)?)?,
+            "--decimal" => {
+                number_format.decimal = parse_separator_name(next_value(&mut iter, flag)?)?
+                    .ok_or_else(|| invalid_input("Decimal separator must be a point or a comma"))?;
+            },
+            "--int-format" => int_format = parse_int_format(next_value(&mut iter, flag)?)?,
+            "--thousands" => number_format.thousands = parse_separator_name(next_value(&mut iter, flag)?)?,
+            "--time-format" => time_format = parse_time_format(next_value(&mut iter, flag)?)?,
+            "--true-probability" => true_probability = Some(parse_probability(next_value(&mut iter, flag)?)?),
+            "--bool-format" => bool_format = parse_bool_format(next_value(&mut iter, flag)?)?,
+            "--cidr" => cidr = Some(parse_cidr(next_value(&mut iter, flag)?)?),
+            "--checksum" => checksum = true,
+            "--bench" => bench = true,
+            "--buffer-size" => buffer_size = parse_buffer_size(next_value(&mut iter, flag)?)?,
+            "--mmap" => mmap = true,
+            "--if-exists" => if_exists = Some(parse_if_exists(next_value(&mut iter, flag)?)?),
+            "--force" => force = true,
+            "--resume" => resume = true,
+            "--on-interrupt" => on_interrupt = parse_on_interrupt(next_value(&mut iter, flag)?)?,
+            "--no-space-check" => space_check = false,
+            "--dry-run" => dry_run = true,
+            "--shards" => shards = parse_shards(next_value(&mut iter, flag)?)?,
+            "-o" | "--output" => filename = Some(next_value(&mut iter, flag)?.clone()),
+            "--table" => table = Some(next_value(&mut iter, flag)?.clone()),
+            "--read" => return Ok(Command::Display(next_value(&mut iter, flag)?.clone())),
+            "--verify" => return Ok(Command::Verify(next_value(&mut iter, flag)?.clone())),
+            "--verify-checksum" => return Ok(Command::VerifyChecksum(next_value(&mut iter, flag)?.clone())),
+            "--clean" => return Ok(Command::Clean),
+            "--save-preset" => {
+                let name = next_value(&mut iter, flag)?;
+                preset::validate_name(name)?;
+                save_preset = Some(name.clone());
+            },
+            // Already handled by load_config and load_manifest, so just step over the value
+            "--config" | "--manifest" | "--output-dir" => {
+                next_value(&mut iter, flag)?;
+            },
+            _ => return Err(usage_error(format!("Unknown option: {}", flag))),
+        }
+    }
+
+    // A graph's only values are its edge weights, so --type and the range describe those
+    let graph = match graph_nodes {
+        Some(nodes) => {
+            if let Some(density) = density {
+                if count.is_some() {
+                    return Err(invalid_input("Use either --edges or --density, not both"));
+                }
+                count = Some(graph::edges_for_density(nodes, density)?);
+            }
+            if !weighted && (data_type.is_some() || min.is_some() || max.is_some()) {
+                return Err(invalid_input("--type, --min and --max set the edge weights, so they need --weighted"));
+            }
+            data_type = data_type.or(Some(DataType::Integer));
+            min = min.or(Some(graph::DEFAULT_WEIGHTS.0));
+            max = max.or(Some(graph::DEFAULT_WEIGHTS.1));
+            Some(Graph { nodes, weighted })
+        },
+        None if density.is_some() || weighted => return Err(invalid_input("--density and --weighted need --graph <nodes>")),
+        None => None,
+    };
+    // A series writes its own two fields, and --type is for the values (doubles unless it says otherwise)
+    if time_series {
+        if schema.is_some() || graph.is_some() {
+            return Err(invalid_input("A time series has its own time and value fields, so it can't have --schema or --graph"));
+        }
+        data_type = data_type.or(Some(DataType::Double));
+    }
+    // --points mixture is the long way of saying --mixture, and the rest are clouds of their own
+    let points = match shape.as_deref() {
+        Some("mixture") if clusters.is_none() => return Err(invalid_input("--points mixture needs --mixture with the clusters")),
+        Some("mixture") | None if dimensions.is_some() || radius.is_some() => {
+            return Err(invalid_input("--dimensions and --radius are for --points box, circle or sphere"));
+        },
+        Some("mixture") | None => None,
+        Some(_) if clusters.is_some() => return Err(invalid_input("--mixture makes its own points, so leave out --points or say --points mixture")),
+        Some(_) if schema.is_some() || graph.is_some() || time_series => {
+            return Err(invalid_input("Points have their own coordinate fields, so they can't have --schema, --graph or a time series"));
+        },
+        Some(name) => {
+            let (shape, default_dimensions) = match name {
+                "box" | "cube" | "square" => (Shape::Box, 2),
+                "circle" => (Shape::Sphere, 2),
+                "sphere" => (Shape::Sphere, 3),
+                other => return Err(invalid_input(format!("Unknown points: {} (box, circle, sphere or mixture)", other))),
+            };
+            if shape == Shape::Box && radius.is_some() {
+                return Err(invalid_input("A box has no radius - --min and --max set its sides"));
+            }
+            if shape == Shape::Sphere && (dimensions.is_some() || min.is_some() || max.is_some()) {
+                return Err(invalid_input("A circle is 2D and a sphere 3D, and --radius sets their size, so leave out --dimensions, --min and --max"));
+            }
+            data_type = data_type.or(Some(DataType::Double));
+            Some(Points { shape, dimensions: dimensions.unwrap_or(default_dimensions), radius: radius.unwrap_or(1.0) })
+        },
+    };
+    // The same goes for a mixture's coordinates
+    let mixture = match clusters {
+        Some(_) if schema.is_some() || graph.is_some() || time_series => {
+            return Err(invalid_input("A mixture has its own coordinate fields, so it can't have --schema, --graph or a time series"));
+        },
+        Some(clusters) => {
+            data_type = data_type.or(Some(DataType::Double));
+            Some(Mixture { clusters, labeled: labels })
+        },
+        None if labels => return Err(invalid_input("--labels writes which cluster each point came from, so it needs --mixture")),
+        None => None,
+    };
+    // A schema gives every field its own type, so --type would have nothing to do
+    if schema.is_some() {
+        if data_type.is_some() {
+            return Err(invalid_input("--schema sets a type for each field, so leave out --type"));
+        }
+        data_type = Some(DataType::Integer);
+    }
+    // Benchmarks don't need a type, count or file, so they get defaults for all three
+    if bench {
+        sizes = sizes.or(Some(parse_sizes(BENCH_SIZES)?));
+        data_type = data_type.or(config.data_type).or(Some(DataType::Integer));
+        filename = filename.or_else(|| Some(env::temp_dir().join(BENCH_FILE).to_string_lossy().into_owned()));
+    }
+    // A block is enough to ask for addresses of its version, the way --categories picks the distribution
+    if let Some(cidr) = cidr {
+        data_type = data_type.or(Some(if cidr.is_ipv4() { DataType::Ipv4 } else { DataType::Ipv6 }));
+    }
+    // A permutation's size is its count and sets its range, which starts at 0 unless --min says otherwise
+    if let Some(size) = permutation {
+        if count.is_some() || sizes.is_some() || target_size.is_some() || max.is_some() || follow.is_some() {
+            return Err(invalid_input("--permutation <n> sets the count and range, so leave out --count, --sizes, --target-size, --max and --follow"));
+        }
+        if columns > 0 && size % columns != 0 {
+            return Err(invalid_input(format!("{} numbers don't fill rows of {} columns evenly", size, columns)));
+        }
+        let start = min.unwrap_or(0.0);
+        let end = start + size as f64 - 1.0;
+        (count, min, max) = (Some(size / columns.max(1)), Some(start), Some(end));
+        // Past what an int holds, so longs
+        let fits_int = start >= i32::MIN as f64 && end <= i32::MAX as f64;
+        data_type = data_type.or(Some(if fits_int { DataType::Integer } else { DataType::Long }));
+    }
+    // A pool brings its own type, range and size - with no --count the file is resampled at the
+    // size it already is, which is what a bootstrap does
+    let pool = match sample_from {
+        Some(path) => {
+            if data_type.is_some() || min.is_some() || max.is_some() || length.is_some() {
+                return Err(invalid_input("--sample-from takes the type and range from the file, so leave out --type, --min, --max and --length"));
+            }
+            let sampling = if replace { Sampling::WithReplacement } else { Sampling::WithoutReplacement };
+            let pool = Pool::load(&path, sampling)?;
+            data_type = Some(pool.data_type);
+            if sizes.is_none() && count.is_none() && target_size.is_none() && config.count.is_none() {
+                let size = u32::try_from(pool.values.len()).map_err(|_| invalid_input(format!("{} is too big to resample whole, so give --count", path)))?;
+                count = Some(size);
+            }
+            Some(pool)
+        },
+        None if !replace => return Err(invalid_input("--no-replacement needs --sample-from <file>")),
+        None => None,
+    };
+    // Option is Rust's answer to NULL - ok_or turns a missing value into an error
+    let data_type = data_type.or(config.data_type).ok_or_else(|| invalid_input("Missing --type"))?;
+    // Anything not given on the command line falls back to the config file, then the type's default
+    let (default_min, default_max) = pool.as_ref().map_or_else(|| config.range_for(data_type), Pool::range);
+    let precision = precision.or(config.precision_for(data_type));
+    let (min_given, max_given) = (min.is_some(), max.is_some());
+    let mut min = min.unwrap_or(default_min);
+    let mut max = max.unwrap_or(default_max);
+    // For strings and blobs the range is the length, so --length is just a friendlier way to set it
+    if let Some((shortest, longest)) = length {
+        if !data_type.has_length() {
+            return Err(invalid_input("--length only applies to --type string and bytes"));
+        }
+        (min, max) = (shortest, longest);
+    }
+    if true_probability.is_some() && !data_type.is_boolean() {
+        return Err(invalid_input("--true-probability only applies to --type bool"));
+    }
+    if !data_type.has_range() && (min_given || max_given) && graph.is_none() {
+        return Err(invalid_input(format!("The {} type has no range, so leave out --min and --max", data_type.name())));
+    }
+    // These all need the count before anything is written, or take it from the file already there
+    let sequence = start.is_some() || step.is_some() || jitter.is_some() || parse_distribution_name(&dist_name)? == "sequence";
+    if target_size.is_some() && (resume || shards > 1 || if_exists == Some(IfExists::Append) || graph.is_some() || time_series || sequence) {
+        return Err(invalid_input("--target-size can't be used with --resume, --shards, appending, graphs, sequences or time series"));
+    }
+    if follow.is_some() && (sizes.is_some() || target_size.is_some()) {
+        return Err(invalid_input("--follow keeps adding to one file until it's stopped, so it can't be used with --sizes or --target-size"));
+    }
+    // In batch mode the checks below run against the biggest file, so --unique is
+    // rejected up front if even one of the files couldn't be filled
+    let count = match (&sizes, count, target_size) {
+        (Some(_), Some(_), _) => return Err(invalid_input("Use either --count or --sizes, not both")),
+        (Some(_), _, Some(_)) | (_, Some(_), Some(_)) => {
+            return Err(invalid_input("--target-size works the count out itself, so leave out --count and --sizes"));
+        },
+        (Some(sizes), None, None) => largest_size(sizes),
+        // Worked out just before writing (see generate_to_size), once everything else is known
+        (None, None, Some(_)) => 1,
+        // No count means no end - it runs until Ctrl-C
+        (None, None, None) if follow.is_some() => u32::MAX,
+        (None, count, None) => count.or(config.count).ok_or_else(|| invalid_input("Missing --count"))?,
+    };
+    let (default_mean, default_std_dev) = generator::default_normal(min, max);
+    // Listing categories is enough to ask for the categorical distribution,
+    // and a start, step or jitter is enough to ask for a sequence
+    if categories.is_some() && parse_distribution_name(&dist_name)? == "uniform" {
+        dist_name = String::from("categorical");
+    }
+    if (start.is_some() || step.is_some() || jitter.is_some()) && parse_distribution_name(&dist_name)? == "uniform" {
+        dist_name = String::from("sequence");
+    }
+    if exponent.is_some() && parse_distribution_name(&dist_name)? == "uniform" {
+        dist_name = String::from("zipf");
+    }
+    // A sequence starts at the bottom of the range - with a top given too it walks across
+    // to it, otherwise it counts up by one and the range grows to fit (below)
+    let total = count as u64 * columns as u64;
+    let start = start.unwrap_or(min);
+    let step = step.unwrap_or(if max_given { generator::sequence_step(start, max, total) } else { 1.0 });
+    let distribution = build_distribution(
+        &dist_name,
+        categories.unwrap_or_default(),
+        mean.unwrap_or(default_mean),
+        std_dev.unwrap_or(default_std_dev),
+        lambda.unwrap_or(1.0),
+        exponent.unwrap_or(1.0),
+        (start, step, jitter.unwrap_or(0.0)),
+    )?;
+    if let Distribution::Sequence { start, step, jitter } = distribution {
+        (min, max) = fit_sequence(data_type, (start, step, jitter), total, min_given.then_some(min), max_given.then_some(max));
+    }
+    // The range clips a series the same way, and grows to fit it when it isn't given
+    let time_series = match time_series {
+        true if !(data_type.has_decimals() || (data_type.is_integer() && !data_type.is_timestamp())) => {
+            return Err(invalid_input("Time series values must be a number type"));
+        },
+        true => {
+            let (lowest, highest) = whole_range(data_type, series.value_range(count));
+            (min, max) = (if min_given { min } else { lowest }, if max_given { max } else { highest });
+            schema = Some(series.fields(data_type, min, max, count));
+            // The rows are records, which text can't hold, so CSV is the plain-text stand-in
+            if format == OutputFormat::Text {
+                format = OutputFormat::Csv;
+            }
+            Some(series)
+        },
+        false => None,
+    };
+    if let Some(mixture) = &mixture {
+        if !(data_type.has_decimals() || (data_type.is_integer() && !data_type.is_timestamp())) {
+            return Err(invalid_input("Mixture coordinates must be a number type"));
+        }
+        mixture::validate_mixture(mixture)?;
+        let (lowest, highest) = whole_range(data_type, mixture.value_range());
+        (min, max) = (if min_given { min } else { lowest }, if max_given { max } else { highest });
+        schema = Some(mixture.fields(data_type, min, max));
+        if format == OutputFormat::Text {
+            format = OutputFormat::Csv;
+        }
+    }
+    if let Some(points) = points {
+        if points.shape == Shape::Sphere && !data_type.has_decimals() {
+            return Err(invalid_input("Points on a circle or sphere are hardly ever whole numbers, so they need --type float or double"));
+        }
+        if !(data_type.has_decimals() || (data_type.is_integer() && !data_type.is_timestamp())) {
+            return Err(invalid_input("Point coordinates must be a number type"));
+        }
+        points::validate_points(points)?;
+        let (lowest, highest) = points.value_range(data_type);
+        (min, max) = (if min_given { min } else { lowest }, if max_given { max } else { highest });
+        schema = Some(points.fields(data_type, min, max));
+        if format == OutputFormat::Text {
+            format = OutputFormat::Csv;
+        }
+    }
+    let format = with_language(format, language)?;
+    // Every field has a flag so far, but a new one can start out at its default before it gets one
+    #[allow(clippy::needless_update)]
+    let mut options = Options {
+        data_type,
+        count,
+        min,
+        max,
+        exclude,
+        distribution,
+        order: build_order(&order_name, disorder)?,
+        unique,
+        permutation: permutation.is_some(),
+        duplicates,
+        outliers,
+        outlier_scale,
+        missing,
+        missing_token,
+        format,
+        columns,
+        csv_header,
+        header_style,
+        line_layout,
+        separator,
+        field_width,
+        endianness,
+        schema: schema.unwrap_or_default(),
+        graph,
+        time_series,
+        mixture,
+        points,
+        pool,
+        seed: seed.or(config.seed),
+        stats,
+        append_stats,
+        histogram,
+        progress,
+        rate,
+        threads,
+        streaming,
+        // A followed file is added to unless it's said otherwise, the way a log is
+        if_exists: if_exists.unwrap_or(if follow.is_some() { IfExists::Append } else { IfExists::Overwrite }),
+        resume,
+        on_interrupt,
+        space_check,
+        shards,
+        compress,
+        framed,
+        precision,
+        scientific,
+        number_format,
+        int_format,
+        time_format,
+        true_probability: true_probability.unwrap_or(0.5),
+        bool_format,
+        cidr,
+        checksum,
+        buffer_size,
+        mmap,
+        table,
+        // A preset can leave the file to be named when it's used, so it's checked with a stand-in
+        filename: match (filename, &save_preset) {
+            (Some(filename), _) => filename,
+            (None, Some(_)) => String::from("data.txt"),
+            (None, None) => return Err(invalid_input("Missing --output")),
+        },
+        ..Options::default()
+    };
+    // Naming the file .gz is enough to ask for compression, and --gzip makes sure the name says so
+    options.filename = in_output_dir(config, &options.filename)?;
+    options.compress |= options.filename.ends_with(".gz");
+    options.filename = gz_name(&options.filename, options.compress);
+    // Catch bad combinations now, before any file gets created
+    // --follow's own checks go first, since it quietly turned on appending
+    if follow.is_some() {
+        check_follow(&options)?;
+    }
+    options.validate()?;
+    // Only checked, not run: the flags are kept as typed (with any presets they used filled in)
+    if let Some(name) = save_preset {
+        return Ok(Command::SavePreset(name, preset_args(args)));
+    }
+    if dry_run && (bench || resume) {
+        return Err(invalid_input("--dry-run estimates a fresh file, so it can't be used with --bench or --resume"));
+    }
+    if bench {
+        if options.writes_to_stdout() || options.socket().is_some() || options.writes_to_fifo() || writes_blob_files(&options) {
+            return Err(invalid_input("--bench needs a real file to time writing to"));
+        }
+        // bench is set, so sizes was filled in above
+        return Ok(Command::Bench(options, sizes.unwrap_or_default()));
+    }
+    if resume && sizes.is_some() {
+        return Err(invalid_input("--resume picks up one file, so it can't be used with --sizes"));
+    }
+    // Nobody said what to do about existing files, so ask if someone's at the keyboard
+    // Resuming is all about the existing file, so there's nothing to ask then, and a dry run
+    // with nobody there to confirm it never writes anything
+    let will_write = !dry_run || io::stdin().is_terminal();
+    if if_exists.is_none() && !force && !resume && follow.is_none() && will_write {
+        let targets: Vec<String> = match &sizes {
+            Some(sizes) => sizes.iter()
+                .flat_map(|size| output_files(&options.filename.replace("{size}", &size.label), options.shards))
+                .collect(),
+            None => output_files(&options.filename, options.shards),
+        };
+        let mut taken = Vec::new();
+        for file in &targets {
+            taken.extend(existing_target(file, &options)?);
+        }
+        options.if_exists = ask_if_exists(&taken)?;
+    }
+    let command = match (sizes, target_size, follow) {
+        (Some(sizes), ..) => {
+            check_template(&options.filename)?;
+            Command::Batch(options, sizes)
+        },
+        (None, Some(target), _) => Command::ToSize(options, target),
+        (None, None, Some(every)) => Command::Follow(options, every),
+        (None, None, None) => Command::Generate(options),
+    };
+    Ok(if dry_run { Command::DryRun(Box::new(command)) } else { command })
+}
+
+// Where presets are saved: the config file can name a file, otherwise presets.toml here
+fn presets_path(config: &Config) -> String {
+    config.presets.clone().unwrap_or_else(|| PRESETS_FILE.to_string())
+}
+
+fn expand_presets(args: &[String], config: &Config) -> error::Result<Vec<String>> {
+    // Most runs don't use presets, so don't go looking for the file unless asked
+    if !args.iter().any(|arg| arg == "--preset") {
+        return Ok(args.to_vec());
+    }
+    let presets = Presets::load(&presets_path(config))?;
+    let mut expanded = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--preset" {
+            let name = next_value(&mut iter, arg)?;
+            expanded.extend(presets.get(name)?.args.iter().cloned());
+        } else {
+            expanded.push(arg.clone());
+        }
+    }
+    Ok(expanded)
+}
+
+// The flags worth keeping in a preset: everything but --save-preset itself, and the flags that
+// are read before any preset is filled in (so they'd do nothing when it's used)
+fn preset_args(args: &[String]) -> Vec<String> {
+    let mut kept = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--save-preset" | "--config" | "--manifest" | "--output-dir" => {
+                iter.next();
+            },
+            _ => kept.push(arg.clone()),
+        }
+    }
+    kept
+}
+
+fn save_preset(out: &mut impl Write, config: &Config, name: &str, args: &[String]) -> error::Result<()> {
+    let mut presets = Presets::load(&presets_path(config))?;
+    presets.save(name, args)?;
+    writeln!(out, "Saved preset {} to {} (use it with --preset {})", name, presets.path(), name)?;
+    Ok(())
+}
+
+fn parse_serve_args(args: &[String]) -> error::Result<Command> {
+    let mut port = DEFAULT_PORT;
+    // Only this machine can connect unless asked otherwise, since anyone who can reach the port gets to use the CPU
+    let mut bind = String::from("127.0.0.1");
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        match flag.as_str() {
+            "--port" => {
+                let value = next_value(&mut iter, flag)?;
+                port = value.parse().map_err(|_| invalid_input(format!("Invalid port: {}", value)))?;
+            },
+            "--bind" => bind = next_value(&mut iter, flag)?.clone(),
+            // Already handled by load_config, and requests still use its defaults
+            "--config" | "--manifest" => {
+                next_value(&mut iter, flag)?;
+            },
+            _ => return Err(usage_error(format!("Unknown option for serve: {}", flag))),
+        }
+    }
+    Ok(Command::Serve(format!("{}:{}", bind, port)))
+}
+
+// The arguments of shuffle <file> --output <file> [--seed <n>] [--force]
+fn parse_shuffle_args(args: &[String], config: &Config) -> error::Result<Command> {
+    let mut input = None;
+    let mut output = None;
+    let mut seed = None;
+    let mut force = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(next_value(&mut iter, arg)?.clone()),
+            "--seed" => seed = Some(parse_seed(next_value(&mut iter, arg)?)?),
+            "--force" => force = true,
+            // Already handled by load_config and load_manifest, so just step over the value
+            "--config" | "--manifest" | "--output-dir" => {
+                next_value(&mut iter, arg)?;
+            },
+            _ if arg.starts_with('-') => return Err(usage_error(format!("Unknown option for shuffle: {}", arg))),
+            _ if input.is_some() => return Err(usage_error(format!("Too many arguments for shuffle: {}", arg))),
+            _ => input = Some(arg.clone()),
+        }
+    }
+    let input = needs_file("shuffle", input)?;
+    // Giving the same name twice shuffles the file in place, so it's never assumed
+    let output = output.ok_or_else(|| invalid_input("Missing --output (give the file's own name to shuffle it in place)"))?;
+    Ok(Command::Shuffle(input, in_output_dir(config, &output)?, seed, force))
+}
+
+// The arguments of merge <file>... --output <file> [--interleave | --order asc|desc] [--force]
+fn parse_merge_args(args: &[String], config: &Config) -> error::Result<Command> {
+    let mut inputs = Vec::new();
+    let mut output = None;
+    let mut order = MergeOrder::Concatenate;
+    let mut force = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(next_value(&mut iter, arg)?.clone()),
+            "--interleave" => order = MergeOrder::Interleave,
+            // The same names as generate's --order, though only the two sorted ones mean anything here
+            "--order" => order = match build_order(next_value(&mut iter, arg)?, 0.0)? {
+                SortOrder::Ascending => MergeOrder::Ascending,
+                SortOrder::Descending => MergeOrder::Descending,
+                _ => return Err(invalid_input("Merged files can only be sorted ascending or descending")),
+            },
+            "--force" => force = true,
+            // Already handled by load_config and load_manifest, so just step over the value
+            "--config" | "--manifest" | "--output-dir" => {
+                next_value(&mut iter, arg)?;
+            },
+            _ if arg.starts_with('-') => return Err(usage_error(format!("Unknown option for merge: {}", arg))),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+    if inputs.is_empty() {
+        return Err(invalid_input("merge needs the files to merge, e.g. ruststf merge a.txt b.txt -o all.txt"));
+    }
+    let output = output.ok_or_else(|| invalid_input("Missing --output"))?;
+    Ok(Command::Merge(inputs, in_output_dir(config, &output)?, order, force))
+}
+
+// The arguments of split <file> --parts <k> | --rows <m> [--output <name>] [--force]
+fn parse_split_args(args: &[String], config: &Config) -> error::Result<Command> {
+    let mut input = None;
+    let mut output = None;
+    let mut size = None;
+    let mut force = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(next_value(&mut iter, arg)?.clone()),
+            "--parts" => size = Some(SplitSize::Parts(parse_shards(next_value(&mut iter, arg)?)?)),
+            "--rows" | "--lines" => {
+                let value = next_value(&mut iter, arg)?;
+                let rows = value.parse().ok().filter(|&rows| rows > 0)
+                    .ok_or_else(|| invalid_input(format!("Rows per part must be a positive number, not {}", value)))?;
+                size = Some(SplitSize::Rows(rows));
+            },
+            "--force" => force = true,
+            // Already handled by load_config and load_manifest, so just step over the value
+            "--config" | "--manifest" | "--output-dir" => {
+                next_value(&mut iter, arg)?;
+            },
+            _ if arg.starts_with('-') => return Err(usage_error(format!("Unknown option for split: {}", arg))),
+            _ if input.is_some() => return Err(usage_error(format!("Too many arguments for split: {}", arg))),
+            _ => input = Some(arg.clone()),
+        }
+    }
+    let input = needs_file("split", input)?;
+    let size = size.ok_or_else(|| invalid_input("split needs --parts <k> or --rows <m>"))?;
+    // Without a name the parts go next to the file, named after it
+    let output = match output {
+        Some(output) => in_output_dir(config, &output)?,
+        None => input.clone(),
+    };
+    Ok(Command::Split(input, output, size, force))
+}
+
+// The arguments of convert <file> --format <format> --output <file> and the few output flags
+// that make sense for values that already exist
+fn parse_convert_args(args: &[String], config: &Config) -> error::Result<Command> {
+    let mut input = None;
+    let mut output = None;
+    let mut format = None;
+    let mut columns = 1;
+    let mut csv_header = false;
+    let mut endianness = Endianness::Little;
+    let mut language = None;
+    let mut precision = None;
+    let mut compress = false;
+    let mut force = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(next_value(&mut iter, arg)?.clone()),
+            "-f" | "--format" => format = Some(parse_format(next_value(&mut iter, arg)?)?),
+            "--columns" => columns = parse_columns(next_value(&mut iter, arg)?)?,
+            "--header" => csv_header = true,
+            "--endianness" | "--endian" => endianness = parse_endianness(next_value(&mut iter, arg)?)?,
+            "--language" => language = Some(parse_language(next_value(&mut iter, arg)?)?),
+            "--precision" => precision = Some(parse_precision(next_value(&mut iter, arg)?)?),
+            "--gzip" => compress = true,
+            "--force" => force = true,
+            // Already handled by load_config and load_manifest, so just step over the value
+            "--config" | "--manifest" | "--output-dir" => {
+                next_value(&mut iter, arg)?;
+            },
+            _ if arg.starts_with('-') => return Err(usage_error(format!("Unknown option for convert: {}", arg))),
+            _ if input.is_some() => return Err(usage_error(format!("Too many arguments for convert: {}", arg))),
+            _ => input = Some(arg.clone()),
+        }
+    }
+    let input = needs_file("convert", input)?;
+    // Guessing either one from the other would be a coin toss, so both have to be given
+    let format = format.ok_or_else(|| invalid_input("convert needs --format <format> for the new file"))?;
+    let format = with_language(format, language)?;
+    let output = output.ok_or_else(|| invalid_input("Missing --output (give the file's own name to convert it in place)"))?;
+    // Everything about the values themselves gets filled in from the file by convert_options
+    let options = Options {
+        count: 0,
+        min: 0.0,
+        max: 0.0,
+        format,
+        columns,
+        csv_header,
+        endianness,
+        if_exists: if force { IfExists::Overwrite } else { IfExists::Abort },
+        compress,
+        precision,
+        filename: in_output_dir(config, &output)?,
+        ..Options::default()
+    };
+    Ok(Command::Convert(input, options))
+}
+
+// Grabs the value that follows a flag like --count, or complains if there isn't one
+fn next_value<'a>(iter: &mut impl Iterator<Item = &'a String>, flag: &str) -> error::Result<&'a String> {
+    iter.next().ok_or_else(|| usage_error(format!("Missing value for {}", flag)))
+}
+
+// In C we'd return -1 for errors. Here we use Result to handle success/failure
+fn get_choice(console: &mut Console<impl BufRead, impl Write>) -> error::Result<i32> {
+    let input = console.read_line("")?; // ? is a shorthand for error handling
+    Ok(input.parse().unwrap_or(-1)) 
+}
+
+// In C we might use chars for this. Rust uses pattern matching which is cleaner
+// With a type in the config file, pressing enter picks it
+fn get_data_type(console: &mut Console<impl BufRead, impl Write>, default: Option<DataType>) -> error::Result<DataType> {
+    console.explain("Data types: i = int (i32), f = float (f32), s = short (i16),")?;
+    console.explain("            l = long (i64), u = unsigned (u32), d = double (f64),")?;
+    console.explain("            t = string (random letters and digits), ts = timestamp (date and time),")?;
+    console.explain("            bytes = random bytes (hex, or a file each for binary output),")?;
+    console.explain("            b = boolean (true or false), uuid = UUID (random version 4 identifier),")?;
+    console.explain("            ip4 = IPv4 address, ip6 = IPv6 address,")?;
+    console.explain("            first, last, email, phone = made-up names and contact details")?;
+    let prompt = match default {
+        Some(data_type) => format!("Enter data type (default {}): ", data_type.name()),
+        None => String::from("Enter data type: "),
+    };
+    console.with_b_answer(|console| console.read_valid(&prompt, |input| match default {
+        Some(data_type) if input.is_empty() => Ok(data_type),
+        _ => parse_data_type(input),
+    }))
+}
+
+// Gets a positive number from user - u32 is like unsigned int
+// A count from the config (or DATAGEN_COUNT) is what a blank answer gets
+fn get_element_count(console: &mut Console<impl BufRead, impl Write>, default: Option<u32>) -> error::Result<u32> {
+    let prompt = match default {
+        Some(count) => format!("Enter number of elements (default {}): ", count),
+        None => String::from("Enter number of elements: "),
+    };
+    console.read_valid(&prompt, |input| match default {
+        Some(count) if input.is_empty() => Ok(count),
+        _ => parse_count(input),
+    })
+}
+
+// A file size for --target-size, like 1G, 500MB or 750k - decimal units, so 1G is 1,000,000,000 bytes
+fn parse_target_size(input: &str) -> error::Result<u64> {
+    let trimmed = input.trim();
+    let number = trimmed.strip_suffix(['B', 'b']).unwrap_or(trimmed);
+    match parse_suffixed(number, "bytes")? {
+        0 => Err(invalid_input("--target-size must be bigger than nothing")),
+        bytes => Ok(bytes),
+    }
+}
+
+// Asks for one end of the range - pressing enter keeps the default
+fn get_bound(console: &mut Console<impl BufRead, impl Write>, prompt: &str, default: f64) -> error::Result<f64> {
+    let input = console.read_line(&format!("{} (default {}): ", prompt, default))?;
+    if input.is_empty() {
+        return Ok(default);
+    }
+    parse_bound(&input)
+}
+
+// Asks how long strings should be - one number for a fixed length, or a range like 8-16
+fn get_length(console: &mut Console<impl BufRead, impl Write>, data_type: DataType, default: f64) -> error::Result<(f64, f64)> {
+    let what = if data_type.is_bytes() { "size of each blob in bytes" } else { "string length" };
+    let input = console.read_line(&format!("Enter {}, e.g. 12 or 8-16 (default {}): ", what, default))?;
+    if input.is_empty() {
+        return Ok((default, default));
+    }
+    parse_length(&input)
+}
+
+fn parse_length(input: &str) -> error::Result<(f64, f64)> {
+    let invalid = || invalid_input(format!("Invalid length: {}", input.trim()));
+    // split_once splits at the first '-' only, giving back both halves
+    let (shortest, longest) = match input.trim().split_once('-') {
+        Some((shortest, longest)) => (shortest, longest),
+        None => (input, input),
+    };
+    let shortest = shortest.trim().parse::<u32>().map_err(|_| invalid())?;
+    let longest = longest.trim().parse::<u32>().map_err(|_| invalid())?;
+    Ok((shortest as f64, longest as f64))
+}
+
+// Like get_bound, but shows the default as a date since nobody knows what 946684800 means
+fn get_time_bound(console: &mut Console<impl BufRead, impl Write>, prompt: &str, default: f64) -> error::Result<f64> {
+    let input = console.read_line(&format!("{}, e.g. 2024-01-31 or 2024-01-31T12:00:00 (default {}): ", prompt, format_iso(default as i64)))?;
+    if input.is_empty() {
+        return Ok(default);
+    }
+    parse_bound(&input)
+}
+
+fn get_time_format(console: &mut Console<impl BufRead, impl Write>) -> error::Result<TimeFormat> {
+    let input = console.read_line("Write times as (i for ISO-8601 dates, e for epoch seconds, default ISO): ")?;
+    if input.is_empty() {
+        return Ok(TimeFormat::Iso);
+    }
+    parse_time_format(&input)
+}
+
+fn parse_time_format(input: &str) -> error::Result<TimeFormat> {
+    match input.trim().to_lowercase().as_str() {
+        "i" | "iso" | "iso8601" | "iso-8601" => Ok(TimeFormat::Iso),
+        "e" | "epoch" | "unix" => Ok(TimeFormat::Epoch),
+        _ => Err(invalid_input("Time format must be iso or epoch")),
+    }
+}
+
+// A boolean's only knob is how often it comes out true
+fn get_true_probability(console: &mut Console<impl BufRead, impl Write>) -> error::Result<f64> {
+    let input = console.read_line("Enter the chance of true, 0 to 1 (default 0.5): ")?;
+    if input.is_empty() {
+        return Ok(0.5);
+    }
+    parse_probability(&input)
+}
+
+fn parse_probability(input: &str) -> error::Result<f64> {
+    match input.trim().parse::<f64>() {
+        Ok(value) if (0.0..=1.0).contains(&value) => Ok(value),
+        _ => Err(invalid_input(format!("Invalid probability (must be 0 to 1): {}", input.trim()))),
+    }
+}
+
+fn get_bool_format(console: &mut Console<impl BufRead, impl Write>) -> error::Result<BoolFormat> {
+    let input = console.read_line("Write booleans as (w for true/false, d for 1/0, default words): ")?;
+    if input.is_empty() {
+        return Ok(BoolFormat::Words);
+    }
+    parse_bool_format(&input)
+}
+
+// Pressing enter allows any address at all
+fn get_cidr(console: &mut Console<impl BufRead, impl Write>) -> error::Result<Option<Cidr>> {
+    let input = console.read_line("Enter a CIDR block to keep addresses inside, e.g. 10.0.0.0/8 (press enter for any): ")?;
+    if input.is_empty() {
+        return Ok(None);
+    }
+    parse_cidr(&input).map(Some)
+}
+
+fn parse_bool_format(input: &str) -> error::Result<BoolFormat> {
+    match input.trim().to_lowercase().as_str() {
+        "w" | "words" | "word" | "true" => Ok(BoolFormat::Words),
+        "d" | "digits" | "digit" | "1" => Ok(BoolFormat::Digits),
+        _ => Err(invalid_input("Bool format must be words or digits")),
+    }
+}
+
+fn parse_missing_token(input: &str) -> error::Result<MissingToken> {
+    match input.trim().to_lowercase().as_str() {
+        "e" | "empty" | "" => Ok(MissingToken::Empty),
+        "null" => Ok(MissingToken::Null),
+        "nan" => Ok(MissingToken::NaN),
+        _ => Err(invalid_input("Missing values can be written as empty, null or nan")),
+    }
+}
+
+// How many values to leave out, and what to write instead - the token only matters once some are
+fn get_missing(console: &mut Console<impl BufRead, impl Write>) -> error::Result<(f64, MissingToken)> {
+    let input = console.read_line("Enter percentage of values to leave missing (0-100, default 0): ")?;
+    if input.is_empty() {
+        return Ok((0.0, MissingToken::Empty));
+    }
+    let percent = parse_param(&input)?;
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(invalid_input("Missing value percentage must be between 0 and 100"));
+    }
+    if percent == 0.0 {
+        return Ok((0.0, MissingToken::Empty));
+    }
+    let token = parse_missing_token(&console.read_line("Write missing values as (empty, null or nan, default empty): ")?)?;
+    Ok((percent, token))
+}
+
+// Asks for the distribution and then only the parameters that distribution needs
+// total is how many values there'll be, so a sequence's step can default to just filling the range
+fn get_distribution(console: &mut Console<impl BufRead, impl Write>, min: f64, max: f64, total: u64) -> error::Result<Distribution> {
+    let input = console.read_line(
+        "Enter distribution (u = uniform, n = normal, e = exponential, p = poisson, z = zipf, s = sequence, primes, squares, pow2 = powers of two, default uniform): "
+    )?;
+    let name = if input.is_empty() { "uniform" } else { input.as_str() };
+    let (default_mean, default_std_dev) = generator::default_normal(min, max);
+    match parse_distribution_name(name)? {
+        "normal" => build_distribution(
+            "normal",
+            Vec::new(),
+            get_param(console, "Enter mean", default_mean)?,
+            get_param(console, "Enter standard deviation", default_std_dev)?,
+            0.0,
+            0.0,
+            (0.0, 0.0, 0.0),
+        ),
+        "exponential" | "poisson" => {
+            let lambda = get_param(console, "Enter lambda", 1.0)?;
+            build_distribution(name, Vec::new(), 0.0, 0.0, lambda, 0.0, (0.0, 0.0, 0.0))
+        },
+        "zipf" => {
+            console.explain("The smallest value is the most common, the next one less so, and so on - a bigger exponent skews it harder")?;
+            let exponent = get_param(console, "Enter exponent", 1.0)?;
+            build_distribution(name, Vec::new(), 0.0, 0.0, 0.0, exponent, (0.0, 0.0, 0.0))
+        },
+        "sequence" => {
+            console.explain("Values go up by the step each time, nudged by a random amount up to the jitter either way")?;
+            let start = get_param(console, "Enter start", min)?;
+            let step = get_param(console, "Enter step", generator::sequence_step(start, max, total))?;
+            let jitter = get_param(console, "Enter jitter", 0.0)?;
+            build_distribution(name, Vec::new(), 0.0, 0.0, 0.0, 0.0, (start, step, jitter))
+        },
+        "primes" | "squares" | "powers-of-two" => build_distribution(name, Vec::new(), 0.0, 0.0, 0.0, 0.0, (0.0, 0.0, 0.0)),
+        _ => Ok(Distribution::Uniform),
+    }
+}
+
+// Turns any accepted spelling into one canonical name
+fn parse_distribution_name(input: &str) -> error::Result<&'static str> {
+    match input.trim().to_lowercase().as_str() {
+        "u" | "uniform" => Ok("uniform"),
+        "n" | "normal" | "gaussian" => Ok("normal"),
+        "e" | "exp" | "exponential" => Ok("exponential"),
+        "p" | "poisson" => Ok("poisson"),
+        "z" | "zipf" | "zipfian" => Ok("zipf"),
+        "c" | "cat" | "categorical" => Ok("categorical"),
+        "s" | "seq" | "sequence" => Ok("sequence"),
+        "prime" | "primes" => Ok("primes"),
+        "square" | "squares" => Ok("squares"),
+        "pow2" | "powers" | "powers-of-two" => Ok("powers-of-two"),
+        other => Err(invalid_input(format!("Unknown distribution: {}", other))),
+    }
+}
+
+// Picks the variant for a name and checks its parameters right away
+// sequence is (start, step, jitter)
+fn build_distribution(
+    name: &str,
+    categories: Vec<Category>,
+    mean: f64,
+    std_dev: f64,
+    lambda: f64,
+    exponent: f64,
+    sequence: (f64, f64, f64),
+) -> error::Result<Distribution> {
+    let distribution = match parse_distribution_name(name)? {
+        "normal" => Distribution::Normal { mean, std_dev },
+        "exponential" => Distribution::Exponential { lambda },
+        "poisson" => Distribution::Poisson { lambda },
+        "zipf" => Distribution::Zipf { exponent },
+        "categorical" if categories.is_empty() => {
+            return Err(invalid_input("--dist categorical needs --categories, e.g. \"A:0.5,B:0.3,C:0.2\""));
+        },
+        "categorical" => Distribution::Categorical(categories),
+        "sequence" => Distribution::Sequence { start: sequence.0, step: sequence.1, jitter: sequence.2 },
+        "primes" => Distribution::Special(Special::Primes),
+        "squares" => Distribution::Special(Special::Squares),
+        "powers-of-two" => Distribution::Special(Special::PowersOfTwo),
+        _ => Distribution::Uniform,
+    };
+    generator::validate_distribution(&distribution)?;
+    Ok(distribution)
+}
+
+// The range for a sequence: an end that was given clips it, and any end that wasn't
+// grows to fit every value (rounded outwards, since integer types need whole numbers there)
+fn fit_sequence(data_type: DataType, sequence: (f64, f64, f64), total: u64, min: Option<f64>, max: Option<f64>) -> (f64, f64) {
+    let (start, step, jitter) = sequence;
+    let (lowest, highest) = whole_range(data_type, generator::sequence_range(start, step, jitter, total));
+    let min = min.unwrap_or(lowest);
+    // A single value with no jitter has no width, but the range still needs a top above its bottom
+    (min, max.unwrap_or(highest.max(min + 1.0)))
+}
+
+// Integer types need whole numbers at the ends of their range, so a worked-out range is rounded outwards
+fn whole_range(data_type: DataType, (low, high): (f64, f64)) -> (f64, f64) {
+    if data_type.is_integer() { (low.floor(), high.ceil()) } else { (low, high) }
+}
+
+// "A:0.5, B:0.3, C:0.2" -> three categories
+// A label without a weight gets 1, so "red,green,blue" picks each one equally often
+fn parse_categories(input: &str) -> error::Result<Vec<Category>> {
+    let mut categories = Vec::new();
+    for entry in input.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let (label, weight) = match entry.split_once(':') {
+            Some((label, weight)) => (label.trim(), weight.trim().parse::<f64>()
+                .map_err(|_| invalid_input(format!("Invalid weight in {}", entry)))?),
+            None => (entry, 1.0),
+        };
+        categories.push(Category { label: label.to_string(), weight });
+    }
+    generator::validate_distribution(&Distribution::Categorical(categories.clone()))?;
+    Ok(categories)
+}
+
+fn get_param(console: &mut Console<impl BufRead, impl Write>, prompt: &str, default: f64) -> error::Result<f64> {
+    let input = console.read_line(&format!("{} (default {}): ", prompt, default))?;
+    if input.is_empty() {
+        return Ok(default);
+    }
+    parse_param(&input)
+}
+
+fn parse_param(input: &str) -> error::Result<f64> {
+    match input.trim().parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => Err(invalid_input(format!("Invalid parameter: {}", input.trim()))),
+    }
+}
+
+fn get_order(console: &mut Console<impl BufRead, impl Write>) -> error::Result<SortOrder> {
+    let input = console.read_line("Enter ordering (r = random, a = ascending, d = descending, n = nearly sorted, default random): ")?;
+    let name = if input.is_empty() { "random" } else { input.as_str() };
+    let disorder = match parse_order_name(name)? {
+        "nearly" => get_param(console, "Enter disorder percentage (0-100)", 5.0)?,
+        _ => 0.0,
+    };
+    build_order(name, disorder)
+}
+
+fn parse_order_name(input: &str) -> error::Result<&'static str> {
+    match input.trim().to_lowercase().as_str() {
+        "r" | "random" => Ok("random"),
+        "a" | "asc" | "ascending" => Ok("ascending"),
+        "d" | "desc" | "descending" => Ok("descending"),
+        "n" | "nearly" | "nearly-sorted" => Ok("nearly"),
+        other => Err(invalid_input(format!("Unknown ordering: {}", other))),
+    }
+}
+
+fn build_order(name: &str, disorder: f64) -> error::Result<SortOrder> {
+    let order = match parse_order_name(name)? {
+        "ascending" => SortOrder::Ascending,
+        "descending" => SortOrder::Descending,
+        "nearly" => SortOrder::NearlySorted { disorder },
+        _ => SortOrder::Random,
+    };
+    generator::validate_order(order)?;
+    Ok(order)
+}
+
+fn get_format(console: &mut Console<impl BufRead, impl Write>) -> error::Result<OutputFormat> {
+    let input = console.read_line("Enter output format (t for text, c for csv, j for json, b for binary, m for matrix, p for parquet, s for sqlite, f for fixed-width, code for a source-code array): ")?;
+    match parse_format(&input)? {
+        OutputFormat::Code(_) => Ok(OutputFormat::Code(get_language(console)?)),
+        format => Ok(format),
+    }
+}
+
+fn parse_format(input: &str) -> error::Result<OutputFormat> {
+    let input = input.trim().to_lowercase();
+    // These all start with j, so they have to be caught before the first-letter check
+    if matches!(input.as_str(), "jsonl" | "jsonlines" | "json-lines" | "ndjson") {
+        return Ok(OutputFormat::JsonLines);
+    }
+    // "code" would be taken for CSV, and the language comes from --language (C unless it says)
+    if matches!(input.as_str(), "code" | "source") {
+        return Ok(OutputFormat::Code(Language::C));
+    }
+    match input.chars().next() {
+        Some('t') => Ok(OutputFormat::Text),
+        Some('c') => Ok(OutputFormat::Csv),
+        Some('j') => Ok(OutputFormat::Json),
+        Some('b') => Ok(OutputFormat::Binary),
+        Some('m') => Ok(OutputFormat::Matrix),
+        Some('l') => Ok(OutputFormat::JsonLines),
+        Some('p') => Ok(OutputFormat::Parquet),
+        Some('s') | Some('d') => Ok(OutputFormat::Sqlite),
+        Some('f') => Ok(OutputFormat::FixedWidth),
+        _ => Err(invalid_input("Invalid output format")),
+    }
+}
+
+// Enter keeps each default, so only the settings that differ need typing
+fn get_field_width(console: &mut Console<impl BufRead, impl Write>) -> error::Result<FieldWidth> {
+    let mut field_width = FieldWidth::default();
+    let input = console.read_line(&format!("Enter field width (default {}): ", field_width.width))?;
+    if !input.is_empty() {
+        field_width.width = parse_width(&input)?;
+    }
+    let input = console.read_line("Pad with which character? (press enter for spaces): ")?;
+    if !input.is_empty() {
+        field_width.fill = parse_fill(&input)?;
+    }
+    let input = console.read_line("Align values (l for left, r for right, default right): ")?;
+    if !input.is_empty() {
+        field_width.align = parse_align(&input)?;
+    }
+    Ok(field_width)
+}
+
+fn parse_width(input: &str) -> error::Result<usize> {
+    match input.trim().parse::<usize>() {
+        Ok(width) if width > 0 => Ok(width),
+        _ => Err(invalid_input(format!("Invalid field width: {}", input.trim()))),
+    }
+}
+
+// A space is awkward to type as a shell argument, so it can be spelled out
+// Not trimmed, since the character itself might be the space
+fn parse_fill(input: &str) -> error::Result<char> {
+    let mut chars = input.chars();
+    match (input, chars.next(), chars.next()) {
+        ("space", _, _) => Ok(' '),
+        ("zero", _, _) => Ok('0'),
+        (_, Some(c), None) => Ok(c),
+        _ => Err(invalid_input(format!("The fill must be a single character, not '{}'", input))),
+    }
+}
+
+fn parse_align(input: &str) -> error::Result<Align> {
+    match input.trim().to_lowercase().chars().next() {
+        Some('l') => Ok(Align::Left),
+        Some('r') => Ok(Align::Right),
+        _ => Err(invalid_input("Alignment must be left or right")),
+    }
+}
+
+fn parse_endianness(input: &str) -> error::Result<Endianness> {
+    match input.trim().to_lowercase().as_str() {
+        "" | "l" | "little" | "le" => Ok(Endianness::Little),
+        "b" | "big" | "be" => Ok(Endianness::Big),
+        _ => Err(invalid_input("Byte order must be little or big")),
+    }
+}
+
+fn parse_language(input: &str) -> error::Result<Language> {
+    match input.trim().to_lowercase().as_str() {
+        "" | "c" => Ok(Language::C),
+        "cpp" | "c++" | "cxx" => Ok(Language::Cpp),
+        "rust" | "rs" => Ok(Language::Rust),
+        "python" | "py" => Ok(Language::Python),
+        _ => Err(invalid_input("Language must be c, cpp, rust or python")),
+    }
+}
+
+// Puts --language into a code format, which is the only one it means anything for
+fn with_language(format: OutputFormat, language: Option<Language>) -> error::Result<OutputFormat> {
+    match (format, language) {
+        (OutputFormat::Code(_), Some(language)) => Ok(OutputFormat::Code(language)),
+        (_, Some(_)) => Err(invalid_input("--language only applies to --format code")),
+        (format, None) => Ok(format),
+    }
+}
+
+fn get_language(console: &mut Console<impl BufRead, impl Write>) -> error::Result<Language> {
+    parse_language(&console.read_line("Language (c, cpp, rust or python, default c): ")?)
+}
+
+fn get_endianness(console: &mut Console<impl BufRead, impl Write>) -> error::Result<Endianness> {
+    let prompt = "Byte order (l for little-endian, b for big-endian, default little): ";
+    parse_endianness(&console.with_b_answer(|console| console.read_line(prompt))?)
+}
+
+fn get_columns(console: &mut Console<impl BufRead, impl Write>) -> error::Result<u32> {
+    let input = console.read_line("Enter number of columns: ")?;
+    parse_columns(&input)
+}
+
+// The named delimiters are the ones that are awkward to type on a command line; anything
+// else is used as it is, like ", " for a list
+fn parse_delimiter(input: &str) -> String {
+    match input {
+        "newline" | "\\n" => String::from("\n"),
+        "comma" => String::from(","),
+        "space" => String::from(" "),
+        "tab" | "\\t" => String::from("\t"),
+        _ => input.to_string(),
+    }
+}
+
+// Points are 2D or 3D - a 1D cloud is just --type double, and there's no fourth axis to draw
+fn parse_dimensions(input: &str) -> error::Result<usize> {
+    match input.trim() {
+        "2" | "2d" | "2D" => Ok(2),
+        "3" | "3d" | "3D" => Ok(3),
+        other => Err(invalid_input(format!("Points have 2 or 3 dimensions, not {}", other))),
+    }
+}
+
+fn parse_columns(input: &str) -> error::Result<u32> {
+    match input.trim().parse::<u32>() {
+        Ok(columns) if columns > 0 => Ok(columns),
+        _ => Err(invalid_input("Number of columns must be a positive number")),
+    }
+}
+
+fn get_separator(console: &mut Console<impl BufRead, impl Write>) -> error::Result<char> {
+    let input = console.read_line("Separate values with (s for space, c for comma): ")?;
+    parse_separator(&input)
+}
+
+// Accepts "space"/"comma" on the command line and s/c at the menu, same as the other single-letter prompts
+fn parse_separator(input: &str) -> error::Result<char> {
+    match input.trim().to_lowercase().chars().next() {
+        Some('s') => Ok(' '),
+        Some('c') | Some(',') => Ok(','),
+        _ => Err(invalid_input("Separator must be space or comma")),
+    }
+}
+
+// Only SQLite has tables - every other format gets None
+fn get_table(console: &mut Console<impl BufRead, impl Write>, format: OutputFormat) -> error::Result<Option<String>> {
+    if format != OutputFormat::Sqlite {
+        return Ok(None);
+    }
+    let input = console.read_line(&format!("Enter table name (press enter for {}): ", DEFAULT_TABLE))?;
+    Ok(if input.is_empty() { None } else { Some(input) })
+}
+
+fn get_shards(console: &mut Console<impl BufRead, impl Write>) -> error::Result<u32> {
+    let input = console.read_line("Split it into how many files? (press enter for one): ")?;
+    if input.is_empty() { Ok(1) } else { parse_shards(&input) }
+}
+
+fn parse_shards(input: &str) -> error::Result<u32> {
+    match input.trim().parse::<u32>() {
+        Ok(shards) if (1..=MAX_SHARDS).contains(&shards) => Ok(shards),
+        _ => Err(invalid_input(format!("Number of shards must be between 1 and {}", MAX_SHARDS))),
+    }
+}
+
+// Seconds between readings, or with a unit: 30s, 15m, 6h or 1d
+fn parse_interval(input: &str) -> error::Result<i64> {
+    let input = input.trim();
+    let (number, unit) = match input.char_indices().last() {
+        Some((i, 's')) => (&input[..i], 1),
+        Some((i, 'm')) => (&input[..i], 60),
+        Some((i, 'h')) => (&input[..i], 3600),
+        Some((i, 'd')) => (&input[..i], 86_400),
+        _ => (input, 1),
+    };
+    match number.parse::<i64>() {
+        Ok(number) if number > 0 => number.checked_mul(unit)
+            .ok_or_else(|| invalid_input(format!("Interval is too long: {}", input))),
+        _ => Err(invalid_input(format!("Invalid interval: {} (use seconds, or e.g. 15m, 6h or 1d)", input))),
+    }
+}
+
+// How often --follow adds a row: 250ms, 2s or 1m, or a plain number of milliseconds
+fn parse_every(input: &str) -> error::Result<Duration> {
+    let input = input.trim();
+    let (number, millis) = if let Some(number) = input.strip_suffix("ms") {
+        (number, 1)
+    } else if let Some(number) = input.strip_suffix('s') {
+        (number, 1000)
+    } else if let Some(number) = input.strip_suffix('m') {
+        (number, 60_000)
+    } else {
+        (input, 1)
+    };
+    match number.trim().parse::<u64>() {
+        Ok(number) if number > 0 => Ok(Duration::from_millis(number.saturating_mul(millis))),
+        _ => Err(invalid_input(format!("Invalid --follow interval: {} (e.g. 250ms, 2s or 1m)", input))),
+    }
+}
+
+// A rate like 100/s, 30/m or 500/h (or just 100, a second), as values per second
+fn parse_rate(input: &str) -> error::Result<f64> {
+    let (number, unit) = input.trim().split_once('/').unwrap_or((input.trim(), "s"));
+    let seconds = match unit.trim() {
+        "s" | "sec" | "second" => 1.0,
+        "m" | "min" | "minute" => 60.0,
+        "h" | "hour" => 3600.0,
+        _ => return Err(invalid_input(format!("Unknown unit in --rate {} - use /s, /m or /h", input))),
+    };
+    number.trim().parse::<f64>().ok()
+        .filter(|rate| rate.is_finite() && *rate > 0.0)
+        .map(|rate| rate / seconds)
+        .ok_or_else(|| invalid_input(format!("Invalid rate: {} (e.g. 100/s, 30/m or 0.5/s)", input)))
+}
+
+fn parse_threads(input: &str) -> error::Result<u32> {
+    input.trim().parse::<u32>()
+        .map_err(|_| invalid_input("Number of threads must be 0 (one per core) or a positive number"))
+}
+
+// Any answer starting with y counts as yes, everything else is no
+fn get_yes_no(console: &mut Console<impl BufRead, impl Write>, prompt: &str) -> error::Result<bool> {
+    let input = console.read_line(prompt)?;
+    Ok(input.to_lowercase().starts_with('y'))
+}
+
+fn get_exclusions(console: &mut Console<impl BufRead, impl Write>, data_type: DataType, min: f64, max: f64) -> error::Result<Vec<(f64, f64)>> {
+    console.read_valid("Enter values to leave out, e.g. 0 or -10..10 (press enter for none): ", |input| {
+        if input.is_empty() {
+            return Ok(Vec::new());
+        }
+        let exclude = generator::parse_exclusions(input)?;
+        // Everything excluded is caught here, while there's still a chance to type it again
+        generator::validate_exclusions(data_type, min, max, &exclude, false, 0)?;
+        Ok(exclude)
+    })
+}
+
+// A scale is only worth asking for once some outliers were
+fn get_outliers(console: &mut Console<impl BufRead, impl Write>, data_type: DataType, min: f64, max: f64) -> error::Result<(f64, f64)> {
+    let scale = generator::DEFAULT_OUTLIER_SCALE;
+    let input = console.read_line("Enter percentage of values that are outliers (0-100, default 0): ")?;
+    if input.is_empty() {
+        return Ok((0.0, scale));
+    }
+    let percent = parse_param(&input)?;
+    let scale = if percent > 0.0 { get_param(console, "Enter how far out they go, in half-ranges", scale)? } else { scale };
+    generator::validate_outliers(data_type, min, max, percent, scale, false)?;
+    Ok((percent, scale))
+}
+
+fn get_duplicates(console: &mut Console<impl BufRead, impl Write>) -> error::Result<f64> {
+    let input = console.read_line("Enter percentage of values that repeat an earlier one (0-100, default 0): ")?;
+    if input.is_empty() {
+        return Ok(0.0);
+    }
+    let percent = parse_param(&input)?;
+    generator::validate_duplicates(DataType::Integer, percent, false)?;
+    Ok(percent)
+}
+
+// Blank keeps the type's usual number of decimals
+fn get_precision(console: &mut Console<impl BufRead, impl Write>, default: usize) -> error::Result<Option<usize>> {
+    let input = console.read_line(&format!("Enter digits after the decimal point (0-{}, default {}): ", MAX_PRECISION, default))?;
+    if input.is_empty() {
+        return Ok(None);
+    }
+    parse_precision(&input).map(Some)
+}
+
+fn parse_precision(input: &str) -> error::Result<usize> {
+    match input.trim().parse::<usize>() {
+        Ok(digits) if digits <= MAX_PRECISION => Ok(digits),
+        _ => Err(invalid_input(format!("Precision must be between 0 and {}", MAX_PRECISION))),
+    }
+}
+
+fn get_int_format(console: &mut Console<impl BufRead, impl Write>) -> error::Result<IntFormat> {
+    let prompt = "Write integers as (d for decimal, h for hex, b for binary, default decimal): ";
+    let input = console.with_b_answer(|console| console.read_line(prompt))?;
+    if input.is_empty() {
+        return Ok(IntFormat::Decimal);
+    }
+    parse_int_format(&input)
+}
+
+fn parse_int_format(input: &str) -> error::Result<IntFormat> {
+    match input.trim().to_lowercase().as_str() {
+        "d" | "dec" | "decimal" => Ok(IntFormat::Decimal),
+        "h" | "x" | "hex" | "hexadecimal" => Ok(IntFormat::Hex),
+        "b" | "bin" | "binary" | "base2" => Ok(IntFormat::Binary),
+        _ => Err(invalid_input("Integer format must be decimal, hex or binary")),
+    }
+}
+
+fn get_number_format(console: &mut Console<impl BufRead, impl Write>) -> error::Result<NumberFormat> {
+    let input = console.read_line(
+        "Number style (p = 1234567.89, c = 1,234,567.89, e = 1.234.567,89, s = 1 234 567,89, w = 1'234'567.89, default p): ",
+    )?;
+    if input.is_empty() {
+        return Ok(NumberFormat::default());
+    }
+    parse_number_style(&input)
+}
+
+// The usual styles around the world, so nobody has to work out the separators themselves
+fn parse_number_style(input: &str) -> error::Result<NumberFormat> {
+    let (decimal, thousands) = match input.trim().to_lowercase().as_str() {
+        "p" | "plain" => ('.', None),
+        "c" | "comma" | "english" => ('.', Some(',')),
+        "e" | "european" | "german" => (',', Some('.')),
+        "s" | "space" | "french" => (',', Some(' ')),
+        "w" | "swiss" | "apostrophe" => ('.', Some('\'')),
+        other => return Err(invalid_input(format!("Unknown number style: {}", other))),
+    };
+    Ok(NumberFormat { decimal, thousands })
+}
+
+// Separators by name, since a bare space or ' needs careful quoting in most shells
+// None means no separator at all
+fn parse_separator_name(input: &str) -> error::Result<Option<char>> {
+    match input.to_lowercase().as_str() {
+        "none" => Ok(None),
+        "comma" | "," => Ok(Some(',')),
+        "point" | "dot" | "period" | "." => Ok(Some('.')),
+        "space" | " " => Ok(Some(' ')),
+        "apostrophe" | "'" => Ok(Some('\'')),
+        "underscore" | "_" => Ok(Some('_')),
+        _ => Err(invalid_input(format!("Unknown separator: '{}'", input))),
+    }
+}
+
+// Blank means "surprise me", anything else has to be a valid u64
+// With a seed in the config (or DATAGEN_SEED), a blank answer takes that instead of a random one
+fn get_seed(console: &mut Console<impl BufRead, impl Write>, default: Option<u64>) -> error::Result<Option<u64>> {
+    let prompt = match default {
+        Some(seed) => format!("Enter seed (default {}): ", seed),
+        None => String::from("Enter seed (blank for random): "),
+    };
+    let input = console.read_line(&prompt)?;
+    if input.is_empty() {
+        return Ok(default);
+    }
+    parse_seed(&input).map(Some)
+}
+
+fn parse_seed(input: &str) -> error::Result<u64> {
+    input.trim().parse::<u64>()
+        .map_err(|_| invalid_input(format!("Invalid seed: {}", input.trim())))
+}
+
+// String in Rust is different from char* in C
+// They're UTF-8 and can't be null, so no buffer overflows
+// This one is for files that are already there, to read or verify
+fn get_filename(console: &mut Console<impl BufRead, impl Write>) -> error::Result<String> {
+    console.read_valid("Enter filename: ", |input| match input {
+        "" => Err(invalid_input("No filename given")),
+        name => Ok(name.to_string()),
+    })
+}
+
+// The name for a file about to be created, checked while it can still be typed again
+fn get_new_filename(console: &mut Console<impl BufRead, impl Write>) -> error::Result<String> {
+    console.read_valid("Enter filename: ", |input| validate_filename(input).map(|()| input.to_string()))
+}
+
+// Accepts a list like "1K, 10K, 100K" or "500 2.5M" - each one the way parse_count takes it
+fn parse_sizes(input: &str) -> error::Result<Vec<BatchSize>> {
+    let mut sizes = Vec::new();
+    // Splitting on both commas and spaces means either style of list works
+    for label in input.split([',', ' ']).filter(|s| !s.is_empty()) {
+        let count = parse_count(label).map_err(|e| invalid_input(format!("Invalid size {}: {}", label, e)))?;
+        sizes.push(BatchSize { label: label.to_string(), count });
+    }
+    if sizes.is_empty() {
+        return Err(invalid_input("No sizes given"));
+    }
+    Ok(sizes)
+}
+
+// Buffers are memory, so K and M are 1024-based here, unlike the counts in --sizes
+fn parse_buffer_size(input: &str) -> error::Result<usize> {
+    let input = input.trim();
+    let (digits, multiplier) = match input.chars().last() {
+        Some('k') | Some('K') => (&input[..input.len() - 1], 1 << 10),
+        Some('m') | Some('M') => (&input[..input.len() - 1], 1 << 20),
+        _ => (input, 1),
+    };
+    digits.parse::<usize>().ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|&size| size > 0 && size <= MAX_BUFFER_SIZE)
+        .ok_or_else(|| invalid_input(format!("Invalid buffer size: {}", input)))
+}
+
+fn largest_size(sizes: &[BatchSize]) -> u32 {
+    // parse_sizes never returns an empty list, so there's always a max
+    sizes.iter().map(|size| size.count).max().unwrap_or(0)
+}
+
+// Without {size} in the name every file in the batch would overwrite the last one
+fn check_template(template: &str) -> error::Result<()> {
+    if !template.contains("{size}") {
+        return Err(invalid_input("Batch filename must contain {size}, e.g. data_{size}.txt"));
+    }
+    Ok(())
+}
+
+fn create_file(console: &mut Console<impl BufRead, impl Write>, config: &Config, manifest: &mut Manifest) -> error::Result<()> {
+    let data_type = get_data_type(console, config.data_type)?;
+    let count = get_element_count(console, config.count)?;
+    let mut options = get_options(console, data_type, count, config)?;
+    let filename = get_new_filename(console)?;
+    // The menu's own prompts go to stdout too, so the data would get tangled up in them
+    if filename == STDOUT_NAME {
+        return Err(invalid_input("Writing to stdout only works from the command line (-o -)"));
+    }
+    options.filename = gz_name(&in_output_dir(config, &filename)?, options.compress);
+    options.table = get_table(console, options.format)?;
+    // With the same answers as last time, an interrupted file can be finished off instead
+    if Path::new(&checkpoint_name(&options.filename)).exists() && resume_blocker(&options).is_none() {
+        options.resume = get_yes_no(console, &format!("{} was interrupted part way. Resume it? (y/n): ", options.filename))?;
+    }
+    let taken = if options.resume { None } else { existing_target(&options.filename, &options)? };
+    if let Some(taken) = taken {
+        options.if_exists = get_if_exists(console, &format!("{} already exists.", taken))?;
+        if options.if_exists == IfExists::Abort {
+            writeln!(console, "Left {} as it was.", options.filename)?;
+            return Ok(());
+        }
+    }
+    let options = with_seed(&options);
+    generate(console, &options, manifest)?;
+    offer_another(console, options, manifest)
+}
+
+// Records have far fewer knobs than single-type files - the schema covers types and ranges
+fn create_records(console: &mut Console<impl BufRead, impl Write>, config: &Config, manifest: &mut Manifest) -> error::Result<()> {
+    console.explain("Fields look like name:type, optionally with a range, e.g.")?;
+    console.explain("  id:int(1..1000000), name:string(4..12), score:float, active:bool, joined:timestamp")?;
+    let schema = parse_schema(&console.read_line("Enter schema: ")?)?;
+    let cidr = if schema.iter().any(|field| field.data_type.is_ip()) { get_cidr(console)? } else { None };
+    let count = parse_count(&console.read_line("Enter number of records: ")?)?;
+    let format = parse_format(&console.read_line("Enter output format (c for csv, l for json lines, p for parquet, s for sqlite): ")?)?;
+    let seed = get_seed(console, config.seed)?;
+    let compress = !matches!(format, OutputFormat::Parquet | OutputFormat::Sqlite) && get_yes_no(console, "Compress with gzip? (y/n): ")?;
+    let checksum = get_yes_no(console, "Write a SHA-256 checksum file? (y/n): ")?;
+    // Each field has its own type and range, so the default ones are just placeholders
+    let mut options = Options {
+        count,
+        format,
+        csv_header: true,
+        schema,
+        seed,
+        progress: console.prompts, // A script has nobody watching the percentage tick up
+        compress,
+        cidr,
+        checksum,
+        ..Options::default()
+    };
+    let filename = get_new_filename(console)?;
+    if filename == STDOUT_NAME {
+        return Err(invalid_input("Writing to stdout only works from the command line (-o -)"));
+    }
+    options.filename = gz_name(&in_output_dir(config, &filename)?, options.compress);
+    options.table = get_table(console, options.format)?;
+    // Records can't be appended to, so it's overwrite or leave it alone
+    if let Some(taken) = existing_target(&options.filename, &options)? {
+        if !get_yes_no(console, &format!("{} already exists. Overwrite it? (y/n): ", taken))? {
+            writeln!(console, "Left {} as it was.", options.filename)?;
+            return Ok(());
+        }
+    }
+    let options = with_seed(&options);
+    generate(console, &options, manifest)?;
+    offer_another(console, options, manifest)
+}
+
+// A graph only needs its size and, optionally, what the weights look like
+fn create_graph(console: &mut Console<impl BufRead, impl Write>, config: &Config, manifest: &mut Manifest) -> error::Result<()> {
+    console.explain("Each line of the file is one edge between two nodes (numbered from 0): u v, or u v w with weights")?;
+    let nodes = parse_count(&console.read_line("Enter number of nodes: ")?)?;
+    let input = console.read_line(&format!(
+        "Enter number of edges (up to {}), or a density like 0.1: ", graph::max_edges(nodes)
+    ))?;
+    // A decimal point is what tells a density apart from an edge count
+    let count = if input.contains('.') {
+        graph::edges_for_density(nodes, parse_param(&input)?)?
+    } else {
+        parse_count(&input)?
+    };
+    let weighted = get_yes_no(console, "Add weights to the edges? (y/n): ")?;
+    let (default_min, default_max) = graph::DEFAULT_WEIGHTS;
+    let (data_type, min, max, distribution) = if weighted {
+        let input = console.read_line("Enter weight type (i for int, d for double, default int): ")?;
+        let data_type = if input.is_empty() { DataType::Integer } else { parse_data_type(&input)? };
+        let min = get_bound(console, "Enter smallest weight", default_min)?;
+        let max = get_bound(console, "Enter largest weight", default_max)?;
+        generator::validate_range(data_type, min, max)?;
+        (data_type, min, max, get_distribution(console, min, max, count as u64)?)
+    } else {
+        (DataType::Integer, default_min, default_max, Distribution::Uniform)
+    };
+    let seed = get_seed(console, config.seed)?;
+    let compress = get_yes_no(console, "Compress with gzip? (y/n): ")?;
+    let checksum = get_yes_no(console, "Write a SHA-256 checksum file? (y/n): ")?;
+    let mut options = Options {
+        data_type,
+        count,
+        min,
+        max,
+        distribution,
+        graph: Some(Graph { nodes, weighted }),
+        seed,
+        progress: console.prompts,
+        compress,
+        checksum,
+        ..Options::default()
+    };
+    let filename = get_new_filename(console)?;
+    if filename == STDOUT_NAME {
+        return Err(invalid_input("Writing to stdout only works from the command line (-o -)"));
+    }
+    options.filename = gz_name(&in_output_dir(config, &filename)?, options.compress);
+    // Same as records: a graph can't be appended to, so it's overwrite or leave it alone
+    if Path::new(&options.filename).exists()
+        && !get_yes_no(console, &format!("{} already exists. Overwrite it? (y/n): ", options.filename))? {
+        writeln!(console, "Left {} as it was.", options.filename)?;
+        return Ok(());
+    }
+    let options = with_seed(&options);
+    generate(console, &options, manifest)?;
+    offer_another(console, options, manifest)
+}
+
+fn create_batch(console: &mut Console<impl BufRead, impl Write>, config: &Config, manifest: &mut Manifest) -> error::Result<()> {
+    let data_type = get_data_type(console, config.data_type)?;
+    let sizes = parse_sizes(&console.read_line("Enter sizes (e.g. 1K,10K,100K): ")?)?;
+    let mut options = get_options(console, data_type, largest_size(&sizes), config)?;
+    let template = console.read_line("Enter filename template (e.g. data_{size}.txt): ")?;
+    options.filename = gz_name(&in_output_dir(config, &template)?, options.compress);
+    options.table = get_table(console, options.format)?;
+    check_template(&options.filename)?;
+    // One question covers the whole batch, and aborting stops at the first file that's already there
+    let mut taken = false;
+    for size in &sizes {
+        taken |= existing_target(&options.filename.replace("{size}", &size.label), &options)?.is_some();
+    }
+    if taken {
+        options.if_exists = get_if_exists(console, "Some of these files already exist.")?;
+    }
+    generate_batch(console, &options, &sizes, manifest)
+}
+
+// Runs a preset saved with --save-preset, asking only for what it leaves out (the filename)
+// and what to do about files that are already there
+fn create_from_preset(console: &mut Console<impl BufRead, impl Write>, config: &Config, manifest: &mut Manifest) -> error::Result<()> {
+    let presets = Presets::load(&presets_path(config))?;
+    if presets.list().is_empty() {
+        writeln!(console, "No presets saved in {} yet (save one with --save-preset <name>).", presets.path())?;
+        return Ok(());
+    }
+    for (i, preset) in presets.list().iter().enumerate() {
+        console.explain(&format!("{:3}. {}: {}", i + 1, preset.name, preset.args.join(" ")))?;
+    }
+    let input = console.read_line("Enter a preset name or number: ")?;
+    let preset = match input.parse::<usize>() {
+        Ok(number) => number.checked_sub(1)
+            .and_then(|i| presets.list().get(i))
+            .ok_or_else(|| invalid_input(format!("Not on the list: {}", input)))?,
+        Err(_) => presets.get(&input)?,
+    };
+    let mut args = preset.args.clone();
+    if !args.iter().any(|arg| arg == "-o" || arg == "--output") {
+        args.extend([String::from("-o"), get_new_filename(console)?]);
+    }
+    run_flags(console, config, manifest, args, &preset.name)
+}
+
+// Runs command-line flags from inside the menu - for presets, and for runs from the history
+fn run_flags(
+    console: &mut Console<impl BufRead, impl Write>,
+    config: &Config,
+    manifest: &mut Manifest,
+    mut args: Vec<String>,
+    name: &str,
+) -> error::Result<()> {
+    // Unless the flags already say what to do about existing files, they're asked about below
+    let decided = args.iter().any(|arg| arg == "--if-exists" || arg == "--force");
+    if !decided {
+        args.push(String::from("--force"));
+    }
+    let (mut options, sizes) = match parse_generate_args(&args, config, false)? {
+        Command::Generate(options) => (options, None),
+        Command::Batch(options, sizes) => (options, Some(sizes)),
+        Command::Bench(options, sizes) => return run_bench(console, &options, &sizes),
+        _ => return Err(invalid_input(format!("{} doesn't generate anything, so it can't be run from here", name))),
+    };
+    if options.writes_to_stdout() {
+        return Err(invalid_input("Writing to stdout only works from the command line (-o -)"));
+    }
+    if !decided {
+        let targets: Vec<String> = match &sizes {
+            Some(sizes) => sizes.iter().map(|size| options.filename.replace("{size}", &size.label)).collect(),
+            None => vec![options.filename.clone()],
+        };
+        let mut taken = None;
+        for file in &targets {
+            taken = taken.or(existing_target(file, &options)?);
+        }
+        if let Some(taken) = taken {
+            options.if_exists = get_if_exists(console, &format!("{} already exists.", taken))?;
+            if options.if_exists == IfExists::Abort {
+                writeln!(console, "Left {} as it was.", taken)?;
+                return Ok(());
+            }
+        }
+    }
+    match sizes {
+        Some(sizes) => generate_batch(console, &options, &sizes, manifest),
+        None => {
+            let options = with_seed(&options);
+            generate(console, &options, manifest)?;
+            offer_another(console, options, manifest)
+        },
+    }
+}
+
+// Lists the runs in the history and does one of them again. A menu run gets its answers
+// given back in order, as if they were being typed, so it goes through the same questions
+fn regenerate(console: &mut Console<impl BufRead, impl Write>, config: &Config, manifest: &mut Manifest) -> error::Result<()> {
+    // Going back from a question in the replayed run starts this over with its answers queued
+    // up already, and they mustn't be swapped for the recipe's again
+    let going_back = !console.replay.is_empty();
+    let entries = manifest.history().entries();
+    if entries.is_empty() {
+        writeln!(console, "Nothing generated yet, so there's nothing to do again.")?;
+        return Ok(());
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        let more = match entry.files.len() {
+            1 => String::new(),
+            n => format!(" and {} more", n - 1),
+        };
+        let seed = entry.seed.map_or(String::from("no seed"), |seed| format!("seed {}", seed));
+        console.explain(&format!("{:3}. {} {}{} ({})", i + 1, format_iso(entry.time), entry.files[0], more, seed))?;
+        console.explain(&format!("       {}", entry.recipe.describe()))?;
+    }
+    let count = entries.len();
+    let number = console.read_valid("Enter the number of the run to do again: ", |input| {
+        input.trim().parse::<usize>().ok()
+            .filter(|number| (1..=count).contains(number))
+            .ok_or_else(|| invalid_input(format!("Expected a number from 1 to {}", count)))
+    })?;
+    let entry = manifest.history().entries()[number - 1].clone();
+    // Relative filenames (and the config file) were found from where it ran, so it has to run there again
+    let here = env::current_dir()?.to_string_lossy().into_owned();
+    if entry.dir != here {
+        return Err(invalid_input(format!("That run was in {} - start ruststf there to do it again", entry.dir)));
+    }
+    if entry.seed.is_none() {
+        writeln!(console, "That run had no seed, so the files will have the same settings but different values.")?;
+    }
+    // A run that wasn't given a seed was given a random one, which goes in as the default
+    // seed - the flags and answers leave it out, so that's the one they end up with
+    let mut config = config.clone();
+    config.seed = entry.seed.or(config.seed);
+    let config = &config;
+    match &entry.recipe {
+        Recipe::Flags(args) => {
+            // --output-dir is read before the flags are, so it has to be put back into the config by hand
+            let mut config = config.clone();
+            if let Some(i) = args.iter().position(|arg| arg == "--output-dir") {
+                config.output_dir = args.get(i + 1).cloned();
+            }
+            run_flags(console, &config, manifest, args.clone(), "That run")?;
+        },
+        Recipe::Menu(option, answers) => {
+            if !going_back {
+                console.replay = answers.iter().cloned().collect();
+            }
+            let result = match option {
+                1 => create_file(console, config, manifest),
+                2 => create_batch(console, config, manifest),
+                6 => create_records(console, config, manifest),
+                7 => create_graph(console, config, manifest),
+                9 => create_from_preset(console, config, manifest),
+                _ => Err(invalid_data(format!("Menu option {} doesn't generate anything", option))),
+            };
+            // An answer that wasn't needed this time (say, to a file that's gone now) is dropped,
+            // unless they're queued up for going back
+            if !console.back {
+                console.replay.clear();
+            }
+            result?;
+        },
+    }
+    manifest.history_mut().record(entry.recipe)
+}
+
+// After a file is made at the keyboard, offers more of the same shape under the next free name
+// (data.txt, then data_2.txt, data_3.txt...). A seed goes up by one each time, so every
+// file has its own values but the whole set can still be made again
+fn offer_another(console: &mut Console<impl BufRead, impl Write>, mut options: Options, manifest: &mut Manifest) -> error::Result<()> {
+    if !console.prompts {
+        return Ok(());
+    }
+    loop {
+        let next = next_free_name(&options.filename)?;
+        if !get_yes_no(console, &format!("Make another like it as {}? (y/n): ", next))? {
+            return Ok(());
+        }
+        options.filename = next;
+        options.seed = options.seed.map(|seed| seed.wrapping_add(1));
+        options.resume = false;
+        generate(console, &options, manifest)?;
+    }
+}
+
+// The name after this one that isn't taken yet - a number before the extension is counted
+// up, and a name without one gets _2. Any .gz stays on the very end
+fn next_free_name(filename: &str) -> error::Result<String> {
+    let (filename, gz) = match filename.strip_suffix(".gz") {
+        Some(rest) => (rest, ".gz"),
+        None => (filename, ""),
+    };
+    // Only a dot in the last part of the path starts the extension, not one in a directory name
+    let name_start = filename.rfind(['/', '\\']).map_or(0, |i| i + 1);
+    let dot = filename[name_start..].rfind('.').filter(|&i| i > 0).map_or(filename.len(), |i| name_start + i);
+    let (stem, extension) = filename.split_at(dot);
+    let (base, mut number) = match stem.rsplit_once('_').map(|(base, n)| (base, n.parse::<u32>())) {
+        Some((base, Ok(n))) => (base, n),
+        _ => (stem, 1),
+    };
+    loop {
+        // A u32 would wrap round to 0 after data_4294967295, like it would in C
+        number = number.checked_add(1)
+            .ok_or_else(|| invalid_input(format!("No number after {} is free to name the next file", filename)))?;
+        let name = format!("{}_{}{}{}", base, number, extension, gz);
+        if !Path::new(&name).exists() {
+            return Ok(name);
+        }
+    }
+}
+
+// Compressed files get .gz on the end, like the gzip command does, so nobody opens one expecting text
+fn gz_name(filename: &str, compress: bool) -> String {
+    if compress && filename != STDOUT_NAME && Socket::parse(filename).is_none() && !filename.ends_with(".gz") {
+        format!("{}.gz", filename)
+    } else {
+        filename.to_string()
+    }
+}
+
+fn get_if_exists(console: &mut Console<impl BufRead, impl Write>, message: &str) -> error::Result<IfExists> {
+    // Said even to an answer file, since it explains why one more answer is needed
+    writeln!(console, "{}", message)?;
+    let input = console.read_line("(o)verwrite, (a)ppend or (c)ancel? ")?;
+    parse_if_exists(&input)
+}
+
+// The command-line version of the menu's question, for when there's no --force or --if-exists
+// A script quietly wiping last week's dataset is worse than a failed run, so without
+// a terminal to ask on the answer is no
+// What's already in the way of writing this file, if anything, for the "already exists" questions
+// A database holds other tables too, so for SQLite it's only the table that matters
+fn existing_target(filename: &str, options: &Options) -> error::Result<Option<String>> {
+    // A pipe is there to be written into, so it doesn't count as being in the way
+    if filename == STDOUT_NAME || Socket::parse(filename).is_some() || is_fifo(filename) {
+        return Ok(None);
+    }
+    if options.format == OutputFormat::Sqlite {
+        let table = table_name(options);
+        return Ok(table_exists(filename, table)?.then(|| format!("Table {} in {}", table, filename)));
+    }
+    Ok(Path::new(filename).exists().then(|| filename.to_string()))
+}
+
+// `taken` names whatever is already there - files, or tables in a database
+fn ask_if_exists(taken: &[String]) -> error::Result<IfExists> {
+    if taken.is_empty() {
+        return Ok(IfExists::Overwrite);
+    }
+    let what = match taken.len() {
+        1 => format!("{} already exists", taken[0]),
+        n => format!("{} of the files already exist", n),
+    };
+    if !io::stdin().is_terminal() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} (use --force to overwrite, or --if-exists append)", what),
+        ).into());
+    }
+    if get_yes_no(&mut Console::stdio(), &format!("{}, overwrite? (y/n): ", what))? {
+        Ok(IfExists::Overwrite)
+    } else {
+        // write_data stops at the first file that's there and leaves it alone
+        Ok(IfExists::Abort)
+    }
+}
+
+fn parse_if_exists(input: &str) -> error::Result<IfExists> {
+    match input.trim().to_lowercase().as_str() {
+        "o" | "overwrite" => Ok(IfExists::Overwrite),
+        "a" | "append" => Ok(IfExists::Append),
+        "c" | "cancel" | "abort" => Ok(IfExists::Abort),
+        _ => Err(invalid_input("Expected overwrite, append or abort")),
+    }
+}
+
+fn parse_on_interrupt(input: &str) -> error::Result<OnInterrupt> {
+    match input.trim().to_lowercase().as_str() {
+        "finish" => Ok(OnInterrupt::Finish),
+        "delete" => Ok(OnInterrupt::Delete),
+        "keep" => Ok(OnInterrupt::Keep),
+        _ => Err(invalid_input("Expected finish, delete or keep")),
+    }
+}
+
+// Everything the menu asks after the type and count - shared by single files and batches
+// The filename is left empty for the caller to fill in
+fn get_options(console: &mut Console<impl BufRead, impl Write>, data_type: DataType, count: u32, config: &Config) -> error::Result<Options> {
+    let (default_min, default_max) = config.range_for(data_type);
+    // Strings are either random letters of some length, or picked from a list of categories
+    let categories = if data_type.is_string() {
+        let input = console.read_line("Enter categories with weights, e.g. A:0.5, B:0.3, C:0.2 (press enter for random strings): ")?;
+        if input.is_empty() { None } else { Some(parse_categories(&input)?) }
+    } else {
+        None
+    };
+    let (min, max) = if data_type.has_length() {
+        // Categories bring their own labels, so the length doesn't matter
+        if categories.is_some() { (default_min, default_max) } else { get_length(console, data_type, default_min)? }
+    } else if !data_type.has_range() {
+        // There's nothing between false and true, so skip straight to how often it's true
+        // (and every bit of a UUID is random and names come from a list, so those have nothing to ask at all)
+        (default_min, default_max)
+    } else {
+        if data_type.is_timestamp() {
+            (get_time_bound(console, "Enter earliest time", default_min)?, get_time_bound(console, "Enter latest time", default_max)?)
+        } else {
+            (get_bound(console, "Enter minimum value", default_min)?, get_bound(console, "Enter maximum value", default_max)?)
+        }
+    };
+    generator::validate_range(data_type, min, max)?;
+    let exclude = if data_type.is_integer() || data_type.has_decimals() { get_exclusions(console, data_type, min, max)? } else { Vec::new() };
+    let true_probability = if data_type.is_boolean() { get_true_probability(console)? } else { 0.5 };
+    let cidr = if data_type.is_ip() { get_cidr(console)? } else { None };
+    let distribution = match categories {
+        Some(categories) => Distribution::Categorical(categories),
+        // Random strings and blobs, booleans, UUIDs and addresses only come in one flavour, so don't ask
+        None if data_type.has_length() || !data_type.has_range() => Distribution::Uniform,
+        None => get_distribution(console, min, max, count as u64)?,
+    };
+    let order = get_order(console)?;
+
+    let format = get_format(console)?;
+    // Tuples let us return several things at once without making a struct
+    let (columns, csv_header, separator) = match format {
+        OutputFormat::Csv => (get_columns(console)?, get_yes_no(console, "Include a header line? (y/n): ")?, ' '),
+        // The count asked for earlier becomes the number of rows
+        OutputFormat::Matrix => (get_columns(console)?, false, get_separator(console)?),
+        OutputFormat::Parquet | OutputFormat::Sqlite | OutputFormat::FixedWidth | OutputFormat::Code(_) => (get_columns(console)?, false, ' '),
+        OutputFormat::Text | OutputFormat::Json | OutputFormat::Binary | OutputFormat::JsonLines => (1, false, ' '),
+    };
+    let field_width = if format == OutputFormat::FixedWidth { get_field_width(console)? } else { FieldWidth::default() };
+    let endianness = if format == OutputFormat::Binary { get_endianness(console)? } else { Endianness::Little };
+    let (missing, missing_token) = if matches!(format, OutputFormat::Csv | OutputFormat::Json) {
+        get_missing(console)?
+    } else {
+        (0.0, MissingToken::Empty)
+    };
+
+    // Only worth asking when it can actually work
+    let unique = data_type.is_integer()
+        && matches!(distribution, Distribution::Uniform)
+        && get_yes_no(console, "Require all values to be unique? (y/n): ")?;
+    if unique {
+        generator::validate_unique(data_type, &distribution, min, max, count as u64 * columns as u64)?;
+    }
+    // The opposite of unique, so there's no point asking once unique was picked
+    let duplicates = if data_type.is_integer() && !unique { get_duplicates(console)? } else { 0.0 };
+    let (outliers, outlier_scale) = if (data_type.is_integer() || data_type.has_decimals()) && !unique {
+        get_outliers(console, data_type, min, max)?
+    } else {
+        (0.0, generator::DEFAULT_OUTLIER_SCALE)
+    };
+
+    // Small files are done before the threads would even start, so only offer it for big ones
+    let threads = if count as u64 * columns as u64 >= PARALLEL_PROMPT_AT
+        && !unique
+        && get_yes_no(console, "Use all CPU cores? (y/n): ")? {
+        0
+    } else {
+        1
+    };
+    // Only decimals have a precision to choose
+    let (precision, scientific) = if !data_type.has_decimals() {
+        (None, false)
+    } else {
+        let default = config.precision_for(data_type);
+        let precision = get_precision(console, default.unwrap_or(data_type.decimals()))?.or(default);
+        (precision, get_yes_no(console, "Use scientific notation? (y/n): ")?)
+    };
+    // Only quantities written out as text have a style to pick
+    let is_number = data_type.has_decimals() || (data_type.is_integer() && !data_type.is_timestamp());
+    let text_format = matches!(format, OutputFormat::Text | OutputFormat::Csv | OutputFormat::Matrix | OutputFormat::FixedWidth);
+    // Hex and binary replace the digits altogether, so there's no style left to pick after them
+    let int_format = if text_format && data_type.is_integer() && !data_type.is_timestamp() {
+        get_int_format(console)?
+    } else {
+        IntFormat::Decimal
+    };
+    let number_format = if is_number && text_format && int_format == IntFormat::Decimal {
+        get_number_format(console)?
+    } else {
+        NumberFormat::default()
+    };
+    // Binary files always hold the seconds, so there's only a choice for the text formats
+    let time_format = if data_type.is_timestamp() && format != OutputFormat::Binary {
+        get_time_format(console)?
+    } else {
+        TimeFormat::Iso
+    };
+    let bool_format = if data_type.is_boolean() && format != OutputFormat::Binary {
+        get_bool_format(console)?
+    } else {
+        BoolFormat::Words
+    };
+    let seed = get_seed(console, config.seed)?;
+    // UUIDs and addresses are labels, not amounts, so there's nothing to work out
+    let stats = !data_type.is_identifier() && get_yes_no(console, "Show statistics when done? (y/n): ")?;
+    // Binary files have no room for text, so only offer appending for the other formats
+    let append_stats = stats
+        && !matches!(format, OutputFormat::Binary | OutputFormat::FixedWidth | OutputFormat::Code(_))
+        && get_yes_no(console, "Append statistics to the file? (y/n): ")?;
+    let histogram = stats && get_yes_no(console, "Draw a histogram of the values? (y/n): ")?;
+    // Stats are the only thing a random-order file keeps in memory, so that's when it's worth asking
+    // (a histogram needs them all, so there's no choice then)
+    let streaming = stats
+        && !histogram
+        && count as u64 * columns as u64 >= PARALLEL_PROMPT_AT
+        && matches!(order, SortOrder::Random)
+        && !unique
+        && get_yes_no(console, "Keep memory use low? The median becomes an estimate (y/n): ")?;
+    // Splitting only pays off once a file is big enough to want several machines on it
+    let shards = if count as u64 * columns as u64 >= PARALLEL_PROMPT_AT && !append_stats && format != OutputFormat::Sqlite {
+        get_shards(console)?
+    } else {
+        1
+    };
+    // Parquet and SQLite compress (or don't) in their own way
+    let compress = !matches!(format, OutputFormat::Parquet | OutputFormat::Sqlite) && get_yes_no(console, "Compress with gzip? (y/n): ")?;
+    let checksum = get_yes_no(console, "Write a SHA-256 checksum file? (y/n): ")?;
+
+    Ok(Options {
+        data_type,
+        count,
+        min,
+        max,
+        exclude,
+        distribution,
+        order,
+        unique,
+        duplicates,
+        outliers,
+        outlier_scale,
+        missing,
+        missing_token,
+        format,
+        columns,
+        csv_header,
+        separator,
+        field_width,
+        endianness,
+        seed,
+        stats,
+        append_stats,
+        histogram,
+        // Someone sitting at the menu wants to see big files ticking along
+        progress: console.prompts,
+        threads,
+        streaming,
+        shards,
+        compress,
+        precision,
+        scientific,
+        number_format,
+        int_format,
+        time_format,
+        true_probability,
+        bool_format,
+        cidr,
+        checksum,
+        ..Options::default()
+    })
+}
+
+// Writes one file per size, filling the size into the filename template
+// Stops at the first failure rather than ploughing on with a half-made batch
+fn generate_batch(out: &mut impl Write, options: &Options, sizes: &[BatchSize], manifest: &mut Manifest) -> error::Result<()> {
+    // One seed for the whole batch, since the history keeps one seed per run
+    let options = &with_seed(options);
+    for size in sizes {
+        let mut file_options = options.clone();
+        file_options.count = size.count;
+        file_options.filename = options.filename.replace("{size}", &size.label);
+        // A template like data_{size}/values.txt gives each file its own directory
+        if let Some(dir) = Path::new(&file_options.filename).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            prepare_dir(dir)?;
+        }
+        writeln!(out, "Writing {} ({} values)...", file_options.filename, size.count)?;
+        generate(out, &file_options, manifest)?;
+    }
+    writeln!(out, "Created {} files.", sizes.len())?;
+    Ok(())
+}
+
+// A run without a seed gets one drawn from the OS (just like an unseeded RNG would be) and then
+// uses it like any other, so the history can record it and the same file can be made again
+// Resuming takes back the one the run started with from its checkpoint instead, so the settings
+// still match and the rest of the file follows on. The menu draws it before generate() does, so
+// offer_another() has a seed to count up from
+fn with_seed(options: &Options) -> Options {
+    let mut options = options.clone();
+    if options.seed.is_none() {
+        options.seed = match options.resume {
+            true => checkpoint_seed(&options.filename),
+            false => Some(OsRng.next_u64()),
+        };
+    }
+    options
+}
+
+// Writes the file and reports back - shared by the menu and the command line
+fn generate(out: &mut impl Write, options: &Options, manifest: &mut Manifest) -> error::Result<()> {
+    let options = &with_seed(options);
+    // Instant is a monotonic clock, like clock_gettime(CLOCK_MONOTONIC) in C
+    let started = Instant::now();
+    let stats = write_data(options)?;
+    debug!("Finished in {:.3}s", started.elapsed().as_secs_f64());
+    // A directory of blobs isn't one file for --clean to delete, so it's left off the list
+    if !writes_blob_files(options) {
+        for file in output_files(&options.filename, options.shards) {
+            manifest.add(&file, options.seed)?;
+        }
+    }
+    // When the data itself is going to stdout, anything we have to say goes to stderr
+    // so it can't end up mixed into the numbers
+    let to_stderr = options.writes_to_stdout();
+    if options.shards > 1 {
+        writeln!(out, "Created {} shards, listed in {}", options.shards, shard_manifest_name(&options.filename))?;
+    } else if options.socket().is_some() || options.writes_to_fifo() {
+        writeln!(out, "Sent to {}", options.filename)?;
+    } else if writes_blob_files(options) {
+        writeln!(out, "Created {} blobs in {}", options.count, options.filename)?;
+    } else if !to_stderr {
+        writeln!(out, "File created successfully!")?;
+    }
+    if options.progress {
+        let total = options.count as u64 * options.values_per_row() as u64;
+        let seconds = started.elapsed().as_secs_f64();
+        // A tiny file can finish in well under a microsecond, so don't divide by zero
+        let rate = if seconds > 0.0 { total as f64 / seconds } else { 0.0 };
+        eprintln!("Wrote {} values in {:.2}s ({:.0} values/sec)", total, seconds, rate);
+    }
+    if options.stats {
+        match stats {
+            Some(stats) if to_stderr => eprintln!("{}", stats_text(&stats, options.data_type)),
+            Some(stats) => writeln!(out, "{}", stats_text(&stats, options.data_type))?,
+            None => warn!("No values generated, nothing to summarize."),
+        }
+    }
+    if let Some(histogram) = stats.and_then(|stats| stats.histogram).filter(|_| options.histogram) {
+        if to_stderr {
+            eprintln!("{}", histogram_text(&histogram, options.data_type));
+        } else {
+            writeln!(out, "{}", histogram_text(&histogram, options.data_type))?;
+        }
+    }
+    Ok(())
+}
+
+// Keeps adding rows to a file until Ctrl-C, for testing code that tails it
+fn follow_file(out: &mut impl Write, options: &Options, every: Duration, manifest: &mut Manifest) -> error::Result<()> {
+    let to_stdout = options.writes_to_stdout();
+    let note = format!("Adding a row to {} every {}ms - Ctrl+C to stop", options.filename, every.as_millis());
+    if to_stdout {
+        eprintln!("{}", note);
+    } else {
+        writeln!(out, "{}", note)?;
+        out.flush()?;
+    }
+    let rows = follow(options, every)?;
+    if to_stdout {
+        eprintln!("Stopped after {} rows", rows);
+        return Ok(());
+    }
+    manifest.add(&options.filename, options.seed)?;
+    writeln!(out, "Stopped after {} rows", rows)?;
+    Ok(())
+}
+
+// Works out the count that makes a file of about `target` bytes from a sample of it,
+// writes it, and says how close it came
+fn generate_to_size(out: &mut impl Write, options: &Options, target: u64, manifest: &mut Manifest) -> error::Result<()> {
+    // Appending would add the target onto whatever is already there
+    if options.if_exists == IfExists::Append {
+        return Err(invalid_input("--target-size makes a new file, so it can't append to one"));
+    }
+    if options.socket().is_some() || options.writes_to_fifo() {
+        return Err(invalid_input("--target-size measures the file it made, so it needs a file, not a socket or pipe"));
+    }
+    let options = Options { count: count_for_size(options, target)?, ..options.clone() };
+    let plan = format!("Aiming for {}: {} values", size_text(target), options.count as u64 * options.values_per_row() as u64);
+    if options.writes_to_stdout() {
+        eprintln!("{}", plan);
+        return generate(out, &options, manifest);
+    }
+    writeln!(out, "{}", plan)?;
+    generate(out, &options, manifest)?;
+    let bytes = Path::new(&options.filename).metadata()?.len();
+    let off = bytes as f64 / target as f64 - 1.0;
+    writeln!(out, "{} is {} ({:+.2}% from {})", options.filename, size_text(bytes), off * 100.0, size_text(target))?;
+    // A tiny file can't get closer than one row either way
+    let row_bytes = bytes as f64 / options.count.max(1) as f64;
+    if off.abs() > SIZE_TOLERANCE && bytes.abs_diff(target) as f64 > row_bytes {
+        warn!("That's more than {}% off - the sample the count came from wasn't typical of the whole file", SIZE_TOLERANCE * 100.0);
+    }
+    Ok(())
+}
+
+// Lists the files generated so far and deletes the ones picked
+fn clean_up(console: &mut Console<impl BufRead, impl Write>, manifest: &mut Manifest) -> error::Result<()> {
+    if manifest.files().is_empty() {
+        writeln!(console, "No generated files to delete.")?;
+        return Ok(());
+    }
+    for (i, file) in manifest.files().iter().enumerate() {
+        let note = if Path::new(file).exists() { "" } else { " (already gone)" };
+        console.explain(&format!("{:3}. {}{}", i + 1, file, note))?;
+    }
+    let input = console.read_line("Enter the numbers to delete (e.g. 1,3), a for all, or press enter to keep them: ")?;
+    let picked: Vec<String> = match input.to_lowercase().as_str() {
+        "" => return Ok(()),
+        "a" | "all" => manifest.files().to_vec(),
+        _ => input.split(',').map(|number| {
+            // checked_sub turns 0 into None instead of wrapping round like unsigned maths in C
+            number.trim().parse::<usize>().ok()
+                .and_then(|number| number.checked_sub(1))
+                .and_then(|i| manifest.files().get(i).cloned())
+                .ok_or_else(|| invalid_input(format!("Not on the list: {}", number.trim())))
+        }).collect::<error::Result<_>>()?,
+    };
+    for file in picked {
+        manifest.delete(&file)?;
+        writeln!(console, "Deleted {}", file)?;
+    }
+    Ok(())
+}
+
+// --clean: deletes everything in the manifest without asking, for scripts
+fn clean_all(out: &mut impl Write, manifest: &mut Manifest) -> error::Result<()> {
+    let path = manifest.path()
+        .ok_or_else(|| invalid_input("--clean needs a manifest (--manifest <file> or manifest in generator.toml)"))?
+        .to_string();
+    let files = manifest.files().to_vec();
+    for file in &files {
+        manifest.delete(file)?;
+        writeln!(out, "Deleted {}", file)?;
+    }
+    writeln!(out, "Removed {} file(s) listed in {}.", files.len(), path)?;
+    Ok(())
+}
+
+// Prints what's in a data file so users can check it without leaving the program
+fn display_file(out: &mut impl Write, filename: &str) -> error::Result<()> {
+    let data = load_file(filename)?;
+    // Closures are like little inline functions - this one matches the text output style
+    let show = |v: &f64| if data.data_type.is_timestamp() {
+        format_iso(*v as i64)
+    } else if data.data_type.is_boolean() {
+        (*v != 0.0).to_string()
+    } else if data.data_type.is_integer() {
+        v.to_string()
+    } else {
+        format!("{:.*}", data.data_type.decimals(), v)
+    };
+    // Text, blobs (as hex), UUIDs and addresses are shown as they are; everything else goes through show
+    let shown: Vec<String> = if data.data_type.is_text() || data.data_type.is_bytes() || data.data_type.is_identifier() {
+        data.strings.clone()
+    } else {
+        data.values.iter().map(show).collect()
+    };
+
+    writeln!(out, "File: {}", filename)?;
+    writeln!(out, "Format: {}", data.format.name())?;
+    writeln!(out, "Type: {}", data.data_type.name())?;
+    match data.declared_count {
+        Some(count) => writeln!(out, "Count: {} (header says {})", data.values.len(), count)?,
+        None => writeln!(out, "Count: {}", data.values.len())?,
+    }
+    if let Some(columns) = data.columns {
+        writeln!(out, "Columns: {}", columns)?;
+    }
+    if let Some(seed) = data.seed {
+        writeln!(out, "Seed: {}", seed)?;
+    }
+
+    const PREVIEW: usize = 5;
+    writeln!(out, "First values: {}", shown[..shown.len().min(PREVIEW)].join(", "))?;
+    if shown.len() > PREVIEW {
+        // Start the tail after the head so short files don't print the same values twice
+        let start = (shown.len() - PREVIEW).max(PREVIEW);
+        writeln!(out, "Last values: {}", shown[start..].join(", "))?;
+    }
+    // Every UUID is 36 characters, so their "lengths" aren't worth a summary
+    if let Some(stats) = compute_stats(&data.values).filter(|_| !data.data_type.is_identifier()) {
+        writeln!(out, "{}", stats_text(&stats, data.data_type))?;
+        if let Some(histogram) = stats.histogram {
+            writeln!(out, "{}", histogram_text(&histogram, data.data_type))?;
+        }
+    }
+    Ok(())
+}
+
+// --dry-run: works out how big and how slow the run will be from a sample, then asks
+// before doing it for real - or just reports, when there's nobody at the keyboard to ask
+fn dry_run(out: &mut impl Write, command: Command, manifest: &mut Manifest) -> error::Result<()> {
+    let (options, sizes) = match command {
+        Command::Generate(options) => (options, None),
+        Command::ToSize(options, target) => (Options { count: count_for_size(&options, target)?, ..options }, None),
+        Command::Batch(options, sizes) => (options, Some(sizes)),
+        _ => return Err(invalid_input("--dry-run only applies to generating files")),
+    };
+    let mut total = Estimate { count: 0, sample: 0, bytes: 0, secs: 0.0 };
+    let files: Vec<(String, u32)> = match &sizes {
+        Some(sizes) => sizes.iter().map(|size| (options.filename.replace("{size}", &size.label), size.count)).collect(),
+        None => vec![(options.filename.clone(), options.count)],
+    };
+    for (filename, count) in files {
+        let estimate = estimate(&Options { count, ..options.clone() })?;
+        writeln!(
+            out,
+            "{}: {} values, about {} ({:.1} bytes per value) in {}",
+            filename, estimate.count, size_text(estimate.bytes), estimate.bytes_per_value(), duration_text(estimate.secs)
+        )?;
+        total.count += estimate.count;
+        total.sample = total.sample.max(estimate.sample);
+        total.bytes += estimate.bytes;
+        total.secs += estimate.secs;
+    }
+    if sizes.is_some() {
+        writeln!(out, "Total: {} values, about {} in {}", total.count, size_text(total.bytes), duration_text(total.secs))?;
+    }
+    if total.sample < total.count {
+        writeln!(out, "(Scaled up from a sample of {} values; sorting and slower disks take longer.)", total.sample)?;
+    }
+    if !io::stdin().is_terminal() {
+        writeln!(out, "Dry run only, nothing was written.")?;
+        return Ok(());
+    }
+    if !get_yes_no(&mut Console::stdio(), "Generate it now? (y/n): ")? {
+        return Ok(());
+    }
+    match sizes {
+        Some(sizes) => generate_batch(out, &options, &sizes, manifest),
+        None => generate(out, &options, manifest),
+    }
+}
+
+// A rough time, rounded to the two biggest units that matter: 45s, 3m 05s, 2h 10m
+fn duration_text(secs: f64) -> String {
+    let whole = secs.round() as u64;
+    match whole {
+        0 => String::from("under a second"),
+        1..=59 => format!("{}s", whole),
+        60..=3599 => format!("{}m {:02}s", whole / 60, whole % 60),
+        _ => format!("{}h {:02}m", whole / 3600, whole % 3600 / 60),
+    }
+}
+
+// Runs the benchmark at each size and prints one row of the table per size
+// Each size is run once, so expect the small ones to jump around a bit between runs
+fn run_bench(out: &mut impl Write, options: &Options, sizes: &[BatchSize]) -> error::Result<()> {
+    let threads = match options.threads {
+        0 => "all cores".to_string(),
+        threads => format!("{} thread(s)", threads),
+    };
+    let what = if options.schema.is_empty() {
+        format!("{} values", options.data_type.name())
+    } else {
+        format!("{}-field records", options.schema.len())
+    };
+    writeln!(out, 
+        "Benchmark: {}, {} format, {}, {} buffer",
+        what, options.format.name(), threads, buffer_label(options.buffer_size)
+    )?;
+    writeln!(out, "{:>8}  {:>18}  {:>18}  {:>12}  {:>10}", "Size", "Generate (vals/s)", "Write (vals/s)", "Write (MB/s)", "File (MB)")?;
+    for size in sizes {
+        let mut options = options.clone();
+        options.count = size.count;
+        let result = bench(&options)?;
+        writeln!(out, 
+            "{:>8}  {:>18.0}  {:>18.0}  {:>12.1}  {:>10.2}",
+            size.label,
+            result.generate_rate(),
+            result.write_rate(),
+            result.write_mb_per_sec(),
+            result.bytes as f64 / 1_000_000.0,
+        )?;
+    }
+
+    // Generating doesn't touch the buffer, so this table only shows the write side
+    let mut buffers = BENCH_BUFFERS.to_vec();
+    if !buffers.contains(&options.buffer_size) {
+        buffers.push(options.buffer_size);
+        buffers.sort();
+    }
+    let mut options = options.clone();
+    options.count = largest_size(sizes);
+    writeln!(out)?;
+    writeln!(out, "Buffer size at {} values:", options.count)?;
+    writeln!(out, "{:>8}  {:>18}  {:>12}", "Buffer", "Write (vals/s)", "Write (MB/s)")?;
+    for buffer_size in buffers {
+        options.buffer_size = buffer_size;
+        let result = bench(&options)?;
+        writeln!(out, "{:>8}  {:>18.0}  {:>12.1}", buffer_label(buffer_size), result.write_rate(), result.write_mb_per_sec())?;
+    }
+    // Binary files can skip the buffer altogether, so they get one more row to compare against
+    if options.format == OutputFormat::Binary && !options.compress {
+        options.mmap = true;
+        let result = bench(&options)?;
+        writeln!(out, "{:>8}  {:>18.0}  {:>12.1}", "mmap", result.write_rate(), result.write_mb_per_sec())?;
+    }
+    Ok(())
+}
+
+// Runs until the program is killed, turning each request into flags and parsing them
+// exactly like the command line, so the same defaults and the same error messages apply
+fn run_server(out: &mut impl Write, address: &str, config: &Config) -> error::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    writeln!(out, "Serving random data on http://{}{}?type=int&count=100 (Ctrl+C to stop)", listener.local_addr()?, GENERATE_PATH)?;
+    out.flush()?;
+    serve(listener, |args| match parse_args(args, config)? {
+        Command::Generate(options) => Ok(options),
+        _ => Err(invalid_input("Only a single dataset can be served, not a batch")),
+    })
+}
+
+// 65536 -> "64K", the same way --buffer-size takes it
+fn buffer_label(bytes: usize) -> String {
+    match bytes {
+        _ if bytes >= 1 << 20 && bytes.is_multiple_of(1 << 20) => format!("{}M", bytes >> 20),
+        _ if bytes >= 1 << 10 && bytes.is_multiple_of(1 << 10) => format!("{}K", bytes >> 10),
+        _ => bytes.to_string(),
+    }
+}
+
+// Compares a file against its .sha256 sidecar
+// Like verify, a mismatch is an error so scripts get a non-zero exit code
+fn check_checksum(out: &mut impl Write, filename: &str) -> error::Result<()> {
+    let check = verify_checksum(filename)?;
+    writeln!(out, "File: {}", filename)?;
+    writeln!(out, "Expected: {}", check.expected)?;
+    writeln!(out, "Actual:   {}", check.actual)?;
+    if check.is_ok() {
+        writeln!(out, "OK - checksum matches")?;
+        return Ok(());
+    }
+    Err(invalid_data(format!("{} doesn't match {}", filename, checksum_name(filename))))
+}
+
+// Shuffles a file's rows into a new file (or over itself, which isn't overwriting anything else)
+fn shuffle(out: &mut impl Write, input: &str, output: &str, seed: Option<u64>, force: bool) -> error::Result<()> {
+    let in_place = Path::new(output).exists()
+        && std::fs::canonicalize(input).ok() == std::fs::canonicalize(output).ok();
+    if Path::new(output).exists() && !in_place && !force {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists (use --force to overwrite)", output)).into());
+    }
+    let rows = shuffle_file(input, output, seed)?;
+    writeln!(out, "Shuffled {} rows of {} into {}", rows, input, output)?;
+    Ok(())
+}
+
+// Joins the files into one - writing over one of them is fine, since it's all read before the rename
+fn merge(out: &mut impl Write, inputs: &[String], output: &str, order: MergeOrder, force: bool) -> error::Result<()> {
+    let inputs = merge_inputs(inputs)?;
+    let is_input = inputs.iter().any(|input| std::fs::canonicalize(input).ok() == std::fs::canonicalize(output).ok());
+    if Path::new(output).exists() && !is_input && !force {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists (use --force to overwrite)", output)).into());
+    }
+    let rows = merge_files(&inputs, output, order)?;
+    writeln!(out, "Merged {} files ({} rows) into {}", inputs.len(), rows, output)?;
+    Ok(())
+}
+
+fn split(out: &mut impl Write, input: &str, output: &str, size: SplitSize, force: bool) -> error::Result<()> {
+    let parts = split_file(input, output, size, force)?;
+    for (name, rows) in &parts {
+        writeln!(out, "{} ({} rows)", name, rows)?;
+    }
+    writeln!(out, "Split {} into {} parts, listed in {}", input, parts.len(), shard_manifest_name(output))?;
+    Ok(())
+}
+
+// Rewrites a file in another format - converting it onto itself is fine, since it's all read in first
+fn convert(out: &mut impl Write, input: &str, options: &Options) -> error::Result<()> {
+    let mut options = convert_options(input, options)?;
+    if std::fs::canonicalize(input).ok() == std::fs::canonicalize(&options.filename).ok() {
+        options.if_exists = IfExists::Overwrite;
+    }
+    write_data(&options)?;
+    let total = options.count as u64 * options.columns as u64;
+    writeln!(out, "Converted {} {} values from {} into {} ({})", total, options.data_type.name(), input, options.filename, options.format.name())?;
+    Ok(())
+}
+
+// Checks a file and lists anything wrong with it
+// A file with problems comes back as an error so scripts get a non-zero exit code
+fn verify(out: &mut impl Write, filename: &str) -> error::Result<()> {
+    let report = verify_file(filename)?;
+    writeln!(out, "File: {}", filename)?;
+    writeln!(out, "Format: {}", report.format.name())?;
+    writeln!(out, "Type: {}", report.data_type.name())?;
+    match report.declared_count {
+        Some(count) => writeln!(out, "Count: {} (header says {})", report.found_count, count)?,
+        None => writeln!(out, "Count: {}", report.found_count)?,
+    }
+    if report.is_ok() {
+        if report.data_type.is_bytes() {
+            writeln!(out, "OK - every value is whole bytes of hex")?;
+        } else {
+            writeln!(out, "OK - every value is a valid {}", report.data_type.name())?;
+        }
+        return Ok(());
+    }
+
+    // A badly broken file could have millions of problems, and the first few tell the story
+    const SHOWN: usize = 20;
+    writeln!(out, "Found {} problem(s):", report.problems.len())?;
+    for problem in report.problems.iter().take(SHOWN) {
+        writeln!(out, "  {}", problem)?;
+    }
+    if report.problems.len() > SHOWN {
+        writeln!(out, "  ... and {} more", report.problems.len() - SHOWN)?;
+    }
+    Err(invalid_data(format!("{} failed verification", filename)))
+}
+
+fn stats_text(stats: &Stats, data_type: DataType) -> String {
+    // A string has no numeric value, so its stats are worked out from the lengths
+    if data_type.is_text() {
+        return format!("({} lengths)\n{}", data_type.name(), stats);
+    }
+    if data_type.is_bytes() {
+        return format!("(blob sizes in bytes)\n{}", stats);
+    }
+    // Seconds are what stats can add up, so say so and show where the range starts and ends
+    if data_type.is_timestamp() {
+        return format!(
+            "(seconds since 1970, from {} to {})\n{}",
+            format_iso(stats.min as i64), format_iso(stats.max as i64), stats
+        );
+    }
+    // true counts as 1 and false as 0, so the mean is the share that came out true
+    if data_type.is_boolean() {
+        return format!("(true = 1, false = 0, so the mean is the share of true)
+{}", stats);
+    }
+    stats.to_string()
+}
+
+// Labels the bins the way the values are written: dates for timestamps, true/false for booleans
+fn histogram_text(histogram: &Histogram, data_type: DataType) -> String {
+    if data_type.is_timestamp() {
+        histogram.render(|seconds| format_iso(seconds as i64))
+    } else if data_type.is_boolean() {
+        histogram.render(|v| (v != 0.0).to_string())
+    } else {
+        histogram.to_string()
+    }
+}
+
+
+// These drive the real menu with typed-in answers, the same way someone at the keyboard would
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A fresh path in the temp directory, so tests running side by side never share a file
+    fn temp_file(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("ruststf_test_{}_{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    // Feeds the answers in one per line and hands back everything the menu printed
+    fn run_with(answers: &[&str], manifest: &mut Manifest) -> String {
+        let input = answers.iter().map(|answer| format!("{}\n", answer)).collect::<String>();
+        let mut console = Console::new(input.as_bytes(), Vec::new());
+        run_menu(&mut console, &Config::default(), manifest).unwrap();
+        String::from_utf8(console.into_output()).unwrap()
+    }
+
+    // Menu answers for an int file: type, count, min, max, exclusions, distribution, order, format, unique,
+    // duplicates, outliers, integer format, number style, seed, stats, compress, checksum, then the filename
+    fn create_ints<'a>(count: &'a str, min: &'a str, max: &'a str, filename: &'a str) -> Vec<&'a str> {
+        vec!["1", "i", count, min, max, "", "", "", "t", "n", "", "", "", "", "42", "n", "n", "n", filename]
+    }
+
+    #[test]
+    fn menu_creates_a_file_in_range() {
+        let filename = temp_file("menu_ints.txt");
+        let mut answers = create_ints("50", "-3", "3", &filename);
+        answers.extend(["n", "11"]);
+        let printed = run_with(&answers, &mut Manifest::session());
+        assert!(printed.contains("File created successfully!"), "{}", printed);
+        assert!(printed.ends_with("Program terminated.\n"));
+
+        let data = load_file(&filename).unwrap();
+        assert_eq!(data.data_type, DataType::Integer);
+        assert_eq!(data.values.len(), 50);
+        assert_eq!(data.seed, Some(42));
+        assert!(data.values.iter().all(|v| (-3.0..=3.0).contains(v)));
+        fs::remove_file(&filename).unwrap();
+    }
+
+    #[test]
+    fn same_answers_same_file() {
+        let first = temp_file("menu_seed_a.txt");
+        let second = temp_file("menu_seed_b.txt");
+        run_with(&create_ints("100", "0", "1000", &first), &mut Manifest::session());
+        run_with(&create_ints("100", "0", "1000", &second), &mut Manifest::session());
+        assert_eq!(fs::read(&first).unwrap(), fs::read(&second).unwrap());
+        fs::remove_file(&first).unwrap();
+        fs::remove_file(&second).unwrap();
+    }
+
+    #[test]
+    fn another_file_like_it_gets_the_next_name() {
+        let filename = temp_file("menu_more.txt");
+        let second = temp_file("menu_more_2.txt");
+        let third = temp_file("menu_more_3.txt");
+        let mut answers = create_ints("8", "0", "9", &filename);
+        answers.extend(["y", "y", "n", "11"]);
+        let printed = run_with(&answers, &mut Manifest::session());
+        assert!(printed.contains(&format!("Make another like it as {}?", third)), "{}", printed);
+        // Same shape, but each seed one more than the last so the values differ
+        for (name, seed) in [(&filename, 42), (&second, 43), (&third, 44)] {
+            let data = load_file(name).unwrap();
+            assert_eq!((data.values.len(), data.seed), (8, Some(seed)));
+            fs::remove_file(name).unwrap();
+        }
+        assert_eq!(next_free_name("out.v1/data_9.csv.gz").unwrap(), "out.v1/data_10.csv.gz");
+        assert_eq!(next_free_name("notes").unwrap(), "notes_2");
+        assert!(next_free_name(&format!("data_{}.txt", u32::MAX)).is_err());
+    }
+
+    #[test]
+    fn menu_generates_a_past_file_again() {
+        let filename = temp_file("menu_again.txt");
+        let mut manifest = Manifest::session();
+        let mut answers = create_ints("30", "0", "99", &filename);
+        answers.extend(["n", "11"]);
+        run_with(&answers, &mut manifest);
+        let made = fs::read(&filename).unwrap();
+
+        // Deleted, then made again from the history with the same seed, so the same bytes
+        fs::remove_file(&filename).unwrap();
+        let printed = run_with(&["10", "1", "11"], &mut manifest);
+        assert!(printed.contains("File created successfully!"), "{}", printed);
+        assert_eq!(fs::read(&filename).unwrap(), made);
+        let entries = manifest.history().entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].seed, Some(42));
+        assert_eq!(entries[1].recipe, entries[0].recipe);
+        assert!(run_with(&["10", "3", "11"], &mut manifest).contains("Expected a number from 1 to 2"));
+        fs::remove_file(&filename).unwrap();
+    }
+
+    #[test]
+    fn an_unseeded_run_comes_out_the_same_again() {
+        let filename = temp_file("menu_unseeded.txt");
+        let mut manifest = Manifest::session();
+        let mut answers = create_ints("30", "0", "99", &filename);
+        answers[14] = ""; // Blank for a random seed
+        answers.extend(["n", "11"]);
+        run_with(&answers, &mut manifest);
+        let made = fs::read(&filename).unwrap();
+        // The seed it was given is the one in the file and the one in the history
+        let seed = manifest.history().entries()[0].seed.expect("no seed recorded");
+        assert_eq!(load_file(&filename).unwrap().seed, Some(seed));
+
+        fs::remove_file(&filename).unwrap();
+        run_with(&["10", "1", "11"], &mut manifest);
+        assert_eq!(fs::read(&filename).unwrap(), made);
+        assert_eq!(manifest.history().entries()[1].seed, Some(seed));
+        fs::remove_file(&filename).unwrap();
+    }
+
+    #[test]
+    fn an_unseeded_run_can_be_resumed() {
+        let filename = temp_file("resume_unseeded.txt");
+        let parse = |flags: &str| {
+            let args: Vec<String> = format!("-t int -n 1000 -o {} {}", filename, flags).split_whitespace().map(String::from).collect();
+            match parse_args(&args, &Config::default()) {
+                Ok(Command::Generate(options)) => options,
+                _ => panic!("{} isn't a plain generate", flags),
+            }
+        };
+        let mut options = parse("");
+        generate(&mut io::sink(), &options, &mut Manifest::session()).unwrap();
+        let whole = fs::read(&filename).unwrap();
+        // Killed after 400 rows, with the random seed it was given in the checkpoint
+        options.seed = load_file(&filename).unwrap().seed;
+        assert!(options.seed.is_some());
+        crate::checkpoint::tests::interrupt(&options, 400, crate::checkpoint::tests::end_of_line(&whole, 402));
+        fs::remove_file(&filename).unwrap();
+
+        generate(&mut io::sink(), &parse("--resume"), &mut Manifest::session()).unwrap();
+        assert_eq!(fs::read(&filename).unwrap(), whole);
+        fs::remove_file(&filename).unwrap();
+    }
+
+    #[test]
+    fn menu_creates_records() {
+        let filename = temp_file("menu_records.csv");
+        let printed = run_with(
+            &["6", "id:int(1..9), ok:bool", "3", "c", "1", "n", "n", &filename, "n", "11"],
+            &mut Manifest::session(),
+        );
+        assert!(printed.contains("File created successfully!"), "{}", printed);
+        let text = fs::read_to_string(&filename).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "id,ok");
+        assert_eq!(lines.len(), 4);
+        for line in &lines[1..] {
+            let (id, ok) = line.split_once(',').unwrap();
+            assert!((1..=9).contains(&id.parse::<i32>().unwrap()), "{}", line);
+            assert!(ok == "true" || ok == "false", "{}", line);
+        }
+        fs::remove_file(&filename).unwrap();
+    }
+
+    #[test]
+    fn menu_creates_a_weighted_graph() {
+        let filename = temp_file("menu_graph.txt");
+        let printed = run_with(
+            &["7", "20", "30", "y", "", "5", "9", "", "4", "n", "n", &filename, "n", "11"],
+            &mut Manifest::session(),
+        );
+        assert!(printed.contains("File created successfully!"), "{}", printed);
+        let text = fs::read_to_string(&filename).unwrap();
+        let edges: Vec<Vec<u32>> = text.lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| line.split(' ').map(|n| n.parse().unwrap()).collect())
+            .collect();
+        assert_eq!(edges.len(), 30);
+        for edge in &edges {
+            assert!(edge[0] < edge[1] && edge[1] < 20, "{:?}", edge);
+            assert!((5..=9).contains(&edge[2]), "{:?}", edge);
+        }
+        fs::remove_file(&filename).unwrap();
+    }
+
+    #[test]
+    fn menu_reads_back_and_cleans_up() {
+        let filename = temp_file("menu_clean.txt");
+        let mut manifest = Manifest::session();
+        let mut answers = create_ints("10", "0", "9", &filename);
+        answers.extend(["n", "3", &filename, "8", "a", "11"]);
+        let printed = run_with(&answers, &mut manifest);
+        assert!(printed.contains("Count: 10"), "{}", printed);
+        assert!(printed.contains("Deleted "), "{}", printed);
+        assert!(!Path::new(&filename).exists());
+        assert!(manifest.files().is_empty());
+    }
+
+    #[test]
+    fn subcommands_pick_the_command() {
+        let parse = |line: &str| {
+            let args: Vec<String> = line.split_whitespace().map(String::from).collect();
+            parse_args(&args, &Config::default())
+        };
+        let filename = temp_file("subcommand.txt");
+        assert!(matches!(parse("inspect data.txt"), Ok(Command::Display(name)) if name == "data.txt"));
+        assert!(matches!(parse("verify data.txt"), Ok(Command::Verify(_))));
+        assert!(matches!(parse("verify --checksum data.txt"), Ok(Command::VerifyChecksum(_))));
+        assert!(matches!(parse("menu --config generator.toml"), Ok(Command::Menu)));
+        assert!(matches!(parse("bench --sizes 10"), Ok(Command::Bench(..))));
+        // generate is the default, so flags on their own still work
+        for line in [format!("generate -t int -n 5 -o {}", filename), format!("-t int -n 5 -o {}", filename)] {
+            assert!(matches!(parse(&line), Ok(Command::Generate(options)) if options.count == 5));
+        }
+        assert!(parse("inspect").is_err());
+        assert!(parse("inspect a.txt b.txt").is_err());
+        assert!(parse("menu extra").is_err());
+        assert!(parse("inspect --stats a.txt").is_err());
+        assert!(matches!(parse("shuffle a.txt -o b.txt --seed 1"), Ok(Command::Shuffle(input, _, Some(1), false)) if input == "a.txt"));
+        assert!(parse("shuffle a.txt").is_err());
+        assert!(matches!(parse("merge a.txt b.txt -o c.txt --order desc"), Ok(Command::Merge(inputs, _, MergeOrder::Descending, false)) if inputs.len() == 2));
+        assert!(parse("merge -o c.txt").is_err());
+        assert!(matches!(parse("split a.txt --rows 1000"), Ok(Command::Split(input, output, SplitSize::Rows(1000), false)) if input == output));
+        assert!(parse("split a.txt").is_err());
+        assert!(matches!(parse("convert a.txt -f csv -o b.csv --columns 3"), Ok(Command::Convert(input, options)) if input == "a.txt" && options.columns == 3));
+        assert!(parse("convert a.txt -o b.csv").is_err());
+        let code = format!("-t int -n 5 -f code --language rust -o {}", filename);
+        assert!(matches!(parse(&code), Ok(Command::Generate(options)) if options.format == OutputFormat::Code(Language::Rust)));
+        assert!(parse("-t int -n 5 --language rust").is_err());
+
+        // DATAGEN_ variables go in front of generate's flags, so the flags win, and leave other commands alone
+        let vars: Vec<(String, String)> = [("DATAGEN_UNIQUE", "1"), ("DATAGEN_FORMAT", "csv"), ("DATAGEN_STATS", "0"), ("DATAGEN_COUNT", "9")]
+            .iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        let args = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<String>>();
+        assert_eq!(with_env_args(args("generate -f json"), &vars).unwrap(), args("generate --format csv --unique -f json"));
+        assert_eq!(with_env_args(args("inspect a.txt"), &vars).unwrap(), args("inspect a.txt"));
+        assert!(with_env_args(args("-t int"), &[(String::from("DATAGEN_UNIQUE"), String::from("maybe"))]).is_err());
+
+        // -q and -v come out wherever they are, and the last one wins
+        let (rest, level) = take_log_level(args("-q -t int -v -n 5"));
+        assert_eq!((rest, level), (args("-t int -n 5"), LevelFilter::Debug));
+        assert_eq!(take_log_level(args("serve")).1, LevelFilter::Info);
+
+        // Only a command line that can't be read gets the usage text, not one with a bad setting
+        let config = Config::default();
+        for (line, usage) in [("-t int --bogus", true), ("-t int -n", true), ("inspect a b", true), ("-t int --min 9 --max 1 -o -", false)] {
+            let Err(e) = parse_args(&args(line), &config) else { panic!("{} was accepted", line) };
+            assert_eq!(matches!(e, GeneratorError::Usage(_)), usage, "{}", line);
+        }
+    }
+
+    #[test]
+    fn counts_sizes_and_rates_are_parsed() {
+        assert_eq!(parse_sizes("1k, 2.5M").unwrap().iter().map(|size| size.count).collect::<Vec<u32>>(), [1000, 2_500_000]);
+        assert_eq!(parse_every("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_every("2s").unwrap(), parse_every("2000").unwrap());
+        assert_eq!(parse_rate("30/m").unwrap(), 0.5);
+        assert_eq!(parse_rate("100").unwrap(), 100.0);
+        assert!(parse_rate("5/fortnight").is_err() && parse_rate("0/s").is_err());
+        // File sizes take the same suffixes, with or without a B, and go past u32
+        assert_eq!(parse_target_size("1.5GB").unwrap(), 1_500_000_000);
+        assert_eq!(parse_target_size("5T").unwrap(), 5_000_000_000_000);
+        assert!(parse_target_size("0MB").is_err());
+    }
+
+    #[test]
+    fn formats_are_shown_the_way_they_are_typed() {
+        let formats = [
+            OutputFormat::Text, OutputFormat::Csv, OutputFormat::Json, OutputFormat::Binary, OutputFormat::Matrix,
+            OutputFormat::JsonLines, OutputFormat::Parquet, OutputFormat::Sqlite, OutputFormat::FixedWidth,
+        ];
+        for format in formats {
+            assert_eq!(parse_format(format.name()).unwrap(), format, "{}", format.name());
+        }
+        // A code format goes by its language, which --language reads back
+        for language in [Language::C, Language::Cpp, Language::Rust, Language::Python] {
+            assert_eq!(parse_language(OutputFormat::Code(language).name()).unwrap(), language);
+        }
+        assert_eq!(OutputFormat::JsonLines.name(), "jsonl");
+    }
+
+    #[test]
+    fn presets_are_saved_and_run_from_the_menu() {
+        let config = Config { presets: Some(temp_file("presets.toml")), ..Config::default() };
+        let filename = temp_file("preset_ints.txt");
+        let args: Vec<String> = ["-t", "int", "-n", "20", "--order", "desc", "--save-preset", "desc-ints"]
+            .iter().map(|arg| arg.to_string()).collect();
+        let Ok(Command::SavePreset(name, saved)) = parse_args(&args, &config) else { panic!("expected a preset") };
+        save_preset(&mut Vec::new(), &config, &name, &saved).unwrap();
+        // The preset left the filename out, so the menu asks for it
+        let input = format!("9\ndesc-ints\n{}\nn\n11\n", filename);
+        let mut console = Console::new(input.as_bytes(), Vec::new());
+        run_menu(&mut console, &config, &mut Manifest::session()).unwrap();
+        let printed = String::from_utf8(console.into_output()).unwrap();
+        assert!(printed.contains("1. desc-ints: -t int -n 20 --order desc"), "{}", printed);
+
+        let data = load_file(&filename).unwrap();
+        assert_eq!(data.values.len(), 20);
+        assert!(data.values.windows(2).all(|pair| pair[0] >= pair[1]));
+        // On the command line, later flags win over the preset's
+        let args: Vec<String> = ["--preset", "desc-ints", "-n", "5", "-o", &filename, "--force"]
+            .iter().map(|arg| arg.to_string()).collect();
+        assert!(matches!(parse_args(&args, &config), Ok(Command::Generate(options)) if options.count == 5));
+        fs::remove_file(&filename).unwrap();
+        fs::remove_file(config.presets.unwrap()).unwrap();
+    }
+
+    #[test]
+    fn answer_files_skip_the_questions() {
+        let filename = temp_file("answer_file.txt");
+        let input = create_ints("5", "0", "9", &filename).join("\n");
+        let mut console = Console::new(input.as_bytes(), Vec::new()).with_prompts(false);
+        run_menu(&mut console, &Config::default(), &mut Manifest::session()).unwrap();
+        // Only what happened gets printed, not the menu or the questions
+        let printed = String::from_utf8(console.into_output()).unwrap();
+        assert_eq!(printed, "File created successfully!\nProgram terminated.\n");
+        assert_eq!(load_file(&filename).unwrap().values.len(), 5);
+        fs::remove_file(&filename).unwrap();
+
+        // A wrong answer would throw every later one out of step, so it stops the run
+        let mut console = Console::new("1\nnonsense\n1\n".as_bytes(), Vec::new()).with_prompts(false);
+        let error = run_menu(&mut console, &Config::default(), &mut Manifest::session()).unwrap_err();
+        assert_eq!(error.to_string(), "Error creating file: Invalid data type: nonsense");
+    }
+
+    #[test]
+    fn the_readme_answer_file_works() {
+        // Taken from the README itself, so a new question can't leave the example behind
+        let readme = include_str!("../README.md");
+        let start = readme.find("printf \"1\\ni").expect("no answer file in the README") + "printf \"".len();
+        let answers = &readme[start..start + readme[start..].find('"').unwrap()];
+        let filename = temp_file("readme_answers.txt");
+        let input = answers.replace("\\n", "\n").replace("out.txt", &filename);
+        let mut console = Console::new(input.as_bytes(), Vec::new()).with_prompts(false);
+        run_menu(&mut console, &Config::default(), &mut Manifest::session()).unwrap();
+        let data = load_file(&filename).unwrap();
+        assert_eq!((data.values.len(), data.seed), (100, Some(42)));
+        assert!(data.values.iter().all(|v| (0.0..=9.0).contains(v)));
+        fs::remove_file(&filename).unwrap();
+    }
+
+    #[test]
+    fn bad_answers_are_reported_not_fatal() {
+        let printed = run_with(&["12", "1", "x", "y", "z", "11"], &mut Manifest::session());
+        assert!(printed.contains("Invalid choice!"));
+        assert!(printed.contains("Invalid data type: x - try again"), "{}", printed);
+        // After MAX_ATTEMPTS the file is given up on, and the menu carries on
+        assert!(printed.contains("Error creating file: Invalid data type: z"), "{}", printed);
+        assert!(printed.ends_with("Program terminated.\n"));
+    }
+
+    #[test]
+    fn typos_are_asked_again_and_cancel_goes_back() {
+        let filename = temp_file("menu_retry.txt");
+        let mut answers = create_ints("5", "0", "9", &filename);
+        answers.splice(1..1, ["nonsense"]);
+        answers.splice(3..3, ["lots"]);
+        answers.extend(["n", "3", "", &filename, "1", "cancel", "11"]);
+        let printed = run_with(&answers, &mut Manifest::session());
+        assert!(printed.contains("Invalid number - try again"), "{}", printed);
+        // The answers before and after the typos all still count
+        assert_eq!(load_file(&filename).unwrap().values.len(), 5);
+        assert!(printed.contains("No filename given - try again"), "{}", printed);
+        assert!(printed.contains("Count: 5"), "{}", printed);
+        assert!(printed.contains("Cancelled.\n"), "{}", printed);
+        assert!(!printed.contains("Error"), "{}", printed);
+        fs::remove_file(&filename).unwrap();
+    }
+
+    #[test]
+    fn bad_filenames_are_refused_up_front() {
+        for bad in ["", "  ", "../data.txt", "out/../../data.txt", "out/", "NUL", "con.txt", "aux/data.txt", "a?b.txt", "tab\there", "data."] {
+            assert!(validate_filename(bad).is_err(), "{:?}", bad);
+        }
+        assert!(validate_filename(&"x".repeat(256)).is_err());
+        for good in ["data.txt", "out/../data.txt", "data_{size}.csv", "-", "console.txt", &temp_file("absolute.txt")] {
+            assert!(validate_filename(good).is_ok(), "{:?}", good);
+        }
+        let args: Vec<String> = ["-t", "int", "-n", "5", "-o", "../escaped.txt"].iter().map(|arg| arg.to_string()).collect();
+        assert!(parse_args(&args, &Config::default()).is_err());
+        assert!(!Path::new("../escaped.txt").exists());
+
+        // In the menu a bad name is just asked for again
+        let filename = temp_file("menu_good_name.txt");
+        let mut answers = create_ints("5", "0", "9", "CON");
+        answers.extend([filename.as_str(), "n", "11"]);
+        let printed = run_with(&answers, &mut Manifest::session());
+        assert!(printed.contains("CON is a reserved device name on Windows - try again"), "{}", printed);
+        assert_eq!(load_file(&filename).unwrap().values.len(), 5);
+        fs::remove_file(&filename).unwrap();
+    }
+
+    #[test]
+    fn back_goes_back_a_question() {
+        let filename = temp_file("menu_back.txt");
+        // Count 50, then back at the min question to change it to 5
+        let mut answers = create_ints("5", "0", "9", &filename);
+        answers.splice(2..2, ["50", "back"]);
+        // b at the type question is boolean, but b at the count goes back to the type
+        answers.extend(["n", "1", "b", "b", "q", "1", "back", "11"]);
+        let printed = run_with(&answers, &mut Manifest::session());
+        assert_eq!(load_file(&filename).unwrap().values.len(), 5);
+        // The questions before the changed one aren't asked again
+        assert_eq!(printed.matches("Enter data type").count(), 4, "{}", printed);
+        assert_eq!(printed.matches("Enter number of elements").count(), 3, "{}", printed);
+        // q gives up on the boolean file, and back at the very first question leaves it too
+        assert_eq!(printed.matches("Cancelled.").count(), 2, "{}", printed);
+        assert!(printed.ends_with("Program terminated.\n"));
+        fs::remove_file(&filename).unwrap();
+    }
+
+    #[test]
+    fn running_out_of_input_exits() {
+        // Stopping halfway through the questions shouldn't leave the menu spinning forever
+        let printed = run_with(&["1", "i"], &mut Manifest::session());
+        assert!(printed.contains("Error creating file: Input ended"), "{}", printed);
+        assert!(printed.ends_with("Program terminated.\n"));
+    }
+}
@@ -0,0 +1,280 @@
+//! Source-code arrays, for pasting test data straight into a program.
+//!
+//! The values come out as a C or C++ array, a Rust const or a Python list, ready to compile
+//! or import. The array is named after the output file, so `-o fixture.rs` gives
+//! `pub const FIXTURE: [i32; 100] = [...]`, and more than one column makes an array of rows.
+
+use std::io::Write;
+use std::path::Path;
+
+use crate::error;
+use crate::generator::{DataType, Value};
+use crate::stats::Collector;
+use crate::writer::write_value;
+use crate::{BoolFormat, Options, TimeFormat};
+
+/// Values on one line of a flat array, so a long one doesn't turn into one enormous line
+const VALUES_PER_LINE: usize = 10;
+
+/// Which language the array is written in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    C,      // static const int32_t data[N] = {...};
+    Cpp,    // constexpr std::int32_t data[N] = {...};
+    Rust,   // pub const DATA: [i32; N] = [...];
+    Python, // data = [...]
+}
+
+impl Language {
+    /// Name used in messages
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::C => "C",
+            Language::Cpp => "C++",
+            Language::Rust => "Rust",
+            Language::Python => "Python",
+        }
+    }
+
+    /// The spelling --language takes
+    pub fn flag(self) -> &'static str {
+        match self {
+            Language::C => "c",
+            Language::Cpp => "cpp",
+            Language::Rust => "rust",
+            Language::Python => "python",
+        }
+    }
+
+    /// C and C++ arrays can't have zero elements, unlike a Rust array or a Python list
+    pub fn allows_empty(self) -> bool {
+        matches!(self, Language::Rust | Language::Python)
+    }
+
+    fn comment(self) -> &'static str {
+        if self == Language::Python { "#" } else { "//" }
+    }
+}
+
+// Writes the whole array: a comment saying what's in it, any includes its type needs, then
+// `count` rows of options.columns values
+pub(crate) fn write_code(
+    writer: &mut impl Write,
+    mut source: impl Iterator<Item = Value>,
+    count: u32,
+    seed: Option<u64>,
+    language: Language,
+    options: &Options,
+    collector: &mut Collector,
+) -> error::Result<()> {
+    let columns = options.columns as usize;
+    let what = match columns {
+        1 => format!("{} {} values", count, options.data_type.name()),
+        _ => format!("{} rows of {} {} values", count, columns, options.data_type.name()),
+    };
+    write!(writer, "{} Generated by ruststf: {}", language.comment(), what)?;
+    if let Some(seed) = seed {
+        write!(writer, " (seed {})", seed)?;
+    }
+    writeln!(writer)?;
+    if let Some(include) = include(language, options) {
+        writeln!(writer, "#include <{}>", include)?;
+    }
+    writeln!(writer)?;
+
+    let (open, close) = if matches!(language, Language::C | Language::Cpp) { ("{", "}") } else { ("[", "]") };
+    writeln!(writer, "{} {}", declaration(language, count, options), open)?;
+    // A flat array wraps every VALUES_PER_LINE values, and an array of rows gets a row per line
+    // Every value is followed by a comma, which all four languages allow after the last one too
+    if columns == 1 {
+        for i in 0..count as usize {
+            if i % VALUES_PER_LINE == 0 {
+                if i > 0 {
+                    writeln!(writer)?;
+                }
+                write!(writer, "    ")?;
+            } else {
+                write!(writer, " ")?;
+            }
+            let value = source.next().unwrap();
+            write!(writer, "{},", literal(value, language, options, collector)?)?;
+        }
+        if count > 0 {
+            writeln!(writer)?;
+        }
+    } else {
+        for _ in 0..count {
+            let row = source.by_ref().take(columns)
+                .map(|value| literal(value, language, options, collector))
+                .collect::<error::Result<Vec<String>>>()?;
+            writeln!(writer, "    {}{}{},", open, row.join(", "), close)?;
+        }
+    }
+    let end = if language == Language::Python { "" } else { ";" };
+    writeln!(writer, "{}{}", close, end)?;
+    Ok(())
+}
+
+// The left-hand side of the array, up to the `=`, e.g. `static const int32_t data[4][3] =`
+fn declaration(language: Language, count: u32, options: &Options) -> String {
+    let name = array_name(&options.filename, language);
+    let columns = options.columns;
+    let (c_type, rust_type) = element_type(options);
+    // Rows are arrays of arrays, with the outer size first in C and last in Rust
+    let c_sizes = match columns {
+        1 => format!("[{}]", count),
+        _ => format!("[{}][{}]", count, columns),
+    };
+    let rust_type = match columns {
+        1 => format!("[{}; {}]", rust_type, count),
+        _ => format!("[[{}; {}]; {}]", rust_type, columns, count),
+    };
+    match language {
+        // The second const makes the pointers themselves read-only, not just the characters
+        Language::C if c_type == "char *" => format!("static const char *const {}{} =", name, c_sizes),
+        Language::C => format!("static const {} {}{} =", c_type, name, c_sizes),
+        Language::Cpp if c_type == "char *" => format!("constexpr const char *{}{} =", name, c_sizes),
+        // <cstdint> promises the fixed-width types in std::, but not always at the top level
+        Language::Cpp if c_type.ends_with("_t") => format!("constexpr std::{} {}{} =", c_type, name, c_sizes),
+        Language::Cpp => format!("constexpr {} {}{} =", c_type, name, c_sizes),
+        Language::Rust => format!("pub const {}: {} =", name, rust_type),
+        Language::Python => format!("{} =", name),
+    }
+}
+
+// One element's type as (C, Rust) - C++ uses the C names, and Python doesn't need one
+// Dates written as text are strings, and epoch seconds are the i64 they are underneath
+fn element_type(options: &Options) -> (&'static str, &'static str) {
+    match options.data_type {
+        DataType::Integer => ("int32_t", "i32"),
+        DataType::Short => ("int16_t", "i16"),
+        DataType::Long => ("int64_t", "i64"),
+        DataType::Unsigned => ("uint32_t", "u32"),
+        DataType::Float => ("float", "f32"),
+        DataType::Double => ("double", "f64"),
+        DataType::Timestamp if options.time_format == TimeFormat::Epoch => ("int64_t", "i64"),
+        // Rust won't take 1 and 0 as a bool, so they stay numbers there
+        DataType::Boolean if options.bool_format == BoolFormat::Digits => ("bool", "u8"),
+        DataType::Boolean => ("bool", "bool"),
+        _ => ("char *", "&str"),
+    }
+}
+
+// The header the element type comes from, if it isn't built in
+fn include(language: Language, options: &Options) -> Option<&'static str> {
+    let c_type = element_type(options).0;
+    match language {
+        Language::C if c_type.ends_with("_t") => Some("stdint.h"),
+        Language::C if c_type == "bool" => Some("stdbool.h"),
+        Language::Cpp if c_type.ends_with("_t") => Some("cstdint"),
+        _ => None,
+    }
+}
+
+/// The array's name: the output file's name up to its first dot, with anything that can't go
+/// in an identifier turned into '_' - "data" for stdout, and upper case for a Rust const
+pub fn array_name(filename: &str, language: Language) -> String {
+    let file = Path::new(filename).file_name().map(|name| name.to_string_lossy().into_owned());
+    let stem = match file {
+        Some(file) if filename != "-" => file.split('.').next().unwrap_or("").to_string(),
+        _ => String::from("data"),
+    };
+    let mut name: String = stem.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    if language == Language::Rust {
+        name = name.to_uppercase();
+    }
+    name
+}
+
+// One value as the language writes it, going through write_value so the digits (and the
+// stats) are exactly what a text file would have
+fn literal(value: Value, language: Language, options: &Options, collector: &mut Collector) -> error::Result<String> {
+    let quoted = match value {
+        // Blobs go in as their hex, the same text the other formats hold
+        Value::Text(_) | Value::Bytes(_) | Value::Uuid(_) | Value::Ip(_) => true,
+        Value::Timestamp(_) => options.time_format == TimeFormat::Iso,
+        _ => false,
+    };
+    let is_float = matches!(value, Value::Float(_) | Value::Double(_));
+    let is_smallest_long = matches!(value, Value::Long(i64::MIN));
+    let mut bytes = Vec::new();
+    collector.push(write_value(&mut bytes, value, options)?);
+    // write_value only ever writes text, so this can't fail
+    let mut text = String::from_utf8(bytes).unwrap();
+    if quoted {
+        // Fake names can have quotes in them, and a backslash means the same in all four languages
+        return Ok(format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"")));
+    }
+    if is_float {
+        // 2 is an integer in every one of these, and Rust won't put one in a float array
+        if !text.contains(['.', 'e']) {
+            text += ".0";
+        }
+        // Without the f, C reads it as a double and narrows it down
+        if options.data_type == DataType::Float && matches!(language, Language::C | Language::Cpp) {
+            text.push('f');
+        }
+    }
+    // C reads -9223372036854775808 as minus a number too big for any type, so it needs the macro
+    if is_smallest_long && matches!(language, Language::C | Language::Cpp) {
+        text = String::from("INT64_MIN");
+    }
+    if language == Language::Python && options.data_type.is_boolean() {
+        text = match text.as_str() {
+            "true" => String::from("True"),
+            "false" => String::from("False"),
+            _ => text,
+        };
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{make_rng, value_source};
+    use crate::{Generator, OutputFormat};
+
+    // The array for the options' values, written straight into memory
+    fn code(options: &Options, language: Language) -> String {
+        let rng = &mut make_rng(options.seed);
+        let values = value_source(rng, options).unwrap();
+        let mut bytes = Vec::new();
+        write_code(&mut bytes, values, options.count, options.seed, language, options, &mut Collector::new(options)).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn arrays_come_out_in_each_language() {
+        let floats = Generator::new().data_type(DataType::Float).count(3).seed(1).range(1.0..2.0).precision(0).build().unwrap();
+        let options = |language, columns| Options {
+            format: OutputFormat::Code(language),
+            columns,
+            count: 3 / columns,
+            filename: String::from("out/test-data.h"),
+            ..floats.clone()
+        };
+        let c = code(&options(Language::C, 1), Language::C);
+        assert!(c.starts_with("// Generated by ruststf: 3 float values (seed 1)\n\nstatic const float test_data[3] = {\n    "), "{}", c);
+        // Whole floats still get a point, and an f so C doesn't read them as doubles
+        let line = c.lines().nth(3).unwrap();
+        assert!(line.trim().split(' ').all(|value| value == "1.0f," || value == "2.0f,"), "{}", line);
+        assert!(c.ends_with("\n};\n"));
+        assert!(code(&options(Language::Rust, 1), Language::Rust).contains("pub const TEST_DATA: [f32; 3] = [\n    "));
+        let python = code(&options(Language::Python, 3), Language::Python);
+        assert!(python.starts_with("# Generated by ruststf: 1 rows of 3 float values (seed 1)\n\ntest_data = [\n    ["), "{}", python);
+        assert!(python.ends_with("],\n]\n"));
+        assert!(code(&options(Language::Cpp, 3), Language::Cpp).contains("constexpr float test_data[1][3] = {\n    {"));
+
+        // Strings are quoted, and C++ integers bring in the header that has their types
+        let words = Options { data_type: DataType::String, min: 2.0, max: 2.0, precision: None, ..options(Language::C, 1) };
+        assert!(code(&words, Language::C).contains("static const char *const test_data[3] = {\n    \""));
+        let longs = Options { data_type: DataType::Long, min: -5.0, max: 5.0, precision: None, ..options(Language::Cpp, 1) };
+        assert!(code(&longs, Language::Cpp).contains("#include <cstdint>\n\nconstexpr std::int64_t test_data[3] = {"));
+        assert_eq!(array_name("-", Language::Rust), "DATA");
+        assert_eq!(array_name("2024.c", Language::C), "_2024");
+    }
+}
@@ -0,0 +1,218 @@
+//! Apache Parquet output, for data pipelines that want typed columns instead of text.
+//!
+//! Parquet stores a file column by column rather than row by row, in row groups of up to
+//! ROW_GROUP_ROWS rows, with a schema at the end giving every column's name and type. Plain
+//! values get a column per `--columns` (col1, col2, ...), and records get one per field. Pages
+//! are compressed with Snappy, which is what most Parquet tools expect by default.
+
+use std::io::{self, Write};
+use std::sync::Arc; // A shared pointer the parquet crate wants its settings in
+
+use parquet::basic::Compression;
+use parquet::column::writer::ColumnWriter;
+use parquet::data_type::{ByteArray, FixedLenByteArray};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use parquet::schema::parser::parse_message_type;
+
+use crate::error;
+use crate::generator::{DataType, Value};
+use crate::stats::{stat_value, Collector};
+use crate::writer::check_finite;
+use crate::Options;
+
+/// Rows per row group - the parquet crate's own default, and about what readers expect
+pub const ROW_GROUP_ROWS: u32 = 1024 * 1024;
+
+/// The schema in Parquet's own text form, e.g. `REQUIRED INT32 col1;` for each column
+pub fn parquet_schema(options: &Options) -> String {
+    let columns: String = options.named_columns()
+        .iter()
+        .map(|(name, data_type)| match column_type(*data_type) {
+            (physical, Some(logical)) => format!("  REQUIRED {} {} ({});\n", physical, name, logical),
+            (physical, None) => format!("  REQUIRED {} {};\n", physical, name),
+        })
+        .collect();
+    format!("message ruststf {{\n{}}}\n", columns)
+}
+
+// How the column is stored, plus a logical type where the storage alone would lose meaning
+fn column_type(data_type: DataType) -> (&'static str, Option<&'static str>) {
+    match data_type {
+        DataType::Integer => ("INT32", None),
+        DataType::Short => ("INT32", Some("INTEGER(16, true)")),
+        // Parquet has no unsigned storage, so a u32 keeps its bits in an INT32 marked unsigned
+        DataType::Unsigned => ("INT32", Some("INTEGER(32, false)")),
+        DataType::Long => ("INT64", None),
+        DataType::Float => ("FLOAT", None),
+        DataType::Double => ("DOUBLE", None),
+        DataType::String => ("BINARY", Some("STRING")),
+        // Plain BINARY with no logical type is just bytes, which is exactly what a blob is
+        DataType::Bytes => ("BINARY", None),
+        DataType::FirstName | DataType::LastName | DataType::Email | DataType::Phone => ("BINARY", Some("STRING")),
+        // Parquet timestamps count in milliseconds at the coarsest, so seconds get scaled up
+        DataType::Timestamp => ("INT64", Some("TIMESTAMP(MILLIS, true)")),
+        DataType::Boolean => ("BOOLEAN", None),
+        // Parquet's own UUID type: the 16 bytes, most significant first
+        DataType::Uuid => ("FIXED_LEN_BYTE_ARRAY (16)", Some("UUID")),
+        // No address type though, so they're stored as their text, which every tool can read
+        DataType::Ipv4 | DataType::Ipv6 => ("BINARY", Some("STRING")),
+    }
+}
+
+/// Writes `count` rows as a Parquet file, a row group at a time
+pub(crate) fn write_parquet(
+    writer: &mut (impl Write + Send),
+    mut source: impl Iterator<Item = Value>,
+    count: u32,
+    options: &Options,
+    collector: &mut Collector,
+) -> error::Result<()> {
+    let columns = options.named_columns();
+    let schema = Arc::new(parse_message_type(&parquet_schema(options)).map_err(parquet_error)?);
+    let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut file = SerializedFileWriter::new(writer, schema, Arc::new(properties)).map_err(parquet_error)?;
+    let mut rows_left = count;
+    while rows_left > 0 {
+        let rows = rows_left.min(ROW_GROUP_ROWS);
+        rows_left -= rows;
+        // Values arrive a row at a time, but Parquet wants each column in one piece,
+        // so a row group's worth gets sorted into one buffer per column first
+        let mut buffers: Vec<ColumnBuffer> = columns.iter().map(|(_, data_type)| ColumnBuffer::new(*data_type)).collect();
+        for _ in 0..rows {
+            for (buffer, value) in buffers.iter_mut().zip(source.by_ref()) {
+                check_finite(&value)?;
+                collector.push(stat_value(&value));
+                buffer.push(value);
+            }
+        }
+        let mut group = file.next_row_group().map_err(parquet_error)?;
+        for buffer in buffers {
+            // One column writer per schema field, handed out in order
+            let mut column = group.next_column().map_err(parquet_error)?.expect("a column writer for every buffer");
+            buffer.write(&mut column)?;
+            column.close().map_err(parquet_error)?;
+        }
+        group.close().map_err(parquet_error)?;
+    }
+    // The schema and row group index go at the end of the file, so nothing's readable until this
+    file.close().map_err(parquet_error)?;
+    Ok(())
+}
+
+// One column of a row group, held in the type Parquet will store it as
+enum ColumnBuffer {
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    Boolean(Vec<bool>),
+    Bytes(Vec<ByteArray>),
+    Fixed(Vec<FixedLenByteArray>),
+}
+
+impl ColumnBuffer {
+    fn new(data_type: DataType) -> ColumnBuffer {
+        match data_type {
+            DataType::Integer | DataType::Short | DataType::Unsigned => ColumnBuffer::Int32(Vec::new()),
+            DataType::Long | DataType::Timestamp => ColumnBuffer::Int64(Vec::new()),
+            DataType::Float => ColumnBuffer::Float(Vec::new()),
+            DataType::Double => ColumnBuffer::Double(Vec::new()),
+            DataType::Boolean => ColumnBuffer::Boolean(Vec::new()),
+            DataType::String | DataType::Bytes | DataType::Ipv4 | DataType::Ipv6 => ColumnBuffer::Bytes(Vec::new()),
+            DataType::FirstName | DataType::LastName | DataType::Email | DataType::Phone => ColumnBuffer::Bytes(Vec::new()),
+            DataType::Uuid => ColumnBuffer::Fixed(Vec::new()),
+        }
+    }
+
+    fn push(&mut self, value: Value) {
+        match (self, value) {
+            (ColumnBuffer::Int32(values), Value::Int(num)) => values.push(num),
+            (ColumnBuffer::Int32(values), Value::Short(num)) => values.push(num as i32),
+            // `as` keeps the bits, so numbers over i32::MAX come out negative here
+            // and turn back into the right u32 in any reader that honours the schema
+            (ColumnBuffer::Int32(values), Value::Unsigned(num)) => values.push(num as i32),
+            (ColumnBuffer::Int64(values), Value::Long(num)) => values.push(num),
+            (ColumnBuffer::Int64(values), Value::Timestamp(seconds)) => values.push(seconds * 1000),
+            (ColumnBuffer::Float(values), Value::Float(num)) => values.push(num),
+            (ColumnBuffer::Double(values), Value::Double(num)) => values.push(num),
+            (ColumnBuffer::Boolean(values), Value::Bool(flag)) => values.push(flag),
+            (ColumnBuffer::Bytes(values), Value::Text(text)) => values.push(ByteArray::from(text.into_bytes())),
+            (ColumnBuffer::Bytes(values), Value::Bytes(blob)) => values.push(ByteArray::from(blob)),
+            (ColumnBuffer::Bytes(values), Value::Ip(address)) => values.push(ByteArray::from(address.to_string().into_bytes())),
+            (ColumnBuffer::Fixed(values), Value::Uuid(bits)) => values.push(ByteArray::from(bits.to_be_bytes().to_vec()).into()),
+            // Each column's buffer was made from the type its values are generated as
+            (_, value) => unreachable!("{:?} doesn't belong in this column", value),
+        }
+    }
+
+    fn write(self, column: &mut SerializedColumnWriter) -> error::Result<()> {
+        // Every value is present (REQUIRED columns), so there are no definition or repetition levels
+        let written = match (column.untyped(), self) {
+            (ColumnWriter::Int32ColumnWriter(writer), ColumnBuffer::Int32(values)) => writer.write_batch(&values, None, None),
+            (ColumnWriter::Int64ColumnWriter(writer), ColumnBuffer::Int64(values)) => writer.write_batch(&values, None, None),
+            (ColumnWriter::FloatColumnWriter(writer), ColumnBuffer::Float(values)) => writer.write_batch(&values, None, None),
+            (ColumnWriter::DoubleColumnWriter(writer), ColumnBuffer::Double(values)) => writer.write_batch(&values, None, None),
+            (ColumnWriter::BoolColumnWriter(writer), ColumnBuffer::Boolean(values)) => writer.write_batch(&values, None, None),
+            (ColumnWriter::ByteArrayColumnWriter(writer), ColumnBuffer::Bytes(values)) => writer.write_batch(&values, None, None),
+            (ColumnWriter::FixedLenByteArrayColumnWriter(writer), ColumnBuffer::Fixed(values)) => writer.write_batch(&values, None, None),
+            _ => unreachable!("the schema and the buffers are made from the same column list"),
+        };
+        written.map_err(parquet_error)?;
+        Ok(())
+    }
+}
+
+// The parquet crate has its own error type, so it's turned into an io::Error, which ? then wraps as GeneratorError::Io
+fn parquet_error(error: ParquetError) -> io::Error {
+    io::Error::other(format!("Parquet: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::tests::options;
+    use crate::generator::{make_rng, value_source};
+    use crate::{parse_schema, write_data, Distribution, OutputFormat};
+    use parquet::file::reader::FileReader;
+    use parquet::file::serialized_reader::SerializedFileReader;
+    use parquet::record::RowAccessor;
+
+    #[test]
+    fn every_type_reads_back_from_parquet() {
+        let mut options = options(DataType::Integer, 0.0, 1.0, Distribution::Uniform);
+        // The unsigned range is all above i32::MAX, to check the bits survive the trip through INT32
+        let schema = "id:int, small:short, big:unsigned(3000000000..4294967295), score:double, ratio:float, \
+                      name:string, ok:bool, at:timestamp, total:long, key:uuid, host:ipv4, peer:ipv6, payload:bytes(1..40)";
+        options.schema = parse_schema(schema).unwrap();
+        options.format = OutputFormat::Parquet;
+        let path = std::env::temp_dir().join(format!("ruststf_test_{}_types.parquet", std::process::id()));
+        options.filename = path.to_string_lossy().into_owned();
+        write_data(&options).unwrap();
+
+        let expected: Vec<Value> = value_source(&mut make_rng(options.seed), &options).unwrap().collect();
+        let reader = SerializedFileReader::try_from(options.filename.as_str()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), options.count as i64);
+        let rows = reader.get_row_iter(None).unwrap().map(|row| row.unwrap());
+        for (row, values) in rows.zip(expected.chunks(options.schema.len())) {
+            for (i, value) in values.iter().enumerate() {
+                match value {
+                    Value::Int(num) => assert_eq!(row.get_int(i).unwrap(), *num),
+                    Value::Short(num) => assert_eq!(row.get_short(i).unwrap(), *num),
+                    Value::Unsigned(num) => assert_eq!(row.get_uint(i).unwrap(), *num),
+                    Value::Long(num) => assert_eq!(row.get_long(i).unwrap(), *num),
+                    Value::Float(num) => assert_eq!(row.get_float(i).unwrap(), *num),
+                    Value::Double(num) => assert_eq!(row.get_double(i).unwrap(), *num),
+                    Value::Text(text) => assert_eq!(row.get_string(i).unwrap(), text),
+                    Value::Bytes(blob) => assert_eq!(row.get_bytes(i).unwrap().data(), &blob[..]),
+                    Value::Bool(flag) => assert_eq!(row.get_bool(i).unwrap(), *flag),
+                    Value::Timestamp(seconds) => assert_eq!(row.get_timestamp_millis(i).unwrap(), seconds * 1000),
+                    Value::Uuid(bits) => assert_eq!(row.get_bytes(i).unwrap().data(), bits.to_be_bytes()),
+                    Value::Ip(address) => assert_eq!(row.get_string(i).unwrap(), &address.to_string()),
+                }
+            }
+        }
+        std::fs::remove_file(&options.filename).unwrap();
+    }
+}
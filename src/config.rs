@@ -0,0 +1,364 @@
+//! Default settings loaded from a `generator.toml` file.
+//!
+//! Only the small part of TOML we need is understood: `key = value` lines,
+//! `# comments`, quoted strings and plain numbers. For example:
+//!
+//! ```toml
+//! output_dir = "data"
+//! type = "float"
+//! min = 0
+//! max = 1
+//! precision = 4
+//! manifest = "generated.txt"
+//! presets = "presets.toml"
+//! history = "history.log"
+//! ```
+//!
+//! Every key can also be set with an environment variable named after it (DATAGEN_TYPE,
+//! DATAGEN_OUTPUT_DIR, ...), which wins over the file, so a CI job can set things up without
+//! writing one.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path};
+
+use crate::error;
+use crate::generator::{self, DataType};
+use crate::socket::Socket;
+use crate::timestamp::parse_iso;
+use crate::units;
+use crate::{invalid_data, invalid_input, MAX_PRECISION, STDOUT_NAME};
+
+/// Name of the config file looked for in the current directory
+pub const CONFIG_FILE: &str = "generator.toml";
+
+/// Start of the environment variables that override settings, like DATAGEN_COUNT
+pub const ENV_PREFIX: &str = "DATAGEN_";
+
+// The keys a string goes in quotes for, when it comes from the environment instead of TOML
+const STRING_KEYS: [&str; 5] = ["output_dir", "type", "manifest", "presets", "history"];
+
+/// Defaults from the config file - anything left out is None and falls back to the built-in default
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub output_dir: Option<String>, // Relative filenames get written in here
+    pub data_type: Option<DataType>,
+    pub count: Option<u32>,
+    pub seed: Option<u64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub precision: Option<usize>,   // Only used for float and double
+    pub manifest: Option<String>,   // Keep a list of generated files here between runs
+    pub presets: Option<String>,    // Where saved presets go, instead of presets.toml
+    pub history: Option<String>,    // Where past runs are logged, instead of history.log
+}
+
+impl Config {
+    /// Reads generator.toml from the current directory, or gives back an empty Config if there isn't one
+    pub fn load_default() -> error::Result<Config> {
+        if !Path::new(CONFIG_FILE).exists() {
+            return Ok(Config::default());
+        }
+        Config::load(CONFIG_FILE)
+    }
+
+    /// Reads a config file - a missing file is an error here since someone asked for it by name
+    pub fn load(path: &str) -> error::Result<Config> {
+        let text = fs::read_to_string(path).map_err(|e| io::Error::new(e.kind(), format!("Can't read config {}: {}", path, e)))?;
+        parse_config(&text).map_err(|e| invalid_data(format!("{}: {}", path, e)))
+    }
+
+    /// Overrides the settings with any DATAGEN_<KEY> variables in `vars` (DATAGEN_OUTDIR is
+    /// short for DATAGEN_OUTPUT_DIR) - others are left for the command line to turn into flags
+    /// Takes the variables as a list rather than reading them itself, so tests don't have to
+    /// change the real environment
+    pub fn apply_env(&mut self, vars: &[(String, String)]) -> error::Result<()> {
+        for (name, value) in vars {
+            let Some(key) = env_key(name) else { continue };
+            if !is_config_key(&key) {
+                continue;
+            }
+            // The environment has no quotes, so strings (and dates for the range) get them here
+            let is_date = matches!(key.as_str(), "min" | "max") && value.parse::<f64>().is_err();
+            let value = if STRING_KEYS.contains(&key.as_str()) || is_date {
+                format!("\"{}\"", value)
+            } else {
+                value.clone()
+            };
+            set_key(self, &key, &value).map_err(|e| invalid_input(format!("{}: {}", name, e)))?;
+        }
+        self.check_range().map_err(|e| invalid_input(format!("{}MIN/{}MAX: {}", ENV_PREFIX, ENV_PREFIX, e)))
+    }
+
+    // Checked once everything is loaded, while we can still say where the range came from
+    fn check_range(&self) -> Result<(), String> {
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            let data_type = self.data_type.unwrap_or(DataType::Double);
+            generator::validate_range(data_type, min, max).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// The default range for a type: the configured one if it was set for this type
+    /// (or for no type in particular), otherwise the type's built-in range
+    pub fn range_for(&self, data_type: DataType) -> (f64, f64) {
+        let (default_min, default_max) = data_type.default_range();
+        if self.data_type.is_some_and(|configured| configured != data_type) {
+            return (default_min, default_max);
+        }
+        (self.min.unwrap_or(default_min), self.max.unwrap_or(default_max))
+    }
+
+    /// The configured precision, but only for types that have decimals
+    pub fn precision_for(&self, data_type: DataType) -> Option<usize> {
+        if !data_type.has_decimals() {
+            return None;
+        }
+        self.precision
+    }
+
+    /// Puts a relative filename inside output_dir - absolute paths, "-" (stdout) and sockets are left alone
+    /// A name with .. in it could climb back out of the directory, so that's refused
+    pub fn output_path(&self, filename: &str) -> error::Result<String> {
+        match &self.output_dir {
+            Some(dir) if filename != STDOUT_NAME && Socket::parse(filename).is_none() && Path::new(filename).is_relative() => {
+                if Path::new(filename).components().any(|part| part == Component::ParentDir) {
+                    return Err(invalid_input(format!(
+                        "{} would end up outside the output directory {}", filename, dir
+                    )));
+                }
+                // join() puts the right separator in between, / or \ depending on the OS
+                Ok(Path::new(dir).join(filename).to_string_lossy().into_owned())
+            },
+            _ => Ok(filename.to_string()),
+        }
+    }
+}
+
+/// Longest file name most filesystems allow (NAME_MAX on Linux, 255 UTF-16 units on Windows)
+pub const MAX_NAME_LEN: usize = 255;
+
+// Names Windows keeps for devices, with or without an extension - NUL.txt is still the null device
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Checks a name typed in for a new file before anything gets created, so a bad one is caught
+/// up front with a message saying what's wrong instead of a cryptic OS error halfway through
+/// "-" (stdout) is always fine, and so is an absolute path, which is a deliberate choice of place
+/// A socket isn't a file at all - Options::validate checks its address
+pub fn validate_filename(filename: &str) -> error::Result<()> {
+    if filename == STDOUT_NAME || Socket::parse(filename).is_some() {
+        return Ok(());
+    }
+    if filename.trim().is_empty() {
+        return Err(invalid_input("No filename given"));
+    }
+    let path = Path::new(filename);
+    // The name of the file itself - None when the path ends in a separator or is just ".."
+    let name = match path.file_name() {
+        Some(name) if !filename.ends_with(['/', '\\']) => name.to_string_lossy(),
+        _ => return Err(invalid_input(format!("{} is a directory, not a file name", filename))),
+    };
+    // Counting the way cd would: a relative name may use .. as long as it never climbs above
+    // the directory it starts in (the output directory, or the current one without one)
+    if path.is_relative() {
+        let mut depth = 0;
+        for part in path.components() {
+            match part {
+                Component::ParentDir if depth == 0 => {
+                    return Err(invalid_input(format!(
+                        "{} points outside the output directory (give a full path to write there on purpose)", filename
+                    )));
+                },
+                Component::ParentDir => depth -= 1,
+                Component::Normal(_) => depth += 1,
+                _ => {},
+            }
+        }
+    }
+    if name.len() > MAX_NAME_LEN {
+        return Err(invalid_input(format!("File name is too long ({} bytes, the limit is {})", name.len(), MAX_NAME_LEN)));
+    }
+    // Characters Windows refuses, plus control characters (a stray tab or newline), which every
+    // system allows but nobody can type back in to open the file. Unix names could hold most of
+    // these, but data files get copied between systems all the time
+    if let Some(c) = name.chars().find(|c| c.is_control() || "<>:\"|?*".contains(*c)) {
+        return Err(invalid_input(format!("File name can't contain {:?}", c)));
+    }
+    if name.ends_with(['.', ' ']) {
+        return Err(invalid_input(format!("File name can't end with a dot or a space: {:?}", name)));
+    }
+    // Windows checks every part of the path against the device names, not just the last one
+    for part in path.components() {
+        if let Component::Normal(part) = part {
+            let part = part.to_string_lossy();
+            let stem = part.split('.').next().unwrap_or_default().trim_end();
+            if RESERVED_NAMES.iter().any(|reserved| stem.eq_ignore_ascii_case(reserved)) {
+                return Err(invalid_input(format!("{} is a reserved device name on Windows", stem)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Creates a directory (and any missing parents, like mkdir -p) and checks we can write in it,
+/// so a bad directory fails straight away instead of after generating millions of values
+pub fn prepare_dir(dir: &Path) -> error::Result<()> {
+    let context = |e: io::Error| io::Error::new(e.kind(), format!("Can't write to {}: {}", dir.display(), e));
+    fs::create_dir_all(dir).map_err(context)?;
+    // Permission bits don't tell the whole story (read-only mounts, ACLs), so the
+    // only sure test is to actually create a file and remove it again
+    let probe = dir.join(".ruststf_write_test");
+    File::create(&probe).map_err(context)?;
+    Ok(fs::remove_file(&probe).map_err(context)?)
+}
+
+// Goes line by line - errors say which line so the file is easy to fix
+fn parse_config(text: &str) -> Result<Config, String> {
+    let mut config = Config::default();
+    for (i, line) in text.lines().enumerate() {
+        let number = i + 1;
+        let line = strip_comment(line).trim();
+        // [sections] aren't used, but skipping them means a tidier file still loads
+        if line.is_empty() || line.starts_with('[') {
+            continue;
+        }
+        let (key, value) = line.split_once('=')
+            .ok_or_else(|| format!("line {}: expected key = value", number))?;
+        set_key(&mut config, key.trim(), value.trim()).map_err(|e| format!("line {}: {}", number, e))?;
+    }
+    config.check_range()?;
+    Ok(config)
+}
+
+// Everything after a # is a comment, unless the # is inside quotes like output_dir = "runs#1"
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {},
+        }
+    }
+    line
+}
+
+// Sets one key from its value as TOML writes it, so strings are in quotes
+fn set_key(config: &mut Config, key: &str, value: &str) -> Result<(), String> {
+    let bad_value = || format!("invalid value for {}: {}", key, value);
+    match key {
+        "output_dir" => config.output_dir = Some(parse_string(value).ok_or_else(bad_value)?),
+        "type" => {
+            // The same spellings --type takes, so "int" works here as well as "integer"
+            let name = parse_string(value).ok_or_else(bad_value)?;
+            config.data_type = Some(generator::parse_data_type(&name).map_err(|_| bad_value())?);
+        },
+        "count" => config.count = Some(units::parse_count(value).map_err(|_| bad_value())?),
+        "seed" => config.seed = Some(value.parse().map_err(|_| bad_value())?),
+        "min" => config.min = Some(parse_number(value).ok_or_else(bad_value)?),
+        "max" => config.max = Some(parse_number(value).ok_or_else(bad_value)?),
+        "manifest" => config.manifest = Some(parse_string(value).ok_or_else(bad_value)?),
+        "presets" => config.presets = Some(parse_string(value).ok_or_else(bad_value)?),
+        "history" => config.history = Some(parse_string(value).ok_or_else(bad_value)?),
+        "precision" => {
+            let digits = value.parse::<usize>().ok().filter(|&digits| digits <= MAX_PRECISION);
+            config.precision = Some(digits.ok_or_else(bad_value)?);
+        },
+        _ => return Err(format!("unknown setting {}", key)),
+    }
+    Ok(())
+}
+
+/// The setting a DATAGEN_ variable is for, in lower case with underscores ("output_dir" for
+/// DATAGEN_OUTPUT_DIR or DATAGEN_OUTDIR), or None for any other variable
+pub fn env_key(name: &str) -> Option<String> {
+    let key = name.strip_prefix(ENV_PREFIX).filter(|key| !key.is_empty())?.to_lowercase();
+    Some(if key == "outdir" { String::from("output_dir") } else { key })
+}
+
+/// Whether a setting lives in the config file, rather than being a flag for generate
+pub fn is_config_key(key: &str) -> bool {
+    STRING_KEYS.contains(&key) || matches!(key, "count" | "seed" | "min" | "max" | "precision")
+}
+
+// TOML strings are always in double quotes
+fn parse_string(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    Some(inner.to_string())
+}
+
+// Timestamp ranges can also be quoted dates like "2024-01-01"
+fn parse_number(value: &str) -> Option<f64> {
+    if let Some(date) = parse_string(value) {
+        return parse_iso(&date).map(|seconds| seconds as f64);
+    }
+    value.parse::<f64>().ok().filter(|number| number.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn environment_variables_win_over_the_file() {
+        let mut config = parse_config("type = \"float\"\ncount = 10\nmax = 5\n").unwrap();
+        let vars = |pairs: &[(&str, &str)]| pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect::<Vec<_>>();
+        config.apply_env(&vars(&[
+            ("DATAGEN_COUNT", "250"),
+            ("DATAGEN_SEED", "7"),
+            ("DATAGEN_OUTDIR", "ci/data"),
+            ("DATAGEN_FORMAT", "csv"), // A flag for generate, so it's not the config's business
+            ("HOME", "/root"),
+        ])).unwrap();
+        assert_eq!((config.data_type, config.count, config.seed, config.max), (Some(DataType::Float), Some(250), Some(7), Some(5.0)));
+        assert_eq!(config.output_dir.as_deref(), Some("ci/data"));
+
+        // Dates work for the range without quotes, and bad values name the variable they came from
+        let mut dates = Config::default();
+        dates.apply_env(&vars(&[("DATAGEN_TYPE", "timestamp"), ("DATAGEN_MIN", "2024-01-01")])).unwrap();
+        assert_eq!(dates.min, Some(1_704_067_200.0));
+        let error = Config::default().apply_env(&vars(&[("DATAGEN_COUNT", "lots")])).unwrap_err();
+        assert!(error.to_string().contains("DATAGEN_COUNT"), "{}", error);
+        assert!(Config::default().apply_env(&vars(&[("DATAGEN_MIN", "9"), ("DATAGEN_MAX", "1")])).is_err());
+    }
+
+    #[test]
+    fn missing_files_are_named() {
+        let error = Config::load("no_such_generator.toml").unwrap_err();
+        assert_eq!(error.to_string(), "Can't read config no_such_generator.toml: No such file or directory (os error 2)");
+        let error = crate::load_file("no_such_data.txt").unwrap_err();
+        assert!(error.to_string().starts_with("Can't read no_such_data.txt: "), "{}", error);
+        let error = crate::shuffle_file("no_such_data.txt", "shuffled.txt", None).unwrap_err();
+        assert!(error.to_string().starts_with("Can't read no_such_data.txt: "), "{}", error);
+    }
+
+    #[test]
+    fn a_hash_in_quotes_is_not_a_comment() {
+        let config = parse_config("# runs go here\noutput_dir = \"runs#1\" # numbered\nhistory = \"h.log\"#\n").unwrap();
+        assert_eq!(config.output_dir.as_deref(), Some("runs#1"));
+        assert_eq!(config.history.as_deref(), Some("h.log"));
+    }
+
+    #[test]
+    fn types_can_use_the_short_names() {
+        assert_eq!(parse_config("type = \"int\"\n").unwrap().data_type, Some(DataType::Integer));
+        assert_eq!(parse_config("type = \"F64\"\n").unwrap().data_type, Some(DataType::Double));
+        assert!(parse_config("type = \"decimal\"\n").is_err());
+        // The environment goes through the same parser, so DATAGEN_TYPE=int works too
+        let mut config = Config::default();
+        config.apply_env(&[(String::from("DATAGEN_TYPE"), String::from("int"))]).unwrap();
+        assert_eq!(config.data_type, Some(DataType::Integer));
+    }
+
+    #[test]
+    fn counts_take_the_same_suffixes_as_the_flags() {
+        assert_eq!(parse_config("count = 2k\n").unwrap().count, Some(2_000));
+        let mut config = Config::default();
+        config.apply_env(&[(String::from("DATAGEN_COUNT"), String::from("1.5M"))]).unwrap();
+        assert_eq!(config.count, Some(1_500_000));
+        assert!(parse_config("count = 5T\n").is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! Rewriting a data file in another format, so changing your mind about the format doesn't
+//! mean generating everything again.
+//!
+//! The file is read back the way `inspect` reads it, and its values go through the same writers
+//! as a new file's, as a pool taken in order. The type, the number of rows and columns, and the
+//! seed all carry over, so the new header says the same things about the data the old one did.
+
+use crate::error;
+use crate::pool::{Pool, Sampling};
+use crate::reader::load_file;
+use crate::{invalid_input, Options};
+
+/// The Options that rewrite `input` as described by `options` - its format, filename, precision
+/// and so on - with the type, count, range, seed and values filled in from the file
+/// Columns come from the file too (a matrix header, or the width of a CSV's rows) unless
+/// `options` asks for more than one, which reshapes the values into that many per row
+pub fn convert_options(input: &str, options: &Options) -> error::Result<Options> {
+    let data = load_file(input)?;
+    // Text, JSON and binary files are one long list, so rows turn into a count of every value
+    let columns = match options.columns {
+        1 if options.format.has_rows() => data.columns.unwrap_or(1),
+        1 => 1,
+        _ if options.format.has_rows() => options.columns,
+        _ => return Err(invalid_input(format!("{} files don't have columns", options.format.name()))),
+    };
+    let seed = data.seed;
+    let pool = Pool::from_loaded(input, data, Sampling::InOrder)?;
+    let total = pool.values.len() as u64;
+    if !total.is_multiple_of(columns as u64) {
+        return Err(invalid_input(format!("{} has {} values, which don't make rows of {}", input, total, columns)));
+    }
+    let count = u32::try_from(total / columns as u64).map_err(|_| invalid_input(format!("{} has too many rows to convert", input)))?;
+    let (min, max) = pool.range();
+    Ok(Options {
+        data_type: pool.data_type,
+        count,
+        min,
+        max,
+        columns,
+        seed,
+        pool: Some(pool),
+        ..options.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::DataType;
+    use crate::{write_data, Generator, OutputFormat};
+    use std::fs;
+
+    #[test]
+    fn converted_files_keep_their_values_and_header() {
+        let name = |end: &str| {
+            let path = std::env::temp_dir().join(format!("ruststf_test_{}_convert_{}", std::process::id(), end));
+            path.to_string_lossy().into_owned()
+        };
+        let (text, csv, binary) = (name("data.txt"), name("data.csv"), name("data.bin"));
+        Generator::new().data_type(DataType::Long).count(6).seed(4).range(-5e9..5e9).write_to(&text).unwrap();
+        let before = load_file(&text).unwrap();
+
+        // A text file into a CSV of two columns, which becomes three rows
+        let template = Generator::new().format(OutputFormat::Csv).columns(2).build().unwrap();
+        let options = convert_options(&text, &Options { filename: csv.clone(), ..template }).unwrap();
+        assert_eq!((options.data_type, options.count, options.seed), (DataType::Long, 3, Some(4)));
+        write_data(&options).unwrap();
+        assert_eq!(load_file(&csv).unwrap().columns, Some(2));
+
+        // ... and the CSV into a binary file, which says its type exactly
+        let template = Options { filename: binary.clone(), ..Generator::new().format(OutputFormat::Binary).build().unwrap() };
+        write_data(&convert_options(&csv, &template).unwrap()).unwrap();
+        let after = load_file(&binary).unwrap();
+        assert_eq!((after.data_type, after.declared_count), (DataType::Long, Some(6)));
+        assert_eq!(after.values, before.values);
+
+        // Five values can't be cut into rows of two
+        fs::write(&text, "Count: 5\n1\n2\n3\n4\n5\n").unwrap();
+        assert!(convert_options(&text, &template).is_ok());
+        assert!(convert_options(&text, &Options { columns: 2, ..template }).is_err());
+        for file in [text, csv, binary] {
+            fs::remove_file(file).unwrap();
+        }
+    }
+}
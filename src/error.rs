@@ -0,0 +1,120 @@
+//! The crate's own error type.
+//!
+//! Nearly everything here ends in reading or writing a file, so it used to report every problem
+//! as an io::Error, with ErrorKind::InvalidInput standing in for "you asked for something
+//! impossible". A program calling us couldn't tell that apart from a full disk without digging
+//! into the error kind. GeneratorError gives each kind of problem a variant of its own, so
+//! callers can match on it like an enum in C, and the I/O errors still carry the io::Error
+//! underneath.
+//!
+//! The messages are full sentences meant to be shown to people as they are ("Minimum (10) must
+//! be less than maximum (1)"), which is exactly what the command line prints after "Error:".
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Everything the crate can fail with
+#[derive(Debug)]
+pub enum GeneratorError {
+    InvalidInput(String),      // Settings or answers that don't make sense on their own or together
+    Usage(String),             // A command line that can't be read - a flag we don't know, or one missing its value
+    InvalidData(String),       // A file that was read back isn't what it should be
+    RangeError(String),        // A min/max range that doesn't fit the type, or is the wrong way round
+    UnsupportedFormat(String), // Something the chosen output format can't hold or do
+    Io(io::Error),             // Creating, writing or reading a file (or socket) went wrong
+    Cancelled,                 // Someone at the menu typed cancel instead of answering
+    Interrupted(String),       // Ctrl-C stopped a run part way - the message says what became of the file
+}
+
+/// Like io::Result, but with GeneratorError - used the same way, as error::Result<T>
+pub type Result<T> = std::result::Result<T, GeneratorError>;
+
+impl GeneratorError {
+    /// Puts `what` in front of the message, keeping the same variant - "Error creating file: ..."
+    pub fn context(self, what: &str) -> GeneratorError {
+        let add = |message: String| format!("{}: {}", what, message);
+        match self {
+            GeneratorError::InvalidInput(message) => GeneratorError::InvalidInput(add(message)),
+            GeneratorError::Usage(message) => GeneratorError::Usage(add(message)),
+            GeneratorError::InvalidData(message) => GeneratorError::InvalidData(add(message)),
+            GeneratorError::RangeError(message) => GeneratorError::RangeError(add(message)),
+            GeneratorError::UnsupportedFormat(message) => GeneratorError::UnsupportedFormat(add(message)),
+            GeneratorError::Io(e) => GeneratorError::Io(io::Error::new(e.kind(), add(e.to_string()))),
+            GeneratorError::Cancelled => GeneratorError::Cancelled,
+            GeneratorError::Interrupted(message) => GeneratorError::Interrupted(add(message)),
+        }
+    }
+
+    /// The io::ErrorKind underneath, for the few callers that care which I/O error it was
+    /// (a reader that went away, input that ran out) - None for everything that isn't I/O
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            GeneratorError::Io(e) => Some(e.kind()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GeneratorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GeneratorError::InvalidInput(message)
+            | GeneratorError::Usage(message)
+            | GeneratorError::InvalidData(message)
+            | GeneratorError::RangeError(message)
+            | GeneratorError::UnsupportedFormat(message)
+            | GeneratorError::Interrupted(message) => write!(f, "{}", message),
+            GeneratorError::Io(e) => write!(f, "{}", e),
+            GeneratorError::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+// source() lets callers (and error-reporting crates) walk down to the io::Error underneath
+impl Error for GeneratorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GeneratorError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// From is what lets the ? operator turn an io::Error into a GeneratorError by itself
+impl From<io::Error> for GeneratorError {
+    fn from(e: io::Error) -> Self {
+        GeneratorError::Io(e)
+    }
+}
+
+// And back again, for the places that have to hand out an io::Error - a Write implementation,
+// or main() - keeping the kind so InvalidInput still reads as InvalidInput
+impl From<GeneratorError> for io::Error {
+    fn from(e: GeneratorError) -> Self {
+        let kind = match e {
+            GeneratorError::Io(e) => return e,
+            GeneratorError::InvalidData(_) => io::ErrorKind::InvalidData,
+            GeneratorError::Cancelled | GeneratorError::Interrupted(_) => io::ErrorKind::Interrupted,
+            _ => io::ErrorKind::InvalidInput,
+        };
+        io::Error::new(kind, e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_keep_their_kind_through_context() {
+        let range = GeneratorError::RangeError("Minimum (10) must be less than maximum (1)".into());
+        let range = range.context("Field 'age'");
+        assert!(matches!(range, GeneratorError::RangeError(_)));
+        assert_eq!(range.to_string(), "Field 'age': Minimum (10) must be less than maximum (1)");
+        let missing = GeneratorError::from(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+        assert_eq!(missing.io_kind(), Some(io::ErrorKind::NotFound));
+        assert!(missing.source().is_some());
+        assert_eq!(io::Error::from(GeneratorError::InvalidData("bad".into())).kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,109 @@
+//! Realistic-looking but made-up people: first and last names, email addresses and phone numbers.
+//!
+//! Test rows often need something that looks like a person without being one, so nothing here
+//! can point at anyone real. The names come from short built-in lists of common names, emails
+//! only use the example.com/.org/.net domains (set aside for documentation and never handed out),
+//! and phone numbers only use 555-0100 to 555-0199, the block reserved for fictional use in
+//! North America - the same one films and TV use.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom; // choose() - picks a random element, like list[rand() % len] in C
+use rand::Rng;
+
+use crate::generator::DataType;
+
+// Plain ASCII letters only, so every name is safe in any format without quoting or escaping
+const FIRST_NAMES: [&str; 48] = [
+    "James", "Mary", "Robert", "Patricia", "John", "Jennifer", "Michael", "Linda", "David", "Elizabeth",
+    "William", "Barbara", "Richard", "Susan", "Joseph", "Jessica", "Thomas", "Sarah", "Charles", "Karen",
+    "Daniel", "Nancy", "Matthew", "Lisa", "Anthony", "Betty", "Mark", "Margaret", "Steven", "Sandra",
+    "Paul", "Ashley", "Andrew", "Emily", "Joshua", "Donna", "Kevin", "Michelle", "Brian", "Carol",
+    "Wei", "Priya", "Mohammed", "Sofia", "Hiroshi", "Amara", "Mateo", "Olga",
+];
+
+const LAST_NAMES: [&str; 48] = [
+    "Smith", "Johnson", "Williams", "Brown", "Jones", "Garcia", "Miller", "Davis", "Rodriguez", "Martinez",
+    "Hernandez", "Lopez", "Gonzalez", "Wilson", "Anderson", "Thomas", "Taylor", "Moore", "Jackson", "Martin",
+    "Lee", "Perez", "Thompson", "White", "Harris", "Sanchez", "Clark", "Ramirez", "Lewis", "Robinson",
+    "Walker", "Young", "Allen", "King", "Wright", "Scott", "Torres", "Nguyen", "Hill", "Flores",
+    "Chen", "Patel", "Kim", "Singh", "Tanaka", "Okafor", "Novak", "Ivanova",
+];
+
+// Reserved by RFC 2606, so mail sent to them goes nowhere
+const EMAIL_DOMAINS: [&str; 3] = ["example.com", "example.org", "example.net"];
+
+/// A random value of one of the fake types (first_name, last_name, email or phone)
+pub fn fake_value(rng: &mut StdRng, data_type: DataType) -> String {
+    match data_type {
+        DataType::FirstName => FIRST_NAMES.choose(rng).unwrap().to_string(),
+        DataType::LastName => LAST_NAMES.choose(rng).unwrap().to_string(),
+        DataType::Email => {
+            let first = FIRST_NAMES.choose(rng).unwrap().to_lowercase();
+            let last = LAST_NAMES.choose(rng).unwrap().to_lowercase();
+            let domain = EMAIL_DOMAINS.choose(rng).unwrap();
+            // A number on the end about half the time, the way real addresses dodge ones already taken
+            if rng.gen_bool(0.5) {
+                format!("{}.{}{}@{}", first, last, rng.gen_range(1..100), domain)
+            } else {
+                format!("{}.{}@{}", first, last, domain)
+            }
+        },
+        // Any area code that could be real (they never start with 0 or 1), then the fictional block
+        DataType::Phone => format!("{}-555-01{:02}", rng.gen_range(200..1000), rng.gen_range(0..100)),
+        _ => unreachable!("{} isn't a fake data type", data_type.name()),
+    }
+}
+
+/// Whether the text could have come from fake_value for this type - used when verifying files
+/// Names are checked for their shape rather than against the lists, so a file can be edited by hand
+pub fn is_valid_fake(data_type: DataType, text: &str) -> bool {
+    let text = text.trim();
+    match data_type {
+        DataType::FirstName | DataType::LastName => is_name(text),
+        DataType::Email => match text.split_once('@') {
+            Some((user, domain)) => {
+                let allowed = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || ".-_".contains(c));
+                allowed(user) && allowed(domain) && domain.contains('.')
+            },
+            None => false,
+        },
+        // ddd-ddd-dddd, the North American layout
+        DataType::Phone => {
+            let parts: Vec<&str> = text.split('-').collect();
+            parts.len() == 3
+                && parts.iter().zip([3, 3, 4]).all(|(part, len)| part.len() == len && part.chars().all(|c| c.is_ascii_digit()))
+        },
+        _ => false,
+    }
+}
+
+// A capital letter followed by lowercase ones, like the names in the lists
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_ascii_uppercase()) && chars.all(|c| c.is_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::make_rng;
+
+    #[test]
+    fn fake_values_look_right_and_stay_fictional() {
+        let mut rng = make_rng(Some(9));
+        for data_type in [DataType::FirstName, DataType::LastName, DataType::Email, DataType::Phone] {
+            for _ in 0..500 {
+                let value = fake_value(&mut rng, data_type);
+                assert!(is_valid_fake(data_type, &value), "{} isn't a valid {}", value, data_type.name());
+                match data_type {
+                    DataType::Email => assert!(EMAIL_DOMAINS.iter().any(|domain| value.ends_with(domain)), "{}", value),
+                    DataType::Phone => assert_eq!(&value[3..9], "-555-0", "{}", value),
+                    _ => {},
+                }
+            }
+        }
+        assert!(!is_valid_fake(DataType::Email, "jane.smith.example.com"));
+        assert!(!is_valid_fake(DataType::Phone, "555-0123"));
+        assert!(!is_valid_fake(DataType::FirstName, "jane"));
+    }
+}
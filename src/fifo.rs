@@ -0,0 +1,137 @@
+//! Writing into a named pipe (a FIFO) that another program is reading from.
+//!
+//! `mkfifo data.pipe` makes something that looks like a file but holds nothing: whatever is
+//! written into it goes straight to the program reading the other end, so big datasets can be
+//! fed to another process without ever touching the disk. Naming an existing FIFO as the output
+//! is all it takes - it's written straight into, with no .tmp file and no rename (which would
+//! swap the pipe for an ordinary file).
+//!
+//! Opening a FIFO for writing waits until something opens it for reading, just like open() in C,
+//! so when nothing is reading yet it says what it's waiting for (as a warning, so -q hides it).
+//! If the reader closes its end before everything has gone through (like `head` does), that's
+//! a warning too, not a failure.
+//!
+//! FIFOs are a Unix thing - on Windows nothing is ever treated as one.
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+
+use log::warn;
+
+use crate::error;
+use crate::stats::Stats;
+use crate::writer::{write_to, OutputFormat};
+use crate::{invalid_input, unsupported_format, Options};
+
+/// Whether `path` is an existing named pipe
+#[cfg(unix)]
+pub fn is_fifo(path: &str) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+pub fn is_fifo(_path: &str) -> bool {
+    false
+}
+
+/// Opens the FIFO for writing, waiting for a reader first if there isn't one yet
+#[cfg(unix)]
+fn open_fifo(path: &str) -> io::Result<File> {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::fs::OpenOptionsExt;
+    // With O_NONBLOCK, open() fails with ENXIO instead of waiting when nobody's reading. Trying
+    // that first and closing it again would be no good: a reader that was waiting would see the
+    // close as the end of the data, so the one that works is kept
+    match OpenOptions::new().write(true).custom_flags(libc::O_NONBLOCK).open(path) {
+        Ok(pipe) => {
+            // Back to ordinary writes that wait while the pipe is full, with fcntl() as in C
+            // Safety: the descriptor belongs to `pipe`, which is open for as long as this runs
+            let fd = pipe.as_raw_fd();
+            let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+            if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_NONBLOCK) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(pipe)
+        },
+        Err(e) if e.raw_os_error() == Some(libc::ENXIO) => {
+            warn!("Waiting for something to read from {}...", path);
+            OpenOptions::new().write(true).open(path)
+        },
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(not(unix))]
+fn open_fifo(path: &str) -> io::Result<File> {
+    OpenOptions::new().write(true).open(path)
+}
+
+/// The settings that read the output back or need a real file are refused
+pub(crate) fn validate_fifo(options: &Options) -> error::Result<()> {
+    if options.format == OutputFormat::Sqlite || options.shards > 1 || options.mmap {
+        return Err(unsupported_format("SQLite, shards and --mmap need real files, so they can't go into a pipe"));
+    }
+    // Both read the file back, and a pipe has nothing to read. Appending is fine, since adding
+    // to a pipe and replacing what's in it are the same thing: writing into it
+    if options.resume || options.checksum {
+        return Err(invalid_input(format!("{} is a pipe, so there's nothing to resume or checksum", options.filename)));
+    }
+    Ok(())
+}
+
+/// Writes everything straight into the pipe - waiting, if need be, for a reader to turn up
+pub(crate) fn write_fifo(options: &Options) -> error::Result<Option<Stats>> {
+    let pipe = open_fifo(&options.filename)?;
+    match write_to(pipe, options) {
+        // EPIPE: the reader went away, which is its choice to make
+        Err(e) if e.io_kind() == Some(io::ErrorKind::BrokenPipe) => {
+            warn!("The reader closed {} before everything was written", options.filename);
+            Ok(None)
+        },
+        written => written,
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::generator::tests::options;
+    use crate::writer::write_data;
+    use crate::{DataType, Distribution, HeaderStyle};
+    use std::io::{BufRead, BufReader, Read};
+    use std::process::Command;
+    use std::thread;
+
+    #[test]
+    fn pipes_are_written_into_and_left_in_place() {
+        let path = std::env::temp_dir().join(format!("ruststf_test_{}_data.pipe", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        assert!(Command::new("mkfifo").arg(&path).status().unwrap().success());
+        assert!(is_fifo(&path));
+        let mut options = options(DataType::Integer, 0.0, 99.0, Distribution::Uniform);
+        (options.count, options.header_style, options.filename) = (1000, HeaderStyle::Omit, path.clone());
+
+        let reader = {
+            let path = path.clone();
+            thread::spawn(move || {
+                let mut text = String::new();
+                fs::File::open(path).unwrap().read_to_string(&mut text).unwrap();
+                text
+            })
+        };
+        write_data(&options).unwrap();
+        assert_eq!(reader.join().unwrap().lines().count(), 1000);
+
+        // A reader that stops after a few lines, like `head`, isn't an error
+        options.count = 1_000_000;
+        let reader = {
+            let path = path.clone();
+            thread::spawn(move || BufReader::new(fs::File::open(path).unwrap()).lines().take(5).count())
+        };
+        assert!(write_data(&options).unwrap().is_none());
+        assert_eq!(reader.join().unwrap(), 5);
+        assert!(is_fifo(&path));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+//! Growing a file forever, the way a program writing a log does.
+//!
+//! `--follow 250ms` keeps the file open and adds a row every 250 milliseconds until Ctrl+C (or
+//! until --count rows, if one is given), so code that tails a file - `tail -f`, a log shipper,
+//! a stream processor - can be tested against one that's still being written. Each row goes out
+//! in a single write and is flushed straight away, so a reader never sees half of one.
+//!
+//! There's no `Count:` header, since nobody knows the count until it stops, and no .tmp file
+//! either - the whole point is for the file to be seen while it grows.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error;
+use crate::generator::{make_rng, value_source};
+use crate::interrupt::{interrupted, Writing};
+use crate::writer::{csv_delimiter, write_value, Blanks, IfExists};
+use crate::{invalid_input, unsupported_format, Options, OutputFormat, SortOrder};
+
+// Longest nap between looks at Ctrl-C, so a slow --follow still stops promptly
+const CHECK_EVERY: Duration = Duration::from_millis(100);
+
+/// Adds a row to options.filename (or stdout) every `every` until Ctrl-C, or until options.count
+/// rows, and says how many rows went in. Stopping with Ctrl-C is the normal way out, so it's
+/// not an error
+pub fn follow(options: &Options, every: Duration) -> error::Result<u64> {
+    check_follow(options)?;
+    options.validate()?;
+    let to_stdout = options.writes_to_stdout();
+    let mut file: Box<dyn Write> = if to_stdout {
+        Box::new(io::stdout())
+    } else {
+        if options.if_exists == IfExists::Abort && fs::metadata(&options.filename).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", options.filename)).into());
+        }
+        // O_APPEND in C: every write lands at the end, even if something else writes there too
+        let mut open = OpenOptions::new();
+        match options.if_exists {
+            IfExists::Overwrite => open.write(true).truncate(true),
+            _ => open.append(true),
+        };
+        Box::new(open.create(true).open(&options.filename)?)
+    };
+    // Column names go at the top of a new file, not in the middle of one that's being added to
+    let empty = to_stdout || fs::metadata(&options.filename)?.len() == 0;
+    if options.format == OutputFormat::Csv && options.csv_header && empty {
+        let names: Vec<String> = (1..=options.columns).map(|i| format!("col{}", i)).collect();
+        writeln!(file, "{}", names.join(&csv_delimiter(options).to_string()))?;
+        file.flush()?;
+    }
+
+    // Ctrl-C raises a flag instead of killing the program, and the loop stops at the next row
+    let _writing = Writing::start();
+    let rng = &mut make_rng(options.seed);
+    let mut values = value_source(rng, options)?;
+    let mut blanks = Blanks::new(options);
+    let delimiter = csv_delimiter(options).to_string();
+    let started = Instant::now();
+    let mut line = Vec::new();
+    let mut rows = 0;
+    while rows < options.count as u64 && wait_until(started + every * rows as u32) {
+        line.clear();
+        for column in 0..options.columns {
+            let Some(value) = values.next() else { return Ok(rows) };
+            if column > 0 {
+                line.extend_from_slice(delimiter.as_bytes());
+            }
+            if !blanks.write_missing(&mut line, options)? {
+                write_value(&mut line, value, options)?;
+            }
+        }
+        line.push(b'\n');
+        // One write per row, so `tail -f` never prints half of one
+        file.write_all(&line)?;
+        file.flush()?;
+        rows += 1;
+    }
+    Ok(rows)
+}
+
+// Sleeps until `due`, a little at a time, and says false if Ctrl-C came first
+fn wait_until(due: Instant) -> bool {
+    while let Some(wait) = due.checked_duration_since(Instant::now()) {
+        if interrupted() {
+            return false;
+        }
+        thread::sleep(wait.min(CHECK_EVERY));
+    }
+    !interrupted()
+}
+
+/// A file that never finishes can only be written a line at a time, from values made one at a time
+pub fn check_follow(options: &Options) -> error::Result<()> {
+    let lines = match options.format {
+        OutputFormat::Text => options.line_layout.is_plain(),
+        OutputFormat::Csv => true,
+        _ => false,
+    };
+    if !lines {
+        return Err(unsupported_format("--follow adds a line at a time, so it needs text (one value per line) or CSV"));
+    }
+    if options.socket().is_some() {
+        return Err(invalid_input("--follow grows a file, so it can't send to a socket (--rate paces one instead)"));
+    }
+    if options.compress || options.framed || options.shards > 1 || options.resume || options.mmap || options.checksum || options.rate.is_some() {
+        return Err(invalid_input("--follow can't be used with gzip, --framed, shards, --resume, --mmap, --checksum or --rate"));
+    }
+    if options.unique || !matches!(options.order, SortOrder::Random) || options.stats || options.append_stats || options.histogram {
+        return Err(invalid_input("--follow never has every value, so it can't sort, make unique values or work out statistics"));
+    }
+    if !options.schema.is_empty() || options.graph.is_some() || options.time_series.is_some() || options.mixture.is_some() || options.points.is_some() {
+        return Err(invalid_input("--follow writes plain values, not records, graphs or time series"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::tests::options;
+    use crate::{DataType, Distribution};
+
+    #[test]
+    fn followed_files_grow_a_row_at_a_time() {
+        let mut options = options(DataType::Integer, 0.0, 99.0, Distribution::Uniform);
+        (options.count, options.format, options.columns, options.csv_header) = (4, OutputFormat::Csv, 3, true);
+        options.if_exists = IfExists::Append;
+        options.filename = std::env::temp_dir().join(format!("ruststf_test_{}_follow.csv", std::process::id()))
+            .to_string_lossy().into_owned();
+        let started = Instant::now();
+        assert_eq!(follow(&options, Duration::from_millis(20)).unwrap(), 4);
+        assert!(started.elapsed() >= Duration::from_millis(60));
+        // Running it again carries on the same file, without a second header
+        assert_eq!(follow(&options, Duration::from_millis(1)).unwrap(), 4);
+        let text = fs::read_to_string(&options.filename).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[0], "col1,col2,col3");
+        assert!(lines[1..].iter().all(|line| line.split(',').count() == 3));
+        fs::remove_file(&options.filename).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+//! Length-prefixed, checksummed frames around the output, for streams that might get cut short.
+//!
+//! A file on disk is either there or not, but data going down a socket or a pipe can stop half
+//! way through (a dropped connection, a killed process) or get damaged on the way, and a binary
+//! stream has nothing in it to show that. `--framed` sends the output in frames instead:
+//!
+//! ```text
+//! length   4 bytes, little-endian - how many bytes of data follow
+//! data     that many bytes of the output, exactly as it would have been written
+//! crc      4 bytes, little-endian - CRC-32 of the data (the one gzip and zip use)
+//! ```
+//!
+//! A frame of length 0 (with a CRC of 0) marks the end, so a reader that runs out before it
+//! knows the stream was cut off, and a CRC that doesn't match shows which frame was damaged.
+//! Each frame is one buffer's worth of output (--buffer-size, 8 KB unless it's changed), or one
+//! value at a time with --rate. Framing goes around everything else, gzip included - undo the
+//! frames first, then treat what's left as the usual output. `Unframed` does that for Rust
+//! programs; in anything else it's a loop of read 4 bytes, read that many, read 4 more.
+
+use std::io::{self, Read, Write};
+
+use crc32fast::Hasher;
+
+/// Bytes a frame adds: the length in front and the CRC after
+pub const FRAME_OVERHEAD: usize = 8;
+
+/// Writes everything it's given as frames, and the end marker when it's finished
+/// Turned off, it passes everything straight through, so the writers can always have one
+pub(crate) struct Framed<W: Write> {
+    inner: W,
+    on: bool,
+}
+
+impl<W: Write> Framed<W> {
+    pub(crate) fn new(inner: W, on: bool) -> Self {
+        Framed { inner, on }
+    }
+
+    /// Writes the end marker (when framing) and flushes, like closing the stream
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        if self.on {
+            self.inner.write_all(&[0; FRAME_OVERHEAD])?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for Framed<W> {
+    // Each call becomes one frame - write_all below makes sure none of it is left over, since a
+    // frame that's been started has to be finished
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.on {
+            return self.inner.write(buf);
+        }
+        // An empty frame would read as the end, and a length has to fit in 4 bytes
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let data = &buf[..buf.len().min(u32::MAX as usize)];
+        let mut hasher = Hasher::new();
+        hasher.update(data);
+        self.inner.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.inner.write_all(data)?;
+        self.inner.write_all(&hasher.finalize().to_le_bytes())?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads a framed stream back as the output inside it, checking every frame on the way
+/// A damaged frame is an InvalidData error and a stream that stops before the end marker is
+/// UnexpectedEof, so nothing short or corrupted gets through looking like the real thing
+pub struct Unframed<R: Read> {
+    inner: R,
+    frame: Vec<u8>,
+    at: usize,   // How much of frame has been handed out
+    ended: bool, // The end marker has been read
+}
+
+impl<R: Read> Unframed<R> {
+    pub fn new(inner: R) -> Self {
+        Unframed { inner, frame: Vec::new(), at: 0, ended: false }
+    }
+
+    // Reads and checks the next frame, which leaves `ended` set if it was the end marker
+    fn next_frame(&mut self) -> io::Result<()> {
+        let mut word = [0u8; 4];
+        let cut_short = |e: io::Error| match e.kind() {
+            io::ErrorKind::UnexpectedEof => io::Error::new(e.kind(), "The stream stopped part way - it ends before the end marker"),
+            _ => e,
+        };
+        self.inner.read_exact(&mut word).map_err(cut_short)?;
+        let len = u32::from_le_bytes(word) as usize;
+        self.frame.resize(len, 0);
+        self.inner.read_exact(&mut self.frame).map_err(cut_short)?;
+        self.inner.read_exact(&mut word).map_err(cut_short)?;
+        let mut hasher = Hasher::new();
+        hasher.update(&self.frame);
+        if hasher.finalize() != u32::from_le_bytes(word) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("A {}-byte frame doesn't match its CRC - it was damaged", len)));
+        }
+        self.at = 0;
+        self.ended = len == 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Unframed<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.at == self.frame.len() {
+            if self.ended {
+                return Ok(0);
+            }
+            self.next_frame()?;
+        }
+        let n = buf.len().min(self.frame.len() - self.at);
+        buf[..n].copy_from_slice(&self.frame[self.at..self.at + n]);
+        self.at += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_show_damage_and_missing_ends() {
+        let mut framed = Framed::new(Vec::new(), true);
+        framed.write_all(b"Count: 3\n").unwrap();
+        framed.write_all(b"1\n2\n3\n").unwrap();
+        let stream = framed.finish().unwrap();
+        assert_eq!(stream.len(), 9 + 6 + 3 * FRAME_OVERHEAD);
+        let mut text = String::new();
+        Unframed::new(&stream[..]).read_to_string(&mut text).unwrap();
+        assert_eq!(text, "Count: 3\n1\n2\n3\n");
+
+        let mut damaged = stream.clone();
+        damaged[14] ^= 1;
+        let error = Unframed::new(&damaged[..]).read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        // Cut off right after a whole frame still shows, since the end marker is missing
+        let error = Unframed::new(&stream[..9 + FRAME_OVERHEAD]).read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        // Off, nothing changes
+        let mut plain = Framed::new(Vec::new(), false);
+        plain.write_all(b"1\n").unwrap();
+        assert_eq!(plain.finish().unwrap(), b"1\n");
+    }
+}
@@ -0,0 +1,1590 @@
+//! Data types, distributions and the random number generation itself.
+
+use std::cmp::Ordering;
+use std::io;
+use std::net::IpAddr;
+
+use rand::Rng; // For random numbers - external package, like linking to a lib in C
+use rand::SeedableRng; // Lets us build an RNG from a fixed seed, like srand() in C
+use rand::rngs::StdRng;
+use rand::distributions::Alphanumeric; // a-z, A-Z and 0-9, picked evenly
+use rand_distr::{Exp, Normal, Poisson, Zipf}; // Extra distributions that don't come with rand itself
+use rayon::prelude::*; // par_iter() - splits work across threads without us touching a mutex
+use rayon::ThreadPool;
+
+use crate::blob::{random_blob, MAX_BLOB_SIZE};
+use crate::error;
+use crate::fake::fake_value;
+use crate::graph::edge_values;
+use crate::mixture::mixture_values;
+use crate::network::random_address;
+use crate::points::points_values;
+use crate::schema::record_values;
+use crate::series::series_values;
+use crate::special::{special_value, Special};
+use crate::timestamp::{parse_iso, MAX_TIMESTAMP, MIN_TIMESTAMP};
+use crate::uuid::{random_uuid, UUID_LENGTH};
+use crate::{invalid_input, range_error, Options};
+
+// Rust needs types for constants, unlike C where you could just #define
+/// Default range for int and float - the user can pick their own range per file
+pub const MIN_VALUE: i32 = -1000; // i32 is like int in C, but explicitly 32-bit
+pub const MAX_VALUE: i32 = 1000;
+
+/// Longest string we'll generate - a million characters per value is already a lot of file
+pub const MAX_STRING_LENGTH: f64 = 1_000_000.0;
+
+/// Biggest whole number an f64 can hold exactly (2^53) - our ranges are stored as f64,
+/// so 64-bit integer ranges have to stay inside this
+pub const MAX_EXACT_F64: f64 = 9_007_199_254_740_992.0;
+
+/// How many values each thread generates at a time in parallel mode
+/// Chunk boundaries decide which RNG stream makes which value, so this is fixed
+/// rather than based on the thread count - otherwise a seed would give different
+/// files on machines with different numbers of cores
+pub const PARALLEL_CHUNK: usize = 65_536;
+
+/// Most earlier values kept around to copy when --duplicates asks for a repeat
+/// A sample this size still picks repeats from right across the file, with a fixed amount of memory
+pub const DUPLICATE_POOL: usize = 10_000;
+
+/// How far out outliers go when nobody says: 10 to 20 half-widths of the range from its middle
+pub const DEFAULT_OUTLIER_SCALE: f64 = 10.0;
+
+/// Draws a bell curve or the like gets to land outside the exclusions before the value is
+/// picked evenly from what's left instead - a curve centred on an excluded spot could
+/// otherwise take ages
+pub const MAX_REDRAWS: u32 = 100;
+
+/// Which kind of number to generate
+// This lets us print the enum for debugging - in C we'd have to write our own print function
+// Copy means it gets copied like a C enum instead of moved, since it's just a tag
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataType {
+    Integer,  // i32, like int
+    Float,    // f32, like float
+    Short,    // i16, like short
+    Long,     // i64, like long long
+    Unsigned, // u32, like unsigned int
+    Double,   // f64, like double
+    String,   // Random letters and digits, like a char array in C
+    Bytes,    // Random bytes, like a uint8_t buffer - hex in text files, files of their own in binary
+    Timestamp, // Seconds since 1970 like time_t, written as a date or as the plain number
+    Boolean,  // true/false, like C99's bool
+    Uuid,     // Random 128-bit identifiers, written like 550e8400-e29b-41d4-a716-446655440000
+    Ipv4,     // Addresses like 192.168.0.1 - a u32 underneath, like in_addr in C
+    Ipv6,     // Addresses like 2001:db8::1 - 128 bits, like in6_addr
+    FirstName, // Made-up people for test rows: names from a built-in list,
+    LastName,
+    Email,     // addresses at the reserved example.com domains,
+    Phone,     // and numbers in the fictional 555-01xx block
+} // More powerful than C enums - you'll see how we use it with pattern matching later
+
+// impl blocks attach functions to a type - the closest thing in C is a set of
+// functions that all take the enum as their first argument
+impl DataType {
+    /// Name used in JSON files and messages
+    pub fn name(self) -> &'static str {
+        match self {
+            DataType::Integer => "integer",
+            DataType::Float => "float",
+            DataType::Short => "short",
+            DataType::Long => "long",
+            DataType::Unsigned => "unsigned",
+            DataType::Double => "double",
+            DataType::String => "string",
+            DataType::Bytes => "bytes",
+            DataType::Timestamp => "timestamp",
+            DataType::Boolean => "boolean",
+            DataType::Uuid => "uuid",
+            DataType::Ipv4 => "ipv4",
+            DataType::Ipv6 => "ipv6",
+            DataType::FirstName => "first_name",
+            DataType::LastName => "last_name",
+            DataType::Email => "email",
+            DataType::Phone => "phone",
+        }
+    }
+
+    /// Timestamps count here too - underneath they're whole seconds
+    pub fn is_integer(self) -> bool {
+        !matches!(self, DataType::Float | DataType::Double | DataType::Boolean | DataType::Bytes) && !self.is_text() && !self.is_identifier()
+    }
+
+    /// Only float and double have digits after the point (and so a precision)
+    pub fn has_decimals(self) -> bool {
+        matches!(self, DataType::Float | DataType::Double)
+    }
+
+    /// For strings the range is the length of each string rather than its value
+    pub fn is_string(self) -> bool {
+        matches!(self, DataType::String)
+    }
+
+    pub fn is_bytes(self) -> bool {
+        matches!(self, DataType::Bytes)
+    }
+
+    /// Strings and blobs: the range is how long each one is, so min == max is allowed
+    pub fn has_length(self) -> bool {
+        self.is_string() || self.is_bytes()
+    }
+
+    /// Names, emails and phone numbers - picked from patterns rather than a range
+    pub fn is_fake(self) -> bool {
+        matches!(self, DataType::FirstName | DataType::LastName | DataType::Email | DataType::Phone)
+    }
+
+    /// Types whose values are plain text (Value::Text): random strings and the fake types
+    /// They read back as strings, and have no place in binary files
+    pub fn is_text(self) -> bool {
+        self.is_string() || self.is_fake()
+    }
+
+    pub fn is_timestamp(self) -> bool {
+        matches!(self, DataType::Timestamp)
+    }
+
+    pub fn is_boolean(self) -> bool {
+        matches!(self, DataType::Boolean)
+    }
+
+    pub fn is_uuid(self) -> bool {
+        matches!(self, DataType::Uuid)
+    }
+
+    pub fn is_ip(self) -> bool {
+        matches!(self, DataType::Ipv4 | DataType::Ipv6)
+    }
+
+    /// UUIDs and IP addresses name things rather than measure them, so there's no range,
+    /// distribution or statistics for them, and JSON quotes them like strings
+    pub fn is_identifier(self) -> bool {
+        self.is_uuid() || self.is_ip()
+    }
+
+    /// Booleans, identifiers and fake data have nothing for --min and --max to set
+    pub fn has_range(self) -> bool {
+        !self.is_boolean() && !self.is_identifier() && !self.is_fake()
+    }
+
+    /// Types that can look just like a plain number or string in a file (1 could be an int
+    /// or true, 1234 an int or a string), so text files name them with a "Type:" line
+    pub fn needs_type_line(self) -> bool {
+        matches!(self, DataType::Timestamp | DataType::Boolean | DataType::Bytes) || self.is_text() || self.is_identifier()
+    }
+
+    /// Range used when the user doesn't pick one
+    pub fn default_range(self) -> (f64, f64) {
+        match self {
+            DataType::Integer | DataType::Float | DataType::Double => (MIN_VALUE as f64, MAX_VALUE as f64),
+            DataType::Short => (i16::MIN as f64, i16::MAX as f64),
+            DataType::Long => (-1_000_000_000_000.0, 1_000_000_000_000.0),
+            DataType::Unsigned => (0.0, 1_000_000.0),
+            DataType::String => (8.0, 8.0),
+            DataType::Bytes => (16.0, 16.0),
+            // 2000-01-01 up to (not quite) 2030-01-01
+            DataType::Timestamp => (946_684_800.0, 1_893_455_999.0),
+            // false and true as numbers - the chance of true is set separately
+            DataType::Boolean => (0.0, 1.0),
+            // Every bit is random, so there's no range - this just keeps validate_range happy
+            // (addresses are narrowed with --cidr instead)
+            DataType::Uuid | DataType::Ipv4 | DataType::Ipv6 => (0.0, 1.0),
+            DataType::FirstName | DataType::LastName | DataType::Email | DataType::Phone => (0.0, 1.0),
+        }
+    }
+
+    /// Smallest and largest values the type can hold at all
+    pub fn limits(self) -> (f64, f64) {
+        match self {
+            DataType::Integer => (i32::MIN as f64, i32::MAX as f64),
+            DataType::Short => (i16::MIN as f64, i16::MAX as f64),
+            DataType::Long => (-MAX_EXACT_F64, MAX_EXACT_F64),
+            DataType::Unsigned => (0.0, u32::MAX as f64),
+            DataType::Float => (f32::MIN as f64, f32::MAX as f64),
+            DataType::Double => (f64::MIN, f64::MAX),
+            DataType::String => (1.0, MAX_STRING_LENGTH),
+            DataType::Bytes => (1.0, MAX_BLOB_SIZE),
+            // Years 1 to 9999, so every timestamp can be written as an ISO date
+            DataType::Timestamp => (MIN_TIMESTAMP as f64, MAX_TIMESTAMP as f64),
+            DataType::Boolean | DataType::Uuid | DataType::Ipv4 | DataType::Ipv6 => (0.0, 1.0),
+            DataType::FirstName | DataType::LastName | DataType::Email | DataType::Phone => (0.0, 1.0),
+        }
+    }
+
+    /// Digits after the decimal point in text output
+    pub fn decimals(self) -> usize {
+        match self {
+            DataType::Double => 6,
+            _ => 3,
+        }
+    }
+
+    /// Bytes per value in binary files - like sizeof() in C
+    /// Strings have no fixed size, which is why they can't go in binary files (0 here)
+    pub fn size(self) -> usize {
+        match self {
+            DataType::Boolean => 1,
+            DataType::Short => 2,
+            DataType::Integer | DataType::Float | DataType::Unsigned | DataType::Ipv4 => 4,
+            DataType::Long | DataType::Double | DataType::Timestamp => 8,
+            DataType::Uuid | DataType::Ipv6 => 16,
+            DataType::String | DataType::Bytes => 0,
+            DataType::FirstName | DataType::LastName | DataType::Email | DataType::Phone => 0,
+        }
+    }
+
+    /// Tag stored in the first byte of a binary file, or None for types binary files can't hold
+    pub fn binary_tag(self) -> Option<u8> {
+        match self {
+            DataType::Integer => Some(0),
+            DataType::Float => Some(1),
+            DataType::Short => Some(2),
+            DataType::Long => Some(3),
+            DataType::Unsigned => Some(4),
+            DataType::Double => Some(5),
+            DataType::String | DataType::FirstName | DataType::LastName | DataType::Email | DataType::Phone => None,
+            DataType::Bytes => None, // Raw blobs each get a file of their own instead
+            DataType::Timestamp => Some(6), // Stored as i64 seconds
+            DataType::Boolean => Some(7),   // One byte, 0 or 1
+            DataType::Uuid => Some(8),      // 16 bytes, most significant first like the text
+            DataType::Ipv4 => Some(9),      // 4 bytes in network order (most significant first), like in_addr
+            DataType::Ipv6 => Some(10),     // 16 bytes, also in network order
+        }
+    }
+
+    pub fn from_binary_tag(tag: u8) -> Option<DataType> {
+        ALL_DATA_TYPES.iter().copied().find(|data_type| data_type.binary_tag() == Some(tag))
+    }
+}
+
+pub const ALL_DATA_TYPES: [DataType; 17] = [
+    DataType::Integer,
+    DataType::Float,
+    DataType::Short,
+    DataType::Long,
+    DataType::Unsigned,
+    DataType::Double,
+    DataType::String,
+    DataType::Bytes,
+    DataType::Timestamp,
+    DataType::Boolean,
+    DataType::Uuid,
+    DataType::Ipv4,
+    DataType::Ipv6,
+    DataType::FirstName,
+    DataType::LastName,
+    DataType::Email,
+    DataType::Phone,
+];
+
+/// Reads a type name or its short letter - shared by the menu, --type and schemas
+/// so they all accept the same spellings
+pub fn parse_data_type(input: &str) -> error::Result<DataType> {
+    // Pattern matching is like a super-powered switch statement
+    // The | lets several spellings share one arm, like stacked case labels in C
+    match input.trim().to_lowercase().as_str() {
+        "i" | "int" | "integer" | "i32" => Ok(DataType::Integer),
+        "f" | "float" | "f32" | "single" => Ok(DataType::Float),
+        "s" | "short" | "i16" => Ok(DataType::Short),
+        "l" | "long" | "i64" => Ok(DataType::Long),
+        "u" | "unsigned" | "u32" => Ok(DataType::Unsigned),
+        "d" | "double" | "f64" => Ok(DataType::Double),
+        "t" | "str" | "string" | "text" => Ok(DataType::String),
+        "bytes" | "blob" | "hex" => Ok(DataType::Bytes),
+        "ts" | "time" | "timestamp" | "date" | "datetime" => Ok(DataType::Timestamp),
+        "b" | "bool" | "boolean" => Ok(DataType::Boolean),
+        "uuid" | "guid" => Ok(DataType::Uuid),
+        "ip" | "ip4" | "ipv4" => Ok(DataType::Ipv4),
+        "ip6" | "ipv6" => Ok(DataType::Ipv6),
+        "first" | "first_name" | "firstname" => Ok(DataType::FirstName),
+        "last" | "last_name" | "lastname" | "surname" => Ok(DataType::LastName),
+        "email" | "e-mail" => Ok(DataType::Email),
+        "phone" | "phone_number" => Ok(DataType::Phone),
+        _ => Err(invalid_input(format!("Invalid data type: {}", input.trim()))),
+    }
+}
+
+/// Reads one end of a range - timestamp ranges can be given as dates, which come back
+/// as seconds since 1970 like any other bound
+pub fn parse_bound(input: &str) -> error::Result<f64> {
+    // is_finite() catches "inf" and "nan", which parse() happily accepts
+    match input.trim().parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => parse_iso(input)
+            .map(|seconds| seconds as f64)
+            .ok_or_else(|| invalid_input(format!("Invalid range value: {}", input.trim()))),
+    }
+}
+
+/// The shape of the random numbers - uniform spreads them evenly over the range,
+/// the others pile them up around certain values
+// Not Copy since Categorical owns a Vec - pass it around by reference instead
+#[derive(Debug, Clone, PartialEq)]
+pub enum Distribution {
+    Uniform,
+    Normal { mean: f64, std_dev: f64 }, // The classic bell curve
+    Exponential { lambda: f64 },        // Lots of small values, a long tail of big ones
+    Poisson { lambda: f64 },            // Counts of random events, always whole numbers >= 0
+    Zipf { exponent: f64 },             // min is the most common, then min + 1... bigger exponents skew harder
+    Categorical(Vec<Category>),         // Fixed labels picked by weight, for enum-like string fields
+    Sequence { start: f64, step: f64, jitter: f64 }, // start, start + step, start + 2 * step... each nudged by up to ± jitter
+    Special(Special),                   // Only primes, squares or powers of two from the range
+}
+
+/// One label a categorical distribution can pick, and how often compared to the others
+#[derive(Debug, Clone, PartialEq)]
+pub struct Category {
+    pub label: String,
+    pub weight: f64, // Weights don't have to add up to 1 - each one's share of the total is what counts
+}
+
+/// Labels go into files unquoted (text, CSV) and get checked by verify,
+/// so they're kept to the same letters and digits as random strings, plus _ and -
+pub fn is_valid_label(label: &str) -> bool {
+    !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// What order the values come out in - handy for benchmarking sorting algorithms
+#[derive(Debug, Clone, Copy)]
+pub enum SortOrder {
+    Random,
+    Ascending,
+    Descending,
+    NearlySorted { disorder: f64 }, // Ascending, then this percentage of values shuffled
+}
+
+/// One generated value - lets the text and binary writers share the same generator
+// No Copy any more: a String owns heap memory, so copying it has to be an explicit clone()
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Float(f32),
+    Short(i16),
+    Long(i64),
+    Unsigned(u32),
+    Double(f64),
+    Text(String),
+    Bytes(Vec<u8>), // Raw bytes, only turned into hex when written as text
+    Timestamp(i64), // Seconds since 1970-01-01 UTC
+    Bool(bool),
+    Uuid(u128), // The bits, only turned into 8-4-4-4-12 text when written
+    Ip(IpAddr), // Either version - the standard library's type already prints and parses both
+}
+
+impl Value {
+    /// The value as a number - f64 is wide enough for every number type we generate
+    /// Strings count as their length, so statistics on a string file describe the lengths
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Value::Int(num) => num as f64,
+            Value::Float(num) => num as f64,
+            Value::Short(num) => num as f64,
+            Value::Long(num) => num as f64,
+            Value::Unsigned(num) => num as f64,
+            Value::Double(num) => num,
+            Value::Text(ref text) => text.len() as f64,
+            Value::Bytes(ref blob) => blob.len() as f64, // Blobs count as their size, the same way
+            Value::Timestamp(seconds) => seconds as f64,
+            Value::Bool(flag) => if flag { 1.0 } else { 0.0 }, // Like C, where true is just 1
+            // Like a string, a UUID counts as its length - an f64 can't hold 128 bits anyway
+            Value::Uuid(_) => UUID_LENGTH as f64,
+            Value::Ip(address) => address.to_string().len() as f64,
+        }
+    }
+
+    /// False only for NaN and infinity - floats and doubles can hold them, but we never write them
+    pub fn is_finite(&self) -> bool {
+        match self {
+            Value::Float(num) => num.is_finite(),
+            Value::Double(num) => num.is_finite(),
+            _ => true,
+        }
+    }
+
+    /// Used for sorting: numbers by size, strings alphabetically (like strcmp in C)
+    pub fn compare(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+            // Byte by byte, like memcmp(), which is also the order of their hex
+            (Value::Bytes(a), Value::Bytes(b)) => a.cmp(b),
+            // Comparing the bits puts them in the same order as their text
+            (Value::Uuid(a), Value::Uuid(b)) => a.cmp(b),
+            // Addresses compare as numbers, so 10.0.0.9 comes before 10.0.0.10
+            (Value::Ip(a), Value::Ip(b)) => a.cmp(b),
+            _ => self.as_f64().total_cmp(&other.as_f64()),
+        }
+    }
+}
+
+/// Checks the range makes sense before we generate anything
+/// In C a swapped min/max would just give garbage, here we catch it up front
+pub fn validate_range(data_type: DataType, min: f64, max: f64) -> error::Result<()> {
+    // parse_bound already refuses these, but Options built in code could still pass NaN,
+    // and NaN fails every comparison below, so it would sneak straight through them
+    if !min.is_finite() || !max.is_finite() {
+        return Err(range_error("Range must be finite numbers, not NaN or infinity"));
+    }
+    if data_type.has_length() {
+        // min == max is fine here - it just means every string (or blob) has the same length
+        if min > max {
+            return Err(range_error(format!("Minimum length ({}) must not be more than maximum ({})", min, max)));
+        }
+    } else if min >= max {
+        return Err(range_error(format!("Minimum ({}) must be less than maximum ({})", min, max)));
+    }
+    if data_type.is_integer() && (min.fract() != 0.0 || max.fract() != 0.0) {
+        return Err(range_error("Integer ranges must use whole numbers"));
+    }
+    if data_type.has_length() && (min.fract() != 0.0 || max.fract() != 0.0) {
+        return Err(range_error("String lengths and blob sizes must be whole numbers"));
+    }
+    let (lowest, highest) = data_type.limits();
+    if min < lowest || max > highest {
+        return Err(range_error(format!(
+            "Range for {} must be between {} and {}",
+            data_type.name(), lowest, highest
+        )));
+    }
+    Ok(())
+}
+
+/// Normal defaults: centered in the range, with nearly all values (6 std devs) landing inside it
+// Halving each end first keeps a range as wide as a double can go from overflowing to infinity
+pub fn default_normal(min: f64, max: f64) -> (f64, f64) {
+    (min / 2.0 + max / 2.0, max / 6.0 - min / 6.0)
+}
+
+/// Checks the parameters up front so generation itself can't fail halfway through a file
+pub fn validate_distribution(distribution: &Distribution) -> error::Result<()> {
+    match *distribution {
+        Distribution::Normal { mean, std_dev } if !(mean.is_finite() && std_dev > 0.0 && std_dev.is_finite()) => {
+            Err(invalid_input("Normal distribution needs a finite mean and a positive standard deviation"))
+        },
+        Distribution::Exponential { lambda } | Distribution::Poisson { lambda } if !(lambda > 0.0 && lambda.is_finite()) => {
+            Err(invalid_input("Lambda must be positive"))
+        },
+        Distribution::Zipf { exponent } if !(exponent > 0.0 && exponent.is_finite()) => {
+            Err(invalid_input("Zipf exponent must be positive"))
+        },
+        Distribution::Categorical(ref categories) => validate_categories(categories),
+        Distribution::Sequence { start, step, .. } if !(start.is_finite() && step.is_finite()) => {
+            Err(invalid_input(format!("Sequence start and step must be finite numbers, not {} and {}", start, step)))
+        },
+        Distribution::Sequence { jitter, .. } if !(jitter >= 0.0 && jitter.is_finite()) => {
+            Err(invalid_input("Jitter must be zero or more"))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Smallest and largest values a sequence of `total` values can reach, jitter included
+/// Used as the range when none is given, so nothing gets clipped
+pub fn sequence_range(start: f64, step: f64, jitter: f64, total: u64) -> (f64, f64) {
+    let end = start + total.saturating_sub(1) as f64 * step;
+    (start.min(end) - jitter, start.max(end) + jitter)
+}
+
+/// The step that walks from start to end in `total` values, for when a range is given but no step
+pub fn sequence_step(start: f64, end: f64, total: u64) -> f64 {
+    (end - start) / total.saturating_sub(1).max(1) as f64
+}
+
+fn validate_categories(categories: &[Category]) -> error::Result<()> {
+    if categories.is_empty() {
+        return Err(invalid_input("Categorical distribution needs at least one category"));
+    }
+    for (i, category) in categories.iter().enumerate() {
+        if !is_valid_label(&category.label) {
+            return Err(invalid_input(format!(
+                "Invalid category '{}' - use letters, digits, _ and - only", category.label
+            )));
+        }
+        if !(category.weight >= 0.0 && category.weight.is_finite()) {
+            return Err(invalid_input(format!("Weight for {} must be zero or more", category.label)));
+        }
+        // Two entries with one label would just be one category with their weights added up, which is confusing
+        if categories[..i].iter().any(|other| other.label == category.label) {
+            return Err(invalid_input(format!("Category {} is listed twice", category.label)));
+        }
+    }
+    if categories.iter().map(|category| category.weight).sum::<f64>() <= 0.0 {
+        return Err(invalid_input("At least one category needs a weight above zero"));
+    }
+    Ok(())
+}
+
+pub fn validate_order(order: SortOrder) -> error::Result<()> {
+    match order {
+        SortOrder::NearlySorted { disorder } if !(0.0..=100.0).contains(&disorder) => {
+            Err(invalid_input("Disorder percentage must be between 0 and 100"))
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Sampling without replacement only works when there are enough distinct values to go around
+pub fn validate_unique(data_type: DataType, distribution: &Distribution, min: f64, max: f64, total: u64) -> error::Result<()> {
+    if !data_type.is_integer() {
+        return Err(invalid_input("Unique values are only supported for integer types"));
+    }
+    if !matches!(distribution, Distribution::Uniform) {
+        return Err(invalid_input("Unique values are only supported with the uniform distribution"));
+    }
+    // +1 because both ends of the range are included
+    let range_size = (max - min) as u64 + 1;
+    if total > range_size {
+        return Err(invalid_input(format!(
+            "Can't pick {} unique values from a range of only {} values",
+            total, range_size
+        )));
+    }
+    Ok(())
+}
+
+/// Reads values to leave out, e.g. "0" or "-10..10, 99" - each one is a single value or a
+/// low..high range with both ends left out too (dates work for timestamps, like the range)
+pub fn parse_exclusions(input: &str) -> error::Result<Vec<(f64, f64)>> {
+    let mut exclude = Vec::new();
+    for item in input.split(',') {
+        let (low, high) = match item.split_once("..") {
+            Some((low, high)) => (parse_bound(low)?, parse_bound(high)?),
+            None => (parse_bound(item)?, parse_bound(item)?),
+        };
+        if low > high {
+            return Err(range_error(format!("Excluded range {} goes backwards", item.trim())));
+        }
+        exclude.push((low, high));
+    }
+    Ok(exclude)
+}
+
+/// Checks the exclusions leave something to generate - and enough of it for unique values
+pub fn validate_exclusions(data_type: DataType, min: f64, max: f64, exclude: &[(f64, f64)], unique: bool, total: u64) -> error::Result<()> {
+    if !(data_type.is_integer() || data_type.has_decimals()) {
+        return Err(invalid_input("Excluded values are only supported for number types and timestamps"));
+    }
+    if exclude.iter().any(|&(low, high)| !low.is_finite() || !high.is_finite() || low > high) {
+        return Err(range_error("Excluded ranges must be finite numbers, low..high"));
+    }
+    let gaps = allowed_gaps(data_type, min, max, exclude);
+    if gaps.is_empty() {
+        return Err(range_error(format!("Every value from {} to {} is excluded", min, max)));
+    }
+    let left: f64 = gaps.iter().map(|&(low, high)| high - low + 1.0).sum();
+    if unique && total as f64 > left {
+        return Err(invalid_input(format!("Can't pick {} unique values when only {} aren't excluded", total, left)));
+    }
+    Ok(())
+}
+
+/// The parts of the range left once the exclusions are taken out, lowest first
+/// Whole-number types start and stop one short of each excluded range, since its ends are
+/// excluded too; for decimals a gap runs right up to the excluded end (which is never picked)
+pub(crate) fn allowed_gaps(data_type: DataType, min: f64, max: f64, exclude: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let whole = data_type.is_integer();
+    let mut exclude = exclude.to_vec();
+    exclude.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut gaps = Vec::new();
+    let mut start = min;
+    for (low, high) in exclude {
+        let end = (if whole { low.ceil() - 1.0 } else { low }).min(max);
+        if end > start || (whole && end == start) {
+            gaps.push((start, end));
+        }
+        start = start.max(if whole { high.floor() + 1.0 } else { high });
+    }
+    if start < max || (whole && start == max) {
+        gaps.push((start, max));
+    }
+    gaps
+}
+
+/// Whether a value is one of the excluded ones - decimals are checked as they'll be written,
+/// so excluding 0 also keeps out 0.0001 when only three digits make it into the file
+pub fn is_excluded(value: &Value, options: &Options) -> bool {
+    let mut number = value.as_f64();
+    if options.data_type.has_decimals() && !options.scientific {
+        let scale = 10f64.powi(options.decimals() as i32);
+        number = (number * scale).round() / scale;
+    }
+    options.exclude.iter().any(|&(low, high)| (low..=high).contains(&number))
+}
+
+/// Checks a duplicate percentage - repeats are only counted exactly for whole numbers,
+/// and asking for unique values at the same time would contradict it
+pub fn validate_duplicates(data_type: DataType, percent: f64, unique: bool) -> error::Result<()> {
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(invalid_input("Duplicate percentage must be between 0 and 100"));
+    }
+    if percent == 0.0 {
+        return Ok(());
+    }
+    if !data_type.is_integer() {
+        return Err(invalid_input("Duplicates are only supported for integer types"));
+    }
+    if unique {
+        return Err(invalid_input("Values can't be unique and have duplicates at the same time"));
+    }
+    Ok(())
+}
+
+/// Checks an outlier percentage and how far out they go - they land outside the range,
+/// so they only make sense for numbers, and there has to be room outside it for them
+pub fn validate_outliers(data_type: DataType, min: f64, max: f64, percent: f64, scale: f64, unique: bool) -> error::Result<()> {
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(invalid_input("Outlier percentage must be between 0 and 100"));
+    }
+    if !(scale >= 1.0 && scale.is_finite()) {
+        return Err(invalid_input("Outlier scale must be at least 1"));
+    }
+    if percent == 0.0 {
+        return Ok(());
+    }
+    if !(data_type.is_integer() || data_type.has_decimals()) {
+        return Err(invalid_input("Outliers are only supported for number types and timestamps"));
+    }
+    let (lowest, highest) = data_type.limits();
+    if min <= lowest && max >= highest {
+        return Err(invalid_input(format!(
+            "The range already covers every {} value, so there's no room for outliers", data_type.name()
+        )));
+    }
+    // Outliers are picked at random outside the range, so nothing stops two being the same
+    if unique {
+        return Err(invalid_input("Values can't be unique and have outliers at the same time"));
+    }
+    Ok(())
+}
+
+/// Same seed in, same numbers out - just like calling srand(seed) before rand() in C
+/// Without a seed we let the OS pick one so every run is different
+pub fn make_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// Hands out every value the file needs, in the requested order
+/// Random order streams straight from the RNG; any sorting means we have to
+/// generate everything up front, since the smallest value could come last
+// Box<dyn Iterator> is like a function pointer + state in C: either kind of source fits behind it
+/// With more than one thread the values come from ParallelValues instead, which
+/// fails only if the thread pool can't be started
+pub fn value_source<'a>(rng: &'a mut StdRng, options: &'a Options) -> error::Result<Box<dyn Iterator<Item = Value> + 'a>> {
+    let total = options.count as usize * options.values_per_row() as usize;
+    // Graphs pick all their edges first, so no edge can come up twice
+    if let Some(graph) = options.graph {
+        return Ok(Box::new(edge_values(rng, options, graph)));
+    }
+    // A time series fills in a record's two fields itself, since each value depends on its step
+    if let Some(series) = options.time_series {
+        return Ok(Box::new(series_values(rng, options, series)));
+    }
+    // A mixture fills in a point's fields together, since they all come from the same cluster
+    if let Some(mixture) = &options.mixture {
+        return Ok(Box::new(mixture_values(rng, options, mixture)));
+    }
+    if let Some(points) = options.points {
+        return Ok(Box::new(points_values(rng, options, points)));
+    }
+    // Records cycle through their fields, each with its own type and range
+    if !options.schema.is_empty() {
+        return Ok(Box::new(record_values(rng, options, total)));
+    }
+    // A permutation shuffles as it goes, so the first number is out before the last is picked
+    if options.permutation {
+        return Ok(Box::new(Permutation::new(rng, options, total)));
+    }
+    // Repeats and outliers are placed with RNGs of their own, seeded before anything else uses the main one
+    let repeats_rng = if options.duplicates > 0.0 { Some(StdRng::seed_from_u64(rng.gen())) } else { None };
+    let outliers_rng = if options.outliers > 0.0 { Some(StdRng::seed_from_u64(rng.gen())) } else { None };
+    // Unique values come from one big sample() call, so there's nothing to split up,
+    // and each value of a sequence follows on from the one before, so that stays on one thread too
+    let sequence = matches!(options.distribution, Distribution::Sequence { .. });
+    // A pool is sampled in one call as well, for the same reason
+    let sampled = options.unique || options.pool.is_some();
+    let parallel = options.threads != 1 && !sampled && !sequence;
+    if let SortOrder::Random = options.order {
+        if parallel {
+            let values = with_duplicates(ParallelValues::new(rng, options, total)?, repeats_rng, options, total);
+            return Ok(with_outliers(values, outliers_rng, options, total));
+        } else if !sampled {
+            let values = with_duplicates((0..total).map(move |i| gen_value_at(rng, options, i)), repeats_rng, options, total);
+            return Ok(with_outliers(values, outliers_rng, options, total));
+        }
+    }
+    // Repeats go in before sorting, so a sorted file has them next to their originals
+    // (and outliers end up at the ends, where a sorted file would put them)
+    let mut values: Vec<Value> = if let Some(pool) = &options.pool {
+        pool.sample(rng, total)
+    } else if options.unique {
+        unique_values(rng, options, total)
+    } else if parallel {
+        let values = with_duplicates(ParallelValues::new(&mut *rng, options, total)?, repeats_rng, options, total);
+        with_outliers(values, outliers_rng, options, total).collect()
+    } else {
+        let values = with_duplicates((0..total).map(|i| gen_value_at(rng, options, i)), repeats_rng, options, total);
+        with_outliers(values, outliers_rng, options, total).collect()
+    };
+    match options.order {
+        SortOrder::Ascending => values.sort_by(Value::compare),
+        SortOrder::Descending => values.sort_by(|a, b| b.compare(a)),
+        SortOrder::NearlySorted { disorder } => {
+            values.sort_by(Value::compare);
+            // Pick disorder% of the positions and shuffle the values among just those spots
+            let amount = (total as f64 * disorder / 100.0).round() as usize;
+            let positions = rand::seq::index::sample(rng, total, amount).into_vec();
+            // Fisher-Yates, but swapping through the picked positions instead of the whole Vec
+            for i in (1..positions.len()).rev() {
+                let j = rng.gen_range(0..=i);
+                values.swap(positions[i], positions[j]);
+            }
+        },
+        SortOrder::Random => {},
+    }
+    Ok(Box::new(values.into_iter()))
+}
+
+// Generates values a batch of chunks at a time, one chunk per task, and hands them out in order
+// Each chunk gets its own RNG seeded from the main one, so threads never share (or fight over) an RNG
+// and the same seed still gives the same file
+struct ParallelValues<'a> {
+    rng: &'a mut StdRng,
+    options: &'a Options,
+    pool: ThreadPool,
+    remaining: usize,                 // Values not generated yet
+    ready: std::vec::IntoIter<Value>, // Values generated but not handed out yet
+}
+
+impl<'a> ParallelValues<'a> {
+    fn new(rng: &'a mut StdRng, options: &'a Options, total: usize) -> error::Result<Self> {
+        // 0 threads means "one per core", which is also what rayon does when we don't set a number
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(options.threads as usize)
+            .build()
+            .map_err(|e| io::Error::other(format!("Couldn't start worker threads: {}", e)))?;
+        Ok(ParallelValues { rng, options, pool, remaining: total, ready: Vec::new().into_iter() })
+    }
+
+    fn fill(&mut self) {
+        // A few chunks per thread keeps every core busy without holding the whole file in memory
+        let batch = (self.pool.current_num_threads() * 4 * PARALLEL_CHUNK).min(self.remaining);
+        self.remaining -= batch;
+        // Seeds are drawn on this thread, in order, before any work is handed out
+        let jobs: Vec<(u64, usize)> = (0..batch)
+            .step_by(PARALLEL_CHUNK)
+            .map(|start| (self.rng.gen(), PARALLEL_CHUNK.min(batch - start)))
+            .collect();
+        let options = self.options;
+        // collect() on a parallel iterator still keeps the chunks in their original order
+        let chunks: Vec<Vec<Value>> = self.pool.install(|| {
+            jobs.par_iter()
+                .map(|&(seed, len)| {
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    (0..len).map(|_| gen_value(&mut rng, options)).collect()
+                })
+                .collect()
+        });
+        self.ready = chunks.concat().into_iter();
+    }
+}
+
+impl Iterator for ParallelValues<'_> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        if let Some(value) = self.ready.next() {
+            return Some(value);
+        }
+        if self.remaining == 0 {
+            return None;
+        }
+        self.fill();
+        self.ready.next()
+    }
+}
+
+// Only wraps the values in Duplicates when some were asked for
+fn with_duplicates<'a>(
+    fresh: impl Iterator<Item = Value> + 'a,
+    rng: Option<StdRng>,
+    options: &Options,
+    total: usize,
+) -> Box<dyn Iterator<Item = Value> + 'a> {
+    match rng {
+        Some(rng) => Box::new(Duplicates::new(fresh, rng, options.duplicates, total)),
+        None => Box::new(fresh),
+    }
+}
+
+// Swaps some of the fresh values for copies of earlier ones, for testing deduplication
+// Each position gets a repeat with chance (repeats left) / (positions left), which spreads
+// them at random but always comes out at exactly the number asked for
+// Copies come from a reservoir sample of the fresh values so far: every value that's gone
+// past has the same chance of being in it, without keeping the whole file in memory
+struct Duplicates<I> {
+    fresh: I,
+    rng: StdRng,
+    pool: Vec<Value>, // Up to DUPLICATE_POOL earlier values to copy from
+    seen: u64,        // Fresh values handed out so far
+    remaining: u64,   // Positions left to fill
+    repeats: u64,     // How many of those still have to be repeats
+}
+
+impl<I: Iterator<Item = Value>> Duplicates<I> {
+    fn new(fresh: I, rng: StdRng, percent: f64, total: usize) -> Self {
+        let total = total as u64;
+        // The first value has nothing before it to repeat, so at most total - 1 can be copies
+        let repeats = ((total as f64 * percent / 100.0).round() as u64).min(total.saturating_sub(1));
+        Duplicates { fresh, rng, pool: Vec::new(), seen: 0, remaining: total, repeats }
+    }
+}
+
+impl<I: Iterator<Item = Value>> Iterator for Duplicates<I> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let repeat = !self.pool.is_empty() && self.rng.gen_range(0..self.remaining) < self.repeats;
+        self.remaining -= 1;
+        if repeat {
+            self.repeats -= 1;
+            return Some(self.pool[self.rng.gen_range(0..self.pool.len())].clone());
+        }
+        let value = self.fresh.next()?;
+        // Reservoir sampling (Algorithm R): fill the pool, then let each new value
+        // replace a random slot with chance pool size / values seen
+        self.seen += 1;
+        if self.pool.len() < DUPLICATE_POOL {
+            self.pool.push(value.clone());
+        } else {
+            let slot = self.rng.gen_range(0..self.seen) as usize;
+            if slot < DUPLICATE_POOL {
+                self.pool[slot] = value.clone();
+            }
+        }
+        Some(value)
+    }
+}
+
+// Only wraps the values in Outliers when some were asked for
+fn with_outliers<'a>(
+    values: Box<dyn Iterator<Item = Value> + 'a>,
+    rng: Option<StdRng>,
+    options: &'a Options,
+    total: usize,
+) -> Box<dyn Iterator<Item = Value> + 'a> {
+    match rng {
+        Some(rng) => Box::new(Outliers::new(values, rng, options, total)),
+        None => values,
+    }
+}
+
+// Swaps some of the values for ones far outside the range, for testing anomaly detectors
+// Positions are picked the same way as Duplicates, so the count comes out exact
+struct Outliers<'a, I> {
+    values: I,
+    rng: StdRng,
+    options: &'a Options,
+    remaining: u64, // Positions left to fill
+    outliers: u64,  // How many of those still have to be outliers
+}
+
+impl<'a, I: Iterator<Item = Value>> Outliers<'a, I> {
+    fn new(values: I, rng: StdRng, options: &'a Options, total: usize) -> Self {
+        let total = total as u64;
+        let outliers = ((total as f64 * options.outliers / 100.0).round() as u64).min(total);
+        Outliers { values, rng, options, remaining: total, outliers }
+    }
+}
+
+impl<I: Iterator<Item = Value>> Iterator for Outliers<'_, I> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        // The value is always taken, so an outlier replaces it rather than shifting the rest along
+        let value = self.values.next()?;
+        let outlier = self.remaining > 0 && self.rng.gen_range(0..self.remaining) < self.outliers;
+        self.remaining = self.remaining.saturating_sub(1);
+        if !outlier {
+            return Some(value);
+        }
+        self.outliers -= 1;
+        // There's not always room for one outside both the range and the exclusions,
+        // and then the value that was there already stays
+        let outlier = (0..MAX_REDRAWS)
+            .map(|_| outlier_value(&mut self.rng, self.options))
+            .find(|outlier| !is_excluded(outlier, self.options));
+        Some(outlier.unwrap_or(value))
+    }
+}
+
+/// A value well outside the range: between scale and twice scale half-widths of the range
+/// away from its middle, on a random side (or the only side the type has room on)
+pub fn outlier_value(rng: &mut StdRng, options: &Options) -> Value {
+    // Halving each end first, like default_normal, so the widest ranges can't overflow
+    let middle = options.min / 2.0 + options.max / 2.0;
+    let distance = (options.max / 2.0 - options.min / 2.0) * options.outlier_scale * rng.gen_range(1.0..2.0);
+    let (lowest, highest) = options.data_type.limits();
+    let below = middle - distance;
+    let above = middle + distance;
+    // validate_outliers made sure at least one side has room past the range
+    let low_side = if options.min <= lowest {
+        false
+    } else if options.max >= highest {
+        true
+    } else {
+        rng.gen_bool(0.5)
+    };
+    let outlier = if low_side { below.max(lowest) } else { above.min(highest) };
+    number_value(rng, options, outlier)
+}
+
+// Draws `total` different integers from the range, in random order
+// index::sample picks distinct offsets from 0..range_size for us, so we just add min
+// With exclusions the offsets count through the gaps between them instead
+fn unique_values(rng: &mut StdRng, options: &Options, total: usize) -> Vec<Value> {
+    let gaps = allowed_gaps(options.data_type, options.min, options.max, &options.exclude);
+    let range_size = gaps.iter().map(|&(low, high)| (high - low) as usize + 1).sum();
+    rand::seq::index::sample(rng, range_size, total)
+        .into_iter()
+        .map(|offset| {
+            let mut offset = offset as i64;
+            let mut num = 0;
+            for &(low, high) in &gaps {
+                let size = (high - low) as i64 + 1;
+                if offset < size {
+                    num = low as i64 + offset;
+                    break;
+                }
+                offset -= size;
+            }
+            integer_value(options.data_type, num)
+        })
+        .collect()
+}
+
+// A whole number as a value of an integer type
+// validate_range made sure every number in the range fits the type
+fn integer_value(data_type: DataType, num: i64) -> Value {
+    match data_type {
+        DataType::Short => Value::Short(num as i16),
+        DataType::Long => Value::Long(num),
+        DataType::Unsigned => Value::Unsigned(num as u32),
+        DataType::Timestamp => Value::Timestamp(num),
+        _ => Value::Int(num as i32),
+    }
+}
+
+// Every number from min to max once, in a random order: Fisher-Yates, handing each number out
+// as soon as its place is settled instead of shuffling the whole array first
+// Slot i holds its number + 1, or 0 for a slot nothing has been swapped into yet, which means
+// it still holds i itself. vec![0; n] gets memory that's already zeroed, like calloc() in C,
+// so the array doesn't have to be filled with 0..n before the first number can go out
+struct Permutation<'a> {
+    rng: &'a mut StdRng,
+    slots: Vec<u32>, // u32 is enough, since the count (and so the size) is a u32 too
+    next: usize,     // Slots before this one are settled and written already
+    min: i64,
+    data_type: DataType,
+}
+
+impl<'a> Permutation<'a> {
+    fn new(rng: &'a mut StdRng, options: &Options, total: usize) -> Self {
+        Permutation { rng, slots: vec![0; total], next: 0, min: options.min as i64, data_type: options.data_type }
+    }
+
+    fn number_at(&self, slot: usize) -> u32 {
+        match self.slots[slot] {
+            0 => slot as u32,
+            held => held - 1,
+        }
+    }
+}
+
+impl Iterator for Permutation<'_> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        let i = self.next;
+        if i == self.slots.len() {
+            return None;
+        }
+        // Swap a random slot from here to the end into place i - every number left is as
+        // likely as the next to come out, which is what makes every order equally likely
+        let j = self.rng.gen_range(i..self.slots.len());
+        let (picked, moved) = (self.number_at(j), self.number_at(i));
+        self.slots[j] = moved + 1;
+        self.next += 1;
+        Some(integer_value(self.data_type, self.min + picked as i64))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.slots.len() - self.next;
+        (left, Some(left))
+    }
+}
+
+// The value at position `index` in the file - only a sequence cares where it is,
+// everything else is a fresh draw each time
+fn gen_value_at(rng: &mut StdRng, options: &Options, index: usize) -> Value {
+    match options.distribution {
+        Distribution::Sequence { start, step, jitter } => {
+            let sample = start + index as f64 * step + gen_jitter(rng, jitter);
+            sample_value(rng, options, sample)
+        },
+        _ => gen_value(rng, options),
+    }
+}
+
+/// Picks one random value of the requested type inside the requested range, leaving out
+/// anything excluded
+pub fn gen_value(rng: &mut StdRng, options: &Options) -> Value {
+    if options.exclude.is_empty() {
+        return draw_value(rng, options);
+    }
+    // Uniform picks straight from the gaps, so it only redraws when a float rounds onto an
+    // excluded end; a curve is drawn as usual and tried again when it lands somewhere excluded
+    let uniform = matches!(options.distribution, Distribution::Uniform);
+    for _ in 0..MAX_REDRAWS {
+        let value = if uniform { allowed_value(rng, options) } else { draw_value(rng, options) };
+        if !is_excluded(&value, options) {
+            return value;
+        }
+    }
+    // validate_exclusions made sure there's something left, so this finds it in the end
+    loop {
+        let value = allowed_value(rng, options);
+        if !is_excluded(&value, options) {
+            return value;
+        }
+    }
+}
+
+// Evenly over what's left of the range: a gap is picked in proportion to its size, then a
+// value inside it - the same as gen_uniform over a range with holes cut out
+fn allowed_value(rng: &mut StdRng, options: &Options) -> Value {
+    let whole = options.data_type.is_integer();
+    let gaps = allowed_gaps(options.data_type, options.min, options.max, &options.exclude);
+    let size = |&(low, high): &(f64, f64)| if whole { high - low + 1.0 } else { high - low };
+    let mut roll = rng.gen_range(0.0..gaps.iter().map(size).sum::<f64>());
+    // Rounding can leave a sliver past the last gap, which belongs to the last gap
+    let mut gap = gaps[gaps.len() - 1];
+    for candidate in &gaps {
+        if roll < size(candidate) {
+            gap = *candidate;
+            break;
+        }
+        roll -= size(candidate);
+    }
+    let sample = if whole {
+        rng.gen_range(gap.0 as i64..=gap.1 as i64) as f64
+    } else {
+        wide_uniform(rng, gap.0, gap.1)
+    };
+    number_value(rng, options, sample)
+}
+
+// One value from the distribution, with nothing excluded
+// &mut is like pointers in C but Rust checks that we use them safely
+// No dangling pointers or double frees!
+fn draw_value(rng: &mut StdRng, options: &Options) -> Value {
+    // The .unwrap()s are safe because validate_distribution already rejected bad parameters
+    let sample = match options.distribution {
+        // Options::validate only lets strings through with uniform or categorical
+        Distribution::Uniform => return gen_uniform(rng, options),
+        Distribution::Categorical(ref categories) => return Value::Text(pick_category(rng, categories).to_string()),
+        Distribution::Normal { mean, std_dev } => rng.sample(Normal::new(mean, std_dev).unwrap()),
+        Distribution::Exponential { lambda } => rng.sample(Exp::new(lambda).unwrap()),
+        Distribution::Poisson { lambda } => rng.sample(Poisson::new(lambda).unwrap()),
+        // Zipf hands back a rank from 1 (the most common) to n, and rank 1 is the bottom of the range
+        Distribution::Zipf { exponent } => {
+            let ranks = (options.max - options.min).floor() as u64 + 1;
+            options.min + rng.sample(Zipf::new(ranks, exponent).unwrap()) - 1.0
+        },
+        // One value on its own has no position, so it's the first one - value_source steps along from there
+        Distribution::Sequence { start, jitter, .. } => start + gen_jitter(rng, jitter),
+        // Always whole numbers inside the range, so the clamp and rounding leave them as they are
+        Distribution::Special(special) => special_value(rng, special, options.min, options.max),
+    };
+    sample_value(rng, options, sample)
+}
+
+// Anywhere from -jitter to +jitter, and no RNG call at all for an exact sequence
+fn gen_jitter(rng: &mut StdRng, jitter: f64) -> f64 {
+    if jitter > 0.0 { rng.gen_range(-jitter..=jitter) } else { 0.0 }
+}
+
+/// Turns a number from one of the distributions into a value of the requested type
+pub(crate) fn sample_value(rng: &mut StdRng, options: &Options, sample: f64) -> Value {
+    // Tails can run past the range, so pull those values back to the nearest edge
+    number_value(rng, options, sample.clamp(options.min, options.max))
+}
+
+// The same without the clamp, for outliers that are meant to land outside the range
+// (they still have to fit the type, which outlier_value sees to)
+fn number_value(rng: &mut StdRng, options: &Options, sample: f64) -> Value {
+    // Rounding first means integer types get the nearest whole number instead of always rounding down
+    let whole = sample.round();
+    match options.data_type {
+        DataType::Integer => Value::Int(whole as i32),
+        DataType::Short => Value::Short(whole as i16),
+        DataType::Long => Value::Long(whole as i64),
+        DataType::Unsigned => Value::Unsigned(whole as u32),
+        DataType::Float => Value::Float(sample as f32),
+        DataType::Double => Value::Double(sample),
+        DataType::String | DataType::Bytes => gen_uniform(rng, options),
+        DataType::Timestamp => Value::Timestamp(whole as i64),
+        DataType::Boolean | DataType::Uuid | DataType::Ipv4 | DataType::Ipv6 => gen_uniform(rng, options),
+        DataType::FirstName | DataType::LastName | DataType::Email | DataType::Phone => gen_uniform(rng, options),
+    }
+}
+
+// Rolls a number up to the total weight and walks along until the running total passes it,
+// so each label gets picked in proportion to its weight
+// A linear walk is fine here - nobody lists more than a handful of categories
+fn pick_category<'a>(rng: &mut StdRng, categories: &'a [Category]) -> &'a str {
+    let total: f64 = categories.iter().map(|category| category.weight).sum();
+    let mut roll = rng.gen_range(0.0..total);
+    for category in categories {
+        if roll < category.weight {
+            return &category.label;
+        }
+        roll -= category.weight;
+    }
+    // Rounding can leave a sliver past the last weight, which belongs to the last real category
+    &categories.iter().rfind(|category| category.weight > 0.0).unwrap_or(&categories[0]).label
+}
+
+// Every value in the range is equally likely - what the program always did before distributions
+fn gen_uniform(rng: &mut StdRng, options: &Options) -> Value {
+    match options.data_type { 
+        // validate_range already made sure these are whole numbers that fit in the type,
+        // so the `as` casts can't wrap around like a C cast would
+        DataType::Integer => Value::Int(rng.gen_range(options.min as i32..=options.max as i32)),
+        DataType::Short => Value::Short(rng.gen_range(options.min as i16..=options.max as i16)),
+        DataType::Long => Value::Long(rng.gen_range(options.min as i64..=options.max as i64)),
+        DataType::Unsigned => Value::Unsigned(rng.gen_range(options.min as u32..=options.max as u32)),
+        DataType::Float => {
+            let (min, max) = (options.min as f32, options.max as f32);
+            if (max - min).is_finite() {
+                Value::Float(rng.gen_range(min..=max))
+            } else {
+                // Any f32 range fits comfortably in a double, so pick there and narrow it after
+                Value::Float(wide_uniform(rng, options.min, options.max) as f32)
+            }
+        },
+        DataType::Double if (options.max - options.min).is_finite() => Value::Double(rng.gen_range(options.min..=options.max)),
+        DataType::Double => Value::Double(wide_uniform(rng, options.min, options.max)),
+        DataType::Timestamp => Value::Timestamp(rng.gen_range(options.min as i64..=options.max as i64)),
+        // gen_bool(p) is true with probability p - Options::validate made sure 0 <= p <= 1
+        DataType::Boolean => Value::Bool(rng.gen_bool(options.true_probability)),
+        DataType::Uuid => Value::Uuid(random_uuid(rng)),
+        DataType::Ipv4 => Value::Ip(random_address(rng, true, options.cidr)),
+        DataType::Ipv6 => Value::Ip(random_address(rng, false, options.cidr)),
+        DataType::FirstName | DataType::LastName | DataType::Email | DataType::Phone => {
+            Value::Text(fake_value(rng, options.data_type))
+        },
+        DataType::String => {
+            // The range is the length here, so min == max gives fixed-length strings
+            let length = rng.gen_range(options.min as usize..=options.max as usize);
+            // Alphanumeric hands back bytes, and `as char` turns each one into a letter or digit
+            Value::Text((0..length).map(|_| rng.sample(Alphanumeric) as char).collect())
+        },
+        DataType::Bytes => Value::Bytes(random_blob(rng, options.min, options.max)),
+    }
+}
+
+// For ranges so wide that max - min overflows to infinity (e.g. -f64::MAX to f64::MAX),
+// which gen_range refuses with a panic - mixing the two ends by a random weight never
+// has to hold the full width, and always lands between them
+fn wide_uniform(rng: &mut StdRng, min: f64, max: f64) -> f64 {
+    let t: f64 = rng.gen(); // 0 <= t < 1
+    (min * (1.0 - t) + max * t).clamp(min, max)
+}
+
+// The guarantee that no NaN or infinity ever comes out, however wide the range
+// Run with `cargo test` - these only get compiled for tests, like #ifdef TEST in C
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Shared with the other modules' tests, which only need to change a field or two
+    pub(crate) fn options(data_type: DataType, min: f64, max: f64, distribution: Distribution) -> Options {
+        Options {
+            data_type,
+            count: 1000,
+            min,
+            max,
+            distribution,
+            seed: Some(1),
+            filename: "-".to_string(),
+            ..Options::default()
+        }
+    }
+
+    // Generates a batch the same way write_data would and checks every value
+    fn assert_all_finite(options: &Options) {
+        options.validate().unwrap();
+        let mut rng = make_rng(options.seed);
+        let values: Vec<Value> = value_source(&mut rng, options).unwrap().collect();
+        assert_eq!(values.len(), options.count as usize);
+        for value in values {
+            assert!(value.is_finite(), "{:?} from {:?}", value, options.distribution);
+            assert!((options.min..=options.max).contains(&value.as_f64()), "{:?} out of range", value);
+        }
+    }
+
+    #[test]
+    fn widest_ranges_stay_finite() {
+        for data_type in [DataType::Float, DataType::Double] {
+            let (lowest, highest) = data_type.limits();
+            let (mean, std_dev) = default_normal(lowest, highest);
+            for distribution in [
+                Distribution::Uniform,
+                Distribution::Normal { mean, std_dev },
+                Distribution::Exponential { lambda: 1e-300 },
+                Distribution::Poisson { lambda: 1e9 },
+            ] {
+                assert_all_finite(&options(data_type, lowest, highest, distribution));
+            }
+        }
+    }
+
+    #[test]
+    fn narrow_and_tiny_ranges_stay_finite() {
+        for (min, max) in [(0.0, 1e-30), (-1e-38, 1e-38), (1e30, 1.0000001e30)] {
+            assert_all_finite(&options(DataType::Float, min, max, Distribution::Uniform));
+            assert_all_finite(&options(DataType::Double, min, max, Distribution::Uniform));
+        }
+    }
+
+    #[test]
+    fn non_finite_bounds_are_rejected() {
+        for (min, max) in [(f64::NAN, 1.0), (0.0, f64::NAN), (f64::NEG_INFINITY, 0.0), (0.0, f64::INFINITY)] {
+            assert!(validate_range(DataType::Double, min, max).is_err());
+        }
+        // Past f32's limits the value would turn into infinity when narrowed
+        assert!(validate_range(DataType::Float, 0.0, 1e39).is_err());
+        assert!(parse_bound("nan").is_err());
+        assert!(parse_bound("inf").is_err());
+    }
+
+    #[test]
+    fn non_finite_distribution_parameters_are_rejected() {
+        assert!(validate_distribution(&Distribution::Normal { mean: f64::NAN, std_dev: 1.0 }).is_err());
+        assert!(validate_distribution(&Distribution::Normal { mean: 0.0, std_dev: f64::INFINITY }).is_err());
+        assert!(validate_distribution(&Distribution::Exponential { lambda: f64::NAN }).is_err());
+        assert!(validate_distribution(&Distribution::Poisson { lambda: f64::INFINITY }).is_err());
+    }
+
+    // A tiny quickcheck: runs the property on `cases` random setups, each from its own seed,
+    // and on failure says which seed so the case can be replayed by hand
+    fn for_all(cases: u64, property: impl Fn(&mut StdRng) -> Result<(), String>) {
+        for case in 0..cases {
+            let mut rng = make_rng(Some(case));
+            if let Err(message) = property(&mut rng) {
+                panic!("case {} failed: {}", case, message);
+            }
+        }
+    }
+
+    // A random but valid range for the type - anywhere from tiny to as wide as the type goes
+    fn random_range(rng: &mut StdRng, data_type: DataType) -> (f64, f64) {
+        let (lowest, highest) = data_type.limits();
+        let (a, b) = match data_type {
+            DataType::String | DataType::Bytes => {
+                let shortest = rng.gen_range(1..20) as f64;
+                (shortest, shortest + rng.gen_range(0..20) as f64)
+            },
+            _ if !data_type.has_range() => return (lowest, highest),
+            DataType::Float | DataType::Double => {
+                // Scales spread out over the whole exponent range, since that's where floats go wrong
+                let scale = [1e-30, 1.0, 1e6, 1e30, highest][rng.gen_range(0..5)].min(highest);
+                (scale * (rng.gen::<f64>() * 2.0 - 1.0), scale * (rng.gen::<f64>() * 2.0 - 1.0))
+            },
+            _ => (rng.gen_range(lowest..=highest).round(), rng.gen_range(lowest..=highest).round()),
+        };
+        let (min, max) = if a <= b { (a, b) } else { (b, a) };
+        if min == max && !data_type.has_length() {
+            // Every type but strings and blobs needs some room between the ends
+            return if max < highest { (min, max + 1.0) } else { (min - 1.0, max) };
+        }
+        (min, max)
+    }
+
+    fn random_distribution(rng: &mut StdRng, data_type: DataType, min: f64, max: f64) -> Distribution {
+        if data_type.has_length() || !data_type.has_range() {
+            return Distribution::Uniform;
+        }
+        match rng.gen_range(0..4) {
+            0 => {
+                let (mean, std_dev) = default_normal(min, max);
+                Distribution::Normal { mean, std_dev: std_dev * rng.gen_range(0.05..1.0) }
+            },
+            1 => Distribution::Exponential { lambda: rng.gen_range(0.01..10.0) },
+            2 => Distribution::Poisson { lambda: rng.gen_range(0.1..100.0) },
+            _ => Distribution::Uniform,
+        }
+    }
+
+    // Float values are rounded to f32, so compare them at f32 precision
+    fn in_range(value: &Value, options: &Options) -> bool {
+        match value {
+            Value::Float(num) => (options.min as f32..=options.max as f32).contains(num),
+            Value::Text(text) if options.data_type.is_string() => (options.min..=options.max).contains(&(text.len() as f64)),
+            Value::Text(_) => true,
+            Value::Uuid(_) | Value::Ip(_) => true,
+            _ => (options.min..=options.max).contains(&value.as_f64()),
+        }
+    }
+
+    #[test]
+    fn values_stay_in_range() {
+        const TYPES: [DataType; 17] = [
+            DataType::Integer, DataType::Short, DataType::Long, DataType::Unsigned, DataType::Float, DataType::Double,
+            DataType::String, DataType::Bytes, DataType::Timestamp, DataType::Boolean, DataType::Uuid, DataType::Ipv4, DataType::Ipv6,
+            DataType::FirstName, DataType::LastName, DataType::Email, DataType::Phone,
+        ];
+        for_all(300, |rng| {
+            let data_type = TYPES[rng.gen_range(0..TYPES.len())];
+            let (min, max) = random_range(rng, data_type);
+            let distribution = random_distribution(rng, data_type, min, max);
+            let mut options = options(data_type, min, max, distribution);
+            options.count = rng.gen_range(1..500);
+            options.seed = Some(rng.gen());
+            options.true_probability = rng.gen();
+            options.order = [SortOrder::Random, SortOrder::Ascending, SortOrder::Descending][rng.gen_range(0..3)];
+            options.threads = rng.gen_range(1..=2);
+            if data_type.is_integer() && rng.gen_bool(0.3) {
+                options.duplicates = rng.gen_range(0.0..=100.0);
+            }
+            options.validate().map_err(|e| format!("{:?} was rejected: {}", options, e))?;
+
+            let mut values_rng = make_rng(options.seed);
+            let values: Vec<Value> = value_source(&mut values_rng, &options).map_err(|e| e.to_string())?.collect();
+            if values.len() != options.count as usize {
+                return Err(format!("{} values instead of {}", values.len(), options.count));
+            }
+            if let Some(value) = values.iter().find(|value| !value.is_finite() || !in_range(value, &options)) {
+                return Err(format!("{:?} outside {}..{} for {:?}", value, min, max, options.distribution));
+            }
+            let sorted = match options.order {
+                SortOrder::Ascending => values.windows(2).all(|pair| pair[0].compare(&pair[1]).is_le()),
+                SortOrder::Descending => values.windows(2).all(|pair| pair[0].compare(&pair[1]).is_ge()),
+                _ => true,
+            };
+            if !sorted {
+                return Err(format!("values not in {:?} order", options.order));
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn unique_values_never_repeat() {
+        for_all(100, |rng| {
+            let min = rng.gen_range(-1000..1000) as f64;
+            let count = rng.gen_range(1..200);
+            // Sometimes exactly as many values as the range holds, which has to use every one
+            let max = min + count as f64 - 1.0 + [0.0, 1.0, 500.0][rng.gen_range(0..3)];
+            let mut options = options(DataType::Integer, min, max, Distribution::Uniform);
+            options.count = count;
+            options.unique = true;
+            options.validate().map_err(|e| e.to_string())?;
+            let mut values_rng = make_rng(options.seed);
+            let mut numbers: Vec<f64> = value_source(&mut values_rng, &options).map_err(|e| e.to_string())?
+                .map(|value| value.as_f64())
+                .collect();
+            numbers.sort_by(f64::total_cmp);
+            numbers.dedup();
+            if numbers.len() != count as usize {
+                return Err(format!("only {} distinct values out of {}", numbers.len(), count));
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn permutations_hold_every_number_once() {
+        for_all(50, |rng| {
+            let min = rng.gen_range(-1000..1000) as f64;
+            let count = rng.gen_range(1..300);
+            let mut options = options(DataType::Long, min, min + count as f64 - 1.0, Distribution::Uniform);
+            (options.count, options.permutation) = (count, true);
+            options.validate().map_err(|e| e.to_string())?;
+            let mut numbers: Vec<f64> = value_source(&mut make_rng(options.seed), &options).map_err(|e| e.to_string())?
+                .map(|value| value.as_f64())
+                .collect();
+            numbers.sort_by(f64::total_cmp);
+            if numbers != (0..count).map(|i| min + i as f64).collect::<Vec<f64>>() {
+                return Err(format!("{} to {} came out as {:?}", min, min + count as f64 - 1.0, numbers));
+            }
+            Ok(())
+        });
+        // Every order of 3 numbers should turn up about as often as the others
+        let mut options = options(DataType::Integer, 1.0, 3.0, Distribution::Uniform);
+        (options.count, options.permutation) = (3, true);
+        let mut orders = std::collections::HashMap::new();
+        let mut rng = make_rng(Some(6));
+        for _ in 0..6000 {
+            let order: Vec<f64> = value_source(&mut rng, &options).unwrap().map(|value| value.as_f64()).collect();
+            *orders.entry(format!("{:?}", order)).or_insert(0) += 1;
+        }
+        assert_eq!(orders.len(), 6);
+        assert!(orders.values().all(|&seen| (850..1150).contains(&seen)), "{:?}", orders);
+        options.count = 4;
+        assert!(options.validate().is_err());
+    }
+
+    #[test]
+    fn permutations_of_one_and_none() {
+        // One number is a range of just that number, which is fine for a permutation
+        let mut options = options(DataType::Integer, 5.0, 5.0, Distribution::Uniform);
+        (options.count, options.permutation) = (1, true);
+        options.validate().unwrap();
+        let values: Vec<Value> = value_source(&mut make_rng(Some(1)), &options).unwrap().collect();
+        assert_eq!(values, vec![Value::Int(5)]);
+        // Nothing at all isn't, and says so rather than complaining about the range
+        (options.count, options.max) = (0, 4.0);
+        let error = options.validate().unwrap_err().to_string();
+        assert!(error.contains("at least one number"), "{}", error);
+        // Errors talk about the permutation, not --unique
+        (options.count, options.max, options.data_type) = (1, 5.0, DataType::Double);
+        let error = options.validate().unwrap_err().to_string();
+        assert!(error.contains("permutation") && !error.contains("Unique"), "{}", error);
+    }
+
+    #[test]
+    fn excluded_values_never_appear() {
+        let exclude = parse_exclusions("0, 2..4, -10..-6").unwrap();
+        assert_eq!(exclude, vec![(0.0, 0.0), (2.0, 4.0), (-10.0, -6.0)]);
+        assert_eq!(allowed_gaps(DataType::Integer, -5.0, 5.0, &exclude), vec![(-5.0, -1.0), (1.0, 1.0), (5.0, 5.0)]);
+        // Uniform picks from the gaps, a curve gets redrawn, and unique has to use up all 7 that are left
+        for (distribution, unique, threads) in [
+            (Distribution::Uniform, false, 2),
+            (Distribution::Normal { mean: 0.0, std_dev: 3.0 }, false, 1),
+            (Distribution::Uniform, true, 1),
+        ] {
+            let mut options = options(DataType::Integer, -5.0, 5.0, distribution);
+            options.exclude = exclude.clone();
+            options.unique = unique;
+            options.threads = threads;
+            options.count = if unique { 7 } else { 5000 };
+            options.validate().unwrap();
+            let mut numbers: Vec<f64> = value_source(&mut make_rng(Some(4)), &options).unwrap().map(|value| value.as_f64()).collect();
+            numbers.sort_by(f64::total_cmp);
+            numbers.dedup();
+            assert_eq!(numbers, [-5.0, -4.0, -3.0, -2.0, -1.0, 1.0, 5.0]);
+        }
+        // Checked as written, so nothing rounds to 0.00 either
+        let mut options = options(DataType::Float, -1.0, 1.0, Distribution::Normal { mean: 0.0, std_dev: 0.01 });
+        options.precision = Some(2);
+        options.exclude = vec![(0.0, 0.0)];
+        let values: Vec<Value> = value_source(&mut make_rng(Some(4)), &options).unwrap().collect();
+        assert!(values.iter().all(|value| value.as_f64().abs() >= 0.005), "{:?}", values);
+
+        assert!(validate_exclusions(DataType::Integer, 1.0, 3.0, &[(1.0, 2.0), (3.0, 3.0)], false, 1).is_err());
+        assert!(validate_exclusions(DataType::Double, 0.0, 1.0, &[(0.5, 0.5)], false, 1).is_ok());
+        assert!(validate_exclusions(DataType::Integer, 1.0, 9.0, &[(2.0, 8.0)], true, 3).is_err());
+        assert!(validate_exclusions(DataType::String, 1.0, 9.0, &[(2.0, 2.0)], false, 1).is_err());
+        assert!(parse_exclusions("5..1").is_err());
+    }
+
+    #[test]
+    fn zipf_keys_get_rarer_down_the_range() {
+        let mut options = options(DataType::Long, 100.0, 199.0, Distribution::Zipf { exponent: 1.2 });
+        options.count = 50_000;
+        options.validate().unwrap();
+        let mut counts = [0u32; 100];
+        for value in value_source(&mut make_rng(Some(6)), &options).unwrap() {
+            counts[value.as_f64() as usize - 100] += 1;
+        }
+        // Rank k turns up about 1/k^1.2 as often as rank 1, so the first few are far ahead
+        assert!(counts[0] > counts[1] && counts[1] > counts[2] && counts[2] > counts[9], "{:?}", counts);
+        let expected = 2f64.powf(1.2);
+        let ratio = counts[0] as f64 / counts[1] as f64;
+        assert!((ratio - expected).abs() < 0.15, "rank 1 is {} times rank 2, expected {}", ratio, expected);
+        assert!(validate_distribution(&Distribution::Zipf { exponent: 0.0 }).is_err());
+    }
+
+    #[test]
+    fn same_seed_same_values() {
+        let options = options(DataType::Double, -5.0, 5.0, Distribution::Normal { mean: 0.0, std_dev: 2.0 });
+        let first: Vec<Value> = value_source(&mut make_rng(Some(9)), &options).unwrap().collect();
+        let second: Vec<Value> = value_source(&mut make_rng(Some(9)), &options).unwrap().collect();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn outliers_land_outside_the_range() {
+        // Unsigned values can't go below 0, so every outlier has to go above the range
+        for (data_type, min, threads) in [(DataType::Double, -50.0, 1), (DataType::Unsigned, 0.0, 2)] {
+            let mut options = options(data_type, min, 50.0, Distribution::Uniform);
+            options.count = 10_000;
+            options.outliers = 2.5;
+            options.threads = threads;
+            options.validate().unwrap();
+            let numbers: Vec<f64> = value_source(&mut make_rng(Some(8)), &options).unwrap().map(|value| value.as_f64()).collect();
+            let outside: Vec<f64> = numbers.into_iter().filter(|n| !(min..=50.0).contains(n)).collect();
+            assert_eq!(outside.len(), 250);
+            let half = (50.0 - min) / 2.0;
+            assert!(outside.iter().all(|n| (n - (min + half)).abs() >= half * DEFAULT_OUTLIER_SCALE), "{:?}", outside);
+        }
+        assert!(validate_outliers(DataType::Short, i16::MIN as f64, i16::MAX as f64, 1.0, 10.0, false).is_err());
+        assert!(validate_outliers(DataType::String, 1.0, 8.0, 1.0, 10.0, false).is_err());
+    }
+
+    #[test]
+    fn sequences_stay_within_the_jitter() {
+        // Threads are asked for but ignored, since each value depends on its position
+        let sequence = Distribution::Sequence { start: 100.0, step: -2.5, jitter: 0.5 };
+        let (min, max) = sequence_range(100.0, -2.5, 0.5, 1000);
+        assert_eq!((min, max), (100.0 - 2.5 * 999.0 - 0.5, 100.5));
+        let mut options = options(DataType::Double, min, max, sequence);
+        options.count = 1000;
+        options.threads = 4;
+        options.validate().unwrap();
+        let values: Vec<f64> = value_source(&mut make_rng(Some(3)), &options).unwrap().map(|value| value.as_f64()).collect();
+        for (i, value) in values.iter().enumerate() {
+            assert!((value - (100.0 - 2.5 * i as f64)).abs() <= 0.5, "value {} is {}", i, value);
+        }
+        assert_eq!(sequence_step(0.0, 100.0, 5), 25.0);
+        assert!(validate_distribution(&Distribution::Sequence { start: 0.0, step: 1.0, jitter: -1.0 }).is_err());
+    }
+
+    #[test]
+    fn duplicates_come_out_at_the_requested_rate() {
+        // A range this wide makes chance repeats vanishingly rare, so every repeat is a planned one
+        for (percent, order, threads) in [(30.0, SortOrder::Random, 1), (5.0, SortOrder::Ascending, 2), (100.0, SortOrder::Random, 1)] {
+            let mut options = options(DataType::Long, -1e15, 1e15, Distribution::Uniform);
+            options.count = 20_000;
+            options.duplicates = percent;
+            options.order = order;
+            options.threads = threads;
+            options.validate().unwrap();
+            let mut numbers: Vec<f64> = value_source(&mut make_rng(Some(5)), &options).unwrap()
+                .map(|value| value.as_f64())
+                .collect();
+            assert_eq!(numbers.len(), 20_000);
+            numbers.sort_by(f64::total_cmp);
+            numbers.dedup();
+            let expected = ((20_000.0 * percent / 100.0) as usize).min(19_999);
+            assert_eq!(20_000 - numbers.len(), expected, "{}%", percent);
+        }
+    }
+
+    #[test]
+    fn duplicates_need_whole_numbers() {
+        assert!(validate_duplicates(DataType::Integer, 10.0, false).is_ok());
+        assert!(validate_duplicates(DataType::Double, 10.0, false).is_err());
+        assert!(validate_duplicates(DataType::Double, 0.0, false).is_ok());
+        assert!(validate_duplicates(DataType::Integer, 10.0, true).is_err());
+        assert!(validate_duplicates(DataType::Integer, 101.0, false).is_err());
+        assert!(validate_duplicates(DataType::Integer, f64::NAN, false).is_err());
+    }
+}
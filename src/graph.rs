@@ -0,0 +1,131 @@
+//! Random graphs, written as edge lists for testing graph algorithms.
+//!
+//! Graphs are Erdős–Rényi style: every possible edge is equally likely, and the edge count is
+//! fixed up front (or worked out from a density). They're undirected with no self-loops or
+//! repeated edges, so each line `u v` is a different pair of nodes, numbered from 0 like C arrays.
+//! Weighted graphs add a third column, `u v w`, generated like any other value.
+
+use std::collections::HashSet;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::error;
+use crate::generator::{gen_value, Value};
+use crate::{invalid_input, Options};
+
+/// Weight range when none is given - positive, since plenty of algorithms (Dijkstra's, for one)
+/// don't work with negative weights
+pub const DEFAULT_WEIGHTS: (f64, f64) = (1.0, 100.0);
+
+/// The shape of a graph - the number of edges is Options::count
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Graph {
+    pub nodes: u32,
+    pub weighted: bool, // Adds a weight to each edge, using the type, range and distribution in Options
+}
+
+/// Most edges a graph can have without repeats or self-loops: one per pair of nodes
+pub fn max_edges(nodes: u32) -> u64 {
+    let nodes = nodes as u64;
+    nodes * nodes.saturating_sub(1) / 2
+}
+
+/// Turns a density (the share of all possible edges, 0 to 1) into an edge count
+pub fn edges_for_density(nodes: u32, density: f64) -> error::Result<u32> {
+    if !(0.0..=1.0).contains(&density) {
+        return Err(invalid_input("Density must be between 0 and 1"));
+    }
+    let edges = (max_edges(nodes) as f64 * density).round();
+    if edges > u32::MAX as f64 {
+        return Err(invalid_input(format!("That density would be {} edges, more than the {} limit", edges, u32::MAX)));
+    }
+    Ok(edges as u32)
+}
+
+/// Checks the graph itself - Options::validate checks how it fits with everything else
+pub fn validate_graph(graph: Graph, edges: u32) -> error::Result<()> {
+    if graph.nodes == 0 {
+        return Err(invalid_input("A graph needs at least one node"));
+    }
+    if edges as u64 > max_edges(graph.nodes) {
+        return Err(invalid_input(format!(
+            "{} nodes can have at most {} edges without repeating one",
+            graph.nodes, max_edges(graph.nodes)
+        )));
+    }
+    Ok(())
+}
+
+/// Each edge as two (or, with weights, three) values in a row: the two nodes, then the weight
+pub(crate) fn edge_values<'a>(rng: &'a mut StdRng, options: &'a Options, graph: Graph) -> impl Iterator<Item = Value> + 'a {
+    let edges = sample_edges(rng, graph.nodes, options.count as u64);
+    // flat_map turns each edge into several values, like a nested loop flattened out
+    edges.into_iter().flat_map(move |(u, v)| {
+        let weight = if graph.weighted { Some(gen_value(rng, options)) } else { None };
+        // chain() on an Option adds the weight only if there is one
+        [Value::Unsigned(u), Value::Unsigned(v)].into_iter().chain(weight)
+    })
+}
+
+// Picks `count` different edges, sorted so the file reads in node order
+// Every edge is held in memory (about 16 bytes each) to make sure none repeats
+fn sample_edges(rng: &mut StdRng, nodes: u32, count: u64) -> Vec<(u32, u32)> {
+    let possible = max_edges(nodes);
+    // Past half of all pairs, random picks would mostly land on edges we already have,
+    // so it's quicker to pick the ones to leave out and keep the rest
+    if count * 2 > possible {
+        let missing = random_pairs(rng, nodes, possible - count);
+        (0..nodes)
+            .flat_map(|u| (u + 1..nodes).map(move |v| (u, v)))
+            .filter(|pair| !missing.contains(pair))
+            .collect()
+    } else {
+        // A HashSet comes back in no particular order, so sort to keep a seed's output the same
+        let mut edges: Vec<(u32, u32)> = random_pairs(rng, nodes, count).into_iter().collect();
+        edges.sort_unstable();
+        edges
+    }
+}
+
+// Keeps rolling random pairs until there are `amount` different ones
+// The smaller node always goes first, so (3, 1) and (1, 3) count as the same edge
+fn random_pairs(rng: &mut StdRng, nodes: u32, amount: u64) -> HashSet<(u32, u32)> {
+    let mut pairs = HashSet::with_capacity(amount as usize);
+    while (pairs.len() as u64) < amount {
+        let u = rng.gen_range(0..nodes);
+        let v = rng.gen_range(0..nodes);
+        if u != v {
+            pairs.insert((u.min(v), u.max(v)));
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::make_rng;
+
+    #[test]
+    fn edges_are_distinct_and_in_range() {
+        // Sparse and dense go down different paths, so try both, plus the complete graph
+        for (nodes, count) in [(50, 10), (50, 1000), (50, 1225), (2, 1), (1, 0)] {
+            let edges = sample_edges(&mut make_rng(Some(3)), nodes, count);
+            assert_eq!(edges.len() as u64, count);
+            assert!(edges.iter().all(|&(u, v)| u < v && v < nodes));
+            // Sorted, and strictly increasing means no edge appears twice
+            assert!(edges.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+
+    #[test]
+    fn density_counts_possible_edges() {
+        assert_eq!(max_edges(10), 45);
+        assert_eq!(edges_for_density(10, 1.0).unwrap(), 45);
+        assert_eq!(edges_for_density(10, 0.2).unwrap(), 9);
+        assert!(edges_for_density(10, 1.5).is_err());
+        assert!(validate_graph(Graph { nodes: 10, weighted: false }, 46).is_err());
+        assert!(validate_graph(Graph { nodes: 0, weighted: false }, 0).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//! Text histograms, so the shape of a distribution can be checked by eye in the terminal.
+//!
+//! The values are split into equal-width bins between the smallest and largest, and each bin
+//! gets a bar of # marks as long as its share of the tallest bin. Whole numbers spread over
+//! fewer values than there are bins get one bin each instead, so 1 to 6 dice rolls show six
+//! bars rather than twenty with gaps in between.
+
+use std::fmt;
+
+/// The most bins a histogram is split into - enough to see a bell curve, few enough to fit a screen
+pub const MAX_BINS: usize = 20;
+
+// Characters in the tallest bar
+const BAR_WIDTH: usize = 40;
+
+/// How many values landed in each bin
+// A fixed-size array rather than a Vec keeps this Copy, like the Stats it's carried in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Histogram {
+    pub start: f64,     // Where the first bin starts (the smallest value)
+    pub bin_width: f64, // Each bin covers start + i * bin_width up to the next one
+    pub bins: usize,    // How many of the counts are used
+    pub counts: [usize; MAX_BINS],
+    pub whole: bool,    // One bin per whole number, so each bin is labelled with just its value
+}
+
+impl Histogram {
+    /// Sorts the values into bins - None for an empty list, since there's no range to split
+    pub fn new(values: &[f64]) -> Option<Histogram> {
+        if values.is_empty() {
+            return None;
+        }
+        let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let whole = max - min < MAX_BINS as f64 && values.iter().all(|v| v.fract() == 0.0);
+        let (bins, bin_width) = if whole {
+            ((max - min) as usize + 1, 1.0)
+        } else if max == min {
+            // Every value the same (and not whole): one bin holds the lot
+            (1, 1.0)
+        } else {
+            (MAX_BINS, (max - min) / MAX_BINS as f64)
+        };
+        let mut counts = [0; MAX_BINS];
+        for value in values {
+            // The largest value would start a bin of its own, so it goes in the last one
+            let bin = (((value - min) / bin_width) as usize).min(bins - 1);
+            counts[bin] += 1;
+        }
+        Some(Histogram { start: min, bin_width, bins, counts, whole })
+    }
+
+    /// Draws one line per bin, with each bin's range written by label
+    /// (so the caller can show timestamps as dates, say, rather than seconds)
+    pub fn render(&self, label: impl Fn(f64) -> String) -> String {
+        let labels: Vec<String> = (0..self.bins).map(|i| {
+            let from = self.start + i as f64 * self.bin_width;
+            if self.whole {
+                label(from)
+            } else {
+                format!("{} to {}", label(from), label(from + self.bin_width))
+            }
+        }).collect();
+        let label_width = labels.iter().map(|label| label.len()).max().unwrap_or(0);
+        let tallest = self.counts.iter().max().cloned().unwrap_or(0).max(1);
+
+        let mut text = String::from("Histogram:");
+        for (label, &count) in labels.iter().zip(&self.counts) {
+            // Round up, so a bin with anything in it never looks empty
+            let bar = "#".repeat((count * BAR_WIDTH).div_ceil(tallest));
+            text.push_str(&format!("\n  {:>label_width$} | {:<BAR_WIDTH$} {}", label, bar, count));
+        }
+        text
+    }
+}
+
+// Plain numbers, with up to three decimals and no trailing zeros
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(number_label))
+    }
+}
+
+/// A bin edge as a short number: 2.5 rather than 2.500, and 3 rather than 3.000
+pub fn number_label(value: f64) -> String {
+    let text = format!("{:.3}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    // -0.000 trims down to -0, which looks like a mistake
+    if text == "-0" { "0".to_string() } else { text.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bins_cover_every_value() {
+        // Dice rolls get a bin per face
+        let dice = Histogram::new(&[1.0, 2.0, 2.0, 6.0, 6.0, 6.0]).unwrap();
+        assert!(dice.whole);
+        assert_eq!(dice.bins, 6);
+        assert_eq!(&dice.counts[..6], &[1, 2, 0, 0, 0, 3]);
+        let text = dice.to_string();
+        assert!(text.starts_with("Histogram:\n  1 | "), "{}", text);
+        assert!(text.contains(&format!("  6 | {} 3", "#".repeat(BAR_WIDTH))), "{}", text);
+
+        // Anything else is split into MAX_BINS equal ranges, with the largest value in the last
+        let values: Vec<f64> = (0..=100).map(|i| i as f64 / 10.0).collect();
+        let spread = Histogram::new(&values).unwrap();
+        assert_eq!(spread.bins, MAX_BINS);
+        assert_eq!(spread.counts.iter().sum::<usize>(), values.len());
+        assert_eq!(spread.counts[MAX_BINS - 1], 6);
+        assert!(spread.to_string().contains(" 0 to 0.5 | "), "{}", spread);
+
+        assert_eq!(Histogram::new(&[2.5, 2.5]).unwrap().counts[0], 2);
+        assert!(Histogram::new(&[]).is_none());
+    }
+}
@@ -0,0 +1,215 @@
+//! A log of every run that generated something, so any of them can be done again later.
+//!
+//! Each run is one block in `history.log` (or wherever `history = "..."` in the config points),
+//! added to the end as it finishes, with the same quoted lists as the presets file:
+//!
+//! ```toml
+//! [2024-01-31T12:00:00Z]
+//! dir = "/home/me/testdata"
+//! seed = 42
+//! files = ["/home/me/testdata/data.txt"]
+//! flags = ["-t", "int", "-n", "100", "--seed", "42", "-o", "data.txt"]
+//! ```
+//!
+//! A run from the command line keeps its flags, and a run from the menu keeps the option it
+//! picked and the answers it was given (`menu = ["1", "i", "100", ...]`). Doing it again means
+//! giving the same flags or answers again. A run without `--seed` (or a blank seed in the menu)
+//! got a random one, and that's the seed recorded, so doing it again still makes the same file.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error;
+use crate::preset::{parse_array, quote};
+use crate::timestamp::{format_iso, parse_iso};
+use crate::invalid_data;
+
+/// Name of the history file looked for in the current directory
+pub const HISTORY_FILE: &str = "history.log";
+
+/// How a run was started, so it can be started the same way again
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recipe {
+    Flags(Vec<String>),     // Command-line flags, as typed (with presets filled in)
+    Menu(u32, Vec<String>), // A menu option and the answers to its questions, in order
+}
+
+impl Recipe {
+    /// One line saying what the run was, for the list in the menu
+    pub fn describe(&self) -> String {
+        match self {
+            Recipe::Flags(args) => format!("ruststf {}", args.join(" ")),
+            Recipe::Menu(option, answers) => format!("menu option {}: {}", option, answers.join(", ")),
+        }
+    }
+}
+
+/// One run that generated something
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub time: i64,          // When it finished, in seconds since 1970
+    pub dir: String,        // The directory it was run in, which relative names are relative to
+    pub seed: Option<u64>,  // None (a followed file) means the values can't be made again
+    pub files: Vec<String>, // Everything it wrote, as absolute paths
+    pub recipe: Recipe,
+}
+
+/// Every run recorded so far, and where (if anywhere) they're saved
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    path: Option<String>, // None keeps the history in memory for this run only
+    entries: Vec<Entry>,  // Oldest first
+    pending: Vec<String>, // Files written since the last record(), waiting to hear how they were made
+    seed: Option<u64>,
+}
+
+impl History {
+    /// A history that only lasts as long as the program is running
+    pub fn session() -> History {
+        History::default()
+    }
+
+    /// Opens a history file - one that isn't there yet just means nothing's been generated
+    pub fn load(path: &str) -> error::Result<History> {
+        let entries = match fs::read_to_string(path) {
+            Ok(text) => parse_history(&text).map_err(|e| invalid_data(format!("{}: {}", path, e)))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(io::Error::new(e.kind(), format!("Can't read history {}: {}", path, e)).into()),
+        };
+        Ok(History { path: Some(path.to_string()), entries, ..History::default() })
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Notes a file that was just written - it's recorded along with the next record() call
+    /// The run's seed is the first file's, since any more (like data_2.txt) follow on from it
+    pub fn generated(&mut self, filename: &str, seed: Option<u64>) {
+        self.pending.push(filename.to_string());
+        self.seed = self.seed.or(seed);
+    }
+
+    /// Records the files written since last time as one run made by `recipe`, and adds it to
+    /// the end of the history file. Nothing was written means there's nothing to record
+    pub fn record(&mut self, recipe: Recipe) -> error::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        // A clock set before 1970 is the only way this fails, and then 0 is as good as anything
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs() as i64);
+        let dir = std::env::current_dir()?.to_string_lossy().into_owned();
+        let files = std::mem::take(&mut self.pending);
+        let entry = Entry { time, dir, seed: self.seed.take(), files, recipe };
+        if let Some(path) = &self.path {
+            if let Some(dir) = Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)?;
+            }
+            // Appending, like a log - earlier runs are never rewritten
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            file.write_all(entry_text(&entry).as_bytes())?;
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+}
+
+// One block per run, ending in a blank line so the file is easy to read through
+fn entry_text(entry: &Entry) -> String {
+    let list = |items: &[String]| items.iter().map(|item| quote(item)).collect::<Vec<_>>().join(", ");
+    let mut text = format!("[{}]\ndir = {}\n", format_iso(entry.time), quote(&entry.dir));
+    if let Some(seed) = entry.seed {
+        text.push_str(&format!("seed = {}\n", seed));
+    }
+    text.push_str(&format!("files = [{}]\n", list(&entry.files)));
+    match &entry.recipe {
+        Recipe::Flags(args) => text.push_str(&format!("flags = [{}]\n", list(args))),
+        Recipe::Menu(option, answers) => {
+            let answers = [&[option.to_string()][..], answers].concat();
+            text.push_str(&format!("menu = [{}]\n", list(&answers)));
+        },
+    }
+    text.push('\n');
+    text
+}
+
+// Goes line by line like the config file - a [time] line starts each run, and its
+// key = value lines follow until the next one
+fn parse_history(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    let mut lines = text.lines().enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .peekable();
+    while let Some((number, line)) = lines.next() {
+        let time = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']'))
+            .ok_or_else(|| format!("line {}: expected [time]", number))?;
+        let time = parse_iso(time).ok_or_else(|| format!("line {}: invalid time {}", number, time))?;
+        let (mut dir, mut seed, mut files, mut recipe) = (None, None, None, None);
+        // next_if only takes the line when it isn't the start of the next run
+        while let Some((number, line)) = lines.next_if(|(_, line)| !line.starts_with('[')) {
+            let (key, value) = line.split_once('=').ok_or_else(|| format!("line {}: expected key = value", number))?;
+            let (key, value) = (key.trim(), value.trim());
+            let bad_value = || format!("line {}: invalid value for {}", number, key);
+            match key {
+                // A lone string is a list of one, which saves a second parser
+                "dir" => dir = Some(parse_array(&format!("[{}]", value)).and_then(|mut items| items.pop()).ok_or_else(bad_value)?),
+                "seed" => seed = Some(value.parse().map_err(|_| bad_value())?),
+                "files" => files = Some(parse_array(value).ok_or_else(bad_value)?),
+                "flags" => recipe = Some(Recipe::Flags(parse_array(value).ok_or_else(bad_value)?)),
+                "menu" => {
+                    let answers = parse_array(value).ok_or_else(bad_value)?;
+                    let (option, answers) = answers.split_first().ok_or_else(bad_value)?;
+                    recipe = Some(Recipe::Menu(option.parse().map_err(|_| bad_value())?, answers.to_vec()));
+                },
+                _ => return Err(format!("line {}: unknown key {}", number, key)),
+            }
+        }
+        let missing = || format!("line {}: the run needs a dir, files, and flags or menu", number);
+        entries.push(Entry {
+            time,
+            dir: dir.ok_or_else(missing)?,
+            seed,
+            files: files.ok_or_else(missing)?,
+            recipe: recipe.ok_or_else(missing)?,
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_survives_a_round_trip() {
+        let path = std::env::temp_dir().join(format!("ruststf_test_{}_history.log", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let _ = fs::remove_file(&path);
+
+        let mut history = History::load(&path).unwrap();
+        // Nothing written, nothing to record
+        history.record(Recipe::Flags(vec![String::from("--bench")])).unwrap();
+        assert!(history.entries().is_empty());
+
+        history.generated("/tmp/a \"quoted\" name.txt", Some(42));
+        history.record(Recipe::Flags(["-t", "int", "-o", "C:\\data.txt"].iter().map(|arg| arg.to_string()).collect())).unwrap();
+        history.generated("/tmp/b_1K.txt", None);
+        history.generated("/tmp/b_2K.txt", None);
+        history.record(Recipe::Menu(2, vec![String::from("i"), String::new(), String::from("b_{size}.txt")])).unwrap();
+
+        let loaded = History::load(&path).unwrap();
+        assert_eq!(loaded.entries(), history.entries());
+        assert_eq!(loaded.entries()[0].seed, Some(42));
+        assert_eq!(loaded.entries()[1].files.len(), 2);
+        assert!(parse_history("dir = \"/tmp\"").is_err());
+        assert!(parse_history("[2024-01-31T12:00:00Z]\ndir = \"/tmp\"\n").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
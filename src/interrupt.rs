@@ -0,0 +1,119 @@
+//! Ctrl-C in the middle of a long run, without leaving a broken file behind.
+//!
+//! Normally Ctrl-C kills the program wherever it happens to be, which can be halfway through a
+//! row, with a `Count:` header promising values that never arrived. Once the command line has
+//! called `install_handler`, a Ctrl-C while a file is being written just raises a flag instead.
+//! The writer sees it after the next complete row, stops, and does what `OnInterrupt` says:
+//! finishes the file off with the rows it has, deletes it, or leaves it for `--resume`.
+//!
+//! Anywhere else (the menu, reading a file back) Ctrl-C still stops the program straight away,
+//! and so does a second Ctrl-C if the first one is taking too long.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error;
+use crate::{Options, OutputFormat};
+
+// Statics shared with the handler, which runs on a thread of its own - atomics rather than a
+// Mutex, since all either side ever does is flip or read a bool
+static WRITING: AtomicBool = AtomicBool::new(false);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Exit code for a run stopped by Ctrl-C - 128 + SIGINT, what a shell reports for one
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// What happens to the output file when a run is stopped with Ctrl-C
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnInterrupt {
+    Finish, // Keep the rows already written and fix the header's count to match
+    Delete, // Remove the file, as if the run had never started
+    Keep,   // Leave it as it is, with its checkpoint, so --resume can carry on later
+}
+
+/// Catches Ctrl-C for the rest of the program - only the command line does this, since a
+/// library shouldn't take over a signal from the program it's part of
+pub fn install_handler() -> error::Result<()> {
+    ctrlc::set_handler(|| {
+        // Nothing is listening, or it already had its chance, so stop the way Ctrl-C always did
+        if !WRITING.load(Ordering::SeqCst) || INTERRUPTED.swap(true, Ordering::SeqCst) {
+            eprintln!();
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
+    }).map_err(|e| io::Error::other(e.to_string()))?;
+    Ok(())
+}
+
+/// Why a half-written file can't be finished off after Ctrl-C, or None if it can
+/// Only files with nothing after their rows can be cut short and still be whole
+pub fn finish_blocker(options: &Options) -> Option<&'static str> {
+    if options.compress {
+        Some("a gzipped file")
+    } else if options.framed {
+        Some("a framed file, whose last frames would have to be rewritten")
+    } else if !stops_between_rows(options) {
+        Some("JSON, Parquet, fixed-width, code, graphs and records have to be written to the end")
+    } else if options.append_stats {
+        Some("statistics at the end, which need every value")
+    } else if !options.line_layout.is_plain() {
+        Some("a custom delimiter, prefix or suffix")
+    } else {
+        None
+    }
+}
+
+/// Text, CSV, matrix and binary values go through Checkpoints, which stops between rows
+/// Everything else needs StopOnInterrupt to stop it at all
+pub(crate) fn stops_between_rows(options: &Options) -> bool {
+    options.graph.is_none()
+        && options.schema.is_empty()
+        && matches!(options.format, OutputFormat::Text | OutputFormat::Csv | OutputFormat::Matrix | OutputFormat::Binary)
+}
+
+/// True once Ctrl-C has been pressed during a write
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Fails once Ctrl-C has been pressed, for the writers to call between rows
+pub(crate) fn check_interrupt() -> error::Result<()> {
+    if interrupted() {
+        return Err(io::Error::other("Interrupted").into());
+    }
+    Ok(())
+}
+
+/// Says a file is being written for as long as it's alive, so Ctrl-C waits for the writer
+/// rather than killing the program - dropping it (however the write ends) puts that back
+pub(crate) struct Writing;
+
+impl Writing {
+    pub(crate) fn start() -> Writing {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        WRITING.store(true, Ordering::SeqCst);
+        Writing
+    }
+}
+
+impl Drop for Writing {
+    fn drop(&mut self) {
+        // In this order, so a Ctrl-C in between exits rather than being lost
+        WRITING.store(false, Ordering::SeqCst);
+        INTERRUPTED.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Wraps a file for the formats that can't stop between rows (JSON, Parquet, ...), failing
+/// the next write after Ctrl-C so the run stops there - the file is deleted afterwards anyway
+pub(crate) struct StopOnInterrupt<W>(pub(crate) W);
+
+impl<W: Write> Write for StopOnInterrupt<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        check_interrupt()?;
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
@@ -0,0 +1,246 @@
+//! Publishing to a Kafka topic, for trying out streaming pipelines on made-up data.
+//!
+//! Only built with `--features kafka`, since it brings librdkafka along (compiled from C source,
+//! which takes a few minutes). `-o kafka://localhost:9092/events` publishes every line of the
+//! output as a message of its own - a record in JSON lines, a row of CSV - without the newline.
+//! Several brokers go before the slash, separated by commas, like bootstrap.servers.
+//!
+//! librdkafka collects the messages into batches and sends each batch in one request. How big
+//! they get is set after a ?, the way a URL takes parameters, as in
+//! `kafka://localhost:9092/events?batch=500&linger=20`. `batch` is the most messages in one
+//! batch (batch.num.messages), and `linger` is how many milliseconds to wait for a batch to fill
+//! up before sending it anyway (linger.ms). Anything left out keeps librdkafka's own default.
+//! The run only finishes once every message has been taken by the brokers, and one that wasn't
+//! makes it fail.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, warn};
+use rdkafka::config::ClientConfig;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::message::DeliveryResult;
+use rdkafka::producer::{BaseProducer, BaseRecord, Producer, ProducerContext};
+use rdkafka::ClientContext;
+
+use crate::error::{self, GeneratorError};
+use crate::stats::Stats;
+use crate::writer::write_to;
+use crate::{invalid_input, Options};
+
+// How long to wait for the brokers to answer before giving up on them
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How long the last batches get to be delivered once everything has been handed over
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Longest topic name Kafka allows
+const MAX_TOPIC_LEN: usize = 249;
+
+/// Where to publish and how to batch, read from brokers/topic?batch=..&linger=..
+#[derive(Debug, Clone, PartialEq)]
+pub struct Topic {
+    pub brokers: String,     // host:port, or several of them separated by commas
+    pub name: String,
+    pub batch: Option<u32>,  // Most messages in a batch - None leaves librdkafka's default
+    pub linger: Option<u32>, // Milliseconds to wait for a batch to fill up
+}
+
+impl Topic {
+    /// Reads the part of a kafka:// name after the ://
+    pub fn parse(address: &str) -> error::Result<Topic> {
+        let (place, settings) = address.split_once('?').unwrap_or((address, ""));
+        let Some((brokers, name)) = place.split_once('/').filter(|(brokers, _)| !brokers.is_empty()) else {
+            return Err(invalid_input("Kafka output needs brokers and a topic, like kafka://localhost:9092/events"));
+        };
+        let valid_name = name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+        if name.is_empty() || name.len() > MAX_TOPIC_LEN || !valid_name {
+            return Err(invalid_input(format!(
+                "\"{}\" isn't a Kafka topic name (up to {} letters, digits, '.', '_' and '-')", name, MAX_TOPIC_LEN
+            )));
+        }
+        let mut topic = Topic { brokers: brokers.to_string(), name: name.to_string(), batch: None, linger: None };
+        for setting in settings.split('&').filter(|setting| !setting.is_empty()) {
+            let (key, value) = setting.split_once('=').unwrap_or((setting, ""));
+            let number = value.parse::<u32>().map_err(|_| invalid_input(format!("Kafka {} must be a whole number", key)));
+            match key {
+                "batch" if number.as_ref().is_ok_and(|&batch| batch > 0) => topic.batch = number.ok(),
+                "batch" => return Err(invalid_input("Kafka batch must be at least 1 message")),
+                "linger" => topic.linger = Some(number?),
+                _ => return Err(invalid_input(format!("Unknown Kafka setting \"{}\" (use batch and linger)", key))),
+            }
+        }
+        Ok(topic)
+    }
+}
+
+/// Publishes everything options would have written to a file, a line per message
+pub(crate) fn publish(address: &str, options: &Options) -> error::Result<Option<Stats>> {
+    let topic = Topic::parse(address)?;
+    let mut config = ClientConfig::new();
+    config.set("bootstrap.servers", &topic.brokers);
+    if let Some(batch) = topic.batch {
+        config.set("batch.num.messages", batch.to_string());
+    }
+    if let Some(linger) = topic.linger {
+        config.set("linger.ms", linger.to_string());
+    }
+    let producer: BaseProducer<Deliveries> = config.create_with_context(Deliveries::default()).map_err(kafka_error)?;
+    // Asking for the topic's details first means a broker that isn't there is caught before
+    // anything is generated, instead of after every message has timed out
+    producer.client().fetch_metadata(Some(&topic.name), CONNECT_TIMEOUT)
+        .map_err(|e| kafka_error(e).context(&format!("Couldn't reach {}", options.filename)))?;
+    // Shared, so it's still here to flush after write_to has finished with the writer
+    let producer = Arc::new(producer);
+    let messages = Messages { producer: Arc::clone(&producer), topic: topic.name, line: Vec::new() };
+    let stats = write_to(messages, options)?;
+    producer.flush(FLUSH_TIMEOUT).map_err(kafka_error)?;
+    let deliveries = producer.context();
+    debug!("{} messages delivered", deliveries.delivered.load(Ordering::Relaxed));
+    let failed = deliveries.failed.load(Ordering::Relaxed);
+    if failed > 0 {
+        let first = deliveries.first_failure.lock().unwrap().take().unwrap_or_default();
+        return Err(io::Error::other(format!("{} messages weren't delivered ({})", failed, first)).into());
+    }
+    Ok(stats)
+}
+
+fn kafka_error(e: KafkaError) -> GeneratorError {
+    io::Error::other(e).into()
+}
+
+// Hears back about every message once the brokers have it (or have given up on it)
+// librdkafka calls this from poll() and flush(), on whichever thread that is
+#[derive(Default)]
+struct Deliveries {
+    delivered: AtomicU64,
+    failed: AtomicU64,
+    first_failure: Mutex<Option<String>>,
+}
+
+impl ClientContext for Deliveries {}
+
+impl ProducerContext for Deliveries {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, result: &DeliveryResult<'_>, _: ()) {
+        match result {
+            Ok(_) => {
+                self.delivered.fetch_add(1, Ordering::Relaxed);
+            },
+            Err((e, _)) => {
+                self.failed.fetch_add(1, Ordering::Relaxed);
+                self.first_failure.lock().unwrap().get_or_insert_with(|| e.to_string());
+            },
+        }
+    }
+}
+
+// Gathers what's written into lines and publishes each one, like Datagrams in socket.rs
+struct Messages {
+    producer: Arc<BaseProducer<Deliveries>>,
+    topic: String,
+    line: Vec<u8>,
+}
+
+impl Messages {
+    // send() only queues the message - librdkafka batches and sends it on a thread of its own
+    fn send_line(&mut self) -> io::Result<()> {
+        let payload = self.line.strip_suffix(b"\n").unwrap_or(&self.line);
+        loop {
+            match self.producer.send(BaseRecord::<(), [u8]>::to(&self.topic).payload(payload)) {
+                Ok(()) => break,
+                // The queue waiting to go out is full, so wait for the brokers to catch up
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), _)) => {
+                    self.producer.poll(Duration::from_millis(100));
+                },
+                Err((e, _)) => return Err(io::Error::other(e)),
+            }
+        }
+        // Runs the delivery callbacks for anything that's arrived since
+        self.producer.poll(Duration::ZERO);
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl Write for Messages {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for piece in buf.split_inclusive(|&byte| byte == b'\n') {
+            self.line.extend_from_slice(piece);
+            if self.line.ends_with(b"\n") {
+                self.send_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    // A flush mustn't cut a line in two, and waiting for delivery is publish()'s job
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Whatever's left after the last newline is still a message
+impl Drop for Messages {
+    fn drop(&mut self) {
+        if !self.line.is_empty() {
+            if let Err(e) = self.send_line() {
+                warn!("The last message couldn't be published: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::tests::options;
+    use crate::{DataType, Distribution, OutputFormat};
+    use rdkafka::consumer::{BaseConsumer, Consumer};
+    use rdkafka::message::Message;
+    use rdkafka::mocking::MockCluster;
+
+    #[test]
+    fn every_row_is_a_message() {
+        // librdkafka's own stand-in for a cluster, running inside the test
+        let cluster = MockCluster::new(1).unwrap();
+        cluster.create_topic("readings", 1, 1).unwrap();
+        let mut options = options(DataType::Integer, 0.0, 99.0, Distribution::Uniform);
+        (options.count, options.format, options.columns) = (25, OutputFormat::Csv, 3);
+        options.filename = format!("kafka://{}/readings?batch=10&linger=5", cluster.bootstrap_servers());
+        options.validate().unwrap();
+        publish(options.filename.strip_prefix("kafka://").unwrap(), &options).unwrap();
+
+        let consumer: BaseConsumer = ClientConfig::new()
+            .set("bootstrap.servers", cluster.bootstrap_servers())
+            .set("group.id", "tests")
+            .set("auto.offset.reset", "earliest")
+            .create()
+            .unwrap();
+        consumer.subscribe(&["readings"]).unwrap();
+        let mut rows = Vec::new();
+        let started = std::time::Instant::now();
+        while rows.len() < 25 && started.elapsed() < Duration::from_secs(30) {
+            if let Some(message) = consumer.poll(Duration::from_millis(100)) {
+                rows.push(String::from_utf8(message.unwrap().payload().unwrap().to_vec()).unwrap());
+            }
+        }
+        assert_eq!(rows.len(), 25);
+        assert!(rows.iter().all(|row| row.split(',').count() == 3 && !row.ends_with('\n')));
+    }
+
+    #[test]
+    fn topics_and_batching_are_read() {
+        let topic = Topic::parse("a:9092,b:9092/sensor.readings?batch=500&linger=20").unwrap();
+        assert_eq!(topic.brokers, "a:9092,b:9092");
+        assert_eq!(topic.name, "sensor.readings");
+        assert_eq!((topic.batch, topic.linger), (Some(500), Some(20)));
+        assert_eq!(Topic::parse("localhost:9092/events").unwrap().batch, None);
+        for bad in ["localhost:9092", "/events", "localhost:9092/", "h:1/a b", "h:1/t?batch=0", "h:1/t?linger=soon", "h:1/t?acks=all"] {
+            assert!(Topic::parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
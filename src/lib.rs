@@ -0,0 +1,908 @@
+//! Random data file generator.
+//!
+//! The `ruststf` binary is a thin wrapper around this library, so other Rust
+//! programs can generate the same files directly instead of shelling out:
+//!
+//! ```no_run
+//! use ruststf::{DataType, Options};
+//!
+//! // Anything left out gets its default, see Options::default
+//! let options = Options {
+//!     data_type: DataType::Integer,
+//!     count: 100,
+//!     min: 0.0,
+//!     max: 255.0,
+//!     seed: Some(42),
+//!     filename: "data.txt".to_string(),
+//!     ..Options::default()
+//! };
+//! ruststf::write_data(&options).unwrap();
+//! ```
+
+// Each file is its own module, like splitting a C program into .c files with headers
+pub mod atomic;    // Writing under a .tmp name and renaming into place
+pub mod bench;     // Timing generation and writing, and estimating runs
+pub mod binary;    // The binary file header and byte order
+pub mod blob;      // Random bytes for the bytes type, as hex or files of their own
+pub mod builder;   // Generator, a chainable way to set up a run from code
+pub mod checkpoint; // Picking interrupted runs back up
+pub mod checksum;  // SHA-256 sidecar files
+pub mod cli;       // Menu, prompts and command-line flags
+pub mod code;      // Arrays for C, C++, Rust and Python source
+pub mod columnar;  // Parquet output
+pub mod config;    // Defaults from generator.toml
+pub mod convert;   // Rewriting existing files in another format
+pub mod error;     // GeneratorError for library users
+pub mod fake;      // Made-up names, emails and phone numbers
+pub mod fifo;      // Writing into named pipes
+pub mod follow;    // Growing a file forever, like a log being written
+pub mod framing;   // Length-prefixed, checksummed frames for streams
+pub mod generator; // Data types and the random number generation itself
+pub mod graph;     // Random graphs as edge lists
+pub mod histogram; // Text bar charts of a distribution
+pub mod history;   // A log of past runs, to do any of them again
+pub mod interrupt; // Stopping cleanly on Ctrl+C
+#[cfg(feature = "kafka")]
+pub mod kafka;     // Publishing to a Kafka topic (with --features kafka)
+pub mod mixture;   // Points drawn from a mix of normal distributions
+pub mod manifest;  // Remembering generated files so they can be cleaned up
+pub mod mapped;    // Memory-mapped binary output
+pub mod merge;     // Joining several data files into one
+pub mod network;   // IP addresses and CIDR blocks
+pub mod numtext;   // Turning numbers into text quickly
+pub mod pipeline;  // Generating and writing on separate threads
+pub mod points;    // Point clouds in a box or on a circle or sphere
+pub mod pool;      // Sampling values from a file of candidates
+pub mod preset;    // Named sets of flags to run again later
+pub mod reader;    // Reading data files back in
+pub mod schema;    // Records with several named fields
+pub mod series;    // Time series with a trend, seasons and noise
+pub mod server;    // Serving generated data over HTTP
+pub mod shard;     // Splitting one dataset across several files
+pub mod shuffle;   // Shuffling the rows of existing files
+pub mod space;     // Checking big files will fit on the disk
+pub mod special;   // Primes, perfect squares and powers of two
+pub mod split;     // Cutting existing files into parts
+pub mod socket;    // Sending values over TCP or UDP
+pub mod sqlite;    // Filling a SQLite table
+pub mod stats;     // Summary statistics
+pub mod throttle;  // Letting values out at a steady rate
+pub mod timestamp; // Dates and times for the timestamp type
+pub mod units;     // Counts and sizes like 10k or 1.5G
+pub mod uuid;      // Random identifiers for the uuid type
+pub mod verify;    // Checking files are well formed
+pub mod writer;    // Output formats
+
+// Re-exports so users can write ruststf::DataType instead of ruststf::generator::DataType
+pub use bench::{bench, estimate, BenchResult, Estimate};
+pub use binary::Endianness;
+pub use builder::Generator;
+pub use checkpoint::{checkpoint_name, Checkpoint};
+pub use checksum::{verify_checksum, write_checksum, ChecksumCheck};
+pub use code::Language;
+pub use config::Config;
+pub use error::GeneratorError;
+pub use generator::{Category, DataType, Distribution, SortOrder, Value};
+pub use framing::{Unframed, FRAME_OVERHEAD};
+pub use graph::Graph;
+pub use histogram::Histogram;
+pub use history::{Entry, History, Recipe};
+pub use interrupt::OnInterrupt;
+pub use manifest::Manifest;
+pub use merge::{merge_files, merge_inputs, MergeOrder};
+pub use mixture::{parse_clusters, Cluster, Mixture};
+pub use network::{parse_cidr, Cidr};
+pub use points::{Points, Shape};
+pub use pool::{Pool, Sampling};
+pub use preset::{Preset, Presets};
+pub use reader::{load_file, LoadedData};
+pub use schema::{parse_schema, Field};
+pub use series::TimeSeries;
+pub use shard::{shard_manifest_name, shard_name};
+pub use shuffle::shuffle_file;
+pub use socket::{Protocol, Socket};
+pub use special::{is_prime, Special};
+pub use split::{split_file, SplitSize};
+pub use stats::{compute_stats, RunningStats, Stats};
+pub use verify::{verify_file, Problem, Verification};
+pub use writer::{
+    write_data, write_to, Align, BoolFormat, FieldWidth, HeaderStyle, IfExists, IntFormat, LineLayout, MissingToken,
+    NumberFormat, OutputFormat, TimeFormat,
+};
+
+/// Everything needed to generate one file, whether it came from prompts, flags or code
+// Clone lets batch mode stamp out one copy per file with just the count and name changed
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub data_type: DataType,
+    pub count: u32,
+    pub min: f64, // f64 so one range works for both integers and floats
+    pub max: f64,
+    pub exclude: Vec<(f64, f64)>, // Values that must never come out, as low..high pairs (low == high for just one)
+    pub distribution: Distribution, // Values outside min/max get clamped into the range
+    pub order: SortOrder,
+    pub unique: bool, // No value repeats - integer types with a uniform distribution only
+    pub permutation: bool, // Every number from min to max exactly once, shuffled - count * columns must be the range's size
+    pub duplicates: f64, // Percentage of values that repeat an earlier one (integer types, 0 = off)
+    pub outliers: f64,   // Percentage of values swapped for ones far outside the range (number types, 0 = off)
+    pub outlier_scale: f64, // How far out: outliers land scale to 2 * scale half-ranges from the middle
+    pub missing: f64,    // Percentage of values written as missing instead (CSV and JSON, 0 = off)
+    pub missing_token: MissingToken, // What a missing value is written as: nothing, NULL or NaN
+    pub format: OutputFormat,
+    pub columns: u32,     // Only used for CSV and matrix - count is the number of rows
+    pub csv_header: bool, // Whether to write a "col1,col2,..." line first
+    pub header_style: HeaderStyle, // Count:/Matrix: header of text and matrix files: plain, as # comments, left out or a template
+    pub line_layout: LineLayout, // Delimiter between values in text files, and a prefix and suffix for each line
+    pub separator: char,  // Between values on a matrix row: ' ' or ','
+    pub field_width: FieldWidth, // Width, fill and alignment of each value in fixed-width files
+    pub endianness: Endianness, // Byte order of the numbers in binary files
+    pub schema: Vec<Field>, // Fields of each record (CSV or JSON lines); empty means plain values of data_type
+    pub graph: Option<Graph>, // Write count random edges instead of values; data_type etc. are for the weights
+    pub time_series: Option<TimeSeries>, // Fill the schema's time and value fields with count readings of this series
+    pub mixture: Option<Mixture>, // Fill the schema's x (and y and z) fields with count points drawn from these clusters
+    pub points: Option<Points>, // Fill the schema's x, y (and z) fields with count points in a box or on a sphere
+    pub pool: Option<Pool>, // Pick the values from this file's instead of generating them
+    pub seed: Option<u64>, // None means a fresh random seed every run
+    pub stats: bool,        // Print min/max/mean/median/std dev after generating
+    pub append_stats: bool, // Also write the summary into the file (not for binary)
+    pub histogram: bool,    // Also draw a histogram of the values (needs stats, and not streaming)
+    pub progress: bool,     // Print a running percentage to stderr while writing
+    pub rate: Option<f64>,  // Values per second to let out, for a live feed; None writes as fast as it can
+    pub threads: u32,       // Threads to generate with: 1 = just this one, 0 = one per core
+    pub streaming: bool,    // Bounded memory: refuse anything that holds every value, estimate the median
+    pub if_exists: IfExists, // What to do if the output file is already there
+    pub resume: bool,       // Finish an interrupted file from its checkpoint instead of starting over
+    pub on_interrupt: OnInterrupt, // What Ctrl-C does to a half-written file (only once the CLI's handler is installed)
+    pub space_check: bool,  // Refuse to start a big file that won't fit in the free disk space
+    pub shards: u32,        // Split the rows across this many numbered files (1 = just the one file)
+    pub compress: bool,     // gzip the output (the filename is used as given, so add .gz yourself)
+    pub framed: bool,       // Send the output in length-prefixed frames with a CRC each, see framing.rs
+    pub precision: Option<usize>, // Digits after the point for floats; None keeps the type's default
+    pub scientific: bool,   // Write floats like 1.234e5 instead of 123400.000
+    pub number_format: NumberFormat, // Decimal separator and thousands grouping for text output
+    pub int_format: IntFormat, // Integers in decimal, hex (0x3FF) or binary (0b1010) for text output
+    pub time_format: TimeFormat, // How timestamps are written: ISO-8601 dates or epoch seconds
+    pub true_probability: f64, // Chance of each boolean being true, 0 to 1
+    pub bool_format: BoolFormat, // How booleans are written: true/false or 1/0
+    pub cidr: Option<Cidr>, // Keeps IP addresses of the same version inside this block; None allows any
+    pub checksum: bool,     // Also write <filename>.sha256 once the file is done
+    pub buffer_size: usize, // Bytes collected in memory before each write to the file
+    pub mmap: bool,         // Write binary files through a memory map instead of the buffer
+    pub table: Option<String>, // Table to fill for SQLite output; None uses sqlite::DEFAULT_TABLE
+    pub filename: String,   // "-" writes to stdout, tcp://host:port and udp://host:port to a socket
+}
+
+/// What a file gets when nothing says otherwise: 100 integers across the type's whole range,
+/// written as text with no seed. Set the fields you care about and fill in the rest with
+/// `..Options::default()`, like a struct initialised from a template in C
+impl Default for Options {
+    fn default() -> Options {
+        let data_type = DataType::Integer;
+        let (min, max) = data_type.default_range();
+        Options {
+            data_type,
+            count: 100,
+            min,
+            max,
+            exclude: Vec::new(),
+            distribution: Distribution::Uniform,
+            order: SortOrder::Random,
+            unique: false,
+            permutation: false,
+            duplicates: 0.0,
+            outliers: 0.0,
+            outlier_scale: generator::DEFAULT_OUTLIER_SCALE,
+            missing: 0.0,
+            missing_token: MissingToken::Empty,
+            format: OutputFormat::Text,
+            columns: 1,
+            csv_header: false,
+            header_style: HeaderStyle::Plain,
+            line_layout: LineLayout::default(),
+            separator: ' ',
+            field_width: FieldWidth::default(),
+            endianness: Endianness::Little,
+            schema: Vec::new(),
+            graph: None,
+            time_series: None,
+            mixture: None,
+            points: None,
+            pool: None,
+            seed: None,
+            stats: false,
+            append_stats: false,
+            histogram: false,
+            progress: false,
+            rate: None,
+            threads: 1,
+            streaming: false,
+            // Whoever made the Options named the file, so there's nobody to ask about replacing it
+            if_exists: IfExists::Overwrite,
+            resume: false,
+            on_interrupt: OnInterrupt::Finish,
+            space_check: true,
+            shards: 1,
+            compress: false,
+            framed: false,
+            precision: None,
+            scientific: false,
+            number_format: NumberFormat::default(),
+            int_format: IntFormat::Decimal,
+            time_format: TimeFormat::Iso,
+            true_probability: 0.5,
+            bool_format: BoolFormat::Words,
+            cidr: None,
+            checksum: false,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            mmap: false,
+            table: None,
+            filename: String::new(),
+        }
+    }
+}
+
+impl Options {
+    /// Checks every setting fits together before any file gets created
+    /// write_data calls this itself, so code that builds Options by hand can't skip it
+    pub fn validate(&self) -> error::Result<()> {
+        // A permutation of one number has a range of just that number, which no other range can be
+        if self.permutation {
+            self.validate_permutation()?;
+        } else {
+            generator::validate_range(self.data_type, self.min, self.max)?;
+        }
+        generator::validate_distribution(&self.distribution)?;
+        generator::validate_order(self.order)?;
+        if self.unique {
+            let total = self.count as u64 * self.columns as u64;
+            generator::validate_unique(self.data_type, &self.distribution, self.min, self.max, total)?;
+        }
+        generator::validate_duplicates(self.data_type, self.duplicates, self.unique)?;
+        generator::validate_outliers(self.data_type, self.min, self.max, self.outliers, self.outlier_scale, self.unique)?;
+        if !self.exclude.is_empty() {
+            self.validate_exclusions()?;
+        }
+        if matches!(self.distribution, Distribution::Categorical(_)) && !self.data_type.is_string() {
+            return Err(invalid_input("Categorical distribution only works with the string type"));
+        }
+        if let Distribution::Special(special) = self.distribution {
+            special::validate_special(special, self)?;
+        }
+        if self.missing != 0.0 {
+            self.validate_missing()?;
+        }
+        if self.columns == 0 {
+            return Err(invalid_input("Number of columns must be a positive number"));
+        }
+        if self.separator != ' ' && self.separator != ',' {
+            return Err(invalid_input("Matrix separator must be a space or a comma"));
+        }
+        if self.header_style != HeaderStyle::Plain {
+            self.validate_header_style()?;
+        }
+        if !self.line_layout.is_plain() {
+            self.validate_line_layout()?;
+        }
+        if let Some(graph) = self.graph {
+            self.validate_graph(graph)?;
+        }
+        if let Some(series) = self.time_series {
+            self.validate_time_series(series)?;
+        }
+        if let Some(mixture) = &self.mixture {
+            self.validate_mixture(mixture)?;
+        }
+        if let Some(points) = self.points {
+            self.validate_points(points)?;
+        }
+        if let Some(pool) = &self.pool {
+            self.validate_pool(pool)?;
+        }
+        if !self.schema.is_empty() {
+            self.validate_records()?;
+        } else if self.format == OutputFormat::JsonLines {
+            return Err(unsupported_format("JSON lines output is for records, so it needs a schema"));
+        }
+        if self.data_type.is_boolean() {
+            // There's only one way to pick between two values: the chance of true
+            if !matches!(self.distribution, Distribution::Uniform) {
+                return Err(invalid_input("Booleans can only use the uniform distribution (set --true-probability instead)"));
+            }
+        }
+        if self.data_type.is_identifier() {
+            // Every bit is random by definition, so there's nothing for a distribution to shape
+            if !matches!(self.distribution, Distribution::Uniform) {
+                return Err(invalid_input("UUIDs and IP addresses can only use the uniform distribution"));
+            }
+            if self.stats || self.append_stats {
+                return Err(invalid_input("UUIDs and IP addresses are identifiers, not amounts, so they have no statistics"));
+            }
+        }
+        if let Some(cidr) = self.cidr {
+            self.validate_cidr(cidr)?;
+        }
+        if !(0.0..=1.0).contains(&self.true_probability) {
+            return Err(invalid_input("Probability of true must be between 0 and 1"));
+        }
+        if self.data_type.is_string() {
+            // A string's "value" is its letters, so there's nothing for a bell curve to shape
+            if !matches!(self.distribution, Distribution::Uniform | Distribution::Categorical(_)) {
+                return Err(invalid_input("Strings can only use the uniform or categorical distribution"));
+            }
+        }
+        if self.data_type.is_bytes() && !matches!(self.distribution, Distribution::Uniform) {
+            return Err(invalid_input("Blobs can only use the uniform distribution"));
+        }
+        // Names and the like are picked from lists and patterns, with nothing for a curve to shape either
+        if self.data_type.is_fake() && !matches!(self.distribution, Distribution::Uniform) {
+            return Err(invalid_input("Names, emails and phone numbers can only use the uniform distribution"));
+        }
+        if self.data_type.is_text() && matches!(self.format, OutputFormat::Binary) {
+            return Err(unsupported_format("Binary output only supports number types"));
+        }
+        if self.precision.is_some() || self.scientific {
+            let has_decimals = if self.schema.is_empty() {
+                self.data_type.has_decimals()
+            } else {
+                self.schema.iter().any(|field| field.data_type.has_decimals())
+            };
+            if !has_decimals {
+                return Err(invalid_input("Precision and scientific notation only apply to float and double"));
+            }
+            if self.decimals() > MAX_PRECISION {
+                return Err(invalid_input(format!("Precision must be between 0 and {}", MAX_PRECISION)));
+            }
+        }
+        if !self.number_format.is_plain() {
+            self.validate_number_format()?;
+        }
+        if self.int_format != IntFormat::Decimal {
+            self.validate_int_format()?;
+        }
+        if self.format == OutputFormat::FixedWidth {
+            self.validate_fixed_width()?;
+        } else if self.field_width != FieldWidth::default() {
+            return Err(unsupported_format("Width, fill and alignment only apply to fixed-width output"));
+        }
+        if self.endianness != Endianness::Little && self.format != OutputFormat::Binary {
+            return Err(unsupported_format("Byte order only applies to binary output"));
+        }
+        if self.buffer_size == 0 || self.buffer_size > MAX_BUFFER_SIZE {
+            return Err(invalid_input(format!("Buffer size must be between 1 byte and {} MB", MAX_BUFFER_SIZE >> 20)));
+        }
+        // Before the checks below, which would only complain about it not being a file
+        if self.framed {
+            self.validate_framed()?;
+        }
+        // Raw blobs make a directory of files rather than one file or stream
+        if blob::writes_blob_files(self) {
+            blob::validate_blob_files(self)?;
+        } else if let Some(socket) = self.socket() {
+            socket.validate(self)?;
+        } else if self.writes_to_fifo() {
+            fifo::validate_fifo(self)?;
+        }
+        // The file is made at its final size up front, so it has to be a fresh binary file on disk
+        if self.mmap && (self.format != OutputFormat::Binary || self.compress || self.writes_to_stdout()
+            || self.if_exists == IfExists::Append || self.resume || self.shards > 1)
+        {
+            return Err(unsupported_format("--mmap only works for binary files written straight to disk (no gzip, stdout, appending, resuming or shards)"));
+        }
+        if self.rate.is_some_and(|rate| !(rate.is_finite() && rate > 0.0)) {
+            return Err(invalid_input("--rate must be a positive number of values per second"));
+        }
+        // These go out in blocks whatever happens, so there's no pacing them value by value
+        if self.rate.is_some() && (self.mmap || matches!(self.format, OutputFormat::Parquet | OutputFormat::Sqlite)) {
+            return Err(unsupported_format("--rate can't pace Parquet, SQLite or --mmap files, which are written in blocks"));
+        }
+        if self.checksum && self.writes_to_stdout() {
+            return Err(invalid_input("A checksum file needs a filename to go next to, not stdout"));
+        }
+        // Sorting, sampling unique values and appending all need every value in memory at once
+        if self.streaming {
+            if !matches!(self.order, SortOrder::Random) {
+                return Err(invalid_input("Streaming can't sort, since the smallest value could come last"));
+            }
+            if self.unique {
+                return Err(invalid_input("Streaming can't guarantee unique values without remembering them all"));
+            }
+            if self.permutation {
+                return Err(invalid_input("Streaming can't shuffle a permutation, which keeps every number (4 bytes each) until it's written"));
+            }
+            if self.if_exists == IfExists::Append {
+                return Err(invalid_input("Streaming can't append, since the existing values have to be read in first"));
+            }
+            if self.histogram {
+                return Err(invalid_input("Streaming can't draw a histogram, since the bins need the whole range first"));
+            }
+        }
+        // The histogram comes out of the same values the statistics are worked out from
+        if self.histogram && !self.stats {
+            return Err(invalid_input("A histogram is drawn alongside the statistics, so it needs --stats too"));
+        }
+        if self.shards != 1 {
+            self.validate_shards()?;
+        }
+        if self.resume {
+            if let Some(reason) = checkpoint::resume_blocker(self) {
+                return Err(invalid_input(format!("Can't resume {}", reason)));
+            }
+        }
+        if self.append_stats && matches!(self.format, OutputFormat::Binary | OutputFormat::Parquet) {
+            return Err(unsupported_format("Appending statistics is not supported for binary or Parquet output"));
+        }
+        if self.format == OutputFormat::Sqlite {
+            self.validate_sqlite()?;
+        } else if self.table.is_some() {
+            return Err(unsupported_format("A table name only means something for SQLite output"));
+        }
+        if self.format == OutputFormat::Parquet {
+            // Pages inside the file are already compressed, and gzip around it would hide the
+            // footer that Parquet readers jump to first
+            if self.compress {
+                return Err(unsupported_format("Parquet files compress themselves, so they can't be gzipped as well"));
+            }
+            if self.if_exists == IfExists::Append {
+                return Err(unsupported_format("Parquet files can't be appended to"));
+            }
+        }
+        if let OutputFormat::Code(language) = self.format {
+            self.validate_code(language)?;
+        }
+        Ok(())
+    }
+
+    // Only text and matrix files have a Count: or Matrix: header to change, and appending
+    // needs it to find the old values and fix up the count
+    fn validate_header_style(&self) -> error::Result<()> {
+        self.header_style.validate()?;
+        if !matches!(self.format, OutputFormat::Text | OutputFormat::Matrix) || !self.schema.is_empty() || self.graph.is_some() {
+            return Err(unsupported_format("Header options only apply to text and matrix files of plain values"));
+        }
+        if self.if_exists == IfExists::Append {
+            return Err(invalid_input("Appending needs the usual header, to find the old values and fix up their count"));
+        }
+        Ok(())
+    }
+
+    // Text files are the only ones with nothing but values on their lines, and the reader
+    // only knows one value per line, so appending can't find the old values
+    fn validate_line_layout(&self) -> error::Result<()> {
+        self.line_layout.validate()?;
+        if self.format != OutputFormat::Text || !self.schema.is_empty() || self.graph.is_some() {
+            return Err(unsupported_format("The delimiter, prefix and suffix only apply to text files of plain values"));
+        }
+        if self.if_exists == IfExists::Append {
+            return Err(invalid_input("Appending only works with one value per line and nothing around it"));
+        }
+        Ok(())
+    }
+
+    // An array is declared with its size and closed off at the end, so there's no adding
+    // to one later, and nowhere for a summary to go that the compiler won't trip over
+    fn validate_code(&self, language: Language) -> error::Result<()> {
+        if self.if_exists == IfExists::Append || self.append_stats {
+            return Err(unsupported_format("Source code can't be appended to or have statistics appended (use --stats to print them instead)"));
+        }
+        if self.count == 0 && !language.allows_empty() {
+            return Err(invalid_input(format!("{} arrays can't be empty", language.name())));
+        }
+        Ok(())
+    }
+
+    // Only the text formats have room for a local style, and only where the
+    // separators can't be mistaken for the gaps between values
+    fn validate_number_format(&self) -> error::Result<()> {
+        self.number_format.validate()?;
+        if !matches!(self.format, OutputFormat::Text | OutputFormat::Csv | OutputFormat::Matrix | OutputFormat::FixedWidth) {
+            return Err(unsupported_format("JSON, binary, Parquet and SQLite numbers are always plain, so the number style only applies to text, CSV, matrix and fixed-width"));
+        }
+        if self.format == OutputFormat::Matrix && self.number_format.uses(self.separator) {
+            return Err(invalid_input("The matrix separator also appears inside the numbers - pick the other one"));
+        }
+        if self.graph.is_some() && self.number_format.uses(' ') {
+            return Err(invalid_input("Edge lists are separated by spaces, so weights can't use a space for thousands"));
+        }
+        // Appending reads the old values back in, and the reader only knows plain numbers
+        if self.if_exists == IfExists::Append {
+            return Err(invalid_input("Appending only works with plain numbers"));
+        }
+        Ok(())
+    }
+
+    // A block only narrows addresses of its own version, so there has to be something for it to narrow
+    fn validate_cidr(&self, cidr: Cidr) -> error::Result<()> {
+        let wanted = if cidr.is_ipv4() { DataType::Ipv4 } else { DataType::Ipv6 };
+        let used = if self.schema.is_empty() {
+            self.graph.is_none() && self.data_type == wanted
+        } else {
+            self.schema.iter().any(|field| field.data_type == wanted)
+        };
+        if !used {
+            return Err(invalid_input(format!("The CIDR block {} needs {} addresses to apply to", cidr, wanted.name())));
+        }
+        Ok(())
+    }
+
+    // Hex and binary are for whole numbers written as text, with nothing else rewriting the digits
+    fn validate_int_format(&self) -> error::Result<()> {
+        let is_plain_integer = |data_type: DataType| data_type.is_integer() && !data_type.is_timestamp();
+        let has_integers = if self.schema.is_empty() {
+            is_plain_integer(self.data_type)
+        } else {
+            self.schema.iter().any(|field| is_plain_integer(field.data_type))
+        };
+        if !has_integers {
+            return Err(invalid_input("Hex and binary only apply to the integer types (int, short, long, unsigned)"));
+        }
+        // JSON has no hex numbers, and the other formats store the number itself
+        if !matches!(self.format, OutputFormat::Text | OutputFormat::Csv | OutputFormat::Matrix | OutputFormat::FixedWidth) {
+            return Err(unsupported_format("Hex and binary integers only apply to text, CSV, matrix and fixed-width output"));
+        }
+        if !self.number_format.is_plain() {
+            return Err(invalid_input("Hex and binary integers can't also have a number style"));
+        }
+        // The reader only knows decimal, so the old values couldn't be read back in
+        if self.if_exists == IfExists::Append {
+            return Err(invalid_input("Appending only works with decimal integers"));
+        }
+        Ok(())
+    }
+
+    // The lines are nothing but padded fields, so there's no header to find the values by
+    // when reading one back, and nowhere to put a summary
+    fn validate_fixed_width(&self) -> error::Result<()> {
+        self.field_width.validate()?;
+        if self.if_exists == IfExists::Append {
+            return Err(unsupported_format("Fixed-width files can't be read back, so they can't be appended to"));
+        }
+        if self.append_stats {
+            return Err(unsupported_format("Fixed-width files have no room for statistics (use --stats to print them instead)"));
+        }
+        if self.graph.is_some() {
+            return Err(unsupported_format("A graph is written as an edge list, not fixed-width fields"));
+        }
+        Ok(())
+    }
+
+    // Frames go around the bytes as they're written, so only a stream written once, front to back, can have them
+    fn validate_framed(&self) -> error::Result<()> {
+        if self.format == OutputFormat::Sqlite || self.mmap {
+            return Err(unsupported_format("SQLite and --mmap files aren't written as a stream, so they can't be framed"));
+        }
+        // The old values are read back in first, and the frames would be in the way
+        if self.if_exists == IfExists::Append {
+            return Err(invalid_input("A framed file can't be appended to"));
+        }
+        Ok(())
+    }
+
+    // A database isn't a stream of bytes, so only the settings that make sense for rows in a table apply
+    fn validate_sqlite(&self) -> error::Result<()> {
+        if let Some(table) = &self.table {
+            if !generator::is_valid_label(table) {
+                return Err(invalid_input("Table names can only use letters, digits, '_' and '-'"));
+            }
+        }
+        if self.writes_to_stdout() {
+            return Err(unsupported_format("SQLite output needs a database file, not stdout"));
+        }
+        if self.compress || self.append_stats || self.shards != 1 {
+            return Err(unsupported_format("SQLite output can't be gzipped, sharded or have statistics appended"));
+        }
+        Ok(())
+    }
+
+    // Every shard is a whole file with its own header, written one after another
+    fn validate_shards(&self) -> error::Result<()> {
+        if self.shards == 0 || self.shards > shard::MAX_SHARDS {
+            return Err(invalid_input(format!("Number of shards must be between 1 and {}", shard::MAX_SHARDS)));
+        }
+        if self.shards > self.count {
+            return Err(invalid_input(format!("Can't split {} rows into {} shards", self.count, self.shards)));
+        }
+        if self.writes_to_stdout() {
+            return Err(invalid_input("Shards are separate files, so they can't go to stdout"));
+        }
+        if self.graph.is_some() {
+            return Err(invalid_input("A graph is written as a single edge list, so it can't be sharded"));
+        }
+        // The summary covers the whole dataset, so no one shard is the right place for it
+        if self.append_stats {
+            return Err(invalid_input("Statistics can't be appended to shards (use --stats to print them instead)"));
+        }
+        if self.if_exists == IfExists::Append || self.resume {
+            return Err(invalid_input("Shards are always written from scratch, so they can't be appended to or resumed"));
+        }
+        Ok(())
+    }
+
+    // Each field brings its own type and range, so the settings that shape a single
+    // column of values don't have anything to act on
+    fn validate_records(&self) -> error::Result<()> {
+        schema::validate_fields(&self.schema)?;
+        if !matches!(self.format, OutputFormat::Csv | OutputFormat::JsonLines | OutputFormat::Parquet | OutputFormat::Sqlite | OutputFormat::FixedWidth) {
+            return Err(unsupported_format("Records can only be written as CSV, JSON lines, Parquet, SQLite or fixed-width"));
+        }
+        let single_column_only = self.unique
+            || self.duplicates > 0.0
+            || self.outliers > 0.0
+            || !matches!(self.order, SortOrder::Random)
+            || self.distribution != Distribution::Uniform
+            || self.columns != 1
+            || self.stats
+            || self.threads != 1
+            // A table takes more rows of the same shape, unlike a file with a header at the top
+            || (self.if_exists == IfExists::Append && self.format != OutputFormat::Sqlite);
+        if single_column_only {
+            return Err(invalid_input(
+                "Records can't be combined with unique, duplicate or outlier values, sorting, distributions, columns, stats, threads or appending",
+            ));
+        }
+        Ok(())
+    }
+
+    // A permutation is every number in the range once, so it has to be exactly as big as the
+    // range, and nothing else gets to add, leave out or move numbers
+    fn validate_permutation(&self) -> error::Result<()> {
+        if !self.data_type.is_integer() {
+            return Err(invalid_input("A permutation is whole numbers, so it needs an integer type"));
+        }
+        if self.distribution != Distribution::Uniform {
+            return Err(invalid_input("A permutation uses every number once, so there's nothing for a distribution to shape"));
+        }
+        let total = self.count as u64 * self.values_per_row() as u64;
+        if total == 0 {
+            return Err(invalid_input("A permutation needs at least one number"));
+        }
+        // The same checks validate_range does, except that min and max can be the same number
+        let (lowest, highest) = self.data_type.limits();
+        if !(self.min.is_finite() && self.max.is_finite()) || self.min.fract() != 0.0 || self.max.fract() != 0.0 {
+            return Err(range_error("A permutation's range must be whole numbers"));
+        }
+        if self.min > self.max {
+            return Err(range_error(format!("A permutation can't start at {} and end at {}", self.min, self.max)));
+        }
+        if self.min < lowest || self.max > highest {
+            return Err(range_error(format!(
+                "A permutation of {} to {} doesn't fit the {} type ({} to {})", self.min, self.max, self.data_type.name(), lowest, highest
+            )));
+        }
+        let size = (self.max - self.min) as u64 + 1;
+        // Each place in the shuffle is a u32, like the count
+        if size > u32::MAX as u64 {
+            return Err(invalid_input(format!("A permutation can be at most {} numbers", u32::MAX)));
+        }
+        if total != size {
+            return Err(invalid_input(format!(
+                "A permutation of {} to {} is {} numbers, so it can't be {}", self.min, self.max, size, total
+            )));
+        }
+        let not_for_permutations = self.unique
+            || self.duplicates > 0.0
+            || self.outliers > 0.0
+            || !self.exclude.is_empty()
+            || !matches!(self.order, SortOrder::Random)
+            || !self.schema.is_empty()
+            || self.graph.is_some()
+            || self.pool.is_some()
+            || self.threads != 1
+            || self.if_exists == IfExists::Append;
+        if not_for_permutations {
+            return Err(invalid_input(
+                "A permutation can't be combined with unique, duplicate, outlier or excluded values, sorting, records, graphs, sampling, threads or appending",
+            ));
+        }
+        Ok(())
+    }
+
+    // Exclusions cut holes in the one range a plain file has - records, graphs and time series
+    // have ranges of their own, and a sequence can't skip a step without breaking its pattern
+    fn validate_exclusions(&self) -> error::Result<()> {
+        if !self.schema.is_empty() || self.graph.is_some() || self.time_series.is_some() || self.mixture.is_some() || self.points.is_some() {
+            return Err(invalid_input("Excluded values only apply to plain values, not records, graphs or time series"));
+        }
+        if matches!(self.distribution, Distribution::Sequence { .. }) {
+            return Err(invalid_input("A sequence steps through its values in order, so it can't leave any out"));
+        }
+        let total = self.count as u64 * self.columns as u64;
+        generator::validate_exclusions(self.data_type, self.min, self.max, &self.exclude, self.unique, total)
+    }
+
+    // Sampling picks values that already exist, so the settings that shape new ones have nothing to work on
+    fn validate_pool(&self, pool: &Pool) -> error::Result<()> {
+        if !self.schema.is_empty() || self.graph.is_some() || self.time_series.is_some() || self.mixture.is_some() || self.points.is_some() {
+            return Err(invalid_input("Sampling from a file makes plain values, not records, graphs or time series"));
+        }
+        if !matches!(self.distribution, Distribution::Uniform) {
+            return Err(invalid_input("Values sampled from a file are picked evenly, so they can't have a distribution"));
+        }
+        if self.unique || self.duplicates > 0.0 || self.outliers > 0.0 || !self.exclude.is_empty() {
+            return Err(invalid_input(
+                "Sampling from a file can't use --unique, --duplicates, --outliers or --exclude (use --no-replacement for no repeats)"
+            ));
+        }
+        if self.data_type != pool.data_type {
+            return Err(invalid_input(format!(
+                "{} holds {} values, not {}", pool.path, pool.data_type.name(), self.data_type.name()
+            )));
+        }
+        pool.validate(self.count as u64 * self.values_per_row() as u64)
+    }
+
+    // Missing values are a gap or a token in a line of text, which only the text formats with
+    // separate fields have room for
+    fn validate_missing(&self) -> error::Result<()> {
+        if !(0.0..=100.0).contains(&self.missing) {
+            return Err(invalid_input("Missing value percentage must be between 0 and 100"));
+        }
+        if !matches!(self.format, OutputFormat::Csv | OutputFormat::Json | OutputFormat::JsonLines) {
+            return Err(unsupported_format("Missing values can only be written to CSV, JSON or JSON lines files"));
+        }
+        // Appending reads the old values back in, and a missing one has no value to read
+        if self.if_exists == IfExists::Append {
+            return Err(invalid_input("Files with missing values can't be appended to"));
+        }
+        Ok(())
+    }
+
+    // A series is written as records, so validate_records covers everything but the two fields
+    fn validate_time_series(&self, series: TimeSeries) -> error::Result<()> {
+        series::validate_series(series)?;
+        let fits = match self.schema.as_slice() {
+            [time, value] => time.data_type.is_timestamp()
+                && (value.data_type.has_decimals() || (value.data_type.is_integer() && !value.data_type.is_timestamp())),
+            _ => false,
+        };
+        if !fits {
+            return Err(invalid_input("A time series needs a schema of a timestamp field then a number field (see TimeSeries::fields)"));
+        }
+        if self.graph.is_some() {
+            return Err(invalid_input("A graph can't be a time series as well"));
+        }
+        Ok(())
+    }
+
+    // Points are written as records too, so it's only the fields that need checking here
+    fn validate_mixture(&self, mixture: &Mixture) -> error::Result<()> {
+        mixture::validate_mixture(mixture)?;
+        let dimensions = mixture.dimensions();
+        let fits = self.schema.len() == dimensions + mixture.labeled as usize
+            && self.schema[..dimensions].iter().all(|field| {
+                field.data_type.has_decimals() || (field.data_type.is_integer() && !field.data_type.is_timestamp())
+            })
+            && (!mixture.labeled || self.schema[dimensions].data_type.is_integer());
+        if !fits {
+            return Err(invalid_input("A mixture needs a schema of a number field per coordinate, then an integer label if labelled (see Mixture::fields)"));
+        }
+        if self.graph.is_some() || self.time_series.is_some() {
+            return Err(invalid_input("A mixture can't be a graph or a time series as well"));
+        }
+        Ok(())
+    }
+
+    // The same for a point cloud, which only has the coordinates
+    fn validate_points(&self, points: Points) -> error::Result<()> {
+        points::validate_points(points)?;
+        // A point on a circle is hardly ever at whole numbers, so rounding would knock it off
+        let number = |data_type: DataType| match points.shape {
+            Shape::Box => data_type.has_decimals() || (data_type.is_integer() && !data_type.is_timestamp()),
+            Shape::Sphere => data_type.has_decimals(),
+        };
+        if self.schema.len() != points.dimensions || !self.schema.iter().all(|field| number(field.data_type)) {
+            return Err(invalid_input("Points need a schema of a number field per coordinate (see Points::fields), floats or doubles on a sphere"));
+        }
+        if self.graph.is_some() || self.time_series.is_some() || self.mixture.is_some() {
+            return Err(invalid_input("Points can't be a graph, a time series or a mixture as well"));
+        }
+        Ok(())
+    }
+
+    // An edge list has one fixed layout, so most of the settings for values don't fit it
+    fn validate_graph(&self, graph: Graph) -> error::Result<()> {
+        graph::validate_graph(graph, self.count)?;
+        if !self.schema.is_empty() {
+            return Err(invalid_input("A graph can't have a schema as well"));
+        }
+        if self.format != OutputFormat::Text {
+            return Err(unsupported_format("Graphs are written as text edge lists, so they need the text format"));
+        }
+        // Weights go through gen_value like any other value, so any number type works
+        if graph.weighted && !(self.data_type.is_integer() || self.data_type.has_decimals()) {
+            return Err(invalid_input("Edge weights must be a number type"));
+        }
+        if self.stats && !graph.weighted {
+            return Err(invalid_input("Only weighted graphs have values to show statistics for"));
+        }
+        let not_for_graphs = self.unique
+            || self.duplicates > 0.0
+            || self.outliers > 0.0
+            || !matches!(self.order, SortOrder::Random)
+            || matches!(self.distribution, Distribution::Sequence { .. })
+            || self.columns != 1
+            || self.threads != 1
+            || self.if_exists == IfExists::Append;
+        if not_for_graphs {
+            return Err(invalid_input("Graphs can't be combined with unique values, duplicates, outliers, sorting, sequences, columns, threads or appending"));
+        }
+        Ok(())
+    }
+
+    /// Bytes to collect before each write - none for a paced run, so every value goes out as it's made
+    pub fn write_buffer_size(&self) -> usize {
+        if self.rate.is_some() { 0 } else { self.buffer_size }
+    }
+
+    /// Values that make up one row of the file: the columns, one per field for records,
+    /// or the two ends of an edge (plus its weight) for graphs
+    pub fn values_per_row(&self) -> u32 {
+        match self.graph {
+            Some(graph) => 2 + graph.weighted as u32,
+            None if self.schema.is_empty() => self.columns,
+            None => self.schema.len() as u32,
+        }
+    }
+
+    /// Name and type of each column, for the formats that declare them up front:
+    /// col1, col2, ... for plain values, or the fields of a record
+    pub fn named_columns(&self) -> Vec<(String, DataType)> {
+        if self.schema.is_empty() {
+            (1..=self.columns).map(|i| (format!("col{}", i), self.data_type)).collect()
+        } else {
+            self.schema.iter().map(|field| (field.name.clone(), field.data_type)).collect()
+        }
+    }
+
+    /// A filename of "-" means standard output, like most Unix tools
+    pub fn writes_to_stdout(&self) -> bool {
+        self.filename == STDOUT_NAME
+    }
+
+    /// A filename of tcp://host:port or udp://host:port sends the values there instead
+    pub fn socket(&self) -> Option<Socket> {
+        Socket::parse(&self.filename)
+    }
+
+    /// An existing named pipe is written straight into, for another program to read (Unix only)
+    pub fn writes_to_fifo(&self) -> bool {
+        fifo::is_fifo(&self.filename)
+    }
+
+    /// Digits written after the decimal point - the chosen precision, or the type's default
+    pub fn decimals(&self) -> usize {
+        self.precision.unwrap_or(self.data_type.decimals())
+    }
+}
+
+/// Filename that sends the data to stdout instead of a file
+pub const STDOUT_NAME: &str = "-";
+
+/// Most digits after the point we'll write - past about 15 a double is just printing noise
+pub const MAX_PRECISION: usize = 15;
+
+/// BufWriter's own default - fine for small files, but big ones can go faster with more
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Largest write buffer we'll allocate (1 GB) - well past the point where bigger stops helping
+pub const MAX_BUFFER_SIZE: usize = 1 << 30;
+
+// Small helpers so every check can build its error in one short call
+pub(crate) fn invalid_input(message: impl Into<String>) -> GeneratorError {
+    GeneratorError::InvalidInput(message.into())
+}
+
+pub(crate) fn usage_error(message: impl Into<String>) -> GeneratorError {
+    GeneratorError::Usage(message.into())
+}
+
+pub(crate) fn invalid_data(message: impl Into<String>) -> GeneratorError {
+    GeneratorError::InvalidData(message.into())
+}
+
+pub(crate) fn range_error(message: impl Into<String>) -> GeneratorError {
+    GeneratorError::RangeError(message.into())
+}
+
+pub(crate) fn unsupported_format(message: impl Into<String>) -> GeneratorError {
+    GeneratorError::UnsupportedFormat(message.into())
+}
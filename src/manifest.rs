@@ -0,0 +1,112 @@
+//! Keeping a list of the files we've generated, so stale test data can be found and deleted later.
+//!
+//! The list always lasts for the current run. With a manifest file (`manifest = "..."` in the
+//! config, or `--manifest <file>`) it's also saved as one path per line, so it carries over
+//! between runs. The history of how each file was made travels along with it.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::checkpoint::checkpoint_name;
+use crate::checksum::checksum_name;
+use crate::error;
+use crate::fifo::is_fifo;
+use crate::history::History;
+use crate::socket::Socket;
+use crate::STDOUT_NAME;
+
+/// The files generated so far, and where (if anywhere) the list is saved
+#[derive(Debug, Clone, Default)]
+pub struct Manifest {
+    path: Option<String>, // None keeps the list in memory for this run only
+    files: Vec<String>,   // Absolute paths, oldest first
+    history: History,     // How they were made, for doing it again
+}
+
+impl Manifest {
+    /// A list that only lasts as long as the program is running
+    pub fn session() -> Manifest {
+        Manifest::default()
+    }
+
+    /// Opens a saved manifest - a file that isn't there yet just means nothing's been recorded
+    pub fn load(path: &str) -> error::Result<Manifest> {
+        let files = match fs::read_to_string(path) {
+            Ok(text) => text.lines().map(str::trim).filter(|line| !line.is_empty()).map(String::from).collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(io::Error::new(e.kind(), format!("Can't read manifest {}: {}", path, e)).into()),
+        };
+        Ok(Manifest { path: Some(path.to_string()), files, history: History::session() })
+    }
+
+    /// Keeps the history of runs in `history` instead of only for this run
+    pub fn with_history(mut self, history: History) -> Manifest {
+        self.history = history;
+        self
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn history_mut(&mut self) -> &mut History {
+        &mut self.history
+    }
+
+    /// Where the list is saved, if it's saved at all
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Every file recorded so far, including ones that have since been deleted by hand
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Remembers a file we just wrote, made with `seed` - stdout, sockets, pipes and files already on the list are skipped
+    /// (a pipe belongs to whoever made it, so cleaning up mustn't delete it)
+    pub fn add(&mut self, filename: &str, seed: Option<u64>) -> error::Result<()> {
+        if filename == STDOUT_NAME || Socket::parse(filename).is_some() || is_fifo(filename) {
+            return Ok(());
+        }
+        // The absolute path still points at the right file after a cd, which matters
+        // for a manifest that's read again in a later run
+        let full = fs::canonicalize(filename)?.to_string_lossy().into_owned();
+        // Written again is still a new run for the history, even though the list has it already
+        self.history.generated(&full, seed);
+        if !self.files.contains(&full) {
+            self.files.push(full);
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Deletes a recorded file (and its .sha256 or .checkpoint, if it has them) and takes it off the list
+    /// A file that's already gone is just forgotten, since the end result is the same
+    pub fn delete(&mut self, filename: &str) -> error::Result<()> {
+        for path in [filename.to_string(), checksum_name(filename), checkpoint_name(filename)] {
+            if let Err(e) = fs::remove_file(&path) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    return Err(e.into());
+                }
+            }
+        }
+        // retain() keeps what the closure says yes to, like filtering an array in place
+        self.files.retain(|file| file != filename);
+        self.save()
+    }
+
+    // Rewrites the whole file - it's one line per generated file, so it never gets big
+    fn save(&self) -> error::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let text: String = self.files.iter().map(|file| format!("{}\n", file)).collect();
+        Ok(fs::write(path, text)?)
+    }
+}
@@ -0,0 +1,98 @@
+//! Writing binary files through a memory map instead of a buffer.
+//!
+//! Every value in a binary file is the same size, so the whole file's length is known before
+//! the first value is generated. That means the file can be created at full size and mapped
+//! into memory (like mmap() in C), and the values written straight into it as if it were one
+//! big array. The OS copies the pages out to disk in the background, so there's no write()
+//! call per buffer-full - that adds up on multi-GB files.
+//!
+//! Where a file can't be mapped (a platform without mmap, a 32-bit build asked for more than
+//! fits in its address space, or a filesystem that won't do it) the file is removed again
+//! and write_data falls back to the usual BufWriter.
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+
+use memmap2::MmapMut;
+
+use crate::binary::BINARY_HEADER_LEN;
+use crate::error;
+use crate::Options;
+
+/// Bytes a binary file of these options takes up: the header, then every value at its type's size
+pub fn binary_len(options: &Options) -> u64 {
+    BINARY_HEADER_LEN + options.count as u64 * options.values_per_row() as u64 * options.data_type.size() as u64
+}
+
+/// A file created at its full size and mapped into memory, ready to be filled in like an array
+pub struct MappedFile {
+    map: MmapMut,
+}
+
+impl MappedFile {
+    /// Creates `path` at the full size of the options' file and maps it, or hands back None
+    /// (with the file removed again) when it can't be mapped. write_data gives it the .tmp name
+    /// and has already checked whether the real one can be replaced
+    pub fn create(path: &str, options: &Options) -> error::Result<Option<MappedFile>> {
+        // Mapping for writing needs read access too
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        match map(&file, binary_len(options)) {
+            Ok(map) => Ok(Some(MappedFile { map })),
+            Err(_) => {
+                drop(file);
+                fs::remove_file(path)?;
+                Ok(None)
+            },
+        }
+    }
+
+    /// The whole file as one slice - &mut [u8] is a Write that fills it from the front
+    pub fn bytes(&mut self) -> &mut [u8] {
+        &mut self.map[..]
+    }
+
+    /// Hands the pages over to be written out and unmaps the file
+    /// Like write() on the buffered path, this doesn't wait for them to reach the disk -
+    /// that's what makes the two fair to compare, and it's the OS's job either way
+    pub fn finish(self) -> error::Result<()> {
+        Ok(self.map.flush_async()?)
+    }
+}
+
+// set_len makes the file the right size without writing anything (most filesystems leave it
+// sparse until the pages are filled in), then the whole of it gets mapped
+fn map(file: &File, len: u64) -> io::Result<MmapMut> {
+    file.set_len(len)?;
+    // usize is only 32 bits on 32-bit systems, so a huge file might not fit in the address space
+    let len = usize::try_from(len).map_err(|_| io::Error::other("file too big to map"))?;
+    // Safety: a map is only unsound if something else shrinks or rewrites the file while it's
+    // mapped (we'd crash with SIGBUS, or see bytes change under us). We've only just created it,
+    // so the only way that happens is another program deliberately truncating a half-written file
+    unsafe { memmap2::MmapOptions::new().len(len).map_mut(file) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::tests::options;
+    use crate::{write_data, DataType, Distribution, IfExists, OutputFormat};
+
+    #[test]
+    fn mapped_files_match_buffered_ones() {
+        let mut options = options(DataType::Short, -500.0, 500.0, Distribution::Uniform);
+        (options.format, options.columns, options.count, options.seed) = (OutputFormat::Binary, 3, 5000, Some(9));
+        options.filename = std::env::temp_dir().join(format!("ruststf_test_{}_mapped.bin", std::process::id()))
+            .to_string_lossy().into_owned();
+        write_data(&options).unwrap();
+        let buffered = fs::read(&options.filename).unwrap();
+        assert_eq!(buffered.len() as u64, binary_len(&options));
+
+        options.mmap = true;
+        write_data(&options).unwrap();
+        assert_eq!(fs::read(&options.filename).unwrap(), buffered);
+        // Nothing gets replaced without being asked to
+        options.if_exists = IfExists::Abort;
+        assert!(write_data(&options).is_err());
+        fs::remove_file(&options.filename).unwrap();
+    }
+}
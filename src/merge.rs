@@ -0,0 +1,293 @@
+//! Joining several data files into one, like putting a sharded dataset back together.
+//!
+//! The files have to match - the same format and type, and the same header apart from the row
+//! count - and the merged file gets one header on top with the counts added up. The rows go in
+//! one file after another, or taking turns a row from each, or re-sorted by value. A shard
+//! manifest can stand in for the shards it lists.
+//!
+//! Rows are copied as they are, never parsed and written again, so nothing gets rounded on the
+//! way through. Only sorting holds them all in memory; the other two stream.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::net::IpAddr;
+
+use crate::atomic::temp_name;
+use crate::binary::{read_header, write_header};
+use crate::error;
+use crate::generator::Value;
+use crate::reader::{binary_number, parse_matrix_header};
+use crate::shard::read_shard_manifest;
+use crate::shuffle::{open_rows, split_comment, RowReader, Rows};
+use crate::timestamp::parse_timestamp;
+use crate::{invalid_data, invalid_input};
+
+/// How the rows of the files go together
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeOrder {
+    Concatenate, // All of the first file, then all of the second, and so on
+    Interleave,  // A row from each file in turn, until they've all run out
+    Ascending,   // Every row by value, smallest first, like --order asc
+    Descending,
+}
+
+// One file being merged
+struct Input {
+    name: String,
+    header: Vec<u8>,
+    rows: RowReader,
+    declared: Option<u64>, // Rows its Count: or Matrix: header promises
+    read: u64,
+}
+
+/// The files to merge, with any shard manifests swapped for the shards they list
+pub fn merge_inputs(names: &[String]) -> error::Result<Vec<String>> {
+    let mut files = Vec::new();
+    for name in names {
+        match read_shard_manifest(name)? {
+            Some(shards) => files.extend(shards),
+            None => files.push(name.clone()),
+        }
+    }
+    Ok(files)
+}
+
+/// Writes every row of `inputs` into `output` under one header, handing back how many rows
+/// there were - the output is written to <output>.tmp and renamed at the end, so it can be one
+/// of the inputs
+pub fn merge_files(inputs: &[String], output: &str, order: MergeOrder) -> error::Result<u64> {
+    if inputs.is_empty() {
+        return Err(invalid_input("Nothing to merge"));
+    }
+    let mut files = Vec::new();
+    for name in inputs {
+        let (_, header, rows) = open_rows(name)?;
+        files.push(Input { name: name.clone(), declared: None, header, rows, read: 0 });
+    }
+    let header = merged_header(&mut files, order)?;
+    let temp_name = temp_name(output);
+    let result = (|| {
+        let mut writer = BufWriter::new(File::create(&temp_name)?);
+        writer.write_all(&header)?;
+        let count = match order {
+            MergeOrder::Concatenate => concatenate(&mut writer, &mut files)?,
+            MergeOrder::Interleave => interleave(&mut writer, &mut files)?,
+            MergeOrder::Ascending | MergeOrder::Descending => sort(&mut writer, &mut files, order)?,
+        };
+        // A header that was wrong to begin with would make the merged one wrong too
+        for file in &files {
+            if file.declared.is_some_and(|declared| declared != file.read) {
+                return Err(invalid_data(format!(
+                    "{}'s header says {} rows but it has {}", file.name, file.declared.unwrap(), file.read
+                )));
+            }
+        }
+        writer.into_inner().map_err(|e| e.into_error())?;
+        fs::rename(&temp_name, output)?;
+        Ok(count)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_name);
+    }
+    result
+}
+
+// Checks the files all have the same header bar their counts, and works out the merged one
+fn merged_header(files: &mut [Input], order: MergeOrder) -> error::Result<Vec<u8>> {
+    let layout = files[0].rows.rows;
+    if let Some(other) = files.iter().find(|file| file.rows.rows != layout) {
+        return Err(invalid_input(format!("{} and {} aren't the same kind of file", files[0].name, other.name)));
+    }
+    // Binary headers are all fixed fields, so it's just the count that changes
+    if let Rows::Fixed(_) = layout {
+        let first = read_header(&files[0].header)?;
+        let mut total = 0;
+        for file in files.iter() {
+            let header = read_header(&file.header)?;
+            if (header.data_type, header.endianness) != (first.data_type, first.endianness) {
+                return Err(invalid_input(format!("{} and {} hold different types or byte orders", files[0].name, file.name)));
+            }
+            total += header.count as u64;
+        }
+        let total = u32::try_from(total).map_err(|_| invalid_input("Binary files can hold at most 4294967295 values"))?;
+        let mut header = Vec::new();
+        // Every type with a binary file has a tag, or it couldn't have been read
+        write_header(&mut header, first.data_type.binary_tag().unwrap(), total, first.endianness)?;
+        return Ok(header);
+    }
+    // Text headers are compared line by line with the counts taken out; a seed only stays
+    // when every file has the same one and they're joined in order, the way shards split up
+    let lines = |file: &Input| String::from_utf8_lossy(&file.header).lines().map(String::from).collect::<Vec<_>>();
+    let (first, first_name) = (lines(&files[0]), files[0].name.clone());
+    let bare = |line: &String| split_comment(line).1.to_string();
+    let compared = |line: &String| !["Count:", "Matrix:", "Seed:"].iter().any(|prefix| bare(line).starts_with(prefix));
+    let mut seeds_agree = true;
+    let mut total = 0;
+    let mut columns = None;
+    for file in files.iter_mut() {
+        let lines = lines(file);
+        if !lines.iter().filter(|line| compared(line)).eq(first.iter().filter(|line| compared(line))) {
+            return Err(invalid_input(format!("{} and {} have different headers, so they can't be merged", first_name, file.name)));
+        }
+        seeds_agree &= lines.iter().find(|line| bare(line).starts_with("Seed:")) == first.iter().find(|line| bare(line).starts_with("Seed:"));
+        for line in lines.iter().map(bare) {
+            if let Some(count) = line.strip_prefix("Count:") {
+                file.declared = Some(count.trim().parse().map_err(|_| invalid_data(format!("{}: invalid {}", file.name, line)))?);
+            } else if line.starts_with("Matrix:") {
+                let (rows, row_columns) = parse_matrix_header(&line).ok_or_else(|| invalid_data(format!("{}: invalid {}", file.name, line)))?;
+                if columns.is_some_and(|columns| columns != row_columns) {
+                    return Err(invalid_input(format!("{} has {} columns, unlike the files before it", file.name, row_columns)));
+                }
+                columns = Some(row_columns);
+                file.declared = Some(rows as u64);
+            }
+        }
+        total += file.declared.unwrap_or(0);
+    }
+    if columns.is_some_and(|columns| columns > 1) && matches!(order, MergeOrder::Ascending | MergeOrder::Descending) {
+        return Err(invalid_input("Matrix rows hold more than one value, so they can't be sorted"));
+    }
+    let mut header = String::new();
+    for line in first {
+        let (mark, bare) = split_comment(&line);
+        if bare.starts_with("Count:") {
+            header += &format!("{}Count: {}\n", mark, total);
+        } else if bare.starts_with("Matrix:") {
+            header += &format!("{}Matrix: {} x {}\n", mark, total, columns.unwrap_or(1));
+        } else if !bare.starts_with("Seed:") || (seeds_agree && order == MergeOrder::Concatenate) {
+            header += &line;
+            header.push('\n');
+        }
+    }
+    Ok(header.into_bytes())
+}
+
+fn concatenate(writer: &mut impl Write, files: &mut [Input]) -> error::Result<u64> {
+    let mut count = 0;
+    for file in files.iter_mut() {
+        while let Some(row) = file.rows.next_row()? {
+            writer.write_all(&row)?;
+            file.read += 1;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+// Round and round the files a row at a time, dropping each one as it runs out
+fn interleave(writer: &mut impl Write, files: &mut [Input]) -> error::Result<u64> {
+    let mut count = 0;
+    let mut running: Vec<&mut Input> = files.iter_mut().collect();
+    while !running.is_empty() {
+        let mut i = 0;
+        while i < running.len() {
+            match running[i].rows.next_row()? {
+                Some(row) => {
+                    writer.write_all(&row)?;
+                    running[i].read += 1;
+                    count += 1;
+                    i += 1;
+                },
+                None => {
+                    running.remove(i);
+                },
+            }
+        }
+    }
+    Ok(count)
+}
+
+// Every row in memory with the value it sorts by, then sorted like --order sorts values
+// sort_by is stable, so equal values stay in the order the files gave them
+fn sort(writer: &mut impl Write, files: &mut [Input], order: MergeOrder) -> error::Result<u64> {
+    let mut rows = Vec::new();
+    for file in files.iter_mut() {
+        let binary = match file.rows.rows {
+            Rows::Fixed(_) => Some(read_header(&file.header)?),
+            Rows::Lines => None,
+        };
+        while let Some(row) = file.rows.next_row()? {
+            let key = match binary {
+                Some(header) if header.data_type.is_identifier() => {
+                    // UUIDs and addresses are stored most significant byte first, so the bytes
+                    // read as one big number sort the same way the values do
+                    Value::Uuid(row.iter().fold(0, |number, &byte| number << 8 | byte as u128))
+                },
+                Some(header) => Value::Double(binary_number(header.data_type, header.endianness, &row)?),
+                None => row_key(&file.name, &row)?,
+            };
+            rows.push((key, row));
+            file.read += 1;
+        }
+    }
+    match order {
+        MergeOrder::Descending => rows.sort_by(|(a, _), (b, _)| b.compare(a)),
+        _ => rows.sort_by(|(a, _), (b, _)| a.compare(b)),
+    }
+    for (_, row) in &rows {
+        writer.write_all(row)?;
+    }
+    Ok(rows.len() as u64)
+}
+
+// The value a line holds, to sort by - numbers (and timestamps, either way they're written)
+// by size, addresses as numbers, and anything else alphabetically
+fn row_key(name: &str, row: &[u8]) -> error::Result<Value> {
+    let text = String::from_utf8_lossy(row);
+    let text = text.trim();
+    if text.contains(',') || text.starts_with('{') {
+        return Err(invalid_input(format!("{} has more than one value on a row, so it can't be sorted", name)));
+    }
+    Ok(if let Ok(number) = text.parse::<f64>() {
+        Value::Double(number)
+    } else if let Some(seconds) = parse_timestamp(text) {
+        Value::Timestamp(seconds)
+    } else if let Ok(address) = text.parse::<IpAddr>() {
+        Value::Ip(address)
+    } else {
+        Value::Text(text.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::DataType;
+    use crate::{Generator, OutputFormat};
+
+    #[test]
+    fn merged_files_add_up_their_headers() {
+        let name = |end: &str| {
+            let path = std::env::temp_dir().join(format!("ruststf_test_{}_merge_{}", std::process::id(), end));
+            path.to_string_lossy().into_owned()
+        };
+        let (a, b, merged) = (name("a.txt"), name("b.txt"), name("merged.txt"));
+        fs::write(&a, "Count: 3\nSeed: 1\n5\n1\n9\n").unwrap();
+        fs::write(&b, "Count: 2\nSeed: 2\n4\n8\n").unwrap();
+        let inputs = [a.clone(), b.clone()];
+
+        assert_eq!(merge_files(&inputs, &merged, MergeOrder::Concatenate).unwrap(), 5);
+        // Different seeds means no one seed made the whole file
+        assert_eq!(fs::read_to_string(&merged).unwrap(), "Count: 5\n5\n1\n9\n4\n8\n");
+        merge_files(&inputs, &merged, MergeOrder::Interleave).unwrap();
+        assert_eq!(fs::read_to_string(&merged).unwrap(), "Count: 5\n5\n4\n1\n8\n9\n");
+        merge_files(&inputs, &merged, MergeOrder::Descending).unwrap();
+        assert_eq!(fs::read_to_string(&merged).unwrap(), "Count: 5\n9\n8\n5\n4\n1\n");
+
+        // A wrong count, or a file of a different kind, stops the merge
+        fs::write(&b, "Count: 3\n4\n8\n").unwrap();
+        assert!(merge_files(&inputs, &merged, MergeOrder::Concatenate).is_err());
+        Generator::new().data_type(DataType::Short).count(4).format(OutputFormat::Binary).write_to(&b).unwrap();
+        assert!(merge_files(&inputs, &merged, MergeOrder::Concatenate).is_err());
+
+        // Binary files get a new count in their header, and sort by value
+        Generator::new().data_type(DataType::Short).count(6).seed(3).format(OutputFormat::Binary).write_to(&a).unwrap();
+        merge_files(&inputs, &merged, MergeOrder::Ascending).unwrap();
+        let values = crate::load_file(&merged).unwrap().values;
+        assert_eq!(values.len(), 10);
+        assert!(values.windows(2).all(|pair| pair[0] <= pair[1]));
+        for file in [a, b, merged] {
+            fs::remove_file(file).unwrap();
+        }
+    }
+}
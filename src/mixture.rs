@@ -0,0 +1,206 @@
+//! Gaussian mixtures: points scattered around a few centres, for testing clustering code.
+//!
+//! A mixture is K clusters, each a normal distribution with its own mean, variance and weight.
+//! Every point picks a cluster first (a cluster with twice the weight gets picked twice as
+//! often), then each of its coordinates is drawn from that cluster's bell curve. That's the
+//! model k-means, DBSCAN and EM are built to pull apart again, so it's the natural test input:
+//! the right answer is known, and with `--labels` it's written next to every point.
+//!
+//! `--mixture "0,0:1:0.5; 6,6:2:0.3; -4,5:0.5:0.2"` gives three clusters in 2D, each written
+//! `mean:variance:weight`. The mean has one number per axis (so its length says whether the
+//! points are 1D, 2D or 3D), the variance is one number for every axis or one per axis, and the
+//! weight can be left off, like a category's, to count as 1. The rows are records with `x`
+//! (and `y` and `z`) fields, plus `cluster` with the index of the cluster, from 0, when labelled.
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand_distr::Normal;
+
+use crate::error;
+use crate::generator::{sample_value, DataType, Value};
+use crate::points::{axis_fields, AXES};
+use crate::schema::{field_options, Field};
+use crate::{invalid_input, Options};
+
+/// Most axes a mixture's points can have
+pub const MAX_DIMENSIONS: usize = AXES.len();
+
+/// One normal distribution in the mixture
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster {
+    pub mean: Vec<f64>,     // The centre, one number per axis
+    pub variance: Vec<f64>, // How far points spread along each axis (standard deviation squared)
+    pub weight: f64,        // How often it's picked compared to the others
+}
+
+/// The clusters to draw points from - the number of points is Options::count
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mixture {
+    pub clusters: Vec<Cluster>,
+    pub labeled: bool, // Also write which cluster each point came from
+}
+
+impl Mixture {
+    /// Axes each point has: 1, 2 or 3
+    pub fn dimensions(&self) -> usize {
+        self.clusters.first().map_or(0, |cluster| cluster.mean.len())
+    }
+
+    /// The records the points are written as: a field per axis, then the label if there is one
+    /// The range clips the coordinates, like the range of any other distribution
+    pub fn fields(&self, value_type: DataType, min: f64, max: f64) -> Vec<Field> {
+        let mut fields = axis_fields(self.dimensions(), value_type, min, max);
+        if self.labeled {
+            let mut label = Field::new("cluster", DataType::Integer);
+            // The range has to be wider than a point, even when there's only one cluster
+            (label.min, label.max) = (0.0, self.clusters.len().saturating_sub(1).max(1) as f64);
+            fields.push(label);
+        }
+        fields
+    }
+
+    /// A range that holds nearly every point: four standard deviations either side of every mean
+    pub fn value_range(&self) -> (f64, f64) {
+        let mut range = (f64::INFINITY, f64::NEG_INFINITY);
+        for cluster in &self.clusters {
+            for (mean, variance) in cluster.mean.iter().zip(&cluster.variance) {
+                let spread = 4.0 * variance.sqrt();
+                range = (range.0.min(mean - spread), range.1.max(mean + spread));
+            }
+        }
+        // A cluster with no spread still needs a top above its bottom
+        (range.0, range.1.max(range.0 + 1.0))
+    }
+}
+
+/// Reads clusters written like "0,0:1:0.5; 6,6:2:0.3" - mean:variance:weight, separated by ;
+pub fn parse_clusters(text: &str) -> error::Result<Vec<Cluster>> {
+    let numbers = |part: &str, entry: &str| -> error::Result<Vec<f64>> {
+        part.split(',').map(|number| match number.trim().parse::<f64>() {
+            Ok(number) if number.is_finite() => Ok(number),
+            _ => Err(invalid_input(format!("Invalid number '{}' in cluster {}", number.trim(), entry))),
+        }).collect()
+    };
+    let mut clusters = Vec::new();
+    for entry in text.split(';').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let parts: Vec<&str> = entry.split(':').collect();
+        let (mean, variance, weight) = match parts[..] {
+            [mean, variance] => (numbers(mean, entry)?, numbers(variance, entry)?, 1.0),
+            [mean, variance, weight] => match numbers(weight, entry)?[..] {
+                [weight] => (numbers(mean, entry)?, numbers(variance, entry)?, weight),
+                _ => return Err(invalid_input(format!("A cluster has one weight, not {}", weight.trim()))),
+            },
+            _ => return Err(invalid_input(format!("A cluster is mean:variance or mean:variance:weight, not {}", entry))),
+        };
+        // One variance is the same spread along every axis
+        let variance = if variance.len() == 1 { vec![variance[0]; mean.len()] } else { variance };
+        clusters.push(Cluster { mean, variance, weight });
+    }
+    Ok(clusters)
+}
+
+/// Checks the mixture can be drawn from - Options::validate checks how it fits with everything else
+pub fn validate_mixture(mixture: &Mixture) -> error::Result<()> {
+    let Some(first) = mixture.clusters.first() else {
+        return Err(invalid_input("A mixture needs at least one cluster"));
+    };
+    if !(1..=MAX_DIMENSIONS).contains(&first.mean.len()) {
+        return Err(invalid_input(format!("Points can have 1 to {} coordinates, not {}", MAX_DIMENSIONS, first.mean.len())));
+    }
+    for cluster in &mixture.clusters {
+        if cluster.mean.len() != first.mean.len() {
+            return Err(invalid_input("Every cluster's mean needs the same number of coordinates"));
+        }
+        if cluster.variance.len() != cluster.mean.len() {
+            return Err(invalid_input("A cluster needs one variance, or one for each coordinate of its mean"));
+        }
+        if !cluster.mean.iter().all(|number| number.is_finite()) {
+            return Err(invalid_input("Cluster means must be finite numbers"));
+        }
+        if !cluster.variance.iter().all(|&variance| variance >= 0.0 && variance.is_finite()) {
+            return Err(invalid_input("Variances must be zero or more"));
+        }
+        if !(cluster.weight > 0.0 && cluster.weight.is_finite()) {
+            return Err(invalid_input("Cluster weights must be positive"));
+        }
+    }
+    Ok(())
+}
+
+/// Each point as a row of values: its coordinates, then its cluster's index when labelled
+pub(crate) fn mixture_values<'a>(rng: &'a mut StdRng, options: &'a Options, mixture: &'a Mixture) -> impl Iterator<Item = Value> + 'a {
+    // Options::validate made sure the first fields are the axes, so their ranges clip the points
+    let axes = field_options(options);
+    // Normal wants the standard deviation, and validate_mixture has made sure it's fine for it
+    let curves: Vec<Vec<Normal<f64>>> = mixture.clusters.iter().map(|cluster| {
+        cluster.mean.iter().zip(&cluster.variance).map(|(&mean, &variance)| Normal::new(mean, variance.sqrt()).unwrap()).collect()
+    }).collect();
+    let total: f64 = mixture.clusters.iter().map(|cluster| cluster.weight).sum();
+    (0..options.count).flat_map(move |_| {
+        let index = pick_cluster(rng, &mixture.clusters, total);
+        let mut row: Vec<Value> = curves[index].iter().zip(&axes).map(|(curve, axis)| {
+            let sample = rng.sample(curve);
+            sample_value(rng, axis, sample)
+        }).collect();
+        if mixture.labeled {
+            row.push(Value::Int(index as i32));
+        }
+        row
+    })
+}
+
+// A cluster in proportion to its weight, the same way pick_category picks a label
+fn pick_cluster(rng: &mut StdRng, clusters: &[Cluster], total: f64) -> usize {
+    let mut roll = rng.gen_range(0.0..total);
+    for (index, cluster) in clusters.iter().enumerate() {
+        if roll < cluster.weight {
+            return index;
+        }
+        roll -= cluster.weight;
+    }
+    // Rounding can leave a sliver past the last weight, which belongs to the last cluster
+    clusters.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::make_rng;
+    use crate::generator::tests::options;
+    use crate::{Distribution, OutputFormat};
+
+    #[test]
+    fn clusters_read_with_and_without_weights() {
+        let clusters = parse_clusters("0,0:1:3; 10,-5:4,0.25").unwrap();
+        assert_eq!(clusters[0], Cluster { mean: vec![0.0, 0.0], variance: vec![1.0, 1.0], weight: 3.0 });
+        assert_eq!(clusters[1], Cluster { mean: vec![10.0, -5.0], variance: vec![4.0, 0.25], weight: 1.0 });
+        let mixture = Mixture { clusters, labeled: true };
+        validate_mixture(&mixture).unwrap();
+        assert_eq!(mixture.value_range(), (-7.0, 18.0));
+        for bad in ["0,0", "0,0:1:2:3", "0:x", "0,0:1; 5:1", "0,0,0,0:1", "0:-1", "0:1:0", ""] {
+            let mixture = parse_clusters(bad).map(|clusters| Mixture { clusters, labeled: false });
+            assert!(mixture.and_then(|mixture| validate_mixture(&mixture)).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn points_gather_around_their_cluster() {
+        let mixture = Mixture { clusters: parse_clusters("-50:1:3; 50:1:1").unwrap(), labeled: true };
+        let mut options = options(DataType::Double, -100.0, 100.0, Distribution::Uniform);
+        (options.count, options.format) = (4000, OutputFormat::Csv);
+        options.schema = mixture.fields(DataType::Double, -100.0, 100.0);
+        options.mixture = Some(mixture.clone());
+        options.validate().unwrap();
+        let values: Vec<Value> = mixture_values(&mut make_rng(Some(4)), &options, &mixture).collect();
+        assert_eq!(values.len(), 8000);
+        let mut picked = [0; 2];
+        for point in values.chunks(2) {
+            let Value::Int(index) = point[1] else { panic!("no label in {:?}", point) };
+            picked[index as usize] += 1;
+            let centre = if index == 0 { -50.0 } else { 50.0 };
+            assert!((point[0].as_f64() - centre).abs() < 6.0);
+        }
+        // Three times the weight, so about three quarters of the points
+        assert!((2800..3200).contains(&picked[0]), "{:?}", picked);
+    }
+}
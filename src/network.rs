@@ -0,0 +1,119 @@
+//! Random IPv4 and IPv6 addresses, optionally kept inside a CIDR block like 10.0.0.0/8.
+//!
+//! An address is just a number (32 bits for IPv4, 128 for IPv6), so picking one is picking
+//! random bits. A CIDR block fixes the first `prefix` bits to the network's and leaves the
+//! rest random - the same masking a router does to decide whether an address is local.
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr}; // The standard library already reads and writes addresses
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::error;
+use crate::invalid_input;
+
+/// A network like 10.0.0.0/8: the address and how many of its leading bits are fixed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cidr {
+    pub network: IpAddr,
+    pub prefix: u8, // Up to 32 for IPv4, 128 for IPv6
+}
+
+impl Cidr {
+    pub fn is_ipv4(&self) -> bool {
+        self.network.is_ipv4()
+    }
+
+    // The fixed bits as a mask: /8 on IPv4 is ff000000
+    fn mask(&self, width: u32) -> u128 {
+        let all = if width == 128 { u128::MAX } else { (1u128 << width) - 1 };
+        // checked_shl because shifting by the full width is an overflow in Rust (and undefined in C)
+        let host = all.checked_shr(self.prefix as u32).unwrap_or(0);
+        all & !host
+    }
+}
+
+// Prints back the way it was typed, e.g. 10.0.0.0/8
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+/// Reads a block like 10.0.0.0/8 or 2001:db8::/32
+/// Bits set past the prefix (10.1.2.3/8) are cleared, since only the network part counts
+pub fn parse_cidr(input: &str) -> error::Result<Cidr> {
+    let input = input.trim();
+    let bad = || invalid_input(format!("Invalid CIDR block: {} (e.g. 10.0.0.0/8 or 2001:db8::/32)", input));
+    let (address, prefix) = input.split_once('/').ok_or_else(bad)?;
+    let network: IpAddr = address.parse().map_err(|_| bad())?;
+    let prefix: u8 = prefix.parse().map_err(|_| bad())?;
+    let width = if network.is_ipv4() { 32 } else { 128 };
+    if prefix as u32 > width {
+        return Err(invalid_input(format!("A prefix of /{} is longer than the {}-bit address", prefix, width)));
+    }
+    let cidr = Cidr { network, prefix };
+    Ok(Cidr { network: from_bits(to_bits(network) & cidr.mask(width), network.is_ipv4()), prefix })
+}
+
+/// A random address: any IPv4 (or IPv6) address, or one inside the block if there is one
+/// A block for the other version is ignored, so one --cidr can apply to some record fields and not others
+pub fn random_address(rng: &mut StdRng, ipv4: bool, cidr: Option<Cidr>) -> IpAddr {
+    let width = if ipv4 { 32 } else { 128 };
+    let random: u128 = if ipv4 { rng.gen::<u32>() as u128 } else { rng.gen() };
+    match cidr {
+        Some(cidr) if cidr.is_ipv4() == ipv4 => {
+            let mask = cidr.mask(width);
+            from_bits((to_bits(cidr.network) & mask) | (random & !mask), ipv4)
+        },
+        _ => from_bits(random, ipv4),
+    }
+}
+
+fn to_bits(address: IpAddr) -> u128 {
+    match address {
+        IpAddr::V4(address) => u32::from(address) as u128,
+        IpAddr::V6(address) => u128::from(address),
+    }
+}
+
+fn from_bits(bits: u128, ipv4: bool) -> IpAddr {
+    if ipv4 {
+        IpAddr::V4(Ipv4Addr::from(bits as u32))
+    } else {
+        IpAddr::V6(Ipv6Addr::from(bits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::make_rng;
+
+    #[test]
+    fn addresses_stay_inside_the_block() {
+        let mut rng = make_rng(Some(5));
+        let private = parse_cidr("10.0.0.0/8").unwrap();
+        let documentation = parse_cidr("2001:db8::/32").unwrap();
+        for _ in 0..1000 {
+            match random_address(&mut rng, true, Some(private)) {
+                IpAddr::V4(address) => assert_eq!(address.octets()[0], 10, "{}", address),
+                other => panic!("expected IPv4, got {}", other),
+            }
+            let address = random_address(&mut rng, false, Some(documentation));
+            assert!(address.to_string().starts_with("2001:db8:"), "{}", address);
+            // The IPv4 block doesn't apply to IPv6 addresses
+            assert!(random_address(&mut rng, false, Some(private)).is_ipv6());
+        }
+        // A /32 (or /128) leaves nothing random, and /0 leaves everything
+        let single = parse_cidr("192.168.1.7/32").unwrap();
+        assert_eq!(random_address(&mut rng, true, Some(single)).to_string(), "192.168.1.7");
+        assert_eq!(parse_cidr("0.0.0.0/0").unwrap().mask(32), 0);
+        // Host bits past the prefix are dropped
+        assert_eq!(parse_cidr("10.1.2.3/8").unwrap().to_string(), "10.0.0.0/8");
+        assert!(parse_cidr("10.0.0.0/33").is_err());
+        assert!(parse_cidr("10.0.0/8").is_err());
+        assert!(parse_cidr("10.0.0.0").is_err());
+    }
+}
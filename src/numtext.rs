@@ -0,0 +1,108 @@
+//! Turning numbers into text without going through format!.
+//!
+//! Writing a text file of doubles spends most of its time in `format!("{:.*}", ...)`: it builds
+//! a String for every value, and works out the exact decimal expansion of the double to round it
+//! (like printf's %.6f in C). Here integers are turned into digits by `itoa`, straight into a
+//! buffer on the stack, and a float with a fixed number of decimals is scaled up to a whole
+//! number (3.14159 at 3 decimals is 3142) and written the same way with the point put back in.
+//!
+//! Scaling rounds a little, which would only show when a value lands almost exactly halfway
+//! between two roundings. Those few go through format! as before, so the text always comes out
+//! exactly as it did - a seed still makes the same file.
+
+use std::io::{self, Write};
+
+// 10^0 up to 10^15 (MAX_PRECISION) - every one of them exact in a double
+const POWERS_OF_TEN: [f64; 16] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15,
+];
+
+// Scaled values from here up are left to format!. Below it, the one rounding in the
+// multiplication is off by less than 2^40 * 2^-53 = 2^-13...
+const FAST_LIMIT: f64 = (1u64 << 40) as f64;
+
+// ...so anything further than this from a halfway point rounds the same way it would exactly
+const HALFWAY_MARGIN: f64 = 1.0 / 1024.0;
+
+// Longest text write_decimals makes: a sign, 13 digits below FAST_LIMIT, a point and 15 decimals
+const FIXED_TEXT: usize = 32;
+
+/// Writes an integer the way {} would
+pub(crate) fn write_integer(writer: &mut impl Write, num: impl itoa::Integer) -> io::Result<()> {
+    writer.write_all(itoa::Buffer::new().format(num).as_bytes())
+}
+
+/// Writes `num` with `decimals` digits after the point, exactly as {:.*} would, and returns the
+/// number that text stands for (what parsing it back would give). None means this one has to go
+/// through format! instead - nothing has been written then
+pub(crate) fn write_decimals(writer: &mut impl Write, num: f64, decimals: usize) -> io::Result<Option<f64>> {
+    let scale = POWERS_OF_TEN[decimals];
+    let scaled = num.abs() * scale;
+    if scaled >= FAST_LIMIT || (scaled.fract() - 0.5).abs() < HALFWAY_MARGIN {
+        return Ok(None);
+    }
+    let units = scaled.round() as u64;
+    let mut digits = itoa::Buffer::new();
+    let digits = digits.format(units).as_bytes();
+
+    // Built up in a buffer and written in one go, like sprintf into a char array in C
+    let mut text = [0u8; FIXED_TEXT];
+    let mut len = 0;
+    let mut push = |bytes: &[u8]| {
+        text[len..len + bytes.len()].copy_from_slice(bytes);
+        len += bytes.len();
+    };
+    // format! keeps the sign of a negative number that rounds to nothing, so -0.0001 is -0.000
+    if num.is_sign_negative() {
+        push(b"-");
+    }
+    // No more digits than decimals means it's under 1, and needs zeros in front: 42 -> 0.042
+    let (whole, fraction) = digits.split_at(digits.len().saturating_sub(decimals));
+    push(if whole.is_empty() { b"0" } else { whole });
+    if decimals > 0 {
+        push(b".");
+        push(&b"000000000000000"[..decimals - fraction.len()]);
+        push(fraction);
+    }
+    writer.write_all(&text[..len])?;
+    // Both sides are exact, and IEEE division rounds once, to the double nearest the text
+    let value = units as f64 / scale;
+    Ok(Some(if num.is_sign_negative() { -value } else { value }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn fixed_text_matches_format() {
+        let mut rng = crate::generator::make_rng(Some(3));
+        let mut checked = 0;
+        for i in 0..200_000 {
+            // Small, huge, negative, whole numbers and halfway cases all turn up
+            let num = match i % 5 {
+                0 => rng.gen_range(-1.0..1.0),
+                1 => rng.gen_range(-1e9..1e9),
+                2 => rng.gen_range(-100..100) as f64 / 8.0,
+                3 => rng.gen_range(-1e-5..1e-5),
+                _ => rng.gen_range(-1e15..1e15),
+            };
+            let decimals = i % 16;
+            let mut text = Vec::new();
+            if let Some(value) = write_decimals(&mut text, num, decimals).unwrap() {
+                let expected = format!("{:.*}", decimals, num);
+                assert_eq!(String::from_utf8(text).unwrap(), expected);
+                assert_eq!(value.to_bits(), expected.parse::<f64>().unwrap().to_bits());
+                checked += 1;
+            } else {
+                assert!(text.is_empty());
+            }
+        }
+        // The point is that most values go the fast way
+        assert!(checked > 100_000);
+        let mut text = Vec::new();
+        write_decimals(&mut text, -0.0, 2).unwrap();
+        assert_eq!(String::from_utf8(text).unwrap(), format!("{:.2}", -0.0));
+    }
+}
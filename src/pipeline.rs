@@ -0,0 +1,107 @@
+//! Generating on one thread and writing on another, so the CPU and the disk work at the same time.
+//!
+//! Done one after the other, a run takes the generating time plus the writing time: the
+//! generator waits while a buffer goes out to disk, and the disk sits idle while the next one
+//! is generated. Here a generator thread makes chunks of CHUNK_VALUES values and passes them
+//! over a bounded channel (like a pipe between two processes in C) to the thread doing the
+//! writing, so a run takes about as long as the slower of the two. The channel only holds a
+//! few chunks, so a generator that's faster than the disk waits instead of filling up memory.
+//!
+//! The values still come out in exactly the same order, so a seed makes the same file.
+//! On a machine with one core the two threads would only take turns, which is slower than
+//! doing both on one, so `worth_pipelining` says to skip it there.
+
+use std::io;
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, Scope};
+use std::vec;
+
+use crate::error;
+use crate::generator::{make_rng, value_source, Value};
+use crate::Options;
+
+/// Values passed over in one go - big enough that the channel costs nothing per value,
+/// small enough that the writer gets its first chunk straight away
+pub const CHUNK_VALUES: usize = 16 * 1024;
+
+// Chunks that can wait in the channel before the generator has to stop and wait for the writer
+const CHANNEL_CHUNKS: usize = 4;
+
+/// True if there's a second core for the generator thread to run on
+pub fn worth_pipelining() -> bool {
+    thread::available_parallelism().is_ok_and(|cores| cores.get() > 1)
+}
+
+/// The writing end: the values the generator thread sends, one at a time
+pub(crate) struct Pipeline {
+    chunks: Receiver<Vec<Value>>,
+    chunk: vec::IntoIter<Value>,
+}
+
+impl Iterator for Pipeline {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        loop {
+            if let Some(value) = self.chunk.next() {
+                return Some(value);
+            }
+            // recv() waits for the next chunk, and fails once the generator has finished and hung up
+            self.chunk = self.chunks.recv().ok()?.into_iter();
+        }
+    }
+}
+
+/// Starts a thread in `scope` generating the options' values (after skipping the first `skip`)
+/// and hands back the end to read them from. It waits until the values are set up, so a
+/// problem like too few unique values is reported before the caller writes anything
+pub(crate) fn start_pipeline<'scope>(scope: &'scope Scope<'scope, '_>, options: &'scope Options, skip: u64) -> error::Result<Pipeline> {
+    let (ready_sender, ready) = mpsc::sync_channel(1);
+    let (sender, chunks) = mpsc::sync_channel(CHANNEL_CHUNKS);
+    scope.spawn(move || {
+        // The RNG lives on this thread, since it's the only one that uses it
+        let rng = &mut make_rng(options.seed);
+        let values = match value_source(rng, options) {
+            Ok(values) => values,
+            Err(e) => {
+                let _ = ready_sender.send(Err(e));
+                return;
+            },
+        };
+        let _ = ready_sender.send(Ok(()));
+        let mut values = values.skip(skip as usize);
+        loop {
+            let chunk: Vec<Value> = values.by_ref().take(CHUNK_VALUES).collect();
+            // An empty chunk means the values have run out, and a failed send means the writer
+            // stopped early (an error, or Ctrl-C) and dropped its end
+            if chunk.is_empty() || sender.send(chunk).is_err() {
+                break;
+            }
+        }
+    });
+    // The only way there's no answer at all is the thread panicking, which the scope passes on
+    ready.recv().map_err(|_| io::Error::other("The generator thread stopped before it started"))??;
+    Ok(Pipeline { chunks, chunk: Vec::new().into_iter() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::tests::options;
+    use crate::{DataType, Distribution};
+
+    #[test]
+    fn pipelined_values_match_generating_them_here() {
+        let mut options = options(DataType::Double, -10.0, 10.0, Distribution::Normal { mean: 0.0, std_dev: 3.0 });
+        // A few chunks and a bit, so the last one is short
+        (options.count, options.seed) = (CHUNK_VALUES as u32 * 3 + 17, Some(11));
+        let rng = &mut make_rng(options.seed);
+        let expected: Vec<Value> = value_source(rng, &options).unwrap().skip(5).collect();
+        let piped: Vec<Value> = thread::scope(|scope| start_pipeline(scope, &options, 5).unwrap().collect());
+        assert_eq!(piped, expected);
+
+        // A reader that stops early doesn't leave the generator stuck waiting to send
+        let first = thread::scope(|scope| start_pipeline(scope, &options, 0).unwrap().next());
+        assert!(first.is_some());
+    }
+}
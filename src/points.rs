@@ -0,0 +1,142 @@
+//! Point clouds: 2D and 3D coordinates for geometry and graphics code.
+//!
+//! `--points box` scatters points evenly through a box (a square in 2D, a cube in 3D) that
+//! runs from --min to --max along every axis. `--points circle` and `--points sphere` put
+//! them on the outline instead - every point exactly --radius from the origin, spread evenly
+//! around it, the test case for convex hulls, normals and anything that projects onto a
+//! surface. `--points mixture` is the same as `--mixture` (see mixture.rs), which takes 3D
+//! means as well. Every kind is written as records with `x`, `y` (and `z`) fields.
+//!
+//! Picking an even spread on a sphere is trickier than it looks: a random angle for latitude
+//! bunches points up at the poles. Drawing each coordinate from a bell curve and scaling the
+//! result to the right length doesn't, since the bell curves together look the same from
+//! every direction - so only the direction is random, and every direction is as likely.
+
+use rand::rngs::StdRng;
+use rand_distr::{Distribution, StandardNormal};
+
+use crate::error;
+use crate::generator::{gen_value, sample_value, DataType, Value};
+use crate::schema::{field_options, Field};
+use crate::{invalid_input, Options};
+
+/// The name of each axis's field, in order - points use the first two or all three
+pub const AXES: [&str; 3] = ["x", "y", "z"];
+
+/// Where the points go
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Box,    // Anywhere inside, evenly - the range is the box
+    Sphere, // On the surface only: a circle in 2D, a sphere in 3D, centred on the origin
+}
+
+/// A cloud of points - the number of points is Options::count
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Points {
+    pub shape: Shape,
+    pub dimensions: usize, // 2 or 3
+    pub radius: f64,       // Distance from the origin, for a sphere
+}
+
+impl Points {
+    /// A range that holds every point: the sphere's radius either side of the origin
+    /// A box has no range of its own, so it's the type's default
+    pub fn value_range(&self, value_type: DataType) -> (f64, f64) {
+        match self.shape {
+            Shape::Box => value_type.default_range(),
+            Shape::Sphere => (-self.radius, self.radius),
+        }
+    }
+
+    /// The records the points are written as: a field per axis, all with the same range
+    pub fn fields(&self, value_type: DataType, min: f64, max: f64) -> Vec<Field> {
+        axis_fields(self.dimensions, value_type, min, max)
+    }
+}
+
+/// A field for each of the first `dimensions` axes, each with the same type and range
+pub(crate) fn axis_fields(dimensions: usize, value_type: DataType, min: f64, max: f64) -> Vec<Field> {
+    AXES[..dimensions].iter().map(|&axis| {
+        let mut field = Field::new(axis, value_type);
+        (field.min, field.max) = (min, max);
+        field
+    }).collect()
+}
+
+/// Checks the cloud can be made - Options::validate checks how it fits with everything else
+pub fn validate_points(points: Points) -> error::Result<()> {
+    if !(2..=AXES.len()).contains(&points.dimensions) {
+        return Err(invalid_input(format!("Points have 2 or 3 coordinates, not {}", points.dimensions)));
+    }
+    if points.shape == Shape::Sphere && !(points.radius > 0.0 && points.radius.is_finite()) {
+        return Err(invalid_input("The radius must be a positive number"));
+    }
+    Ok(())
+}
+
+/// Each point as a row of values, one for each axis
+pub(crate) fn points_values<'a>(rng: &'a mut StdRng, options: &'a Options, points: Points) -> impl Iterator<Item = Value> + 'a {
+    // Options::validate made sure there's a field per axis, and their ranges clip the points
+    let axes = field_options(options);
+    let mut direction = vec![0.0; points.dimensions];
+    (0..options.count).flat_map(move |_| -> Vec<Value> {
+        match points.shape {
+            Shape::Box => axes.iter().map(|axis| gen_value(rng, axis)).collect(),
+            Shape::Sphere => {
+                // A direction of (almost) no length can't be scaled up, so it's drawn again -
+                // it's about as likely as the bell curves all landing on exactly 0
+                let mut length = 0.0;
+                while length < f64::MIN_POSITIVE {
+                    direction.iter_mut().for_each(|coordinate| *coordinate = StandardNormal.sample(rng));
+                    length = direction.iter().map(|coordinate| coordinate * coordinate).sum::<f64>().sqrt();
+                }
+                direction.iter().zip(&axes).map(|(coordinate, axis)| sample_value(rng, axis, coordinate / length * points.radius)).collect()
+            },
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::make_rng;
+    use crate::generator::tests::options;
+    use crate::OutputFormat;
+
+    fn cloud(points: Points, min: f64, max: f64) -> Vec<Value> {
+        let mut options = options(DataType::Double, min, max, crate::Distribution::Uniform);
+        (options.count, options.format) = (2000, OutputFormat::Csv);
+        options.schema = points.fields(DataType::Double, min, max);
+        options.points = Some(points);
+        options.validate().unwrap();
+        points_values(&mut make_rng(Some(8)), &options, points).collect()
+    }
+
+    #[test]
+    fn sphere_points_are_all_on_the_surface() {
+        let points = Points { shape: Shape::Sphere, dimensions: 3, radius: 2.5 };
+        let (min, max) = points.value_range(DataType::Double);
+        let values = cloud(points, min, max);
+        assert_eq!(values.len(), 6000);
+        let mut above = 0;
+        for point in values.chunks(3) {
+            let length = point.iter().map(|value| value.as_f64().powi(2)).sum::<f64>().sqrt();
+            assert!((length - 2.5).abs() < 1e-9, "{:?}", point);
+            above += (point[2].as_f64() > 0.0) as u32;
+        }
+        // Evenly spread, so about half of them are in the top half
+        assert!((900..1100).contains(&above), "{}", above);
+        assert!(validate_points(Points { radius: 0.0, ..points }).is_err());
+        assert!(validate_points(Points { dimensions: 4, ..points }).is_err());
+    }
+
+    #[test]
+    fn box_points_fill_the_range() {
+        let values = cloud(Points { shape: Shape::Box, dimensions: 2, radius: 1.0 }, -1.0, 1.0);
+        assert_eq!(values.len(), 4000);
+        assert!(values.iter().all(|value| (-1.0..=1.0).contains(&value.as_f64())));
+        // A quarter of the square is the top right corner
+        let corner = values.chunks(2).filter(|point| point[0].as_f64() > 0.0 && point[1].as_f64() > 0.0).count();
+        assert!((400..600).contains(&corner), "{}", corner);
+    }
+}
@@ -0,0 +1,177 @@
+//! Values picked from a file of candidates instead of made up, for bootstrapping and resampling.
+//!
+//! The pool can be any file this program writes or any CSV, which covers a plain list with one
+//! value per line. The reader works out the type the same way --validate does, and every cell
+//! is one candidate.
+//!
+//! `convert` uses a pool too, taking every value once in the order it's in the file, so the
+//! writers that make new files can rewrite an old one in another format.
+
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::blob::parse_hex;
+use crate::error;
+use crate::generator::{DataType, Value};
+use crate::reader::{load_file, LoadedData};
+use crate::OutputFormat;
+use crate::uuid::parse_uuid;
+use crate::{invalid_data, invalid_input};
+
+/// How values are taken out of a pool
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+    WithReplacement,    // Each pick comes from the whole pool
+    WithoutReplacement, // Never the same one twice
+    InOrder,            // Every value once, as the file has them - for converting a file
+}
+
+/// The candidates to pick from, and how they're picked
+// Arc shares the values between the copies of Options that batch mode makes, instead of
+// copying a big pool for every file
+#[derive(Clone, PartialEq)]
+pub struct Pool {
+    pub path: String,
+    pub data_type: DataType,
+    pub values: Arc<Vec<Value>>,
+    pub sampling: Sampling,
+}
+
+// Checkpoints compare runs by their Debug text, and a million values would swamp it
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Pool {{ path: {:?}, values: {}, sampling: {:?} }}", self.path, self.values.len(), self.sampling)
+    }
+}
+
+impl Pool {
+    /// Reads the candidates from a file
+    pub fn load(path: &str, sampling: Sampling) -> error::Result<Pool> {
+        Pool::from_loaded(path, load_file(path)?, sampling)
+    }
+
+    /// Makes the candidates out of a file that's already been read
+    pub fn from_loaded(path: &str, data: LoadedData, sampling: Sampling) -> error::Result<Pool> {
+        // JSON and binary files say exactly what type they hold, so only the others are widened
+        let data_type = match data.format {
+            OutputFormat::Json | OutputFormat::Binary => data.data_type,
+            _ => widen(data.data_type, &data.values),
+        };
+        let values: Vec<Value> = if data_type.is_text() {
+            data.strings.into_iter().map(Value::Text).collect()
+        } else if data_type.is_uuid() {
+            // The reader already tidied these, so they all parse
+            data.strings.iter().filter_map(|text| parse_uuid(text)).map(Value::Uuid).collect()
+        } else if data_type.is_ip() {
+            data.strings.iter().filter_map(|text| text.parse::<IpAddr>().ok()).map(Value::Ip).collect()
+        } else if data_type.is_bytes() {
+            data.strings.iter().filter_map(|text| parse_hex(text)).map(Value::Bytes).collect()
+        } else {
+            data.values.iter().map(|&num| number(data_type, num)).collect()
+        };
+        if values.is_empty() {
+            return Err(invalid_data(format!("{} has no values to pick from", path)));
+        }
+        Ok(Pool { path: path.to_string(), data_type, values: Arc::new(values), sampling })
+    }
+
+    /// Smallest and largest value (lengths for strings and blobs), to use as the file's range
+    pub fn range(&self) -> (f64, f64) {
+        if !self.data_type.has_range() {
+            return self.data_type.default_range();
+        }
+        let numbers = self.values.iter().map(Value::as_f64);
+        let min = numbers.clone().fold(f64::INFINITY, f64::min);
+        let max = numbers.fold(f64::NEG_INFINITY, f64::max);
+        // A range has to be wider than a point for everything but strings and blobs
+        if min == max && !self.data_type.has_length() { (min, min + 1.0) } else { (min, max) }
+    }
+
+    /// Checks `total` picks can be made
+    pub fn validate(&self, total: u64) -> error::Result<()> {
+        let len = self.values.len() as u64;
+        match self.sampling {
+            Sampling::WithoutReplacement if total > len => Err(invalid_input(format!(
+                "Can't pick {} values without replacement from a pool of only {}", total, len
+            ))),
+            Sampling::InOrder if total != len => Err(invalid_input(format!(
+                "{} has {} values, so it can't fill {}", self.path, len, total
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// `total` values from the pool, in the order they were picked
+    pub fn sample(&self, rng: &mut StdRng, total: usize) -> Vec<Value> {
+        match self.sampling {
+            Sampling::WithReplacement => (0..total).map(|_| self.values[rng.gen_range(0..self.values.len())].clone()).collect(),
+            // The same sampling without replacement --unique uses on a range
+            Sampling::WithoutReplacement => {
+                rand::seq::index::sample(rng, self.values.len(), total).into_iter().map(|i| self.values[i].clone()).collect()
+            },
+            Sampling::InOrder => self.values[..total].to_vec(),
+        }
+    }
+}
+
+// The reader guesses floats and integers from how the numbers look, which suits checking a
+// file but not copying values out of it - f32 would round a long decimal, and i32 would
+// clip a big ID - so pools get the wider types when they need them
+fn widen(data_type: DataType, values: &[f64]) -> DataType {
+    // A float is written with a few decimals, so values with more than that must be doubles
+    let decimals = DataType::Float.decimals();
+    let fits_float = |num: f64| format!("{:.*}", decimals, num).parse::<f64>().ok() == Some(num);
+    match data_type {
+        DataType::Float if !values.iter().all(|&num| fits_float(num)) => DataType::Double,
+        DataType::Integer if values.iter().any(|&num| num < i32::MIN as f64 || num > i32::MAX as f64) => DataType::Long,
+        _ => data_type,
+    }
+}
+
+// A value read back as a number, as the type it was written as
+fn number(data_type: DataType, num: f64) -> Value {
+    match data_type {
+        DataType::Short => Value::Short(num as i16),
+        DataType::Long => Value::Long(num as i64),
+        DataType::Unsigned => Value::Unsigned(num as u32),
+        DataType::Float => Value::Float(num as f32),
+        DataType::Double => Value::Double(num),
+        DataType::Timestamp => Value::Timestamp(num as i64),
+        DataType::Boolean => Value::Bool(num != 0.0),
+        _ => Value::Int(num as i32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::make_rng;
+
+    #[test]
+    fn pools_are_sampled_with_and_without_replacement() {
+        let path = std::env::temp_dir().join(format!("ruststf_test_{}_pool.txt", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        std::fs::write(&path, "apple\nbanana\n\ncherry\n").unwrap();
+        let pool = Pool::load(&path, Sampling::WithoutReplacement).unwrap();
+        assert_eq!((pool.data_type, pool.values.len()), (DataType::String, 3));
+        // Without replacement, taking the whole pool is a shuffle of it
+        let mut picked: Vec<String> = pool.sample(&mut make_rng(Some(1)), 3).iter().map(|value| format!("{:?}", value)).collect();
+        picked.sort();
+        assert_eq!(picked, ["Text(\"apple\")", "Text(\"banana\")", "Text(\"cherry\")"]);
+        assert!(pool.validate(4).is_err());
+        let pool = Pool { sampling: Sampling::WithReplacement, ..pool };
+        assert!(pool.validate(4).is_ok());
+        assert_eq!(pool.sample(&mut make_rng(Some(1)), 100).len(), 100);
+
+        // A header isn't a candidate, and an ID too big for an int makes them all longs
+        std::fs::write(&path, "id\n5\n9000000000\n").unwrap();
+        let pool = Pool::load(&path, Sampling::WithReplacement).unwrap();
+        assert_eq!(*pool.values, [Value::Long(5), Value::Long(9_000_000_000)]);
+        assert_eq!(pool.range(), (5.0, 9e9));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
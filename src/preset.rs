@@ -0,0 +1,189 @@
+//! Named sets of command-line flags, saved so a recurring setup can be run again by name.
+//!
+//! A preset is just the flags that were given when it was saved, kept in `presets.toml`
+//! (or wherever `presets = "..."` in the config points) as one TOML array per name:
+//!
+//! ```toml
+//! sorting-bench-large = ["--type", "int", "--count", "10000000", "--order", "desc"]
+//! ```
+//!
+//! Storing the flags rather than the settings they make means a preset always means
+//! exactly what typing those flags would, and later flags can still override it.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::error;
+use crate::{invalid_data, invalid_input};
+
+/// Name of the presets file looked for in the current directory
+pub const PRESETS_FILE: &str = "presets.toml";
+
+/// One saved set of flags
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+/// Every preset in a presets file, and where the file is
+#[derive(Debug, Clone)]
+pub struct Presets {
+    path: String,
+    presets: Vec<Preset>, // In the order they were first saved
+}
+
+impl Presets {
+    /// Opens a presets file - one that isn't there yet just means nothing's been saved
+    pub fn load(path: &str) -> error::Result<Presets> {
+        let presets = match fs::read_to_string(path) {
+            Ok(text) => parse_presets(&text).map_err(|e| invalid_data(format!("{}: {}", path, e)))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(io::Error::new(e.kind(), format!("Can't read presets {}: {}", path, e)).into()),
+        };
+        Ok(Presets { path: path.to_string(), presets })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn list(&self) -> &[Preset] {
+        &self.presets
+    }
+
+    /// Looks a preset up by name - the error lists what there is, since a typo is the usual reason
+    pub fn get(&self, name: &str) -> error::Result<&Preset> {
+        self.presets.iter().find(|preset| preset.name == name).ok_or_else(|| {
+            let names: Vec<&str> = self.presets.iter().map(|preset| preset.name.as_str()).collect();
+            match names.len() {
+                0 => invalid_input(format!("No preset called {} (none saved in {} yet)", name, self.path)),
+                _ => invalid_input(format!("No preset called {} (saved: {})", name, names.join(", "))),
+            }
+        })
+    }
+
+    /// Saves the flags under a name, replacing any preset that already had it, and writes the file
+    pub fn save(&mut self, name: &str, args: &[String]) -> error::Result<()> {
+        validate_name(name)?;
+        let preset = Preset { name: name.to_string(), args: args.to_vec() };
+        match self.presets.iter_mut().find(|preset| preset.name == name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+        if let Some(dir) = Path::new(&self.path).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut text = String::from("# Saved with --save-preset <name>; run one again with --preset <name>\n");
+        for preset in &self.presets {
+            let args: Vec<String> = preset.args.iter().map(|arg| quote(arg)).collect();
+            text.push_str(&format!("{} = [{}]\n", preset.name, args.join(", ")));
+        }
+        Ok(fs::write(&self.path, text)?)
+    }
+}
+
+/// Preset names are bare TOML keys, so letters, digits, - and _ only
+pub fn validate_name(name: &str) -> error::Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(invalid_input(format!("Invalid preset name: {} (use letters, digits, - and _)", name)));
+    }
+    Ok(())
+}
+
+// One name = [...] line per preset, with # comments and blank lines skipped like the config file
+fn parse_presets(text: &str) -> Result<Vec<Preset>, String> {
+    let mut presets: Vec<Preset> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let number = i + 1;
+        let line = line.trim();
+        // A # can be inside a quoted flag (--categories "a#1"), so only whole comment lines are skipped
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, value) = line.split_once('=').ok_or_else(|| format!("line {}: expected name = [flags]", number))?;
+        let name = name.trim();
+        validate_name(name).map_err(|e| format!("line {}: {}", number, e))?;
+        let args = parse_array(value.trim()).ok_or_else(|| format!("line {}: expected a list of quoted flags", number))?;
+        if presets.iter().any(|preset| preset.name == name) {
+            return Err(format!("line {}: {} is defined twice", number, name));
+        }
+        presets.push(Preset { name: name.to_string(), args });
+    }
+    Ok(presets)
+}
+
+// ["a", "b c", "d\"e"] - walks the characters since a comma can sit inside a string
+pub(crate) fn parse_array(value: &str) -> Option<Vec<String>> {
+    let inner = value.strip_prefix('[')?.strip_suffix(']')?;
+    let mut items = Vec::new();
+    let mut chars = inner.chars().peekable();
+    loop {
+        // Skip the space before the next string, and stop at the end of the list
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return Some(items);
+        }
+        if chars.next()? != '"' {
+            return None;
+        }
+        let mut item = String::new();
+        loop {
+            match chars.next()? {
+                '"' => break,
+                '\\' => item.push(chars.next()?),
+                c => item.push(c),
+            }
+        }
+        items.push(item);
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        match chars.next() {
+            Some(',') => {},
+            None => return Some(items),
+            Some(_) => return None,
+        }
+    }
+}
+
+// The other way round: backslashes before quotes and backslashes, like a C string literal
+pub(crate) fn quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_survive_a_round_trip() {
+        let path = std::env::temp_dir().join(format!("ruststf_test_{}_presets.toml", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let _ = fs::remove_file(&path);
+
+        let mut presets = Presets::load(&path).unwrap();
+        assert!(presets.list().is_empty());
+        let args: Vec<String> = ["--type", "string", "--categories", "a:1, \"b\":2", "-o", "C:\\data.txt"]
+            .iter().map(|arg| arg.to_string()).collect();
+        presets.save("labels", &args).unwrap();
+        presets.save("big_ints", &["-n".to_string(), "10000000".to_string()]).unwrap();
+        // Saving a name again replaces it in place
+        presets.save("labels", &args[..2]).unwrap();
+
+        let loaded = Presets::load(&path).unwrap();
+        assert_eq!(loaded.list().len(), 2);
+        assert_eq!(loaded.get("labels").unwrap().args, &args[..2]);
+        assert_eq!(loaded.list()[1].name, "big_ints");
+        assert!(loaded.get("missing").unwrap_err().to_string().contains("labels, big_ints"));
+
+        presets.save("labels", &args).unwrap();
+        assert_eq!(Presets::load(&path).unwrap().get("labels").unwrap().args, args);
+        assert!(presets.save("has space", &args).is_err());
+        assert!(parse_presets("x = [\"--type\" \"int\"]").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,469 @@
+//! Reading data files back in, whatever format they were written in.
+
+use std::io::{self, Read};
+use std::iter::Peekable;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::Lines;
+
+use flate2::read::GzDecoder;
+
+use crate::binary::{is_binary, read_header, Endianness};
+use crate::blob::{format_hex, parse_hex};
+use crate::error;
+use crate::generator::{DataType, ALL_DATA_TYPES};
+use crate::timestamp::{parse_iso, parse_timestamp};
+use crate::uuid::{format_uuid, parse_uuid};
+use crate::writer::OutputFormat;
+use crate::invalid_data;
+
+/// Everything we could learn about a data file by reading it back
+#[derive(Debug)]
+pub struct LoadedData {
+    pub format: OutputFormat,
+    pub data_type: DataType,
+    pub declared_count: Option<u32>, // What the header claims, if the format has a header
+    pub columns: Option<u32>, // Values per row, for matrix files and CSVs with more than one column
+    pub seed: Option<u64>,
+    pub values: Vec<f64>, // f64 holds every i32 and f32 exactly, so one Vec covers both types
+    pub strings: Vec<String>, // Only filled for string and UUID files - `values` then holds their lengths
+}
+
+/// Opens any file this program can write and works out which format it is from the contents
+pub fn load_file(filename: &str) -> error::Result<LoadedData> {
+    let bytes = uncomment_header(read_bytes(filename)?);
+    if bytes.starts_with(b"Count:") {
+        return load_text(&to_text(bytes)?);
+    }
+    if bytes.starts_with(b"Matrix:") {
+        return load_matrix(&to_text(bytes)?);
+    }
+    if bytes.starts_with(b"{") {
+        return load_json(&to_text(bytes)?);
+    }
+    if is_binary(&bytes) {
+        return load_binary(&bytes);
+    }
+    load_csv(&to_text(bytes)?)
+}
+
+// A header written with --comment-header, with the "# " taken back off so it reads like any other
+// Only our own header lines are touched - the # lines of a graph or a summary don't start this way
+pub(crate) fn uncomment_header(bytes: Vec<u8>) -> Vec<u8> {
+    if !bytes.starts_with(b"# Count:") && !bytes.starts_with(b"# Matrix:") {
+        return bytes;
+    }
+    let mut uncommented = Vec::with_capacity(bytes.len());
+    let mut in_header = true;
+    for line in bytes.split_inclusive(|&byte| byte == b'\n') {
+        let header_line = ["# Count:", "# Matrix:", "# Seed:", "# Type:"].iter().any(|prefix| line.starts_with(prefix.as_bytes()));
+        in_header &= header_line;
+        uncommented.extend_from_slice(if in_header { &line[2..] } else { line });
+    }
+    uncommented
+}
+
+// Reads the whole file into memory, unpacking it first if it's gzipped
+pub(crate) fn read_bytes(filename: &str) -> error::Result<Vec<u8>> {
+    let bytes = std::fs::read(filename).map_err(|e| io::Error::new(e.kind(), format!("Can't read {}: {}", filename, e)))?;
+    // Every gzip file starts with these two "magic" bytes, so compressed files
+    // are unpacked first and then go through the same checks as everything else
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut unpacked = Vec::new();
+        GzDecoder::new(&bytes[..]).read_to_end(&mut unpacked)?;
+        return Ok(unpacked);
+    }
+    // Parquet files start (and end) with "PAR1" - they're columns of typed pages, not something
+    // this program reads back, so send people to a tool that does
+    if bytes.starts_with(b"PAR1") {
+        return Err(invalid_data(format!("{} is a Parquet file - open it with a Parquet tool like pandas or DuckDB", filename)));
+    }
+    // The same goes for SQLite databases, which start with their own name
+    if bytes.starts_with(b"SQLite format 3\0") {
+        return Err(invalid_data(format!("{} is a SQLite database - query it with the sqlite3 command instead", filename)));
+    }
+    Ok(bytes)
+}
+
+pub(crate) fn to_text(bytes: Vec<u8>) -> error::Result<String> {
+    String::from_utf8(bytes).map_err(|_| invalid_data("File is not valid text"))
+}
+
+// Parses one number from the file, keeping track of whether it looked like a float
+fn parse_number(token: &str, saw_float: &mut bool) -> error::Result<f64> {
+    let token = token.trim();
+    // Scientific notation like 1e5 is a float even without a decimal point
+    if token.contains(['.', 'e', 'E']) {
+        *saw_float = true;
+    }
+    match token.parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => Err(invalid_data(format!("Not a number: {}", token))),
+    }
+}
+
+// Lines starting with # hold appended stats rather than data
+fn is_blank_or_comment(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with('#')
+}
+
+/// The tidied text of a UUID or IP address (lowercase, IPv6 with its zeros squeezed out),
+/// or None if the token isn't one of `data_type`
+pub(crate) fn identifier_text(data_type: DataType, token: &str) -> Option<String> {
+    let token = token.trim();
+    match data_type {
+        DataType::Uuid => parse_uuid(token).map(format_uuid),
+        // parse() knows the address forms from the standard library, like inet_pton in C
+        DataType::Ipv4 => token.parse::<Ipv4Addr>().ok().map(|address| address.to_string()),
+        DataType::Ipv6 => token.parse::<Ipv6Addr>().ok().map(|address| address.to_string()),
+        _ => None,
+    }
+}
+
+fn guess_type(saw_float: bool) -> DataType {
+    if saw_float { DataType::Float } else { DataType::Integer }
+}
+
+// Turns the raw tokens into numbers, strings or timestamps - `kind` is the type the file
+// says it holds, and for anything but strings and timestamps the numbers decide for themselves
+// Strings keep their lengths in `values` too, so stats still have something to work with
+fn parse_tokens(tokens: Vec<&str>, kind: Option<DataType>) -> error::Result<(DataType, Vec<f64>, Vec<String>)> {
+    // Names, emails and phone numbers are strings too, just with a particular shape
+    if let Some(kind) = kind.filter(|kind| kind.is_text()) {
+        let strings: Vec<String> = tokens.iter().map(|token| token.trim().to_string()).collect();
+        let lengths = strings.iter().map(|text| text.len() as f64).collect();
+        return Ok((kind, lengths, strings));
+    }
+    // UUIDs and addresses are kept as text too, tidied into the form we write
+    if let Some(kind) = kind.filter(|kind| kind.is_identifier()) {
+        let strings = tokens.iter().map(|token| {
+            identifier_text(kind, token).ok_or_else(|| invalid_data(format!("Not a {}: {}", kind.name(), token.trim())))
+        }).collect::<error::Result<Vec<String>>>()?;
+        let lengths = strings.iter().map(|text| text.len() as f64).collect();
+        return Ok((kind, lengths, strings));
+    }
+    // Blobs stay as their hex (in lowercase, as we write it), with their size in bytes as the value
+    if kind == Some(DataType::Bytes) {
+        let strings = tokens.iter().map(|token| {
+            parse_hex(token).map(|blob| format_hex(&blob)).ok_or_else(|| invalid_data(format!("Not hex bytes: {}", token.trim())))
+        }).collect::<error::Result<Vec<String>>>()?;
+        let sizes = strings.iter().map(|text| (text.len() / 2) as f64).collect();
+        return Ok((DataType::Bytes, sizes, strings));
+    }
+    // Timestamps become their seconds, whichever way they were written
+    if kind == Some(DataType::Timestamp) {
+        let values = tokens.iter().map(|token| {
+            parse_timestamp(token).map(|seconds| seconds as f64)
+                .ok_or_else(|| invalid_data(format!("Not a timestamp: {}", token.trim())))
+        }).collect::<error::Result<Vec<f64>>>()?;
+        return Ok((DataType::Timestamp, values, Vec::new()));
+    }
+    if kind == Some(DataType::Boolean) {
+        let values = tokens.iter().map(|token| {
+            parse_bool(token).map(|flag| flag as u8 as f64)
+                .ok_or_else(|| invalid_data(format!("Not a boolean: {}", token.trim())))
+        }).collect::<error::Result<Vec<f64>>>()?;
+        return Ok((DataType::Boolean, values, Vec::new()));
+    }
+    let mut saw_float = false;
+    let mut values = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        values.push(parse_number(token, &mut saw_float)?);
+    }
+    Ok((guess_type(saw_float), values, Vec::new()))
+}
+
+fn is_number(token: &str) -> bool {
+    token.trim().parse::<f64>().is_ok()
+}
+
+fn is_iso_timestamp(token: &str) -> bool {
+    parse_iso(token).is_some()
+}
+
+/// Either way a boolean can be written: true/false or 1/0
+pub(crate) fn parse_bool(token: &str) -> Option<bool> {
+    match token.trim() {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+// Looks the Type: name up - only types that can't be told apart by how they look get one
+fn declared_type(name: &str) -> error::Result<DataType> {
+    match ALL_DATA_TYPES.iter().copied().find(|data_type| data_type.name() == name) {
+        Some(data_type) if data_type.needs_type_line() => Ok(data_type),
+        _ => Err(invalid_data(format!("Unknown type: {}", name))),
+    }
+}
+
+// Text layout: "Count: N", an optional "Seed: S", a "Type:" line for strings and timestamps, then one value per line
+fn load_text(text: &str) -> error::Result<LoadedData> {
+    let mut lines = text.lines().peekable();
+    let count_line = lines.next().unwrap_or_default();
+    let declared_count = count_line.trim_start_matches("Count:").trim().parse::<u32>()
+        .map_err(|_| invalid_data("Malformed Count header"))?;
+    let (seed, kind) = read_extra_headers(&mut lines)?;
+
+    let tokens = lines.filter(|line| !is_blank_or_comment(line)).collect();
+    let (data_type, values, strings) = parse_tokens(tokens, kind)?;
+
+    Ok(LoadedData {
+        format: OutputFormat::Text,
+        data_type,
+        declared_count: Some(declared_count),
+        columns: None,
+        seed,
+        values,
+        strings,
+    })
+}
+
+// Matrix layout: "Matrix: R x C", then the same optional headers as text, then R rows of C values
+fn load_matrix(text: &str) -> error::Result<LoadedData> {
+    let mut lines = text.lines().peekable();
+    let (rows, columns) = parse_matrix_header(lines.next().unwrap_or_default())
+        .ok_or_else(|| invalid_data("Malformed Matrix header"))?;
+    let (seed, kind) = read_extra_headers(&mut lines)?;
+
+    let tokens = lines.filter(|line| !is_blank_or_comment(line)).flat_map(split_matrix_row).collect();
+    let (data_type, values, strings) = parse_tokens(tokens, kind)?;
+
+    Ok(LoadedData {
+        format: OutputFormat::Matrix,
+        data_type,
+        // The header counts rows and columns, but everything else counts values
+        declared_count: rows.checked_mul(columns),
+        columns: Some(columns),
+        seed,
+        values,
+        strings,
+    })
+}
+
+/// Reads "Matrix: R x C" into (rows, columns)
+pub(crate) fn parse_matrix_header(line: &str) -> Option<(u32, u32)> {
+    let (rows, columns) = line.strip_prefix("Matrix:")?.split_once('x')?;
+    Some((rows.trim().parse().ok()?, columns.trim().parse().ok()?))
+}
+
+/// Matrix rows can be separated by spaces or commas, so split on either
+pub(crate) fn split_matrix_row(line: &str) -> impl Iterator<Item = &str> {
+    line.split(|c: char| c == ',' || c.is_whitespace()).filter(|token| !token.is_empty())
+}
+
+// The optional "Seed:" and "Type:" lines that can follow the first line of a text or matrix file
+fn read_extra_headers(lines: &mut Peekable<Lines<'_>>) -> error::Result<(Option<u64>, Option<DataType>)> {
+    // peek() looks at the next line without using it up
+    let mut seed = None;
+    if let Some(line) = lines.peek() {
+        if let Some(rest) = line.strip_prefix("Seed:") {
+            seed = Some(rest.trim().parse::<u64>().map_err(|_| invalid_data("Malformed Seed header"))?);
+            lines.next();
+        }
+    }
+    // Only string and timestamp files have a Type line - numbers are told apart by how they look
+    let mut kind = None;
+    if let Some(line) = lines.peek() {
+        if let Some(rest) = line.strip_prefix("Type:") {
+            kind = Some(declared_type(rest.trim())?);
+            lines.next();
+        }
+    }
+    Ok((seed, kind))
+}
+
+// Every cell counts as a value, and any cell that isn't a number or an ISO timestamp makes it a file of strings
+// A first row of words over rows of numbers (or timestamps) is the header; for strings we can only
+// recognise our own col1,col2,... header, since any other header looks just like data
+// Only the words count here - a CSV of 1s and 0s is just numbers
+fn is_bool_row(row: &str) -> bool {
+    row.split(',').all(|cell| matches!(cell.trim(), "true" | "false"))
+}
+
+// A row of values of any one kind we write, rather than a row of names
+fn is_plain_row(row: &str) -> bool {
+    let cells = || row.split(',');
+    cells().all(is_number) || cells().all(is_iso_timestamp) || is_bool_row(row)
+        || [DataType::Uuid, DataType::Ipv4, DataType::Ipv6].into_iter().any(|kind| cells().all(|cell| identifier_text(kind, cell).is_some()))
+}
+
+/// Whether the first row of a CSV is a header: our own "col1,col2,..." line, or any line of
+/// names above rows that are all values
+pub(crate) fn is_csv_header<'a>(first: &str, mut rest: impl Iterator<Item = &'a str>) -> bool {
+    let our_header = first.split(',').all(|cell| {
+        // is_some_and is like checking for NULL and then looking inside, in one step
+        cell.trim().strip_prefix("col").is_some_and(|n| n.parse::<u32>().is_ok())
+    });
+    our_header || (!is_plain_row(first) && rest.all(is_plain_row))
+}
+
+fn load_csv(text: &str) -> error::Result<LoadedData> {
+    let mut rows: Vec<&str> = text.lines().filter(|line| !is_blank_or_comment(line)).collect();
+    let numeric_row = |row: &&str| row.split(',').all(is_number);
+    let timestamp_row = |row: &&str| row.split(',').all(is_iso_timestamp);
+    let bool_row = |row: &&str| is_bool_row(row);
+    let identifier_row = |kind: DataType| move |row: &&str| row.split(',').all(|cell| identifier_text(kind, cell).is_some());
+    let (uuid_row, ipv4_row, ipv6_row) = (identifier_row(DataType::Uuid), identifier_row(DataType::Ipv4), identifier_row(DataType::Ipv6));
+    if let Some(&first) = rows.first() {
+        if is_csv_header(first, rows[1..].iter().copied()) {
+            rows.remove(0);
+        }
+    }
+
+    let kind = if rows.iter().all(numeric_row) {
+        None
+    } else if rows.iter().all(timestamp_row) {
+        Some(DataType::Timestamp)
+    } else if rows.iter().all(bool_row) {
+        Some(DataType::Boolean)
+    } else if rows.iter().all(uuid_row) {
+        Some(DataType::Uuid)
+    } else if rows.iter().all(ipv4_row) {
+        Some(DataType::Ipv4)
+    } else if rows.iter().all(ipv6_row) {
+        Some(DataType::Ipv6)
+    } else {
+        Some(DataType::String)
+    };
+    // Rows that are all as wide as each other make a table, like a matrix without the header
+    let width = rows.first().map_or(0, |row| row.split(',').count());
+    let columns = Some(width as u32).filter(|_| width > 1 && rows.iter().all(|row| row.split(',').count() == width));
+    let tokens = rows.iter().flat_map(|row| row.split(',')).collect();
+    let (data_type, values, strings) = parse_tokens(tokens, kind)?;
+
+    Ok(LoadedData {
+        format: OutputFormat::Csv,
+        data_type,
+        declared_count: None,
+        columns,
+        seed: None,
+        values,
+        strings,
+    })
+}
+
+// We only ever write a flat object of numbers or plain strings, so a tiny hand-rolled reader is enough
+fn load_json(text: &str) -> error::Result<LoadedData> {
+    // Grabs the raw text after "key": up to the next comma or closing brace
+    let field = |key: &str| -> Option<String> {
+        let start = text.find(&format!("\"{}\":", key))? + key.len() + 3;
+        let rest = &text[start..];
+        let end = rest.find([',', '}']).unwrap_or(rest.len());
+        Some(rest[..end].trim().trim_matches('"').to_string())
+    };
+
+    let declared_count = field("count")
+        .and_then(|count| count.parse::<u32>().ok())
+        .ok_or_else(|| invalid_data("Missing or malformed \"count\" field"))?;
+    let seed = match field("seed") {
+        Some(seed) => Some(seed.parse::<u64>().map_err(|_| invalid_data("Malformed \"seed\" field"))?),
+        None => None,
+    };
+
+    let start = text.find("\"values\":").ok_or_else(|| invalid_data("Missing \"values\" field"))?;
+    let open = start + text[start..].find('[').ok_or_else(|| invalid_data("Malformed \"values\" array"))?;
+    let close = open + text[open..].find(']').ok_or_else(|| invalid_data("Malformed \"values\" array"))?;
+
+    let declared_type = match field("type") {
+        Some(name) => ALL_DATA_TYPES.iter().copied().find(|data_type| data_type.name() == name),
+        None => None,
+    };
+    let tokens: Vec<&str> = text[open + 1..close].split(',').filter(|token| !token.trim().is_empty()).collect();
+    // Strings are always quoted in JSON, so even without a "type" field we can tell
+    // (ISO timestamps are quoted too, but we always write the "type" field alongside them)
+    let kind = match declared_type {
+        Some(data_type) if data_type.needs_type_line() => Some(data_type),
+        _ if tokens.iter().any(|token| token.trim().starts_with('"')) => Some(DataType::String),
+        _ => None,
+    };
+    let tokens = tokens.iter().map(|token| token.trim().trim_matches('"')).collect();
+    let (guessed_type, values, strings) = parse_tokens(tokens, kind)?;
+
+    Ok(LoadedData {
+        format: OutputFormat::Json,
+        data_type: declared_type.unwrap_or(guessed_type),
+        declared_count: Some(declared_count),
+        columns: None,
+        seed,
+        values,
+        strings,
+    })
+}
+
+// Checks the binary header agrees with the file size before trusting any of it
+/// One number from a binary file, given its bytes - identifiers aren't numbers, so they're left to the caller
+pub(crate) fn binary_number(data_type: DataType, order: Endianness, chunk: &[u8]) -> error::Result<f64> {
+    let value = match data_type {
+        DataType::Integer => i32::from_le_bytes(order.little(chunk)) as f64,
+        DataType::Short => i16::from_le_bytes(order.little(chunk)) as f64,
+        DataType::Long | DataType::Timestamp => i64::from_le_bytes(order.little(chunk)) as f64,
+        DataType::Boolean => match chunk[0] {
+            0 | 1 => chunk[0] as f64,
+            byte => return Err(invalid_data(format!("Boolean byte must be 0 or 1, found {}", byte))),
+        },
+        DataType::Unsigned => u32::from_le_bytes(order.little(chunk)) as f64,
+        DataType::Float => f32::from_le_bytes(order.little(chunk)) as f64,
+        DataType::Double => f64::from_le_bytes(order.little(chunk)),
+        // from_binary_tag never hands back String, since it has no tag
+        DataType::String | DataType::FirstName | DataType::LastName | DataType::Email | DataType::Phone => {
+            unreachable!("text types have no binary tag")
+        },
+        DataType::Uuid | DataType::Ipv4 | DataType::Ipv6 => unreachable!("identifiers are read as text"),
+        DataType::Bytes => unreachable!("raw blobs are files of their own, with no tag"),
+    };
+    if !value.is_finite() {
+        return Err(invalid_data("File contains NaN or infinite values"));
+    }
+    Ok(value)
+}
+
+fn load_binary(bytes: &[u8]) -> error::Result<LoadedData> {
+    let header = read_header(bytes)?;
+    let (data_type, count, order) = (header.data_type, header.count, header.endianness);
+    let size = data_type.size();
+    let expected_len = header.size() + count as u64 * size as u64;
+    if bytes.len() as u64 != expected_len {
+        return Err(invalid_data(format!(
+            "Header says {} values ({} bytes) but file is {} bytes",
+            count, expected_len, bytes.len()
+        )));
+    }
+    let body = &bytes[header.size() as usize..];
+
+    // UUIDs and addresses are kept as text like strings are (a UUID is too wide for an f64 anyway)
+    if data_type.is_identifier() {
+        let strings: Vec<String> = body.chunks_exact(size)
+            .map(|chunk| match data_type {
+                DataType::Uuid => format_uuid(u128::from_be_bytes(chunk.try_into().unwrap())),
+                DataType::Ipv4 => Ipv4Addr::from(<[u8; 4]>::try_from(chunk).unwrap()).to_string(),
+                _ => Ipv6Addr::from(<[u8; 16]>::try_from(chunk).unwrap()).to_string(),
+            })
+            .collect();
+        return Ok(LoadedData {
+            format: OutputFormat::Binary,
+            data_type,
+            declared_count: Some(count),
+            columns: None,
+            seed: None,
+            values: strings.iter().map(|text| text.len() as f64).collect(),
+            strings,
+        });
+    }
+    // chunks_exact hands us one value's bytes at a time, like stepping a pointer through an array in C
+    // little() turns each chunk round into little-endian order first if the file is big-endian
+    let values = body.chunks_exact(size)
+        .map(|chunk| binary_number(data_type, order, chunk))
+        .collect::<error::Result<Vec<f64>>>()?;
+
+    Ok(LoadedData {
+        format: OutputFormat::Binary,
+        data_type,
+        declared_count: Some(count),
+        columns: None,
+        seed: None,
+        values,
+        strings: Vec::new(),
+    })
+}
@@ -0,0 +1,268 @@
+//! Records made of several named fields, each with its own type - like a row in a database table.
+//!
+//! A schema is a comma-separated list of `name:type` pairs, e.g. `id:int, score:float, active:bool`.
+//! A field can also carry its own range in brackets: `age:int(18..90)`, `name:string(4..12)`
+//! (for strings the range is the length, same as everywhere else) or
+//! `joined:timestamp(2024-01-01..2024-12-31)`.
+//!
+//! A number field can follow an earlier one with a correlation coefficient after a `~`:
+//! `height:int(150..200), weight:int(50..120) ~ height 0.8` makes tall records tend to be heavy.
+
+use std::collections::HashSet;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::error;
+use crate::generator::{
+    gen_value, is_valid_label, parse_bound, parse_data_type, sample_value, validate_range, DataType, Distribution, Value,
+};
+use crate::{invalid_input, Options};
+
+/// One field of a record
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String, // Goes in the CSV header and as the JSON key
+    pub data_type: DataType,
+    pub min: f64, // Same meaning as Options::min/max, so for strings it's the length
+    pub max: f64,
+    pub correlated: Option<(String, f64)>, // An earlier field this one follows, and how closely (-1 to 1)
+}
+
+impl Field {
+    /// A field using its type's default range
+    pub fn new(name: &str, data_type: DataType) -> Field {
+        let (min, max) = data_type.default_range();
+        Field { name: name.to_string(), data_type, min, max, correlated: None }
+    }
+}
+
+/// Reads a schema like "id:int(1..1000), score:float, active:bool"
+pub fn parse_schema(text: &str) -> error::Result<Vec<Field>> {
+    // collect() into error::Result stops at the first bad field, like checking each return value in C
+    let fields = text.split(',').map(parse_field).collect::<error::Result<Vec<Field>>>()?;
+    validate_fields(&fields)?;
+    Ok(fields)
+}
+
+fn parse_field(text: &str) -> error::Result<Field> {
+    // "~ other r" on the end says which field this one follows, and how closely
+    let (text, correlated) = match text.split_once('~') {
+        Some((text, partner)) => {
+            let bad = || invalid_input(format!("Correlations look like ~ field r, e.g. ~ height 0.8, not '~{}'", partner));
+            let mut words = partner.split_whitespace();
+            let name = words.next().ok_or_else(bad)?;
+            let r = words.next().and_then(|r| r.parse::<f64>().ok()).ok_or_else(bad)?;
+            if words.next().is_some() {
+                return Err(bad());
+            }
+            (text, Some((name.to_string(), r)))
+        },
+        None => (text, None),
+    };
+    let text = text.trim();
+    // split_once only splits at the first ':', so times like 12:00:00 in the range are left alone
+    let (name, kind) = text.split_once(':')
+        .ok_or_else(|| invalid_input(format!("Schema fields look like name:type, not '{}'", text)))?;
+    let (kind, range) = match kind.split_once('(') {
+        Some((kind, range)) => {
+            let range = range.trim().strip_suffix(')')
+                .ok_or_else(|| invalid_input(format!("Missing ) after the range in '{}'", text)))?;
+            (kind, Some(range))
+        },
+        None => (kind, None),
+    };
+    let mut field = Field::new(name.trim(), parse_data_type(kind)?);
+    if let Some(range) = range {
+        // .. rather than - so negative numbers and dates don't get split in the wrong place
+        let (min, max) = range.split_once("..")
+            .ok_or_else(|| invalid_input(format!("Ranges look like (min..max), not ({})", range)))?;
+        if !field.data_type.has_range() {
+            return Err(invalid_input(format!("Field '{}' is a {}, which has no range", field.name, field.data_type.name())));
+        }
+        field.min = parse_bound(min)?;
+        field.max = parse_bound(max)?;
+    }
+    field.correlated = correlated;
+    Ok(field)
+}
+
+/// Checks the fields can all be generated and written - Options::validate calls this too,
+/// so schemas built in code get the same checks as parsed ones
+pub fn validate_fields(fields: &[Field]) -> error::Result<()> {
+    // A HashSet is a hash table of keys, handy for spotting repeats without a nested loop
+    let mut seen = HashSet::new();
+    for field in fields {
+        // Names go unquoted into CSV headers, so they follow the same rules as category labels
+        if !is_valid_label(&field.name) {
+            return Err(invalid_input(format!(
+                "Field name '{}' can only use letters, digits, _ and -", field.name
+            )));
+        }
+        if !seen.insert(field.name.as_str()) {
+            return Err(invalid_input(format!("Field '{}' appears more than once", field.name)));
+        }
+        validate_range(field.data_type, field.min, field.max)
+            .map_err(|e| e.context(&format!("Field '{}'", field.name)))?;
+        if let Some((partner, r)) = &field.correlated {
+            validate_correlation(field, partner, *r, fields)?;
+        }
+    }
+    Ok(())
+}
+
+// A record is made left to right, so the partner has to come first to have a value to follow,
+// and both need to be numbers for "bigger" to mean anything
+fn validate_correlation(field: &Field, partner: &str, r: f64, fields: &[Field]) -> error::Result<()> {
+    let is_number = |data_type: DataType| data_type.is_integer() || data_type.has_decimals();
+    let earlier = fields.iter().take_while(|other| other.name != field.name).find(|other| other.name == partner);
+    let partner = earlier.ok_or_else(|| invalid_input(format!(
+        "Field '{}' follows '{}', which has to be a field before it", field.name, partner
+    )))?;
+    if !is_number(field.data_type) || !is_number(partner.data_type) {
+        return Err(invalid_input(format!("Only number fields can be correlated, not '{}' and '{}'", partner.name, field.name)));
+    }
+    if !(-1.0..=1.0).contains(&r) {
+        return Err(invalid_input(format!("Correlation for '{}' must be between -1 and 1", field.name)));
+    }
+    Ok(())
+}
+
+/// Settings for generating and writing each field: a copy of the file's options with the
+/// field's own type and range swapped in, so gen_value and the writer need nothing new
+pub(crate) fn field_options(options: &Options) -> Vec<Options> {
+    options.schema.iter().map(|field| Options {
+        data_type: field.data_type,
+        min: field.min,
+        max: field.max,
+        distribution: Distribution::Uniform,
+        schema: Vec::new(),
+        ..options.clone()
+    }).collect()
+}
+
+/// The seed for one field's own RNG: the file's seed mixed with the field's name
+/// Going by the name rather than the position means a field keeps its values when others
+/// are added, removed, moved or changed - only renaming it gives it new ones
+pub fn field_seed(seed: u64, name: &str) -> u64 {
+    // FNV-1a, the classic tiny hash from C code: xor a byte in, multiply by a prime, repeat
+    let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+    for byte in seed.to_le_bytes().iter().chain(name.as_bytes()) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01B3); // wrapping_mul overflows like unsigned maths in C
+    }
+    hash
+}
+
+/// The values of `total` fields, one record after another - the writer splits them back into records
+/// Each field draws from its own RNG, so how many random numbers one field uses (a longer string,
+/// a wider range) can't shift the values of the fields next to it
+pub(crate) fn record_values<'a>(rng: &'a mut StdRng, options: &Options, total: usize) -> impl Iterator<Item = Value> + 'a {
+    let fields = field_options(options);
+    // Without a seed there's nothing to keep stable, so the main RNG hands out the seeds
+    let mut rngs: Vec<StdRng> = options.schema.iter().map(|field| {
+        let seed = options.seed.map_or_else(|| rng.gen(), |seed| field_seed(seed, &field.name));
+        StdRng::seed_from_u64(seed)
+    }).collect();
+    // Which column each correlated field follows - validate_fields made sure it's an earlier one
+    let partners: Vec<Option<(usize, f64)>> = options.schema.iter().map(|field| {
+        field.correlated.as_ref().and_then(|(partner, r)| {
+            options.schema.iter().position(|other| &other.name == partner).map(|column| (column, *r))
+        })
+    }).collect();
+    let followed: Vec<bool> = (0..fields.len()).map(|column| partners.iter().flatten().any(|&(partner, _)| partner == column)).collect();
+    // The current record's values, for just the columns something follows
+    let mut record = vec![0.0; fields.len()];
+    // move hands the Vecs over to the closure, so they live as long as the iterator does
+    (0..total).map(move |i| {
+        let column = i % fields.len();
+        let value = match partners[column] {
+            Some((partner, r)) => {
+                let partner_r = partners[partner].map(|(_, r)| r);
+                correlated_value(&mut rngs[column], &fields[column], (&fields[partner], record[partner], partner_r), r)
+            },
+            None => gen_value(&mut rngs[column], &fields[column]),
+        };
+        if followed[column] {
+            record[column] = value.as_f64();
+        }
+        value
+    })
+}
+
+// Simple linear mixing: the partner's value and some fresh noise, each scaled to a mean of 0 and
+// a variance of 1, are added with weights r and √(1 - r²), which gives a correlation of r.
+// The mix is then stretched back over the field's own range - exactly to its ends, so nothing
+// gets clamped and bent out of line. `partner` is its options, its value in this record and
+// the r it follows a field of its own with, if it does
+fn correlated_value(rng: &mut StdRng, field: &Options, partner: (&Options, f64, Option<f64>), r: f64) -> Value {
+    let (partner, partner_value, partner_r) = partner;
+    let root3 = 3f64.sqrt();
+    // Halving each end first, like default_normal, so the widest ranges can't overflow
+    let middle = |options: &Options| options.min / 2.0 + options.max / 2.0;
+    let half = |options: &Options| options.max / 2.0 - options.min / 2.0;
+    let standard = (partner_value - middle(partner)) / half(partner) * reach(partner_r);
+    let mixed = r * standard + (1.0 - r * r).sqrt() * rng.gen_range(-root3..=root3);
+    sample_value(rng, field, middle(field) + mixed / reach(Some(r)) * half(field))
+}
+
+// How far from the middle a mix with weight r can get, in standard deviations - the half-width
+// of the range it's stretched over. A plain uniform value (no r) reaches √3
+fn reach(r: Option<f64>) -> f64 {
+    let r = r.unwrap_or(1.0);
+    (r.abs() + (1.0 - r * r).sqrt()) * 3f64.sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::make_rng;
+    use crate::generator::tests::options;
+
+    #[test]
+    fn fields_keep_their_values_when_the_schema_changes() {
+        let mut before = options(DataType::Integer, 0.0, 100.0, Distribution::Uniform);
+        before.seed = Some(42);
+        before.schema = parse_schema("id:int, name:string(4..10), score:double").unwrap();
+        // Longer names use more random numbers, and a new field goes in at the front
+        let mut after = before.clone();
+        after.schema = parse_schema("extra:uuid, id:int, name:string(20..30), score:double").unwrap();
+        let before: Vec<Value> = record_values(&mut make_rng(before.seed), &before, 30).collect();
+        let after: Vec<Value> = record_values(&mut make_rng(after.seed), &after, 40).collect();
+        let column = |values: &[Value], at: usize, width: usize| -> Vec<Value> {
+            values.iter().skip(at).step_by(width).cloned().collect()
+        };
+        assert_eq!(column(&before, 0, 3), column(&after, 1, 4));
+        assert_eq!(column(&before, 2, 3), column(&after, 3, 4));
+        assert_ne!(column(&before, 1, 3), column(&after, 2, 4));
+        assert_ne!(field_seed(42, "id"), field_seed(42, "score"));
+        assert_ne!(field_seed(42, "id"), field_seed(43, "id"));
+    }
+
+    #[test]
+    fn correlated_fields_follow_their_partner() {
+        let pearson = |xs: &[f64], ys: &[f64]| {
+            let n = xs.len() as f64;
+            let (mx, my) = (xs.iter().sum::<f64>() / n, ys.iter().sum::<f64>() / n);
+            let cov: f64 = xs.iter().zip(ys).map(|(x, y)| (x - mx) * (y - my)).sum();
+            let vx: f64 = xs.iter().map(|x| (x - mx).powi(2)).sum();
+            let vy: f64 = ys.iter().map(|y| (y - my).powi(2)).sum();
+            cov / (vx * vy).sqrt()
+        };
+        let mut options = options(DataType::Integer, 0.0, 100.0, Distribution::Uniform);
+        options.schema = parse_schema("height:double(150..200), id:int, weight:double(50..120) ~ height 0.8, drag:int(0..9)~weight -0.5").unwrap();
+        let values: Vec<f64> = record_values(&mut make_rng(Some(3)), &options, 4 * 20_000).map(|value| value.as_f64()).collect();
+        let column = |at: usize| -> Vec<f64> { values.iter().skip(at).step_by(4).copied().collect() };
+        let (height, weight) = (column(0), column(2));
+        assert!((pearson(&height, &weight) - 0.8).abs() < 0.02, "{}", pearson(&height, &weight));
+        assert!(weight.iter().all(|w| (50.0..=120.0).contains(w)));
+        // Integers 0 to 9 round a fair bit of the relationship away
+        assert!(pearson(&weight, &column(3)) < -0.45, "{}", pearson(&weight, &column(3)));
+        assert!(pearson(&height, &column(1)).abs() < 0.05);
+
+        assert!(parse_schema("a:int ~ b 0.5, b:int").is_err());
+        assert!(parse_schema("a:int, b:string ~ a 0.5").is_err());
+        assert!(parse_schema("a:int, b:int ~ a 2").is_err());
+        assert!(parse_schema("a:int, b:int ~ a").is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! Time series: one reading per time step, following a trend and a seasonal wave with noise on top.
+//!
+//! Each row is a timestamp and a value, where the value at step `i` is
+//!
+//! ```text
+//! base + trend * i + amplitude * sin(2π * i / period) + noise
+//! ```
+//!
+//! and the noise is drawn from a normal distribution centred on 0. That's the shape forecasting
+//! and anomaly-detection code expects to pull apart again, so it makes good test input.
+//! The rows are written as records with a `time` and a `value` field, so every format that
+//! takes a schema (CSV, JSON lines, Parquet, SQLite, fixed-width) works for them too.
+
+use std::f64::consts::TAU;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand_distr::Normal;
+
+use crate::error;
+use crate::generator::{sample_value, DataType, Value};
+use crate::schema::{field_options, Field};
+use crate::{invalid_input, Options};
+
+/// First timestamp when none is given: 2024-01-01T00:00:00Z
+pub const DEFAULT_START: i64 = 1_704_067_200;
+
+/// Seconds between readings when none is given - hourly
+pub const DEFAULT_INTERVAL: i64 = 3600;
+
+/// Steps in one seasonal cycle when none is given - a day of hourly readings
+pub const DEFAULT_PERIOD: f64 = 24.0;
+
+/// The shape of a time series - the number of readings is Options::count
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeSeries {
+    pub start: i64,     // Timestamp of the first reading, in seconds since 1970
+    pub interval: i64,  // Seconds from one reading to the next
+    pub base: f64,      // Value at the first step, before the wave and noise
+    pub trend: f64,     // Added to the value every step
+    pub amplitude: f64, // Height of the seasonal wave above (and depth below) the trend
+    pub period: f64,    // Steps in one full wave, e.g. 24 for a daily cycle of hourly readings
+    pub noise: f64,     // Standard deviation of the random part (0 = a perfectly smooth curve)
+}
+
+// Hourly readings from the start of 2024, with nothing but noise around 0 until asked for more
+impl Default for TimeSeries {
+    fn default() -> Self {
+        TimeSeries {
+            start: DEFAULT_START,
+            interval: DEFAULT_INTERVAL,
+            base: 0.0,
+            trend: 0.0,
+            amplitude: 0.0,
+            period: DEFAULT_PERIOD,
+            noise: 1.0,
+        }
+    }
+}
+
+impl TimeSeries {
+    /// The middle of the value at step `i`: trend plus wave, without any noise
+    pub fn expected(&self, i: u64) -> f64 {
+        let i = i as f64;
+        self.base + self.trend * i + self.amplitude * (TAU * i / self.period).sin()
+    }
+
+    /// The records a series is written as: the time of each reading, then its value
+    /// The value's range clips it, like the range of any other distribution
+    pub fn fields(&self, value_type: DataType, min: f64, max: f64, count: u32) -> Vec<Field> {
+        let end = self.start + count.saturating_sub(1) as i64 * self.interval;
+        let mut time = Field::new("time", DataType::Timestamp);
+        // The range has to be wider than a point, even when there's only one reading
+        (time.min, time.max) = (self.start as f64, end.max(self.start + 1) as f64);
+        let mut value = Field::new("value", value_type);
+        (value.min, value.max) = (min, max);
+        vec![time, value]
+    }
+
+    /// A range that holds nearly every value of `count` readings: the trend from end to end,
+    /// the wave on top and four standard deviations of noise either side
+    pub fn value_range(&self, count: u32) -> (f64, f64) {
+        let end = self.base + self.trend * count.saturating_sub(1) as f64;
+        let spread = self.amplitude.abs() + 4.0 * self.noise;
+        let (low, high) = (self.base.min(end) - spread, self.base.max(end) + spread);
+        // A flat line with no noise still needs a top above its bottom
+        (low, high.max(low + 1.0))
+    }
+}
+
+/// Checks the series can be generated - Options::validate checks how it fits with everything else
+pub fn validate_series(series: TimeSeries) -> error::Result<()> {
+    if series.interval <= 0 {
+        return Err(invalid_input("The interval between readings must be at least a second"));
+    }
+    let numbers = [series.base, series.trend, series.amplitude, series.period, series.noise];
+    if !numbers.iter().all(|number| number.is_finite()) {
+        return Err(invalid_input("Time series settings must be finite numbers"));
+    }
+    if series.period <= 0.0 {
+        return Err(invalid_input("The seasonal period must be a positive number of steps"));
+    }
+    if series.noise < 0.0 {
+        return Err(invalid_input("Noise must be zero or more"));
+    }
+    Ok(())
+}
+
+/// Each reading as two values in a row: its timestamp, then the value itself
+pub(crate) fn series_values<'a>(rng: &'a mut StdRng, options: &'a Options, series: TimeSeries) -> impl Iterator<Item = Value> + 'a {
+    // Options::validate made sure the second field is the value, and that the noise is fine for Normal
+    let value_options = field_options(options).swap_remove(1);
+    let noise = Normal::new(0.0, series.noise).unwrap();
+    (0..options.count as u64).flat_map(move |i| {
+        let time = Value::Timestamp(series.start + i as i64 * series.interval);
+        // No RNG call at all for a smooth curve
+        let sample = series.expected(i) + if series.noise > 0.0 { rng.sample(noise) } else { 0.0 };
+        [time, sample_value(rng, &value_options, sample)]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::make_rng;
+    use crate::generator::tests::options;
+    use crate::{Distribution, OutputFormat};
+
+    #[test]
+    fn readings_follow_the_trend_and_wave() {
+        let series = TimeSeries { start: DEFAULT_START, interval: 60, base: 10.0, trend: 0.5, amplitude: 3.0, period: 4.0, noise: 0.0 };
+        let (min, max) = series.value_range(100);
+        assert_eq!((min, max), (7.0, 62.5));
+        let mut options = options(DataType::Double, min, max, Distribution::Uniform);
+        options.count = 100;
+        options.format = OutputFormat::Csv;
+        options.schema = series.fields(DataType::Double, min, max, 100);
+        options.time_series = Some(series);
+        options.validate().unwrap();
+        let values: Vec<Value> = series_values(&mut make_rng(Some(1)), &options, series).collect();
+        assert_eq!(values.len(), 200);
+        assert_eq!(values[2], Value::Timestamp(DEFAULT_START + 60));
+        // A quarter of the way round the wave it's at its peak
+        assert_eq!(values[3], Value::Double(10.5 + 3.0));
+        assert_eq!(values[5], Value::Double(11.0));
+
+        // With noise the values scatter around the curve, but not far
+        let noisy = TimeSeries { noise: 1.0, ..series };
+        let noisy: Vec<Value> = series_values(&mut make_rng(Some(1)), &options, noisy).collect();
+        for (i, pair) in noisy.chunks(2).enumerate() {
+            assert!((pair[1].as_f64() - series.expected(i as u64)).abs() < 6.0);
+        }
+        assert!(validate_series(TimeSeries { period: 0.0, ..series }).is_err());
+    }
+}
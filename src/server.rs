@@ -0,0 +1,255 @@
+//! The `serve` subcommand: a small HTTP server that generates data on demand.
+//!
+//! `GET /generate?type=int&count=100` streams freshly generated values back as the response
+//! body, so web tests can fetch data without touching the filesystem. The query takes the same
+//! settings as the command-line flags, minus the dashes (`min=0&max=9&format=csv&columns=3`).
+//! Each connection is handled on its own thread, and the body is written as it's generated,
+//! with the end marked by closing the connection - the oldest and simplest way HTTP has of
+//! saying "that's everything" when the length isn't known up front.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use log::{info, warn};
+
+use crate::error;
+use crate::writer::write_to;
+use crate::{invalid_input, Options, OutputFormat};
+
+/// Port used when --port isn't given
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// Where the data is served from
+pub const GENERATE_PATH: &str = "/generate";
+
+// Query settings that turn into flags. Anything that names a file, reads one or changes how
+// the server runs (output, config, threads, ...) is left out, since a web request shouldn't
+// be able to touch the disk
+const QUERY_FLAGS: [&str; 71] = [
+    "type", "count", "min", "max", "exclude", "length", "dist", "mean", "std-dev", "lambda", "exponent", "start", "step", "jitter",
+    "categories", "cidr",
+    "order", "disorder", "unique", "permutation", "duplicates", "outliers", "outlier-scale", "missing", "missing-as", "format", "columns", "header", "no-header",
+    "comment-header", "header-template", "delimiter", "prefix", "suffix", "separator",
+    "width", "fill", "align", "endianness", "language", "schema", "graph", "edges", "density", "weighted", "time-series", "from",
+    "mixture", "labels", "points", "dimensions", "radius",
+    "interval", "base", "trend", "amplitude", "period", "noise", "seed", "precision",
+    "scientific", "number-style", "int-format", "decimal", "thousands", "time-format",
+    "true-probability", "bool-format", "gzip", "framed", "rate",
+];
+
+// The settings that are on/off switches rather than taking a value
+const QUERY_SWITCHES: [&str; 10] = [
+    "unique", "header", "no-header", "comment-header", "weighted", "time-series", "labels", "scientific", "gzip", "framed",
+];
+
+// Longest request line or header we'll read - anything bigger isn't a real client
+const MAX_LINE: usize = 8 * 1024;
+
+/// Answers requests until the program is stopped. `options_for` turns a request's flags
+/// (see query_args) into Options, so the server takes the same defaults as the command line
+pub fn serve(listener: TcpListener, options_for: impl Fn(&[String]) -> error::Result<Options> + Sync) -> error::Result<()> {
+    // A scope lets every connection's thread borrow options_for, like passing a pointer to
+    // pthread_create - Rust just checks the threads can't outlive what they borrow
+    thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                // One client failing to connect is no reason to stop serving everyone else
+                Err(e) => {
+                    warn!("Connection failed: {}", e);
+                    continue;
+                },
+            };
+            let options_for = &options_for;
+            scope.spawn(move || {
+                if let Err(e) = handle_connection(stream, options_for) {
+                    // Clients hanging up part way through a download is normal, not an error
+                    if !matches!(e.io_kind(), Some(io::ErrorKind::BrokenPipe | io::ErrorKind::ConnectionReset)) {
+                        warn!("Request failed: {}", e);
+                    }
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+// Reads one request and writes one response, then closes the connection
+fn handle_connection(stream: TcpStream, options_for: &impl Fn(&[String]) -> error::Result<Options>) -> error::Result<()> {
+    // try_clone() gives a second handle on the same socket, so one can read while the other writes
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let request = read_line(&mut reader)?;
+    // The headers don't change anything here, but they have to be read so the client's done sending
+    while !read_line(&mut reader)?.is_empty() {}
+
+    let mut stream = stream;
+    // "GET /generate?type=int HTTP/1.1" - the method, the target and the protocol version
+    let mut parts = request.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    // Through the logger rather than println!, so -q keeps a busy server quiet
+    info!("{} {}", method, target);
+    if method != "GET" {
+        return respond(&mut stream, "405 Method Not Allowed", "Only GET requests are served\n");
+    }
+    if path != GENERATE_PATH {
+        return respond(&mut stream, "404 Not Found", &format!("Try {}?type=int&count=100\n", GENERATE_PATH));
+    }
+    let options = match query_args(query).and_then(|args| options_for(&args)) {
+        Ok(options) => options,
+        Err(e) => return respond(&mut stream, "400 Bad Request", &format!("{}\n", e)),
+    };
+    write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nConnection: close\r\n", content_type(options.format))?;
+    if options.compress {
+        // Tells browsers and HTTP libraries to unpack it themselves
+        write!(stream, "Content-Encoding: gzip\r\n")?;
+    }
+    write!(stream, "\r\n")?;
+    // The socket's a writer like any file, so the data goes straight out as it's made
+    write_to(stream, &options)?;
+    Ok(())
+}
+
+// One line of the request, without its \r\n
+fn read_line(reader: &mut impl BufRead) -> error::Result<String> {
+    let mut line = String::new();
+    // take() stops a client that never sends a newline from filling up memory
+    Read::take(reader, MAX_LINE as u64).read_line(&mut line)?;
+    if !line.ends_with('\n') {
+        return Err(invalid_input("Request line too long or cut off"));
+    }
+    Ok(line.trim_end().to_string())
+}
+
+// Error responses are short plain text, with a length so the client knows it's complete
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> error::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body,
+    )?;
+    Ok(())
+}
+
+fn content_type(format: OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Text | OutputFormat::Matrix | OutputFormat::FixedWidth | OutputFormat::Code(_) => "text/plain; charset=utf-8",
+        OutputFormat::Csv => "text/csv; charset=utf-8",
+        OutputFormat::Json => "application/json",
+        OutputFormat::JsonLines => "application/x-ndjson",
+        OutputFormat::Binary | OutputFormat::Parquet | OutputFormat::Sqlite => "application/octet-stream",
+    }
+}
+
+/// Turns a query string like `type=int&count=100&unique` into the matching flags
+/// (`--type int --count 100 --unique`), plus `--output -` so nothing goes to disk
+pub fn query_args(query: &str) -> error::Result<Vec<String>> {
+    let mut args = Vec::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let (name, value) = (percent_decode(name)?, percent_decode(value)?);
+        if !QUERY_FLAGS.contains(&name.as_str()) {
+            return Err(invalid_input(format!("Unknown setting: {}", name)));
+        }
+        if QUERY_SWITCHES.contains(&name.as_str()) {
+            // unique, unique=1 and unique=true all switch it on; unique=false leaves it off
+            match value.as_str() {
+                "" | "1" | "true" | "yes" => args.push(format!("--{}", name)),
+                "0" | "false" | "no" => {},
+                _ => return Err(invalid_input(format!("{} is on or off, not {}", name, value))),
+            }
+        } else {
+            args.push(format!("--{}", name));
+            args.push(value);
+        }
+    }
+    args.push("--output".to_string());
+    args.push("-".to_string());
+    Ok(args)
+}
+
+// URLs can't hold spaces or some punctuation, so they're sent as %XX hex bytes (and
+// forms send spaces as +) - a schema like "id:int, ok:bool" arrives as id%3Aint%2C+ok%3Abool
+fn percent_decode(text: &str) -> error::Result<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = rest.get(..2).and_then(|hex| std::str::from_utf8(hex).ok());
+                let decoded = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| invalid_input(format!("Bad % escape in {}", text)))?;
+                bytes.push(decoded);
+                rest = &rest[2..];
+            },
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid_input(format!("{} isn't valid UTF-8 once decoded", text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::tests::options;
+    use crate::generator::{make_rng, value_source, DataType, Value};
+    use crate::Distribution;
+
+    #[test]
+    fn query_becomes_flags() {
+        let args = query_args("type=int&count=100&schema=id%3Aint%2C+ok%3Abool&unique&header=false").unwrap();
+        let expected = ["--type", "int", "--count", "100", "--schema", "id:int, ok:bool", "--unique", "--output", "-"];
+        assert_eq!(args, expected);
+        // Nothing that could write or read a file gets through
+        assert!(query_args("type=int&output=%2Fetc%2Fpasswd").is_err());
+        assert!(query_args("count=%zz").is_err());
+    }
+
+    // One request against a real socket, answered by handle_connection on the other end
+    fn fetch(target: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let target = target.to_string();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+        let (stream, _) = listener.accept().unwrap();
+        // Stands in for the command line's parsing: the count from the query, everything else fixed
+        let options_for = |args: &[String]| -> error::Result<Options> {
+            let mut options = options(DataType::Integer, 0.0, 9.0, Distribution::Uniform);
+            options.count = args[1].parse().map_err(|_| invalid_input("bad count"))?;
+            options.seed = Some(7);
+            Ok(options)
+        };
+        handle_connection(stream, &options_for).unwrap();
+        client.join().unwrap()
+    }
+
+    #[test]
+    fn generate_streams_the_values() {
+        let response = fetch("/generate?count=5");
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(head.contains("Content-Type: text/plain"));
+        let mut options = options(DataType::Integer, 0.0, 9.0, Distribution::Uniform);
+        options.count = 5;
+        options.seed = Some(7);
+        let values: Vec<String> = value_source(&mut make_rng(options.seed), &options).unwrap()
+            .map(|value| match value {
+                Value::Int(num) => num.to_string(),
+                other => panic!("expected an int, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(body, format!("Count: 5\nSeed: 7\n{}\n", values.join("\n")));
+
+        assert!(fetch("/generate?count=lots").starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(fetch("/other").starts_with("HTTP/1.1 404 Not Found"));
+    }
+}
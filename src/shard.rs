@@ -0,0 +1,123 @@
+//! Splitting one dataset across several numbered files, so it can be processed in parallel.
+//!
+//! The values come from a single generator run and are dealt out in order, so the shards
+//! joined back together are exactly the file you'd get without sharding (minus the repeated
+//! headers). Each shard is a complete file of its own, and `<name>_manifest.txt` lists them
+//! all with their row counts.
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::error;
+use crate::{invalid_data, Options};
+
+/// Most shards in one dataset - the part number is padded to four digits so they sort by name
+pub const MAX_SHARDS: u32 = 9999;
+
+/// Name of one shard: data.txt -> data_part0001.txt (parts count from 1)
+pub fn shard_name(filename: &str, part: u32) -> String {
+    with_suffix(filename, &format!("_part{:04}", part), None)
+}
+
+/// Name of the file listing every shard: data.txt -> data_manifest.txt
+/// It's always plain text, even when the shards themselves are binary or gzipped
+pub fn shard_manifest_name(filename: &str) -> String {
+    with_suffix(filename, "_manifest", Some(".txt"))
+}
+
+/// Rows in each shard - as even as possible, with any leftovers going one each to the first shards
+pub fn shard_rows(count: u32, shards: u32) -> impl Iterator<Item = u32> {
+    (0..shards).map(move |i| count / shards + (i < count % shards) as u32)
+}
+
+/// Every file a run will create: just the one, or each shard plus the manifest
+pub fn output_files(filename: &str, shards: u32) -> Vec<String> {
+    if shards <= 1 {
+        return vec![filename.to_string()];
+    }
+    let mut files: Vec<String> = (1..=shards).map(|part| shard_name(filename, part)).collect();
+    files.push(shard_manifest_name(filename));
+    files
+}
+
+/// Writes the manifest once every shard is done, so a half-made set never has one
+pub(crate) fn write_shard_manifest(options: &Options) -> error::Result<()> {
+    let mut text = format!("Dataset: {}\n", file_name(&options.filename));
+    if options.schema.is_empty() {
+        text += &format!("Type: {}\n", options.data_type.name());
+    }
+    text += &format!("Format: {}\n", options.format.name());
+    text += &format!("Rows: {}\n", options.count);
+    if let Some(seed) = options.seed {
+        text += &format!("Seed: {}\n", seed);
+    }
+    text += &format!("Shards: {}\n", options.shards);
+    // Then one line per shard, rows first so names with spaces in them still read back
+    // Bare names, since the shards always sit next to the manifest
+    let parts = (1..=options.shards).zip(shard_rows(options.count, options.shards));
+    for (part, rows) in parts {
+        text += &format!("{} {}\n", rows, file_name(&shard_name(&options.filename, part)));
+    }
+    Ok(fs::write(shard_manifest_name(&options.filename), text)?)
+}
+
+/// The shards a manifest lists, in order, as paths next to the manifest - or None if the file
+/// isn't a shard manifest at all
+pub fn read_shard_manifest(path: &str) -> error::Result<Option<Vec<String>>> {
+    // Only the start is needed to tell, and the file could be a huge data file
+    let mut start = [0; 8];
+    let cant_read = |e: io::Error| io::Error::new(e.kind(), format!("Can't read {}: {}", path, e));
+    let read = File::open(path).and_then(|mut file| file.read(&mut start)).map_err(cant_read)?;
+    if !start[..read].starts_with(b"Dataset:") {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(path).map_err(cant_read)?;
+    // The shard lines are everything after Shards:, each "<rows> <name>"
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    let mut files = Vec::new();
+    for line in text.lines().skip_while(|line| !line.starts_with("Shards:")).skip(1) {
+        let (_, name) = line.split_once(' ').ok_or_else(|| invalid_data(format!("{}: malformed shard line: {}", path, line)))?;
+        files.push(dir.join(name).to_string_lossy().into_owned());
+    }
+    if files.is_empty() {
+        return Err(invalid_data(format!("{} doesn't list any shards", path)));
+    }
+    Ok(Some(files))
+}
+
+// Puts the suffix before the first dot of the file's name, so the extension (even a double
+// one like .txt.gz) stays on the end; `extension` replaces the old one if given
+fn with_suffix(filename: &str, suffix: &str, extension: Option<&str>) -> String {
+    let path = Path::new(filename);
+    let name = file_name(filename);
+    // Skipping the first character keeps a hidden file like .data as one name
+    let split = name.char_indices().skip(1).find(|&(_, c)| c == '.').map_or(name.len(), |(i, _)| i);
+    let (stem, old_extension) = name.split_at(split);
+    let new_name = format!("{}{}{}", stem, suffix, extension.unwrap_or(old_extension));
+    path.with_file_name(new_name).to_string_lossy().into_owned()
+}
+
+fn file_name(filename: &str) -> String {
+    Path::new(filename).file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_keep_the_extension() {
+        assert_eq!(shard_name("data.txt", 1), "data_part0001.txt");
+        assert_eq!(shard_name("out/data.txt.gz", 12), "out/data_part0012.txt.gz");
+        assert_eq!(shard_name("values", 3), "values_part0003");
+        assert_eq!(shard_manifest_name("out/data.bin"), "out/data_manifest.txt");
+    }
+
+    #[test]
+    fn rows_add_up() {
+        assert_eq!(shard_rows(10, 4).collect::<Vec<_>>(), [3, 3, 2, 2]);
+        assert_eq!(shard_rows(8, 4).collect::<Vec<_>>(), [2, 2, 2, 2]);
+        assert_eq!(shard_rows(1_000_003, 7).sum::<u32>(), 1_000_003);
+    }
+}
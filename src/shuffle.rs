@@ -0,0 +1,325 @@
+//! Putting the rows of a file that already exists into a random order, like `shuf` but keeping
+//! the file's header on top.
+//!
+//! Text, CSV, matrix, fixed-width and JSON lines files are shuffled a line at a time, and binary
+//! files a value at a time. The header (`Count:`, a CSV's column names, a graph's `#` lines...)
+//! stays at the top, and the `#` lines --append-stats leaves at the bottom stay there. The
+//! `Seed:` line is dropped, since the seed can't make the rows in their new order.
+//!
+//! A file that fits in SHUFFLE_MEMORY is shuffled in memory. A bigger one is dealt out at random
+//! into temporary bucket files that do fit, then each bucket is shuffled on its own and they're
+//! joined back up. Every order still comes out equally likely, and memory use stays about the
+//! same however big the file gets.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::atomic::temp_name;
+use crate::binary::{is_binary, read_header, BINARY_HEADER_LEN};
+use crate::error;
+use crate::generator::make_rng;
+use crate::reader::is_csv_header;
+use crate::{invalid_data, unsupported_format};
+
+/// Bytes of rows held in memory at once - bigger files go through bucket files on disk
+pub const SHUFFLE_MEMORY: u64 = 256 * 1024 * 1024;
+
+// Text header lines, as written by the text and matrix formats
+const HEADER_PREFIXES: [&str; 4] = ["Count:", "Matrix:", "Seed:", "Type:"];
+
+/// A header line split into the "# " that --comment-header puts in front (or nothing) and
+/// the line itself, so merging and splitting can find the counts either way
+pub(crate) fn split_comment(line: &str) -> (&str, &str) {
+    match line.strip_prefix("# ") {
+        Some(bare) => ("# ", bare),
+        None => ("", line),
+    }
+}
+
+// How a file splits into the rows that get moved around
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Rows {
+    Lines,        // Up to and including each newline
+    Fixed(usize), // Every value the same number of bytes, like in a binary file
+}
+
+// Hands out a file's rows one at a time, starting with any it had to read early
+// to find where the header ended - merging reads its files the same way
+pub(crate) struct RowReader {
+    reader: BufReader<File>,
+    pub(crate) rows: Rows,
+    pending: Vec<Vec<u8>>, // Read already, in reverse order so pop() takes the first
+    trailer: Vec<u8>,      // # lines after the header, which go back at the end
+}
+
+impl RowReader {
+    pub(crate) fn next_row(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            let row = match self.pending.pop() {
+                Some(row) => row,
+                None => match read_row(&mut self.reader, self.rows)? {
+                    Some(row) => row,
+                    None => return Ok(None),
+                },
+            };
+            if let Rows::Lines = self.rows {
+                // Blank lines aren't rows, and comments stay where they were
+                if row.trim_ascii().is_empty() {
+                    continue;
+                }
+                if row.starts_with(b"#") {
+                    self.trailer.extend_from_slice(&row);
+                    continue;
+                }
+            }
+            return Ok(Some(row));
+        }
+    }
+}
+
+// One row, or None at the end of the file
+fn read_row(reader: &mut impl BufRead, rows: Rows) -> io::Result<Option<Vec<u8>>> {
+    let mut row = Vec::new();
+    match rows {
+        Rows::Lines => {
+            if reader.read_until(b'\n', &mut row)? == 0 {
+                return Ok(None);
+            }
+            // The last line might not end in a newline, but once it's moved somewhere else it needs one
+            if !row.ends_with(b"\n") {
+                row.push(b'\n');
+            }
+        },
+        Rows::Fixed(size) => {
+            if reader.fill_buf()?.is_empty() {
+                return Ok(None);
+            }
+            // The length was checked against the header, so a value can't be cut short
+            row.resize(size, 0);
+            reader.read_exact(&mut row)?;
+        },
+    }
+    Ok(Some(row))
+}
+
+/// Writes the rows of `input` to `output` in a random order, with the header kept on top,
+/// and hands back how many rows there were
+/// The output is written next to where it's going and renamed at the end, so a file can be
+/// shuffled onto itself, and a failure halfway through leaves whatever was there alone
+pub fn shuffle_file(input: &str, output: &str, seed: Option<u64>) -> error::Result<u64> {
+    shuffle_in(input, output, seed, SHUFFLE_MEMORY)
+}
+
+// shuffle_file with the memory limit passed in, so tests can make a small file spill to disk
+fn shuffle_in(input: &str, output: &str, seed: Option<u64>, memory: u64) -> error::Result<u64> {
+    let (len, mut header, mut rows) = open_rows(input)?;
+    if rows.rows == Rows::Lines {
+        header = drop_seed(&header);
+    }
+    let rng = &mut make_rng(seed);
+    let temp_name = temp_name(output);
+    // Each bucket ends up around half the limit, which leaves room for an unlucky one
+    let buckets = (len / (memory / 2).max(1)) as usize + 1;
+    let result = (|| {
+        let mut writer = BufWriter::new(File::create(&temp_name)?);
+        writer.write_all(&header)?;
+        let count = if buckets == 1 {
+            shuffle_in_memory(&mut writer, &mut rows, rng)?
+        } else {
+            shuffle_through_buckets(&mut writer, &mut rows, rng, output, buckets)?
+        };
+        writer.write_all(&rows.trailer)?;
+        writer.into_inner().map_err(|e| e.into_error())?;
+        fs::rename(&temp_name, output)?;
+        Ok(count)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_name);
+    }
+    result
+}
+
+// The seed made the rows in their old order, not this one, so its Seed: line would be a lie
+fn drop_seed(header: &[u8]) -> Vec<u8> {
+    header.split_inclusive(|&byte| byte == b'\n')
+        .filter(|line| !split_comment(&String::from_utf8_lossy(line)).1.starts_with("Seed:"))
+        .flatten().copied().collect()
+}
+
+/// Opens a file to read row by row, handing back its length, its header's bytes and its rows
+pub(crate) fn open_rows(input: &str) -> error::Result<(u64, Vec<u8>, RowReader)> {
+    let file = File::open(input).map_err(|e| io::Error::new(e.kind(), format!("Can't read {}: {}", input, e)))?;
+    let len = file.metadata()?.len();
+    let (header, rows) = read_layout(BufReader::new(file), input, len)?;
+    Ok((len, header, rows))
+}
+
+// Works out where the rows start and how they're laid out, returning the header's bytes
+fn read_layout(mut reader: BufReader<File>, input: &str, len: u64) -> error::Result<(Vec<u8>, RowReader)> {
+    let start = reader.fill_buf()?;
+    if start.starts_with(&[0x1f, 0x8b]) {
+        return Err(unsupported_format(format!("{} is gzipped - unpack it with gunzip first", input)));
+    }
+    if start.starts_with(b"PAR1") || start.starts_with(b"SQLite format 3\0") {
+        return Err(unsupported_format(format!("{} is a Parquet file or SQLite database, which can't be shuffled here", input)));
+    }
+    // Binary files are a fixed header and then values that are all the same size
+    if is_binary(start) {
+        let binary = read_header(&start[..start.len().min(BINARY_HEADER_LEN as usize)])?;
+        let size = binary.data_type.size();
+        let expected_len = binary.size() + binary.count as u64 * size as u64;
+        if len != expected_len {
+            return Err(invalid_data(format!("Header says {} values ({} bytes) but file is {} bytes", binary.count, expected_len, len)));
+        }
+        let mut header = vec![0; binary.size() as usize];
+        reader.read_exact(&mut header)?;
+        return Ok((header, RowReader { reader, rows: Rows::Fixed(size), pending: Vec::new(), trailer: Vec::new() }));
+    }
+    // Header lines come first, along with any # lines above the values
+    let mut header = Vec::new();
+    let mut first = None;
+    while let Some(line) = read_row(&mut reader, Rows::Lines)? {
+        let text = String::from_utf8_lossy(&line);
+        if text.trim() == "{" {
+            return Err(unsupported_format(format!("{} is a JSON file, which is one object rather than rows - use JSON lines instead", input)));
+        }
+        if HEADER_PREFIXES.iter().any(|prefix| text.starts_with(prefix)) || text.starts_with('#') || text.trim().is_empty() {
+            header.extend_from_slice(&line);
+        } else {
+            first = Some(line);
+            break;
+        }
+    }
+    let mut pending = Vec::new();
+    if let Some(first) = first {
+        // Without a header line of our own it might be a CSV, which can have a row of names
+        // on top - that needs the row after it to tell, the same way the reader does
+        let second = if header.is_empty() { read_row(&mut reader, Rows::Lines)? } else { None };
+        let names = second.as_ref().is_some_and(|second| {
+            is_csv_header(String::from_utf8_lossy(&first).trim(), [String::from_utf8_lossy(second).trim()].into_iter())
+        });
+        pending.extend(second);
+        if names {
+            header.extend_from_slice(&first);
+        } else {
+            pending.push(first);
+        }
+    }
+    Ok((header, RowReader { reader, rows: Rows::Lines, pending, trailer: Vec::new() }))
+}
+
+// Small enough to hold, so it's one shuffle() of every row
+fn shuffle_in_memory(writer: &mut impl Write, rows: &mut RowReader, rng: &mut StdRng) -> io::Result<u64> {
+    let mut all = Vec::new();
+    while let Some(row) = rows.next_row()? {
+        all.push(row);
+    }
+    all.shuffle(rng);
+    for row in &all {
+        writer.write_all(row)?;
+    }
+    Ok(all.len() as u64)
+}
+
+// Deals each row into a random bucket file, then shuffles and writes out one bucket at a time
+// Which bucket a row lands in is as random as where it lands inside it, so any row can still
+// end up anywhere
+fn shuffle_through_buckets(
+    writer: &mut impl Write,
+    rows: &mut RowReader,
+    rng: &mut StdRng,
+    output: &str,
+    buckets: usize,
+) -> io::Result<u64> {
+    let names: Vec<String> = (0..buckets).map(|i| format!("{}.bucket{}", output, i)).collect();
+    let result = (|| {
+        let mut files = names.iter().map(|name| File::create(name).map(BufWriter::new)).collect::<io::Result<Vec<_>>>()?;
+        let mut count = 0;
+        while let Some(row) = rows.next_row()? {
+            files[rng.gen_range(0..buckets)].write_all(&row)?;
+            count += 1;
+        }
+        for file in files {
+            file.into_inner().map_err(|e| e.into_error())?;
+        }
+        for name in &names {
+            let bytes = fs::read(name)?;
+            let mut bucket: Vec<&[u8]> = match rows.rows {
+                Rows::Lines => bytes.split_inclusive(|&byte| byte == b'\n').collect(),
+                Rows::Fixed(size) => bytes.chunks_exact(size).collect(),
+            };
+            bucket.shuffle(rng);
+            for row in bucket {
+                writer.write_all(row)?;
+            }
+            fs::remove_file(name)?;
+        }
+        Ok(count)
+    })();
+    if result.is_err() {
+        for name in &names {
+            let _ = fs::remove_file(name);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::DataType;
+    use crate::Generator;
+
+    #[test]
+    fn shuffled_files_keep_their_header_and_rows() {
+        let dir = std::env::temp_dir();
+        let name = |end: &str| dir.join(format!("ruststf_test_{}_shuffle_{}", std::process::id(), end)).to_string_lossy().into_owned();
+        let (input, output) = (name("in.csv"), name("out.csv"));
+        let mut text = String::from("id,score\n");
+        for i in 0..1_000 {
+            text.push_str(&format!("{},{}\n", i, i * 2));
+        }
+        text.push_str("# min: 0\n");
+        fs::write(&input, &text).unwrap();
+
+        // A tiny limit sends it through the bucket files, which have to be tidied away afterwards
+        assert_eq!(shuffle_in(&input, &output, Some(7), 4_096).unwrap(), 1_000);
+        let shuffled = fs::read_to_string(&output).unwrap();
+        let lines: Vec<&str> = shuffled.lines().collect();
+        assert_eq!((lines[0], lines[1_001]), ("id,score", "# min: 0"));
+        assert_ne!(shuffled, text);
+        let mut rows = lines[1..1_001].to_vec();
+        rows.sort_by_key(|row| row.split(',').next().unwrap().parse::<u32>().unwrap());
+        assert_eq!(rows.join("\n"), text.lines().skip(1).take(1_000).collect::<Vec<_>>().join("\n"));
+        assert!(!std::path::Path::new(&format!("{}.bucket0", output)).exists());
+        // A file can be shuffled onto itself
+        assert_eq!(shuffle_file(&input, &input, Some(7)).unwrap(), 1_000);
+        assert!(fs::read_to_string(&input).unwrap().starts_with("id,score\n"));
+
+        // The seed made the old order, so it doesn't come along, with or without a # in front
+        for header in ["Count: 3\nSeed: 5\n", "# Count: 3\n# Seed: 5\n"] {
+            fs::write(&input, format!("{}1\n2\n3\n", header)).unwrap();
+            shuffle_file(&input, &output, Some(3)).unwrap();
+            let shuffled = fs::read_to_string(&output).unwrap();
+            assert!(!shuffled.contains("Seed:") && shuffled.contains("Count: 3\n"), "{}", shuffled);
+            assert_eq!(crate::load_file(&output).unwrap().seed, None);
+        }
+
+        // Binary values move as whole values behind the header
+        Generator::new().data_type(DataType::Long).count(500).seed(1)
+            .format(crate::OutputFormat::Binary).write_to(&input).unwrap();
+        shuffle_in(&input, &output, Some(2), 1_024).unwrap();
+        let mut before = crate::load_file(&input).unwrap().values;
+        let mut after = crate::load_file(&output).unwrap().values;
+        assert_ne!(before, after);
+        before.sort_by(f64::total_cmp);
+        after.sort_by(f64::total_cmp);
+        assert_eq!(before, after);
+        fs::remove_file(&input).unwrap();
+        fs::remove_file(&output).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+//! Sending the values over the network instead of writing a file.
+//!
+//! `-o tcp://localhost:9000` connects to whatever is listening there and sends exactly what the
+//! file would have held - header, values and all - then closes the connection, the way `nc` would
+//! pass a file along. `-o udp://localhost:9000` sends datagrams instead, one line of the file in
+//! each, which is how syslog, StatsD and most metrics collectors take their messages. Either way
+//! it's for load-testing an ingestion service straight from the generator, with --rate to set
+//! the pace.
+//!
+//! UDP has no connection, so nothing says whether the datagrams arrived. A closed port usually
+//! comes back as "Connection refused" on a later send, but a busy listener just drops what it
+//! can't keep up with - count what came in at the other end.
+//!
+//! `-o kafka://localhost:9092/events` publishes a message per line to a Kafka topic instead,
+//! when the program was built with the kafka feature - see kafka.rs.
+
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+
+use log::warn;
+
+use crate::error::{self, GeneratorError};
+#[cfg(feature = "kafka")]
+use crate::kafka::{publish, Topic};
+use crate::stats::Stats;
+use crate::writer::{write_to, IfExists, OutputFormat};
+use crate::{invalid_input, unsupported_format, Options};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Tcp, // One connection, with the whole file sent down it
+    Udp, // A datagram for every line
+    Kafka, // A message on a topic for every line
+}
+
+/// Where to send the values, read from an output name like tcp://localhost:9000
+#[derive(Debug, Clone, PartialEq)]
+pub struct Socket {
+    pub protocol: Protocol,
+    pub address: String, // host:port, looked up when the run starts
+}
+
+impl Socket {
+    /// Reads a tcp://, udp:// or kafka:// output name - None means it's an ordinary file (or stdout)
+    pub fn parse(filename: &str) -> Option<Socket> {
+        let (scheme, address) = filename.split_once("://")?;
+        let protocol = match scheme {
+            "tcp" => Protocol::Tcp,
+            "udp" => Protocol::Udp,
+            "kafka" => Protocol::Kafka,
+            _ => return None,
+        };
+        Some(Socket { protocol, address: address.to_string() })
+    }
+
+    /// Refuses the settings that need a file on disk, or that UDP can't split into lines
+    pub fn validate(&self, options: &Options) -> error::Result<()> {
+        if self.protocol == Protocol::Kafka {
+            #[cfg(feature = "kafka")]
+            Topic::parse(&self.address)?;
+            #[cfg(not(feature = "kafka"))]
+            return Err(unsupported_format("This copy was built without Kafka support (build it with --features kafka)"));
+        } else {
+            // rsplit, so an IPv6 address like [::1]:9000 keeps its own colons
+            let port = self.address.rsplit_once(':').filter(|(host, _)| !host.is_empty()).map(|(_, port)| port.parse::<u16>());
+            if !matches!(port, Some(Ok(port)) if port != 0) {
+                return Err(invalid_input(format!("{} needs a host and a port, like localhost:9000", options.filename)));
+            }
+        }
+        if options.format == OutputFormat::Sqlite || options.shards > 1 || options.mmap {
+            return Err(unsupported_format("SQLite, shards and --mmap need files on disk, so they can't be sent to a socket"));
+        }
+        if options.resume || options.checksum || options.if_exists == IfExists::Append {
+            return Err(invalid_input("A socket has nothing to resume, append to or put a checksum next to"));
+        }
+        if self.protocol != Protocol::Tcp && !sends_lines(options) {
+            return Err(unsupported_format(format!(
+                "{} sends a line at a time, so it needs text (one value per line), CSV, a matrix or JSON lines",
+                if self.protocol == Protocol::Udp { "UDP" } else { "Kafka" },
+            )));
+        }
+        Ok(())
+    }
+}
+
+// Formats where every line stands on its own, so each makes sense as a message by itself
+fn sends_lines(options: &Options) -> bool {
+    // gzip runs the lines together into one compressed stream, and frames cut across them
+    !options.compress && !options.framed && match options.format {
+        OutputFormat::Text => options.line_layout.is_one_per_line(),
+        OutputFormat::Csv | OutputFormat::Matrix | OutputFormat::JsonLines => true,
+        _ => false,
+    }
+}
+
+/// Connects and sends everything options would have written to a file
+pub(crate) fn send(socket: &Socket, options: &Options) -> error::Result<Option<Stats>> {
+    let connect_error = |e: io::Error| GeneratorError::from(e).context(&format!("Couldn't reach {}", options.filename));
+    match socket.protocol {
+        Protocol::Tcp => {
+            let stream = TcpStream::connect(&socket.address).map_err(connect_error)?;
+            // Nagle's algorithm holds small writes back to make bigger packets, which would
+            // bunch up values that --rate meant to go out one at a time
+            stream.set_nodelay(options.rate.is_some())?;
+            write_to(stream, options)
+        },
+        Protocol::Udp => {
+            let datagrams = Datagrams::connect(&socket.address).map_err(connect_error)?;
+            write_to(datagrams, options)
+        },
+        #[cfg(feature = "kafka")]
+        Protocol::Kafka => publish(&socket.address, options),
+        // validate() has already said so
+        #[cfg(not(feature = "kafka"))]
+        Protocol::Kafka => Err(unsupported_format("This copy was built without Kafka support")),
+    }
+}
+
+// Gathers what's written into lines and sends each one as a datagram of its own
+struct Datagrams {
+    socket: UdpSocket,
+    line: Vec<u8>,
+}
+
+impl Datagrams {
+    fn connect(address: &str) -> io::Result<Datagrams> {
+        let Some(target) = address.to_socket_addrs()?.next() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no address found"));
+        };
+        // Any free port of our own, from the same family (IPv4 or IPv6) as the target
+        let local = if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(local)?;
+        // connect() on UDP just fixes where send() goes, like connect() on a SOCK_DGRAM in C
+        socket.connect(target)?;
+        Ok(Datagrams { socket, line: Vec::new() })
+    }
+}
+
+impl Write for Datagrams {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for piece in buf.split_inclusive(|&byte| byte == b'\n') {
+            self.line.extend_from_slice(piece);
+            if self.line.ends_with(b"\n") {
+                self.socket.send(&self.line)?;
+                self.line.clear();
+            }
+        }
+        Ok(buf.len())
+    }
+
+    // Half a line stays put until the rest of it arrives - a flush mustn't cut one in two
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Whatever's left after the last newline still goes, as the final datagram
+impl Drop for Datagrams {
+    fn drop(&mut self) {
+        if !self.line.is_empty() {
+            if let Err(e) = self.socket.send(&self.line) {
+                warn!("The last datagram couldn't be sent: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::tests::options;
+    use crate::{DataType, Distribution, HeaderStyle};
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn values_go_down_tcp_and_udp() {
+        let mut options = options(DataType::Integer, 0.0, 9.0, Distribution::Uniform);
+        (options.count, options.seed, options.header_style) = (50, Some(7), HeaderStyle::Omit);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        options.filename = format!("tcp://{}", listener.local_addr().unwrap());
+        let receiver = thread::spawn(move || {
+            let mut received = Vec::new();
+            listener.accept().unwrap().0.read_to_end(&mut received).unwrap();
+            received
+        });
+        options.validate().unwrap();
+        send(&Socket::parse(&options.filename).unwrap(), &options).unwrap();
+        let expected = receiver.join().unwrap();
+        assert_eq!(expected.iter().filter(|&&byte| byte == b'\n').count(), 50);
+
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        options.filename = format!("udp://{}", listener.local_addr().unwrap());
+        send(&Socket::parse(&options.filename).unwrap(), &options).unwrap();
+        let mut datagram = [0u8; 64];
+        let mut received = Vec::new();
+        for _ in 0..50 {
+            let size = listener.recv(&mut datagram).unwrap();
+            assert_eq!(datagram[size - 1], b'\n');
+            received.extend_from_slice(&datagram[..size]);
+        }
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn socket_names_need_a_port() {
+        assert_eq!(Socket::parse("udp://[::1]:514").unwrap().address, "[::1]:514");
+        assert_eq!(Socket::parse("data.txt"), None);
+        let mut options = options(DataType::Integer, 0.0, 9.0, Distribution::Uniform);
+        for bad in ["tcp://localhost", "tcp://:9000", "udp://host:99999"] {
+            options.filename = bad.to_string();
+            assert!(options.validate().is_err(), "{}", bad);
+        }
+        options.filename = "udp://localhost:9000".to_string();
+        options.format = OutputFormat::Binary;
+        assert!(options.validate().is_err());
+    }
+}
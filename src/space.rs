@@ -0,0 +1,109 @@
+//! Checking a big file will fit on the disk before spending an hour generating it.
+//!
+//! Running out of space three quarters of the way through a 200 GB file throws away all the
+//! time up to that point, and leaves the disk full for everything else too. So before a big
+//! run its size is estimated from a sample, the same way --dry-run does it, and compared with
+//! the free space where the file is going. A file that won't fit is refused before anything
+//! is written, and one that would nearly fill the disk gets a warning.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use log::{debug, warn};
+
+use crate::atomic::temp_name;
+use crate::bench::{estimate, ESTIMATE_SAMPLE};
+use crate::error;
+use crate::{IfExists, Options};
+
+/// Runs of up to this many values aren't checked - the sample would be the whole file, so
+/// checking would mean writing it twice, and files this size are rarely what fills a disk
+pub const SPACE_CHECK_FROM: u64 = ESTIMATE_SAMPLE as u64;
+
+// Using more than this share of the free space gets a warning
+const NEARLY_FULL: f64 = 0.9;
+
+/// Free bytes on the disk `filename` would go on, or None where that can't be found out
+pub fn free_space(filename: &str) -> Option<u64> {
+    // A bare name like data.txt has an empty parent, which means the current directory
+    let dir = match Path::new(filename).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    // statvfs() on Unix and GetDiskFreeSpaceEx() on Windows, counting only what we're allowed to use
+    fs2::available_space(dir).ok()
+}
+
+/// Refuses a run whose output won't fit in the free space, and warns about one that nearly fills it
+pub(crate) fn check_space(options: &Options) -> error::Result<()> {
+    let values = options.count as u64 * options.values_per_row() as u64;
+    if !options.space_check || options.writes_to_stdout() || options.socket().is_some() || options.writes_to_fifo() || values <= SPACE_CHECK_FROM {
+        return Ok(());
+    }
+    let Some(free) = free_space(&options.filename) else {
+        debug!("Couldn't tell how much space is free for {}, so it wasn't checked", options.filename);
+        return Ok(());
+    };
+    let mut needed = estimate(&Options { space_check: false, ..options.clone() })?.bytes;
+    let size_of = |name: &str| fs::metadata(name).map_or(0, |metadata| metadata.len());
+    // Appending makes a new copy with the old values in it as well, and a resumed file has
+    // part of its size on the disk already
+    if options.if_exists == IfExists::Append {
+        needed += size_of(&options.filename);
+    }
+    if options.resume {
+        needed = needed.saturating_sub(size_of(&temp_name(&options.filename)));
+    }
+    debug!("{} should take about {}, with {} free", options.filename, size_text(needed), size_text(free));
+    compare(&options.filename, needed, free)
+}
+
+// The verdict on its own, so it can be tested without a disk that's actually full
+fn compare(filename: &str, needed: u64, free: u64) -> error::Result<()> {
+    if needed > free {
+        return Err(io::Error::new(
+            io::ErrorKind::StorageFull,
+            format!(
+                "{} would take about {}, but there's only {} free there (--no-space-check to try anyway)",
+                filename, size_text(needed), size_text(free)
+            ),
+        ).into());
+    }
+    if needed as f64 > free as f64 * NEARLY_FULL {
+        warn!("{} will take about {} of the {} free there, leaving the disk nearly full", filename, size_text(needed), size_text(free));
+    }
+    Ok(())
+}
+
+/// Sizes in decimal units like disk makers and the benchmark table use: 1 MB = 1,000,000 bytes
+pub fn size_text(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{} bytes", bytes);
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.2} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_that_will_not_fit_are_refused() {
+        let full = compare("data.txt", 5_000_000_000, 1_200_000_000).unwrap_err();
+        assert_eq!(full.io_kind(), Some(io::ErrorKind::StorageFull));
+        assert!(full.to_string().starts_with("data.txt would take about 5.00 GB, but there's only 1.20 GB free there"), "{}", full);
+        // Nearly full is only a warning
+        assert!(compare("data.txt", 950, 1000).is_ok());
+        assert_eq!(size_text(999), "999 bytes");
+        assert_eq!(size_text(1_500_000), "1.50 MB");
+        assert!(free_space("data.txt").is_some());
+    }
+}
@@ -0,0 +1,188 @@
+//! Primes, perfect squares and powers of two - whole numbers with something special about them.
+//!
+//! `--dist primes` (or `squares`, or `powers-of-two`) still picks from the range, but only the
+//! numbers of that kind in it, each as likely as the next. They're handy test cases for
+//! number-theory code (is_prime, factoring, gcd) and for hash tables, where sizes that are
+//! powers of two or primes behave very differently.
+//!
+//! Squares and powers of two are picked by their root or their exponent, so there's no
+//! searching: 10..1000 has the squares 16 (4²) up to 961 (31²), and picking 4..31 picks one
+//! of them. Primes have no formula, so a number is picked from the range and tested, and
+//! picked again until it's prime. Near n about one number in ln(n) is prime - one in 37 for
+//! 16-digit numbers - so that takes a few dozen tries at most.
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::error;
+use crate::{invalid_input, range_error, Options};
+
+/// Which kind of number to pick
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Special {
+    Primes,      // 2, 3, 5, 7, 11... only divisible by 1 and themselves
+    Squares,     // 0, 1, 4, 9, 16... some whole number times itself
+    PowersOfTwo, // 1, 2, 4, 8, 16... a single bit set, like 1 << n in C
+}
+
+impl Special {
+    pub fn name(self) -> &'static str {
+        match self {
+            Special::Primes => "primes",
+            Special::Squares => "squares",
+            Special::PowersOfTwo => "powers of two",
+        }
+    }
+
+    // What actually gets picked for a range: a number, a root or an exponent, from low to
+    // high - None when there's nothing of this kind between min and max at all
+    fn picks(self, min: f64, max: f64) -> Option<(u64, u64)> {
+        // None of these are negative, so a range that's all below zero has none of them
+        if max < 0.0 {
+            return None;
+        }
+        let (low, high) = (min.max(0.0) as u64, max as u64);
+        let picks = match self {
+            // Gaps between primes stay short (under 1500 for anything a u64 holds), so looking
+            // for the first one is quick
+            Special::Primes => ((low.max(2)..=high).find(|&n| is_prime(n))?, high),
+            // isqrt() rounds down, which is right for the top; the bottom has to round up
+            Special::Squares => {
+                let root = low.isqrt();
+                (if root * root == low { root } else { root + 1 }, high.isqrt())
+            },
+            // The exponent of the first power at or above low, and of the last one at or below high
+            Special::PowersOfTwo if high == 0 => return None,
+            Special::PowersOfTwo => (low.max(1).next_power_of_two().trailing_zeros() as u64, high.ilog2() as u64),
+        };
+        (picks.0 <= picks.1).then_some(picks)
+    }
+}
+
+/// Checks the type can hold them and the range has at least one
+pub(crate) fn validate_special(special: Special, options: &Options) -> error::Result<()> {
+    if !options.data_type.is_integer() || options.data_type.is_timestamp() {
+        return Err(invalid_input(format!("{} are whole numbers, so they need an integer type", capitalized(special))));
+    }
+    // Both would put ordinary numbers in among them
+    if !options.exclude.is_empty() || options.outliers > 0.0 {
+        return Err(invalid_input(format!("Only {} are picked, so there's nothing to exclude and no outliers", special.name())));
+    }
+    if special.picks(options.min, options.max).is_none() {
+        return Err(range_error(format!("There are no {} between {} and {}", special.name(), options.min, options.max)));
+    }
+    Ok(())
+}
+
+fn capitalized(special: Special) -> String {
+    let name = special.name();
+    name[..1].to_uppercase() + &name[1..]
+}
+
+/// One number of the kind, picked evenly from the ones between min and max
+/// validate_special has already made sure there's at least one
+pub fn special_value(rng: &mut StdRng, special: Special, min: f64, max: f64) -> f64 {
+    // Picking any number and keeping the primes gives every prime the same chance
+    // (and skips the search in picks(), which would only slow every value down)
+    if special == Special::Primes {
+        let (low, high) = (min.max(2.0) as u64, max as u64);
+        loop {
+            let number = rng.gen_range(low..=high);
+            if is_prime(number) {
+                return number as f64;
+            }
+        }
+    }
+    let (low, high) = special.picks(min, max).expect("the range was checked to have some");
+    let picked = rng.gen_range(low..=high);
+    match special {
+        Special::Squares => (picked * picked) as f64,
+        _ => (1u64 << picked) as f64,
+    }
+}
+
+/// Whether n is prime, for any u64
+/// Miller-Rabin with these twelve bases gives the right answer for every 64-bit number
+pub fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    // Small numbers (and their multiples) are quicker to rule out by dividing
+    for base in BASES {
+        if n.is_multiple_of(base) {
+            return n == base;
+        }
+    }
+    // n - 1 = d * 2^s with d odd
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    'bases: for base in BASES {
+        let mut x = pow_mod(base, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+// a * b % m without overflowing - the product of two u64s always fits in a u128
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+// base^exponent % m by squaring, one bit of the exponent at a time
+fn pow_mod(mut base: u64, mut exponent: u64, m: u64) -> u64 {
+    let mut result = 1;
+    base %= m;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exponent >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::make_rng;
+
+    #[test]
+    fn primality_matches_trial_division() {
+        let slow = |n: u64| n >= 2 && (2..).take_while(|d| d * d <= n).all(|d| !n.is_multiple_of(d));
+        for n in 0..10_000 {
+            assert_eq!(is_prime(n), slow(n), "{}", n);
+        }
+        // The biggest 64-bit prime, a Carmichael number, and one that fools Miller-Rabin when
+        // it only tries the bases 2, 3, 5 and 7
+        assert!(is_prime(18_446_744_073_709_551_557));
+        assert!(!is_prime(561));
+        assert!(!is_prime(3_215_031_751));
+    }
+
+    #[test]
+    fn only_the_special_numbers_in_the_range_come_out() {
+        let mut rng = make_rng(Some(9));
+        for _ in 0..1000 {
+            let prime = special_value(&mut rng, Special::Primes, 24.0, 40.0);
+            assert!([29.0, 31.0, 37.0].contains(&prime));
+            let square = special_value(&mut rng, Special::Squares, -50.0, 50.0) as u64;
+            assert!(square <= 49 && square.isqrt().pow(2) == square);
+            let power = special_value(&mut rng, Special::PowersOfTwo, 3.0, 1000.0) as u64;
+            assert!((4..=512).contains(&power) && power.is_power_of_two());
+        }
+        assert_eq!(Special::Primes.picks(24.0, 28.0), None);
+        assert_eq!(Special::Squares.picks(17.0, 24.0), None);
+        assert_eq!(Special::PowersOfTwo.picks(-10.0, 0.0), None);
+    }
+}
@@ -0,0 +1,179 @@
+//! Cutting a data file that already exists into smaller ones, for jobs that each take a chunk.
+//!
+//! The parts are named like shards (data_part0001.txt, ...) and each one is a complete file,
+//! with the header rewritten to its own row count. A `_manifest.txt` lists them the way
+//! --shards does, so `merge` can put them back together.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::atomic::{publish, temp_name};
+use crate::binary::{read_header, write_header};
+use crate::error;
+use crate::reader::parse_matrix_header;
+use crate::shard::{shard_manifest_name, shard_name, shard_rows, MAX_SHARDS};
+use crate::shuffle::{open_rows, split_comment, Rows};
+use crate::{invalid_data, invalid_input};
+
+/// How big the parts are
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitSize {
+    Parts(u32), // This many parts, as even as possible
+    Rows(u64),  // At most this many rows each - the last part gets whatever is left
+}
+
+/// Splits `input` into parts named after `output` (output_part0001 and so on), handing back
+/// each part's name and rows - `force` overwrites parts that are already there
+pub fn split_file(input: &str, output: &str, size: SplitSize, force: bool) -> error::Result<Vec<(String, u64)>> {
+    // One pass to count the rows, since a CSV or JSON lines file doesn't say, then one to copy them
+    let (_, _, mut rows) = open_rows(input)?;
+    let mut total = 0u64;
+    while rows.next_row()?.is_some() {
+        total += 1;
+    }
+    let counts: Vec<u64> = match size {
+        SplitSize::Parts(0) | SplitSize::Rows(0) => return Err(invalid_input("Parts must have at least one row")),
+        SplitSize::Parts(parts) if parts as u64 > total => {
+            return Err(invalid_input(format!("{} has only {} rows, not enough for {} parts", input, total, parts)));
+        },
+        // shard_rows counts in u32, like the row counts in headers
+        SplitSize::Parts(parts) => {
+            let total = u32::try_from(total).map_err(|_| invalid_input("Too many rows to split into parts - use --rows instead"))?;
+            shard_rows(total, parts).map(u64::from).collect()
+        },
+        SplitSize::Rows(most) => (0..total.div_ceil(most)).map(|part| most.min(total - part * most)).collect(),
+    };
+    if counts.len() > MAX_SHARDS as usize {
+        return Err(invalid_input(format!("That would make {} parts, and the most is {}", counts.len(), MAX_SHARDS)));
+    }
+
+    let (_, header, mut rows) = open_rows(input)?;
+    let seed = check_header(input, &header, rows.rows, total)?;
+    let names: Vec<String> = (1..=counts.len() as u32).map(|part| shard_name(output, part)).collect();
+    // Checked before any part is written, so a clash can't leave half a set behind
+    if let Some(taken) = names.iter().find(|name| !force && Path::new(name).exists()) {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists (use --force to overwrite)", taken)).into());
+    }
+    let mut parts = Vec::new();
+    for (name, &count) in names.into_iter().zip(&counts) {
+        // Each part appears whole under its own name, the same as a generated file
+        let mut writer = BufWriter::new(File::create(temp_name(&name))?);
+        writer.write_all(&part_header(&header, rows.rows, count)?)?;
+        for _ in 0..count {
+            // The rows were all counted above, so they're all still there
+            let row = rows.next_row()?.ok_or_else(|| invalid_data(format!("{} got shorter while it was being split", input)))?;
+            writer.write_all(&row)?;
+        }
+        writer.into_inner().map_err(|e| e.into_error())?;
+        publish(&name, true)?;
+        parts.push((name, count));
+    }
+    write_manifest(output, total, seed, &parts)?;
+    Ok(parts)
+}
+
+// Checks the header's count matches the rows actually there, and hands back the seed it records
+fn check_header(input: &str, header: &[u8], rows: Rows, total: u64) -> error::Result<Option<String>> {
+    if let Rows::Fixed(_) = rows {
+        // open_rows already checked a binary file's length against its count
+        return Ok(None);
+    }
+    let mut seed = None;
+    for line in String::from_utf8_lossy(header).lines() {
+        let line = split_comment(line).1;
+        let declared = match line.strip_prefix("Count:") {
+            Some(count) => count.trim().parse::<u64>().ok(),
+            None if line.starts_with("Matrix:") => parse_matrix_header(line).map(|(rows, _)| rows as u64),
+            None => {
+                if let Some(value) = line.strip_prefix("Seed:") {
+                    seed = Some(value.trim().to_string());
+                }
+                continue;
+            },
+        };
+        if declared != Some(total) {
+            return Err(invalid_data(format!("{}'s header says \"{}\" but it has {} rows", input, line, total)));
+        }
+    }
+    Ok(seed)
+}
+
+// The header with the part's own row count - the seed goes in the manifest instead, the way
+// shards leave it out, since it regenerates the whole file rather than one part
+fn part_header(header: &[u8], rows: Rows, count: u64) -> error::Result<Vec<u8>> {
+    if let Rows::Fixed(_) = rows {
+        let binary = read_header(header)?;
+        let mut bytes = Vec::new();
+        // The count was u32 in the whole file's header, so each part's fits too
+        write_header(&mut bytes, binary.data_type.binary_tag().unwrap(), count as u32, binary.endianness)?;
+        return Ok(bytes);
+    }
+    let mut text = String::new();
+    for line in String::from_utf8_lossy(header).lines() {
+        let (mark, bare) = split_comment(line);
+        if bare.starts_with("Count:") {
+            text += &format!("{}Count: {}\n", mark, count);
+        } else if let Some((_, columns)) = parse_matrix_header(bare) {
+            text += &format!("{}Matrix: {} x {}\n", mark, count, columns);
+        } else if !bare.starts_with("Seed:") {
+            text += line;
+            text.push('\n');
+        }
+    }
+    Ok(text.into_bytes())
+}
+
+// Lists the parts the same way a sharded run's manifest does, with bare names since the
+// parts sit next to it
+fn write_manifest(output: &str, total: u64, seed: Option<String>, parts: &[(String, u64)]) -> error::Result<()> {
+    let bare = |name: &str| Path::new(name).file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let mut text = format!("Dataset: {}\n", bare(output));
+    text += &format!("Rows: {}\n", total);
+    if let Some(seed) = seed {
+        text += &format!("Seed: {}\n", seed);
+    }
+    text += &format!("Shards: {}\n", parts.len());
+    for (name, rows) in parts {
+        text += &format!("{} {}\n", rows, bare(name));
+    }
+    Ok(fs::write(shard_manifest_name(output), text)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merge::{merge_files, merge_inputs, MergeOrder};
+
+    #[test]
+    fn parts_get_their_own_headers_and_merge_back() {
+        let name = |end: &str| {
+            let path = std::env::temp_dir().join(format!("ruststf_test_{}_split_{}", std::process::id(), end));
+            path.to_string_lossy().into_owned()
+        };
+        let (input, output, merged) = (name("data.txt"), name("chunk.txt"), name("merged.txt"));
+        let text = "Count: 5\nSeed: 9\n1\n2\n3\n4\n5\n# min: 1\n";
+        fs::write(&input, text).unwrap();
+
+        let parts = split_file(&input, &output, SplitSize::Rows(2), false).unwrap();
+        assert_eq!(parts.iter().map(|(_, rows)| *rows).collect::<Vec<_>>(), [2, 2, 1]);
+        assert_eq!(fs::read_to_string(&parts[2].0).unwrap(), "Count: 1\n5\n");
+        // Existing parts are left alone unless forced
+        assert!(split_file(&input, &output, SplitSize::Parts(3), false).is_err());
+        let parts = split_file(&input, &output, SplitSize::Parts(2), true).unwrap();
+        assert_eq!(fs::read_to_string(&parts[0].0).unwrap(), "Count: 3\n1\n2\n3\n");
+        assert!(split_file(&input, &output, SplitSize::Parts(6), true).is_err());
+
+        // The manifest lists just the two parts written last, and keeps the seed for the whole
+        let inputs = merge_inputs(&[shard_manifest_name(&output)]).unwrap();
+        merge_files(&inputs, &merged, MergeOrder::Concatenate).unwrap();
+        assert_eq!(fs::read_to_string(&merged).unwrap(), "Count: 5\n1\n2\n3\n4\n5\n");
+        assert!(fs::read_to_string(shard_manifest_name(&output)).unwrap().contains("Seed: 9\n"));
+        for part in 1..=3 {
+            fs::remove_file(shard_name(&output, part)).unwrap();
+        }
+        for file in [input, merged, shard_manifest_name(&output)] {
+            fs::remove_file(file).unwrap();
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Filling a table in a SQLite database, for testing code that reads from one.
+//!
+//! The database file is created if it isn't there, and other tables in it are left alone -
+//! `--if-exists` decides what happens to a table with the same name. Plain values get a
+//! column per `--columns` (col1, col2, ...), and records get one per field. Rows go in
+//! through one prepared INSERT, BATCH_ROWS at a time inside a transaction, since SQLite
+//! commits every statement on its own otherwise (and each commit waits for the disk).
+
+use std::io;
+
+use rusqlite::types::Value as SqlValue; // Renamed so it doesn't clash with our own Value
+use rusqlite::{params_from_iter, Connection};
+
+use crate::error;
+use crate::generator::{DataType, Value};
+use crate::stats::{stat_value, Collector};
+use crate::timestamp::format_iso;
+use crate::uuid::format_uuid;
+use crate::writer::check_finite;
+use crate::{IfExists, Options, TimeFormat};
+
+/// Table filled when no --table is given
+pub const DEFAULT_TABLE: &str = "data";
+
+/// Rows per transaction - big enough that commits are rare, small enough that the
+/// journal doesn't balloon on a huge run
+pub const BATCH_ROWS: u32 = 100_000;
+
+/// The table options.table names, or the default one
+pub fn table_name(options: &Options) -> &str {
+    options.table.as_deref().unwrap_or(DEFAULT_TABLE)
+}
+
+/// Whether the database file already has a table with this name
+/// A missing database simply has no tables yet
+pub fn table_exists(filename: &str, table: &str) -> error::Result<bool> {
+    if std::fs::metadata(filename).is_err() {
+        return Ok(false);
+    }
+    has_table(&Connection::open(filename).map_err(sqlite_error)?, table)
+}
+
+// sqlite_master is SQLite's own table of everything in the database
+fn has_table(connection: &Connection, table: &str) -> error::Result<bool> {
+    let found: i64 = connection
+        .query_row("SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?1", [table], |row| row.get(0))
+        .map_err(sqlite_error)?;
+    Ok(found > 0)
+}
+
+/// The CREATE TABLE statement for these options
+pub fn create_statement(options: &Options) -> String {
+    let columns: Vec<String> = options.named_columns()
+        .iter()
+        .map(|(name, data_type)| format!("{} {} NOT NULL", quote(name), column_type(*data_type, options.time_format)))
+        .collect();
+    format!("CREATE TABLE {} ({})", quote(table_name(options)), columns.join(", "))
+}
+
+// SQLite only has a handful of storage classes, so every integer type shares INTEGER
+fn column_type(data_type: DataType, time_format: TimeFormat) -> &'static str {
+    match data_type {
+        DataType::Integer | DataType::Short | DataType::Long | DataType::Unsigned => "INTEGER",
+        DataType::Float | DataType::Double => "REAL",
+        DataType::String => "TEXT",
+        // SQLite does have this one, and stores the bytes as they are
+        DataType::Bytes => "BLOB",
+        DataType::FirstName | DataType::LastName | DataType::Email | DataType::Phone => "TEXT",
+        // There's no boolean type either - 0 and 1 are how SQLite itself stores them
+        DataType::Boolean => "INTEGER",
+        // Nor a UUID or address type - the text form is what people query with and what other tools expect
+        DataType::Uuid | DataType::Ipv4 | DataType::Ipv6 => "TEXT",
+        // Nor a date type: SQLite's date functions take ISO text or epoch seconds, so either works
+        DataType::Timestamp if time_format == TimeFormat::Epoch => "INTEGER",
+        DataType::Timestamp => "TEXT",
+    }
+}
+
+// Double quotes make a name an identifier, so a field called "order" or "first-name" still works
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name)
+}
+
+/// Creates (or replaces, or adds to) the table and inserts `count` rows from the source
+pub(crate) fn write_table(mut source: impl Iterator<Item = Value>, options: &Options, collector: &mut Collector) -> error::Result<()> {
+    let mut connection = Connection::open(&options.filename).map_err(sqlite_error)?;
+    let table = table_name(options);
+    let exists = has_table(&connection, table)?;
+    match options.if_exists {
+        IfExists::Abort if exists => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Table {} already exists in {} (choose overwrite or append to replace or extend it)", table, options.filename),
+            ).into());
+        },
+        IfExists::Overwrite if exists => {
+            connection.execute(&format!("DROP TABLE {}", quote(table)), []).map_err(sqlite_error)?;
+            connection.execute(&create_statement(options), []).map_err(sqlite_error)?;
+        },
+        // Appending to a table that isn't there yet is just creating it
+        IfExists::Append if exists => {},
+        _ => {
+            connection.execute(&create_statement(options), []).map_err(sqlite_error)?;
+        },
+    }
+    let per_row = options.values_per_row() as usize;
+    // ?1, ?2, ... are placeholders, filled in for each row - like a prepared statement in C's sqlite3 API
+    let placeholders: Vec<String> = (1..=per_row).map(|i| format!("?{}", i)).collect();
+    let insert = format!("INSERT INTO {} VALUES ({})", quote(table), placeholders.join(", "));
+    let mut rows_left = options.count;
+    while rows_left > 0 {
+        let rows = rows_left.min(BATCH_ROWS);
+        rows_left -= rows;
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+        {
+            // The statement borrows the transaction, so it has to be dropped before the commit
+            let mut statement = transaction.prepare_cached(&insert).map_err(sqlite_error)?;
+            for _ in 0..rows {
+                let row = source.by_ref().take(per_row)
+                    .map(|value| {
+                        check_finite(&value)?;
+                        collector.push(stat_value(&value));
+                        Ok(sql_value(value, options.time_format))
+                    })
+                    .collect::<error::Result<Vec<SqlValue>>>()?;
+                statement.execute(params_from_iter(row)).map_err(sqlite_error)?;
+            }
+        }
+        transaction.commit().map_err(sqlite_error)?;
+    }
+    Ok(())
+}
+
+// Our values in SQLite's own types
+fn sql_value(value: Value, time_format: TimeFormat) -> SqlValue {
+    match value {
+        Value::Int(num) => SqlValue::Integer(num as i64),
+        Value::Short(num) => SqlValue::Integer(num as i64),
+        Value::Long(num) => SqlValue::Integer(num),
+        Value::Unsigned(num) => SqlValue::Integer(num as i64),
+        // Going through the text avoids f32 -> f64 noise, so 0.1 is stored as 0.1 and not 0.10000000149
+        Value::Float(num) => SqlValue::Real(num.to_string().parse().unwrap()),
+        Value::Double(num) => SqlValue::Real(num),
+        Value::Text(text) => SqlValue::Text(text),
+        Value::Bytes(blob) => SqlValue::Blob(blob),
+        Value::Bool(flag) => SqlValue::Integer(flag as i64),
+        Value::Timestamp(seconds) if time_format == TimeFormat::Epoch => SqlValue::Integer(seconds),
+        Value::Timestamp(seconds) => SqlValue::Text(format_iso(seconds)),
+        Value::Uuid(bits) => SqlValue::Text(format_uuid(bits)),
+        Value::Ip(address) => SqlValue::Text(address.to_string()),
+    }
+}
+
+// rusqlite has its own error type, so it's turned into an io::Error, which ? then wraps as GeneratorError::Io
+fn sqlite_error(error: rusqlite::Error) -> io::Error {
+    io::Error::other(format!("SQLite: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::tests::options;
+    use crate::generator::{make_rng, value_source};
+    use crate::{parse_schema, write_data, Distribution, OutputFormat};
+
+    #[test]
+    fn records_read_back_from_the_table() {
+        let mut options = options(DataType::Integer, 0.0, 1.0, Distribution::Uniform);
+        options.schema = parse_schema("id:int, score:double, name:string, ok:bool, at:timestamp").unwrap();
+        options.format = OutputFormat::Sqlite;
+        options.table = Some("people".to_string());
+        let path = std::env::temp_dir().join(format!("ruststf_test_{}_records.db", std::process::id()));
+        options.filename = path.to_string_lossy().into_owned();
+        let _ = std::fs::remove_file(&options.filename);
+        write_data(&options).unwrap();
+
+        let expected: Vec<Value> = value_source(&mut make_rng(options.seed), &options).unwrap().collect();
+        let connection = Connection::open(&options.filename).unwrap();
+        let mut statement = connection.prepare("SELECT id, score, name, ok, at FROM people ORDER BY rowid").unwrap();
+        let rows: Vec<(i32, f64, String, bool, String)> = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(rows.len(), options.count as usize);
+        for (row, values) in rows.iter().zip(expected.chunks(5)) {
+            assert_eq!(Value::Int(row.0), values[0]);
+            assert_eq!(Value::Double(row.1), values[1]);
+            assert_eq!(Value::Text(row.2.clone()), values[2]);
+            assert_eq!(Value::Bool(row.3), values[3]);
+            match values[4] {
+                Value::Timestamp(seconds) => assert_eq!(row.4, format_iso(seconds)),
+                ref other => panic!("expected a timestamp, got {:?}", other),
+            }
+        }
+        drop(statement);
+        drop(connection);
+
+        // The table's there now, so abort refuses and appending doubles it
+        options.if_exists = IfExists::Abort;
+        assert!(write_data(&options).is_err());
+        options.if_exists = IfExists::Append;
+        write_data(&options).unwrap();
+        let connection = Connection::open(&options.filename).unwrap();
+        let total: i64 = connection.query_row("SELECT count(*) FROM people", [], |row| row.get(0)).unwrap();
+        assert_eq!(total, 2 * options.count as i64);
+        std::fs::remove_file(&options.filename).unwrap();
+    }
+}
@@ -0,0 +1,247 @@
+//! Summary statistics over generated or loaded values.
+
+use std::fmt;
+
+use crate::generator::Value;
+use crate::histogram::Histogram;
+use crate::Options;
+
+/// Summary numbers for a set of values
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub std_dev: f64, // Population standard deviation
+    pub median_estimated: bool, // True when the median came from RunningStats rather than sorting every value
+    pub histogram: Option<Histogram>, // None from RunningStats, since the bins need the whole range up front
+}
+
+/// Works out the summary numbers - returns None for an empty list since there's no min or mean
+pub fn compute_stats(values: &[f64]) -> Option<Stats> {
+    if values.is_empty() {
+        return None;
+    }
+    let count = values.len();
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let mean = values.iter().sum::<f64>() / count as f64;
+    // Population standard deviation: square root of the average squared distance from the mean
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count as f64;
+
+    // The median needs sorted data - we sort a copy so the caller's order is untouched
+    // f64 doesn't implement Ord (because of NaN), so we use total_cmp instead of plain sort()
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = if count.is_multiple_of(2) {
+        (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0
+    } else {
+        sorted[count / 2]
+    };
+
+    Some(Stats {
+        count,
+        min,
+        max,
+        mean,
+        median,
+        std_dev: variance.sqrt(),
+        median_estimated: false,
+        histogram: Histogram::new(values),
+    })
+}
+
+/// Statistics kept up to date one value at a time, in a fixed amount of memory
+/// Everything is exact except the median, which is estimated with the P² algorithm
+/// (Jain and Chlamtac, 1985) - an exact median would need every value kept and sorted
+#[derive(Debug, Clone)]
+pub struct RunningStats {
+    count: usize,
+    min: f64,
+    max: f64,
+    mean: f64,
+    sum_squares: f64, // Sum of squared distances from the mean so far (Welford's method)
+    // The five P² markers: the min, the max, the median and halfway to it from each side
+    heights: [f64; 5],   // Estimated value at each marker
+    positions: [f64; 5], // Where each marker actually sits in the sorted values (0-based)
+    desired: [f64; 5],   // Where each marker ought to sit
+}
+
+// How far each desired position moves per value for the median: 0, 1/4, 1/2, 3/4, 1
+const MARKER_STEPS: [f64; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+
+impl RunningStats {
+    pub fn new() -> RunningStats {
+        RunningStats {
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            mean: 0.0,
+            sum_squares: 0.0,
+            heights: [0.0; 5],
+            positions: [0.0, 1.0, 2.0, 3.0, 4.0],
+            desired: [0.0, 1.0, 2.0, 3.0, 4.0],
+        }
+    }
+
+    pub fn push(&mut self, value: f64) {
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        // Welford's method updates the mean and spread without adding up huge sums,
+        // which would lose precision over billions of values
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.sum_squares += delta * (value - self.mean);
+
+        // The first five values just fill the markers, sorted
+        if self.count <= 5 {
+            self.heights[self.count - 1] = value;
+            self.heights[..self.count].sort_by(|a, b| a.total_cmp(b));
+            return;
+        }
+        // Find which gap between markers the value landed in, stretching the ends if needed
+        let cell = if value < self.heights[0] {
+            self.heights[0] = value;
+            0
+        } else if value >= self.heights[4] {
+            self.heights[4] = value;
+            3
+        } else {
+            (0..4).find(|&i| value < self.heights[i + 1]).unwrap_or(3)
+        };
+        for position in &mut self.positions[cell + 1..] {
+            *position += 1.0;
+        }
+        for (desired, step) in self.desired.iter_mut().zip(MARKER_STEPS) {
+            *desired += step;
+        }
+        // Nudge the middle markers one place towards where they should be
+        for i in 1..4 {
+            let off = self.desired[i] - self.positions[i];
+            let room_right = self.positions[i + 1] - self.positions[i] > 1.0;
+            let room_left = self.positions[i - 1] - self.positions[i] < -1.0;
+            if (off >= 1.0 && room_right) || (off <= -1.0 && room_left) {
+                let step = off.signum();
+                let guess = self.parabolic(i, step);
+                // Fall back to a straight line if the curve overshoots a neighbour
+                self.heights[i] = if self.heights[i - 1] < guess && guess < self.heights[i + 1] {
+                    guess
+                } else {
+                    self.linear(i, step)
+                };
+                self.positions[i] += step;
+            }
+        }
+    }
+
+    // Fits a parabola through marker i and its neighbours and reads off the height one step along
+    fn parabolic(&self, i: usize, step: f64) -> f64 {
+        let (q, n) = (&self.heights, &self.positions);
+        q[i] + step / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + step) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - step) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    fn linear(&self, i: usize, step: f64) -> f64 {
+        let j = if step > 0.0 { i + 1 } else { i - 1 };
+        self.heights[i] + step * (self.heights[j] - self.heights[i]) / (self.positions[j] - self.positions[i])
+    }
+
+    /// The summary so far - None until the first value arrives, like compute_stats
+    pub fn stats(&self) -> Option<Stats> {
+        if self.count == 0 {
+            return None;
+        }
+        // With five or fewer values the markers are just the sorted values, so the median is exact
+        let (median, median_estimated) = if self.count <= 5 {
+            let sorted = &self.heights[..self.count];
+            let median = if self.count.is_multiple_of(2) {
+                (sorted[self.count / 2 - 1] + sorted[self.count / 2]) / 2.0
+            } else {
+                sorted[self.count / 2]
+            };
+            (median, false)
+        } else {
+            (self.heights[2], true)
+        };
+        Some(Stats {
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            mean: self.mean,
+            median,
+            std_dev: (self.sum_squares / self.count as f64).sqrt(),
+            median_estimated,
+            histogram: None,
+        })
+    }
+}
+
+// Clippy likes a type with new() to have a Default too
+impl Default for RunningStats {
+    fn default() -> Self {
+        RunningStats::new()
+    }
+}
+
+// Gathers the values for the summary as the writer goes past them
+pub(crate) enum Collector {
+    Off,                   // Stats weren't asked for, so nothing is kept
+    All(Vec<f64>),         // Every value, for an exact median
+    Running(RunningStats), // Just running totals, so streaming runs stay in bounded memory
+}
+
+impl Collector {
+    pub(crate) fn new(options: &Options) -> Collector {
+        match (options.stats, options.streaming) {
+            (false, _) => Collector::Off,
+            (true, false) => Collector::All(Vec::new()),
+            (true, true) => Collector::Running(RunningStats::new()),
+        }
+    }
+
+    pub(crate) fn push(&mut self, value: f64) {
+        match self {
+            Collector::Off => {},
+            Collector::All(values) => values.push(value),
+            Collector::Running(running) => running.push(value),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> Option<Stats> {
+        match self {
+            Collector::Off => None,
+            Collector::All(values) => compute_stats(values),
+            Collector::Running(running) => running.stats(),
+        }
+    }
+}
+
+// Implementing Display means println!("{}", stats) just works, like giving a struct its own %-format in C
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Statistics:")?;
+        writeln!(f, "  Count:   {}", self.count)?;
+        writeln!(f, "  Min:     {}", self.min)?;
+        writeln!(f, "  Max:     {}", self.max)?;
+        writeln!(f, "  Mean:    {:.3}", self.mean)?;
+        if self.median_estimated {
+            writeln!(f, "  Median:  {:.3} (estimated)", self.median)?;
+        } else {
+            writeln!(f, "  Median:  {:.3}", self.median)?;
+        }
+        write!(f, "  Std dev: {:.3}", self.std_dev)
+    }
+}
+
+/// What a value adds to the stats when it isn't written as text - the same number the text formats would report
+pub(crate) fn stat_value(value: &Value) -> f64 {
+    match value {
+        // Display prints the shortest text that round-trips, so parsing it back avoids f32 -> f64 noise
+        Value::Float(num) => num.to_string().parse().unwrap(),
+        _ => value.as_f64(),
+    }
+}
@@ -0,0 +1,57 @@
+//! Letting values out at a steady rate, for feeding another program as if they were a live feed.
+//!
+//! `Throttle` wraps the values on their way to the writer and sleeps until each one is due.
+//! The times are worked out from when the run started (value n is due n / rate seconds in)
+//! rather than by sleeping a fixed gap after each value, so the time spent generating and
+//! writing doesn't add up into drift - after an hour at 100/s there are 360,000 values, not fewer.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::generator::Value;
+
+/// Sleeps before each value until it's due, at `per_second` values a second
+pub(crate) struct Throttle<I> {
+    inner: I,
+    per_second: Option<f64>, // None lets everything straight through
+    started: Instant,
+    sent: u64,
+}
+
+impl<I> Throttle<I> {
+    pub(crate) fn new(inner: I, per_second: Option<f64>) -> Self {
+        Throttle { inner, per_second, started: Instant::now(), sent: 0 }
+    }
+}
+
+impl<I: Iterator<Item = Value>> Iterator for Throttle<I> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        if let Some(per_second) = self.per_second {
+            // The first value goes straight away, and each one after it 1/rate later
+            let due = self.started + Duration::from_secs_f64(self.sent as f64 / per_second);
+            // Like nanosleep() in C - and if we're behind, there's nothing to wait for
+            if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                thread::sleep(wait);
+            }
+            self.sent += 1;
+        }
+        self.inner.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_come_out_at_the_rate() {
+        let values = (0..21).map(Value::Int);
+        let started = Instant::now();
+        assert_eq!(Throttle::new(values, Some(200.0)).count(), 21);
+        // 20 gaps of 5ms, give or take a busy machine
+        let took = started.elapsed();
+        assert!(took >= Duration::from_millis(100) && took < Duration::from_secs(2), "{:?}", took);
+    }
+}
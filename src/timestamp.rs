@@ -0,0 +1,105 @@
+//! Converting between Unix timestamps (seconds since 1970-01-01 UTC) and ISO-8601 text.
+//!
+//! Only UTC is supported, so there are no time zones or leap seconds to worry about -
+//! every day is exactly 86,400 seconds, which is what Unix time assumes too.
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// 0001-01-01T00:00:00Z, the earliest time ISO-8601 can write with a 4-digit year
+pub const MIN_TIMESTAMP: i64 = -62_135_596_800;
+
+/// 9999-12-31T23:59:59Z, the latest
+pub const MAX_TIMESTAMP: i64 = 253_402_300_799;
+
+/// Writes a timestamp like 2024-03-05T14:30:00Z
+pub fn format_iso(timestamp: i64) -> String {
+    // div_euclid rounds down even for negative numbers, unlike / which rounds toward zero like in C
+    let days = timestamp.div_euclid(SECONDS_PER_DAY);
+    let seconds = timestamp.rem_euclid(SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60
+    )
+}
+
+/// Reads 2024-03-05, 2024-03-05T14:30:00 or 2024-03-05T14:30:00Z (a space instead of the T works too)
+pub fn parse_iso(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let text = text.strip_suffix('Z').unwrap_or(text);
+    let (date, time) = match text.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+
+    let mut parts = date.split('-');
+    let year = parse_field(parts.next()?, 4)?;
+    let month = parse_field(parts.next()?, 2)?;
+    let day = parse_field(parts.next()?, 2)?;
+    if parts.next().is_some() {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    // Going back the other way catches dates like February 30th, which would otherwise roll over
+    if civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+
+    let seconds = match time {
+        Some(time) => {
+            let mut parts = time.split(':');
+            let hour = parse_field(parts.next()?, 2)?;
+            let minute = parse_field(parts.next()?, 2)?;
+            let second = parse_field(parts.next()?, 2)?;
+            if parts.next().is_some() || hour > 23 || minute > 59 || second > 59 {
+                return None;
+            }
+            hour * 3600 + minute * 60 + second
+        },
+        None => 0,
+    };
+    Some(days * SECONDS_PER_DAY + seconds)
+}
+
+/// Either form a timestamp can be written in: epoch seconds or ISO-8601
+pub fn parse_timestamp(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let timestamp = match text.parse::<i64>() {
+        Ok(seconds) => seconds,
+        Err(_) => parse_iso(text)?,
+    };
+    Some(timestamp).filter(|timestamp| (MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(timestamp))
+}
+
+// A fixed number of digits and nothing else - "2024-3-5" isn't ISO-8601
+fn parse_field(text: &str, digits: usize) -> Option<i64> {
+    if text.len() != digits || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+// Days since 1970-01-01 for a date, from Howard Hinnant's well-known date algorithms
+// The trick is to start the year in March, so the leap day lands at the very end
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400); // 400-year cycles, since that's when the leap years repeat
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// The reverse: (year, month, day) for a number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153; // 0 = March
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
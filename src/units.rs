@@ -0,0 +1,64 @@
+//! Reading numbers with a unit suffix, like 10k values or 1.5G bytes.
+//!
+//! The command line, the menu's prompts and generator.toml all take counts this way, so it
+//! lives here rather than in the CLI - the config file can read a count without depending on
+//! the code that parses flags. The suffixes are decimal (k is 1,000, not 1,024), and a decimal
+//! point is fine as long as the number still comes out whole: 2.5M is 2,500,000 but 1.5 isn't a count.
+
+use crate::error;
+use crate::invalid_input;
+
+/// A count like 5000, 10k, 2.5M or 1G - k, M and G are thousand, million and billion
+///
+/// Every flag and prompt that asks how many goes through this, and so does the config file's count
+pub fn parse_count(input: &str) -> error::Result<u32> {
+    let count = parse_suffixed(input, "values")?;
+    u32::try_from(count).map_err(|_| invalid_input(format!("{} is too many - the most is {}", input.trim(), u32::MAX)))
+}
+
+// A whole number with an optional k, M, G or T after it, and decimals as long as they come out even
+pub(crate) fn parse_suffixed(input: &str, unit: &str) -> error::Result<u64> {
+    let input = input.trim();
+    // The number is everything up to the first character that can't be part of one
+    let (number, suffix) = input.split_at(input.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(input.len()));
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() || fraction.contains('.') {
+        return Err(invalid_input("Invalid number"));
+    }
+    let multiplier: u64 = match suffix.trim() {
+        "" => 1,
+        "k" | "K" => 1_000,
+        "m" | "M" => 1_000_000,
+        "g" | "G" => 1_000_000_000,
+        "t" | "T" => 1_000_000_000_000,
+        other => return Err(invalid_input(format!("Unknown suffix {} in {} - use k (thousand), M (million), G (billion) or T (trillion)", other, input))),
+    };
+    // Worked out in whole numbers, since 2.3M in floating point is 2299999.9999...
+    let not_whole = || invalid_input(format!("{} isn't a whole number of {}", input, unit));
+    let fraction = fraction.trim_end_matches('0');
+    let scale = 10u64.checked_pow(fraction.len() as u32).filter(|&scale| scale <= multiplier).ok_or_else(not_whole)?;
+    let fraction_part = fraction.parse::<u64>().unwrap_or(0) * (multiplier / scale);
+    // checked_mul returns None on overflow instead of silently wrapping like C would
+    whole.parse::<u64>().ok()
+        .and_then(|whole| whole.checked_mul(multiplier))
+        .and_then(|total| total.checked_add(fraction_part))
+        .ok_or_else(|| invalid_input(format!("{} is too big", input)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_take_suffixes_and_decimals() {
+        for (input, count) in [("5000", 5000), ("10k", 10_000), ("2.5M", 2_500_000), (" 1G ", 1_000_000_000), ("2.30m", 2_300_000), ("4 K", 4000)] {
+            assert_eq!(parse_count(input).unwrap(), count, "{}", input);
+        }
+        let message = |input: &str| parse_count(input).unwrap_err().to_string();
+        assert!(message("10x").contains("Unknown suffix x"), "{}", message("10x"));
+        assert!(message("1.5").contains("isn't a whole number"));
+        assert!(message("1.2345k").contains("isn't a whole number"));
+        assert!(message("5G").contains("too many"));
+        assert_eq!(message("k"), "Invalid number");
+    }
+}
@@ -0,0 +1,73 @@
+//! Random (version 4) UUIDs, written in the usual 8-4-4-4-12 hex form.
+//!
+//! A UUID is just a 128-bit number, so it's kept as a u128 and only turned into text when
+//! written. Version 4 means all but six of the bits are random: four say "version 4" and
+//! two say "the standard RFC 9562 layout", which is what databases and APIs expect to see.
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+/// Characters in the text form, e.g. 550e8400-e29b-41d4-a716-446655440000
+pub const UUID_LENGTH: usize = 36;
+
+// Where the hyphens go in the text, counting characters
+const HYPHENS: [usize; 4] = [8, 13, 18, 23];
+
+/// 128 random bits with the version and variant bits set
+pub fn random_uuid(rng: &mut StdRng) -> u128 {
+    let bits: u128 = rng.gen();
+    // The version lives in the top four bits of the 7th byte, the variant in the top two of the 9th
+    // (clearing them with & and setting them with |, same as bit twiddling in C)
+    let bits = (bits & !(0xf << 76)) | (0x4 << 76);
+    (bits & !(0x3 << 62)) | (0x2 << 62)
+}
+
+/// The standard lowercase text form
+pub fn format_uuid(bits: u128) -> String {
+    // {:032x} pads the hex out to all 32 digits, like %032x would for a 128-bit int in C
+    let hex = format!("{:032x}", bits);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// Reads the 8-4-4-4-12 form back (either case), or None if it isn't one
+/// Any version is accepted, since a UUID from somewhere else is still a UUID
+pub fn parse_uuid(text: &str) -> Option<u128> {
+    let text = text.trim();
+    if text.len() != UUID_LENGTH {
+        return None;
+    }
+    let mut hex = String::with_capacity(32);
+    for (i, c) in text.char_indices() {
+        match c {
+            '-' if HYPHENS.contains(&i) => {},
+            _ if c.is_ascii_hexdigit() && !HYPHENS.contains(&i) => hex.push(c),
+            _ => return None,
+        }
+    }
+    u128::from_str_radix(&hex, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::make_rng;
+
+    #[test]
+    fn uuids_are_version_4_and_read_back() {
+        let mut rng = make_rng(Some(3));
+        for _ in 0..1000 {
+            let bits = random_uuid(&mut rng);
+            let text = format_uuid(bits);
+            assert_eq!(text.len(), UUID_LENGTH);
+            // The version digit and the variant digit sit at fixed places in the text
+            assert_eq!(&text[14..15], "4");
+            assert!("89ab".contains(&text[19..20]), "{}", text);
+            assert_eq!(parse_uuid(&text), Some(bits));
+            assert_eq!(parse_uuid(&text.to_uppercase()), Some(bits));
+        }
+        assert_eq!(format_uuid(0), "00000000-0000-0000-0000-000000000000");
+        assert_eq!(parse_uuid("550e8400e29b41d4a716446655440000"), None);
+        assert_eq!(parse_uuid("550e8400-e29b-41d4-a716-44665544000g"), None);
+        assert_eq!(parse_uuid("550e840-0e29b-41d4-a716-446655440000"), None);
+    }
+}
@@ -0,0 +1,484 @@
+//! Checking a data file is well formed: the count matches the header and every value is valid.
+
+use std::fmt;
+use std::iter::Peekable;
+
+use crate::binary::{is_binary, read_header};
+use crate::blob::parse_hex;
+use crate::error;
+use crate::fake::is_valid_fake;
+use crate::generator::{is_valid_label, DataType, ALL_DATA_TYPES};
+use crate::reader::{identifier_text, parse_bool, parse_matrix_header, read_bytes, split_matrix_row, to_text, uncomment_header};
+use crate::timestamp::{parse_iso, parse_timestamp};
+use crate::uuid::parse_uuid;
+use crate::writer::OutputFormat;
+
+/// What verify_file found - an empty `problems` list means the file is good
+#[derive(Debug)]
+pub struct Verification {
+    pub format: OutputFormat,
+    pub data_type: DataType,
+    pub declared_count: Option<u32>, // What the header claims, if the format has a header
+    pub found_count: u64,            // Values actually in the file, good or bad
+    pub problems: Vec<Problem>,
+}
+
+impl Verification {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// One thing wrong with the file
+#[derive(Debug)]
+pub struct Problem {
+    pub line: Option<usize>, // 1-based like an editor shows it; None for binary files and whole-file issues
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Re-reads a file and checks it line by line
+/// Unlike load_file this doesn't stop at the first bad value - it keeps going and
+/// reports everything it finds. Err only comes back if the file can't be read at all
+pub fn verify_file(filename: &str) -> error::Result<Verification> {
+    let bytes = uncomment_header(read_bytes(filename)?);
+    if bytes.starts_with(b"Count:") {
+        return Ok(verify_text(&to_text(bytes)?));
+    }
+    if bytes.starts_with(b"Matrix:") {
+        return Ok(verify_matrix(&to_text(bytes)?));
+    }
+    if bytes.starts_with(b"{") {
+        return Ok(verify_json(&to_text(bytes)?));
+    }
+    if is_binary(&bytes) {
+        return verify_binary(&bytes);
+    }
+    Ok(verify_csv(&to_text(bytes)?))
+}
+
+// Small helper so every check below can report a problem in one line
+fn problem(line: Option<usize>, message: impl Into<String>) -> Problem {
+    Problem { line, message: message.into() }
+}
+
+// Files that don't say what type they hold get it from their first value,
+// and every value after that has to match
+// Text files always say when they hold strings, so only CSV and JSON can guess one
+fn guess_type(token: &str, allow_string: bool) -> DataType {
+    let token = token.trim();
+    if parse_iso(token).is_some() {
+        DataType::Timestamp
+    } else if parse_uuid(token).is_some() {
+        DataType::Uuid
+    } else if identifier_text(DataType::Ipv4, token).is_some() {
+        DataType::Ipv4
+    } else if identifier_text(DataType::Ipv6, token).is_some() {
+        DataType::Ipv6
+    } else if matches!(token, "true" | "false") {
+        DataType::Boolean
+    } else if allow_string && token.parse::<f64>().is_err() {
+        DataType::String
+    } else if token.contains(['.', 'e', 'E']) {
+        DataType::Float
+    } else {
+        DataType::Integer
+    }
+}
+
+// Returns what's wrong with one value, or None if it's fine for the type
+// A guessed type only tells whole numbers from decimals (a text file of longs looks just
+// like one of ints), so the range is only checked when the file says what its type is
+fn check_value(token: &str, data_type: DataType, check_range: bool) -> Option<String> {
+    let token = token.trim();
+    if data_type.is_fake() {
+        if !is_valid_fake(data_type, token) {
+            return Some(format!("'{}' is not a valid {}", token, data_type.name()));
+        }
+        return None;
+    }
+    if data_type.is_string() {
+        // Random strings are letters and digits, and category labels can also have _ and -
+        if !is_valid_label(token) {
+            return Some(format!("'{}' is not a valid string value", token));
+        }
+        return None;
+    }
+    if data_type.is_bytes() {
+        if parse_hex(token).is_none() {
+            return Some(format!("'{}' is not hex bytes", token));
+        }
+        return None;
+    }
+    // Either way of writing a time is fine, as long as it's a real date
+    if data_type.is_timestamp() {
+        if parse_timestamp(token).is_none() {
+            return Some(format!("'{}' is not a valid timestamp", token));
+        }
+        return None;
+    }
+    if data_type.is_boolean() {
+        if parse_bool(token).is_none() {
+            return Some(format!("'{}' is not true, false, 1 or 0", token));
+        }
+        return None;
+    }
+    if data_type.is_identifier() {
+        if identifier_text(data_type, token).is_none() {
+            return Some(format!("'{}' is not a valid {}", token, data_type.name()));
+        }
+        return None;
+    }
+    let (lowest, highest) = data_type.limits();
+    let value = if data_type.is_integer() {
+        // i64 parsing rejects decimals and holds every integer type we write
+        match token.parse::<i64>() {
+            Ok(value) => value as f64,
+            Err(_) => return Some(format!("'{}' is not a whole number", token)),
+        }
+    } else {
+        match token.parse::<f64>() {
+            Ok(value) if value.is_finite() => value,
+            _ => return Some(format!("'{}' is not a number", token)),
+        }
+    };
+    if check_range && (value < lowest || value > highest) {
+        return Some(format!("{} is out of range for {}", token, data_type.name()));
+    }
+    None
+}
+
+fn count_problem(line: Option<usize>, declared: u32, found: u64) -> Option<Problem> {
+    if declared as u64 == found {
+        return None;
+    }
+    Some(problem(line, format!("Header says {} values but the file has {}", declared, found)))
+}
+
+fn verify_text(text: &str) -> Verification {
+    let mut problems = Vec::new();
+    // enumerate() counts from 0, so add 1 to get the line numbers people expect
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line)).peekable();
+
+    let declared_count = match lines.next() {
+        Some((number, line)) => match line.trim_start_matches("Count:").trim().parse::<u32>() {
+            Ok(count) => Some(count),
+            Err(_) => {
+                problems.push(problem(Some(number), "Malformed Count header"));
+                None
+            },
+        },
+        None => None,
+    };
+    let mut data_type = check_extra_headers(&mut lines, &mut problems);
+
+    let mut found_count = 0;
+    for (number, line) in lines {
+        // Appended stats are comments, not values
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        found_count += 1;
+        if line.trim().is_empty() {
+            problems.push(problem(Some(number), "Empty line where a value should be"));
+            continue;
+        }
+        let expected = *data_type.get_or_insert_with(|| guess_type(line, false));
+        if let Some(message) = check_value(line, expected, false) {
+            problems.push(problem(Some(number), message));
+        }
+    }
+
+    if let Some(declared) = declared_count {
+        problems.extend(count_problem(Some(1), declared, found_count));
+    }
+    Verification {
+        format: OutputFormat::Text,
+        data_type: data_type.unwrap_or(DataType::Integer),
+        declared_count,
+        found_count,
+        problems,
+    }
+}
+
+// Matrix files are checked like text files, except each line is a row that must be exactly C values wide
+fn verify_matrix(text: &str) -> Verification {
+    let mut problems = Vec::new();
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line)).peekable();
+
+    // lines can't be empty here - verify_file only sends files starting with "Matrix:"
+    let (rows, columns) = match lines.next().and_then(|(_, line)| parse_matrix_header(line)) {
+        Some((rows, columns)) => (Some(rows), Some(columns)),
+        None => {
+            problems.push(problem(Some(1), "Malformed Matrix header"));
+            (None, None)
+        },
+    };
+    let mut data_type = check_extra_headers(&mut lines, &mut problems);
+
+    let mut found_rows = 0;
+    let mut found_count = 0;
+    for (number, line) in lines {
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        found_rows += 1;
+        let cells: Vec<&str> = split_matrix_row(line).collect();
+        if let Some(columns) = columns {
+            if cells.len() != columns as usize {
+                problems.push(problem(Some(number), format!("Expected {} columns, found {}", columns, cells.len())));
+            }
+        }
+        for cell in cells {
+            found_count += 1;
+            let expected = *data_type.get_or_insert_with(|| guess_type(cell, false));
+            if let Some(message) = check_value(cell, expected, false) {
+                problems.push(problem(Some(number), message));
+            }
+        }
+    }
+
+    if let Some(rows) = rows.filter(|&rows| rows != found_rows) {
+        problems.push(problem(Some(1), format!("Header says {} rows but the file has {}", rows, found_rows)));
+    }
+    Verification {
+        format: OutputFormat::Matrix,
+        data_type: data_type.unwrap_or(DataType::Integer),
+        // checked_mul gives None instead of wrapping if R x C doesn't fit in a u32
+        declared_count: rows.zip(columns).and_then(|(rows, columns)| rows.checked_mul(columns)),
+        found_count,
+        problems,
+    }
+}
+
+// The optional "Seed:" and "Type:" lines that can follow the first line of a text or matrix file
+// Hands back the type if the file declares one
+fn check_extra_headers<'a, I>(lines: &mut Peekable<I>, problems: &mut Vec<Problem>) -> Option<DataType>
+where
+    I: Iterator<Item = (usize, &'a str)>,
+{
+    if let Some(&(number, line)) = lines.peek() {
+        if let Some(rest) = line.strip_prefix("Seed:") {
+            if rest.trim().parse::<u64>().is_err() {
+                problems.push(problem(Some(number), "Malformed Seed header"));
+            }
+            lines.next();
+        }
+    }
+    let mut data_type = None;
+    if let Some(&(number, line)) = lines.peek() {
+        if let Some(rest) = line.strip_prefix("Type:") {
+            match ALL_DATA_TYPES.iter().copied().find(|data_type| data_type.name() == rest.trim()) {
+                Some(declared) => data_type = Some(declared),
+                None => problems.push(problem(Some(number), format!("Unknown type: {}", rest.trim()))),
+            }
+            lines.next();
+        }
+    }
+    data_type
+}
+
+// CSV has no count header, so the checks are that every row is the same width and every cell is valid
+fn verify_csv(text: &str) -> Verification {
+    let mut problems = Vec::new();
+    let mut rows: Vec<(usize, &str)> = text.lines().enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .collect();
+    // Same rule as the reader: our own col1,col2,... line, or a row of words over rows of numbers, is the header
+    let numeric_row = |row: &str| row.split(',').all(|cell| cell.trim().parse::<f64>().is_ok());
+    let our_header = |row: &str| row.split(',').all(|cell| {
+        cell.trim().strip_prefix("col").is_some_and(|n| n.parse::<u32>().is_ok())
+    });
+    let mut has_header = false;
+    if let Some(&(_, first)) = rows.first() {
+        if our_header(first) || (!numeric_row(first) && rows[1..].iter().all(|(_, row)| numeric_row(row))) {
+            rows.remove(0);
+            has_header = true;
+        }
+    }
+
+    let mut data_type = None;
+    let mut columns = None;
+    let mut found_count = 0;
+    for (number, row) in rows {
+        let cells: Vec<&str> = row.split(',').collect();
+        let expected_columns = *columns.get_or_insert(cells.len());
+        if cells.len() != expected_columns {
+            problems.push(problem(Some(number), format!(
+                "Expected {} columns, found {}", expected_columns, cells.len()
+            )));
+        }
+        for cell in cells {
+            found_count += 1;
+            let expected = *data_type.get_or_insert_with(|| guess_type(cell, true));
+            if let Some(message) = check_value(cell, expected, false) {
+                problems.push(problem(Some(number), message));
+            }
+        }
+    }
+    // Anything without a header we know ends up here, so a README or a file of nothing but
+    // comments would otherwise pass as a CSV - one with no values, or one of single words
+    if found_count == 0 {
+        problems.push(problem(None, "No values found - this doesn't look like a data file"));
+    } else if !has_header && data_type == Some(DataType::String) {
+        problems.push(problem(None, "No recognisable header (Count:, Matrix: or a CSV header) and the values are words, so this doesn't look like a data file - write string CSVs with --header"));
+    }
+
+    Verification {
+        format: OutputFormat::Csv,
+        data_type: data_type.unwrap_or(DataType::Integer),
+        declared_count: None,
+        found_count,
+        problems,
+    }
+}
+
+fn verify_json(text: &str) -> Verification {
+    let mut problems = Vec::new();
+    // Line number of a byte offset - count the newlines before it
+    let line_at = |offset: usize| text[..offset].matches('\n').count() + 1;
+    let field = |key: &str| -> Option<(usize, String)> {
+        let start = text.find(&format!("\"{}\":", key))?;
+        let rest = &text[start + key.len() + 3..];
+        let end = rest.find([',', '}']).unwrap_or(rest.len());
+        Some((line_at(start), rest[..end].trim().trim_matches('"').to_string()))
+    };
+
+    let declared_count = match field("count") {
+        Some((line, count)) => match count.parse::<u32>() {
+            Ok(count) => Some(count),
+            Err(_) => {
+                problems.push(problem(Some(line), "Malformed \"count\" field"));
+                None
+            },
+        },
+        None => {
+            problems.push(problem(None, "Missing \"count\" field"));
+            None
+        },
+    };
+    let mut data_type: Option<DataType> = match field("type") {
+        Some((line, name)) => {
+            let found = ALL_DATA_TYPES.iter().copied().find(|data_type| data_type.name() == name);
+            if found.is_none() {
+                problems.push(problem(Some(line), format!("Unknown type: {}", name)));
+            }
+            found
+        },
+        None => None,
+    };
+    if let Some((line, seed)) = field("seed") {
+        if seed.parse::<u64>().is_err() {
+            problems.push(problem(Some(line), "Malformed \"seed\" field"));
+        }
+    }
+
+    let declared_type = data_type.is_some();
+    let mut found_count = 0;
+    let array = text.find("\"values\":").and_then(|start| {
+        let open = start + text[start..].find('[')?;
+        let close = open + text[open..].find(']')?;
+        Some((open, close))
+    });
+    match array {
+        // [] is a fine empty array, so only split when there's something inside
+        Some((open, close)) if !text[open + 1..close].trim().is_empty() => {
+            let mut offset = open + 1;
+            for token in text[open + 1..close].split(',') {
+                // Point at where the value starts, not at the newline before it
+                let line = line_at(offset + token.len() - token.trim_start().len());
+                offset += token.len() + 1;
+                found_count += 1;
+                let token = token.trim();
+                if token.is_empty() {
+                    problems.push(problem(Some(line), "Empty value in array"));
+                    continue;
+                }
+                let quoted = token.len() >= 2 && token.starts_with('"') && token.ends_with('"');
+                let expected = *data_type.get_or_insert_with(|| guess_type(token.trim_matches('"'), true));
+                // Text, blobs, UUIDs and addresses need their quotes and numbers mustn't have them
+                // Timestamps can go either way - quoted dates or plain seconds
+                if quoted != (expected.is_text() || expected.is_bytes() || expected.is_identifier()) && !expected.is_timestamp() {
+                    problems.push(problem(Some(line), format!("{} is not a valid {} value", token, expected.name())));
+                } else if let Some(message) = check_value(token.trim_matches('"'), expected, declared_type) {
+                    problems.push(problem(Some(line), message));
+                }
+            }
+        },
+        Some(_) => {},
+        None => problems.push(problem(None, "Missing or malformed \"values\" array")),
+    }
+
+    if let Some(declared) = declared_count {
+        problems.extend(count_problem(field("count").map(|(line, _)| line), declared, found_count));
+    }
+    Verification {
+        format: OutputFormat::Json,
+        data_type: data_type.unwrap_or(DataType::Integer),
+        declared_count,
+        found_count,
+        problems,
+    }
+}
+
+// Binary has no lines, so problems point at the value number instead
+// A header that can't be read means there's nothing to check the values against, so that's an Err
+fn verify_binary(bytes: &[u8]) -> error::Result<Verification> {
+    let header = read_header(bytes)?;
+    let (data_type, declared, order) = (header.data_type, header.count, header.endianness);
+    let mut problems = Vec::new();
+    let body = &bytes[header.size() as usize..];
+    let size = data_type.size();
+    if !body.len().is_multiple_of(size) {
+        problems.push(problem(None, format!("{} stray bytes at the end of the file", body.len() % size)));
+    }
+    let found_count = (body.len() / size) as u64;
+    // Every bit pattern is a valid integer, but floats can hold NaN and infinity
+    // and a boolean byte can only be 0 or 1
+    if data_type.is_boolean() {
+        for (i, byte) in body.iter().enumerate() {
+            if *byte > 1 {
+                problems.push(problem(None, format!("value {}: {} is not a boolean (0 or 1)", i + 1, byte)));
+            }
+        }
+    } else if data_type.has_decimals() {
+        for (i, chunk) in body.chunks_exact(size).enumerate() {
+            let value = match data_type {
+                DataType::Float => f32::from_le_bytes(order.little(chunk)) as f64,
+                _ => f64::from_le_bytes(order.little(chunk)),
+            };
+            if !value.is_finite() {
+                problems.push(problem(None, format!("value {}: {} is not a finite number", i + 1, value)));
+            }
+        }
+    }
+    problems.extend(count_problem(None, declared, found_count));
+    Ok(Verification { format: OutputFormat::Binary, data_type, declared_count: Some(declared), found_count, problems })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_that_arent_data_fail() {
+        // Headings and comments leave no values at all, and a line of words has no header
+        for text in ["# Notes\n\n# More notes\n", "", "hello\nworld\n"] {
+            let report = verify_csv(text);
+            assert!(!report.is_ok(), "{:?} passed", text);
+        }
+        // A CSV without a header is still fine when its values are numbers, and strings are
+        // fine under a header
+        assert!(verify_csv("1,2\n3,4\n").is_ok());
+        assert!(verify_csv("col1\nabc\nxyz9\n").is_ok());
+    }
+}
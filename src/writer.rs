@@ -0,0 +1,1600 @@
+//! Writing generated values out in each of the supported formats.
+
+use std::fs::{self, File, OpenOptions}; // Like FILE* in C but with Rust's safety features
+use std::io::{self, Write, BufWriter, Seek, SeekFrom}; // How we handle I/O, like stdio.h in C
+use std::net::IpAddr;
+use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use flate2::write::GzEncoder; // gzip, the same compression as the gzip command
+use flate2::Compression;
+use log::{debug, warn};
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::atomic::{publish, remove_stale_temp, temp_name};
+use crate::binary::write_header;
+use crate::blob::{format_hex, parse_hex, write_blob_files, writes_blob_files};
+use crate::checkpoint::{checkpoint_name, load_checkpoint, remove_checkpoint, resume_blocker, Checkpoint, Checkpoints};
+use crate::checksum::write_checksum;
+use crate::code::{write_code, Language};
+use crate::columnar::write_parquet;
+use crate::error::{self, GeneratorError};
+use crate::fifo::write_fifo;
+use crate::framing::Framed;
+use crate::generator::{make_rng, value_source, DataType, Value};
+use crate::interrupt::{finish_blocker, interrupted, stops_between_rows, OnInterrupt, StopOnInterrupt, Writing};
+use crate::mapped::MappedFile;
+use crate::numtext::{write_decimals, write_integer};
+use crate::pipeline::{start_pipeline, worth_pipelining};
+use crate::schema::field_options;
+use crate::shard::{output_files, shard_name, shard_rows, write_shard_manifest};
+use crate::socket::send;
+use crate::space::check_space;
+use crate::sqlite::write_table;
+use crate::reader::load_file;
+use crate::stats::{Collector, Stats};
+use crate::throttle::Throttle;
+use crate::timestamp::format_iso;
+use crate::uuid::{format_uuid, parse_uuid, UUID_LENGTH};
+use crate::{invalid_data, invalid_input, unsupported_format, Graph, Options};
+
+/// How the values are laid out in the file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text, // One value per line under a "Count:" header
+    Csv,  // Rows of comma-separated columns
+    Json, // An object with "count", "type" and "values" fields
+    Binary, // Raw values after a small header, see binary.rs
+    Matrix, // A "Matrix: R x C" header, then one row per line
+    JsonLines, // One JSON object per line, for records with a schema
+    Parquet, // Apache Parquet columns, see columnar.rs
+    Sqlite, // Rows inserted into a table in a SQLite database, see sqlite.rs
+    FixedWidth, // Every value padded to the same width, with nothing between them
+    Code(Language), // An array in a program's source, see code.rs
+}
+
+impl OutputFormat {
+    /// The spelling --format takes, for messages and the shard manifest - a code format goes by
+    /// its --language, since that's what says which kind of source it is
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::Binary => "binary",
+            OutputFormat::Matrix => "matrix",
+            OutputFormat::JsonLines => "jsonl",
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Sqlite => "sqlite",
+            OutputFormat::FixedWidth => "fixed",
+            OutputFormat::Code(language) => language.flag(),
+        }
+    }
+
+    /// Both JSON formats quote strings and dates the same way
+    pub fn is_json(self) -> bool {
+        matches!(self, OutputFormat::Json | OutputFormat::JsonLines)
+    }
+
+    /// Formats that lay plain values out in rows of options.columns - the rest are one long list
+    pub fn has_rows(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Csv | OutputFormat::Matrix | OutputFormat::FixedWidth | OutputFormat::Parquet | OutputFormat::Sqlite | OutputFormat::Code(_)
+        )
+    }
+}
+
+/// How timestamps are written in text-based formats (binary files always hold the seconds)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeFormat {
+    Iso,   // 2024-03-05T14:30:00Z - readable, and sorts correctly as plain text
+    Epoch, // 1709649000 - seconds since 1970, like a time_t in C
+}
+
+/// How booleans are written in text-based formats (binary files always use one byte, 0 or 1)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoolFormat {
+    Words,  // true / false - also what JSON uses, so no quotes needed
+    Digits, // 1 / 0, like C
+}
+
+/// How numbers are written in text-based formats, for programs that expect a local style
+/// like 1.234,56 instead of 1234.56 (JSON and binary always use the plain style)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberFormat {
+    pub decimal: char,           // '.' or ','
+    pub thousands: Option<char>, // Put between each group of three digits, e.g. 1,234,567
+}
+
+impl Default for NumberFormat {
+    // Plain Rust/C style: no grouping and a decimal point
+    fn default() -> Self {
+        NumberFormat { decimal: '.', thousands: None }
+    }
+}
+
+/// How the integer types are written in text-based formats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IntFormat {
+    Decimal, // 1023
+    Hex,     // 0x3FF, like printf("%#X") in C
+    Binary,  // 0b1111111111, for bit masks and register values
+}
+
+/// Characters that can go between groups of thousands
+pub const THOUSANDS_SEPARATORS: [char; 5] = [',', '.', ' ', '\'', '_'];
+
+impl NumberFormat {
+    /// The plain style, which numbers are already written in
+    pub fn is_plain(&self) -> bool {
+        *self == NumberFormat::default()
+    }
+
+    /// Whether `c` can turn up inside a number written this way
+    pub fn uses(&self, c: char) -> bool {
+        self.decimal == c || self.thousands == Some(c)
+    }
+
+    /// Checks the separators can be told apart when the number is read back
+    pub fn validate(&self) -> error::Result<()> {
+        if self.decimal != '.' && self.decimal != ',' {
+            return Err(invalid_input("Decimal separator must be a point or a comma"));
+        }
+        if let Some(thousands) = self.thousands {
+            if !THOUSANDS_SEPARATORS.contains(&thousands) {
+                return Err(invalid_input("Thousands separator must be a comma, point, space, apostrophe or underscore"));
+            }
+            if thousands == self.decimal {
+                return Err(invalid_input("The thousands and decimal separators must be different"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rewrites a plainly written number like "-1234567.89" or "1.5e3" in this style
+    pub fn apply(&self, plain: &str) -> String {
+        let (sign, rest) = match plain.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", plain),
+        };
+        // Only the part before the e gets separators - grouping an exponent would be odd
+        let (mantissa, exponent) = rest.split_at(rest.find('e').unwrap_or(rest.len()));
+        let (whole, fraction) = match mantissa.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (mantissa, None),
+        };
+        let grouping = self.thousands.filter(|_| exponent.is_empty());
+        let mut text = String::from(sign);
+        for (i, digit) in whole.chars().enumerate() {
+            // A separator goes wherever the digits left to write are a multiple of three
+            if let Some(separator) = grouping {
+                if i > 0 && (whole.len() - i) % 3 == 0 {
+                    text.push(separator);
+                }
+            }
+            text.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            text.push(self.decimal);
+            text.push_str(fraction);
+        }
+        text.push_str(exponent);
+        text
+    }
+}
+
+/// Characters per field in fixed-width files when --width isn't given
+pub const DEFAULT_FIELD_WIDTH: usize = 12;
+
+/// Widest field allowed - well past any value we generate, short of a huge --length
+pub const MAX_FIELD_WIDTH: usize = 10_000;
+
+/// Which side of a fixed-width field the value sits on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,  // Padding after the value, like %-12s in C
+    Right, // Padding before it, like %12s - the usual choice for numbers
+}
+
+/// How values are padded out in fixed-width files, for legacy parsers that find each
+/// field by its position on the line rather than by a separator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldWidth {
+    pub width: usize, // Characters per field
+    pub fill: char,   // What the padding is made of, usually ' ' or '0'
+    pub align: Align,
+}
+
+impl Default for FieldWidth {
+    fn default() -> Self {
+        FieldWidth { width: DEFAULT_FIELD_WIDTH, fill: ' ', align: Align::Right }
+    }
+}
+
+impl FieldWidth {
+    pub fn validate(&self) -> error::Result<()> {
+        if self.width == 0 || self.width > MAX_FIELD_WIDTH {
+            return Err(invalid_input(format!("Field width must be between 1 and {}", MAX_FIELD_WIDTH)));
+        }
+        // A newline or tab in the padding would break the lines apart
+        if self.fill.is_control() {
+            return Err(invalid_input("The fill character must be printable"));
+        }
+        Ok(())
+    }
+
+    /// Pads the text out to the width, or fails if it's already too wide - cutting it
+    /// down would quietly hand the parser a different value
+    pub fn pad(&self, text: &str) -> error::Result<String> {
+        // chars() rather than len(), which counts bytes - a fill like '·' is two bytes but one column
+        let length = text.chars().count();
+        if length > self.width {
+            return Err(invalid_data(format!(
+                "{} doesn't fit in a {}-character field (raise --width)", text, self.width
+            )));
+        }
+        let padding: String = std::iter::repeat_n(self.fill, self.width - length).collect();
+        Ok(match self.align {
+            Align::Left => format!("{}{}", text, padding),
+            // Zeros go between the sign (or 0x) and the digits, like %012d and %#010x in C:
+            // -0000042 rather than 0000-42, and 0x0003FF rather than 0000x3FF
+            Align::Right if self.fill.is_ascii_digit() => {
+                let (prefix, digits) = text.split_at(prefix_length(text));
+                format!("{}{}{}", prefix, padding, digits)
+            },
+            Align::Right => format!("{}{}", padding, text),
+        })
+    }
+}
+
+/// How the header of text and matrix files is written, for programs that choke on a
+/// "Count:" line they didn't expect
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeaderStyle {
+    Plain,            // Count: N (or Matrix: R x C), then the Seed: and Type: lines
+    Comment,          // The same lines with "# " in front, for tools that skip comments
+    Omit,             // Nothing but the values
+    Template(String), // One line of your own, with the placeholders below filled in
+}
+
+/// How the values of a text file are laid out: what goes between them, and what goes at the
+/// start and end of each line, for pasting into code like `int data[] = {1, 2, 3};`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineLayout {
+    pub delimiter: String, // Between values - "\n" (the default) puts each on a line of its own
+    pub prefix: String,    // Before each line, or before the whole list when it's on one line
+    pub suffix: String,    // After each line, or after the whole list
+}
+
+impl Default for LineLayout {
+    fn default() -> Self {
+        LineLayout { delimiter: String::from("\n"), prefix: String::new(), suffix: String::new() }
+    }
+}
+
+impl LineLayout {
+    /// The usual one value per line, with nothing added
+    pub fn is_plain(&self) -> bool {
+        *self == LineLayout::default()
+    }
+
+    pub fn is_one_per_line(&self) -> bool {
+        self.delimiter == "\n"
+    }
+
+    pub fn validate(&self) -> error::Result<()> {
+        // Values run together would be one long number
+        if self.delimiter.is_empty() {
+            return Err(invalid_input("The delimiter can't be empty"));
+        }
+        // A prefix or suffix with a newline would make lines with no value on them
+        if [&self.prefix, &self.suffix].iter().any(|text| text.contains(['\n', '\r'])) {
+            return Err(invalid_input("The line prefix and suffix can't have newlines in them"));
+        }
+        Ok(())
+    }
+}
+
+/// What a --header-template can have filled in
+pub const HEADER_PLACEHOLDERS: [&str; 5] = ["{count}", "{columns}", "{type}", "{seed}", "{timestamp}"];
+
+impl HeaderStyle {
+    pub fn validate(&self) -> error::Result<()> {
+        let HeaderStyle::Template(template) = self else { return Ok(()) };
+        // The header is one line, so a newline would make the values start early
+        if template.contains(['\n', '\r']) {
+            return Err(invalid_input("A header template has to be one line"));
+        }
+        // Anything in braces that isn't a placeholder is most likely a typo like {cuont}
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..].find('}').map_or(rest.len(), |end| start + end + 1);
+            if !HEADER_PLACEHOLDERS.contains(&&rest[start..end]) {
+                return Err(invalid_input(format!(
+                    "Unknown placeholder {} in the header template (use {})", &rest[start..end], HEADER_PLACEHOLDERS.join(", ")
+                )));
+            }
+            rest = &rest[end..];
+        }
+        Ok(())
+    }
+
+    /// The template with the file's details filled in - a file with no seed gets "none"
+    pub fn fill(template: &str, count: u32, seed: Option<u64>, options: &Options) -> String {
+        // The time the file was written, which is the one thing here a seed can't make again
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs() as i64);
+        template
+            .replace("{count}", &count.to_string())
+            .replace("{columns}", &options.columns.to_string())
+            .replace("{type}", options.data_type.name())
+            .replace("{seed}", &seed.map_or_else(|| String::from("none"), |seed| seed.to_string()))
+            .replace("{timestamp}", &format_iso(now))
+    }
+}
+
+// How much of a number comes before its digits: a sign and/or a 0x or 0b
+fn prefix_length(text: &str) -> usize {
+    let sign = if text.starts_with(['-', '+']) { 1 } else { 0 };
+    match text[sign..].get(..2) {
+        Some("0x") | Some("0b") => sign + 2,
+        _ => sign,
+    }
+}
+
+/// What to do when the output file is already there
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IfExists {
+    Overwrite, // Start the file over, like fopen(name, "w")
+    Append,    // Keep what's there, add the new values and fix up the count
+    Abort,     // Leave the file alone and fail
+}
+
+/// What's written in place of a missing value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MissingToken {
+    Empty, // Nothing at all: 1,,3 in CSV, and null in JSON
+    Null,  // NULL, the way databases export it (null in JSON)
+    NaN,   // NaN, the way pandas and numpy write it (a bare NaN in JSON too, which Python's json reads)
+}
+
+impl MissingToken {
+    pub fn text(self, json: bool) -> &'static str {
+        match self {
+            MissingToken::Empty | MissingToken::Null if json => "null",
+            MissingToken::Empty => "",
+            MissingToken::Null => "NULL",
+            MissingToken::NaN => "NaN",
+        }
+    }
+}
+
+// Mixed into the seed for picking missing values, so the picks are a different stream from
+// the values themselves - with the same seed, the smallest values would be the ones to go
+const MISSING_SEED_MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// Picks which values are written as missing, with an RNG of its own
+// The values are still generated either way, so the ones left are the same as without --missing
+pub(crate) struct Blanks {
+    rng: Option<StdRng>, // None when nothing goes missing, so no time is spent rolling for it
+    chance: f64,
+    token: MissingToken,
+}
+
+impl Blanks {
+    pub(crate) fn new(options: &Options) -> Blanks {
+        let rng = (options.missing > 0.0).then(|| make_rng(options.seed.map(|seed| seed ^ MISSING_SEED_MIX)));
+        Blanks { rng, chance: options.missing / 100.0, token: options.missing_token }
+    }
+
+    // Rolls for the values already in a resumed file, so the rest of it carries on the same
+    fn skip(&mut self, values: u64) {
+        if let Some(rng) = &mut self.rng {
+            for _ in 0..values {
+                rng.gen_bool(self.chance);
+            }
+        }
+    }
+
+    // Writes the token and says so when this value is one of the missing ones
+    pub(crate) fn write_missing(&mut self, writer: &mut impl Write, options: &Options) -> error::Result<bool> {
+        let missing = self.rng.as_mut().is_some_and(|rng| rng.gen_bool(self.chance));
+        if missing {
+            write!(writer, "{}", self.token.text(options.format.is_json()))?;
+        }
+        Ok(missing)
+    }
+}
+
+/// The part that actually writes the file - used by the menu, the flags and library users
+/// Hands back the statistics when they were asked for (None otherwise, or for an empty file)
+pub fn write_data(options: &Options) -> error::Result<Option<Stats>> {
+    options.validate()?;
+    log_settings(options);
+    // Before anything is written, so a file that won't fit costs nothing
+    check_space(options)?;
+    if options.shards > 1 {
+        return write_shards(options);
+    }
+    if options.format == OutputFormat::Sqlite {
+        return write_database(options);
+    }
+    if let Some(socket) = options.socket() {
+        return send(&socket, options);
+    }
+    if options.writes_to_fifo() {
+        return write_fifo(options);
+    }
+    if writes_blob_files(options) {
+        return write_blob_files(options);
+    }
+    let to_stdout = options.writes_to_stdout();
+    // The file is written as <filename>.tmp and renamed when it's complete, so anything watching
+    // the directory never sees half of it. A .tmp from a run that crashed goes first, unless
+    // it's the one being resumed
+    let temp_name = temp_name(&options.filename);
+    if !to_stdout {
+        if options.if_exists == IfExists::Abort && fs::metadata(&options.filename).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists (choose overwrite or append to replace or extend it)", options.filename),
+            ).into());
+        }
+        if !options.resume {
+            remove_stale_temp(&options.filename)?;
+        }
+    }
+    if options.mmap {
+        if let Some(file) = MappedFile::create(&temp_name, options)? {
+            return write_mapped(file, options);
+        }
+        warn!("{} can't be memory-mapped here, so it's being written through the buffer instead", options.filename);
+    }
+    // A stopwatch that's restarted after each step, for the breakdown --verbose shows
+    let mut timer = Instant::now();
+    // While this is alive, Ctrl-C stops the run between rows instead of killing the program
+    // Output on stdout has no file to tidy up, so that still just stops
+    let writing = (!to_stdout).then(Writing::start);
+    let appending = !to_stdout && options.if_exists == IfExists::Append && fs::metadata(&options.filename).is_ok();
+    let existing = if appending { existing_values(options)? } else { Vec::new() };
+    // How far an interrupted run got, when we're picking one back up
+    let checkpoint = if options.resume { Some(load_checkpoint(options)?) } else { None };
+    // Box<dyn Write> holds either a file or stdout, like a FILE* that might be stdout in C
+    // (+ Send, since the Parquet writer insists on being able to move between threads)
+    // Appending writes a fresh copy too, so a failure halfway through can't eat the data that
+    // was already there
+    let file: Box<dyn Write + Send> = match checkpoint {
+        _ if to_stdout => Box::new(io::stdout()),
+        Some(ref checkpoint) => Box::new(open_at_checkpoint(&temp_name, checkpoint)?),
+        None => Box::new(File::create(&temp_name)?),
+    };
+    let file: Box<dyn Write + Send> = if stops_between_rows(options) { file } else { Box::new(StopOnInterrupt(file)) };
+    // with_capacity is like setvbuf() in C - a bigger buffer means fewer, larger writes
+    let mut writer = BufWriter::with_capacity(options.write_buffer_size(), OutputFile::new(file, options));
+    let start = checkpoint.map_or(0, |checkpoint| checkpoint.rows);
+    // A fresh file makes any checkpoint left over from an earlier run of the same name meaningless
+    if !options.resume && !to_stdout {
+        remove_checkpoint(&checkpoint_name(&options.filename))?;
+    }
+    let mut checkpoints = match resume_blocker(options) {
+        None => Checkpoints::new(options, start),
+        Some(_) => Checkpoints::counting(options),
+    };
+    debug!("Opened {} in {:.3}s (starting at row {})", options.filename, lap(&mut timer), start);
+    let values_per_row = options.values_per_row() as u64;
+    let total = (options.count - start) as u64 * values_per_row;
+    // A seed makes the same values again, so the ones already in the file are generated and
+    // thrown away - still much quicker than writing them. Without a seed there's nothing to
+    // match up with, so only the rows still missing are generated
+    let rest;
+    let source_options = if start > 0 && options.seed.is_none() {
+        rest = Options { count: options.count - start, ..options.clone() };
+        &rest
+    } else {
+        options
+    };
+    let skip = if options.seed.is_some() { start as u64 * values_per_row } else { 0 };
+    // The header counts rows, so work out how many rows the old values made up
+    let count = options.count + (existing.len() / options.columns as usize) as u32;
+    // A seed can only regenerate the values it made, so it's left out once a file is a mix
+    let seed = options.seed.filter(|_| !appending);
+    let mut collector = Collector::new(options);
+    let mut blanks = Blanks::new(options);
+    blanks.skip(skip);
+    let rng = &mut make_rng(options.seed);
+    // With a spare core the values are generated on a thread of their own (see pipeline.rs) while
+    // this one writes. A scope makes sure that thread has finished before we get past it, so it
+    // can borrow the options
+    let written = thread::scope(|scope| {
+        let values: Box<dyn Iterator<Item = Value>> = if worth_pipelining() {
+            Box::new(start_pipeline(scope, source_options, skip)?)
+        } else {
+            Box::new(value_source(rng, source_options)?.skip(skip as usize))
+        };
+        // Sorted, unique and sampled values are all made before the first one comes through
+        debug!("Set up the values in {:.3}s", lap(&mut timer));
+        // chain() puts the old values first, then carries on with the new ones
+        // A --rate paces only the new values - the old ones are already in the file
+        let source = existing.into_iter().chain(Progress::new(Throttle::new(values, options.rate), total, options.progress));
+        write_body(&mut writer, source, count, seed, options, &mut collector, &mut blanks, &mut checkpoints)
+    });
+    if written.is_err() && interrupted() {
+        return stop_early(writer, checkpoints.rows(), count, seed, appending, options);
+    }
+    written?;
+    // Every row is in, so there's nothing left to stop early - Ctrl-C during the last
+    // flush stops the program the usual way
+    drop(writing);
+    debug!("Generated and wrote {} values in {:.3}s", total, lap(&mut timer));
+
+    // into_inner() flushes the buffer and hands back the file so gzip can write its ending
+    writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+    checkpoints.finish()?;
+    if !to_stdout {
+        publish(&options.filename, options.if_exists != IfExists::Abort)?;
+    }
+    debug!("Flushed and closed the file in {:.3}s", lap(&mut timer));
+    // Has to wait until the file is complete, so it's the very last thing
+    if options.checksum {
+        write_checksum(&options.filename)?;
+        debug!("Wrote the checksum in {:.3}s", lap(&mut timer));
+    }
+    Ok(collector.stats())
+}
+
+// What --verbose shows before a file is written: the settings that explain how fast it
+// went and which values came out
+fn log_settings(options: &Options) {
+    match options.seed {
+        Some(seed) => debug!("Seed: {}", seed),
+        None => debug!("Seed: none, so the values come from the system's randomness and can't be made again"),
+    }
+    debug!(
+        "Format: {}, {} rows of {} values, {} thread(s)",
+        options.format.name(), options.count, options.values_per_row(), options.threads
+    );
+    debug!(
+        "Buffer: {} bytes{}{}",
+        options.buffer_size,
+        if options.compress { ", gzipped" } else { "" },
+        if options.mmap { ", memory-mapped if possible" } else { "" },
+    );
+}
+
+// Ctrl-C stopped the run after `rows` complete rows, so the file is finished off with them,
+// deleted or left for --resume, as options.on_interrupt says
+fn stop_early(
+    writer: BufWriter<OutputFile>,
+    rows: u32,
+    count: u32,
+    seed: Option<u64>,
+    appending: bool,
+    options: &Options,
+) -> error::Result<Option<Stats>> {
+    // The percentage was left part way along its line
+    if options.progress {
+        eprintln!();
+    }
+    let name = &options.filename;
+    let temp_name = temp_name(name);
+    let message = match options.on_interrupt {
+        // Whatever the setting, the half-made copy goes and the original stays as it was
+        _ if appending => {
+            drop(writer);
+            fs::remove_file(&temp_name)?;
+            format!("Interrupted - {} was left as it was before", name)
+        },
+        OnInterrupt::Finish if finish_blocker(options).is_none() => {
+            writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+            fix_count(&temp_name, rows, count, seed, options)?;
+            // The file is complete now, just shorter, so there's nothing to resume
+            remove_checkpoint(&checkpoint_name(name))?;
+            publish(name, options.if_exists != IfExists::Abort)?;
+            format!("Interrupted after {} of {} rows - {} was finished with those", rows, count, name)
+        },
+        // Dropping the writer flushes the complete rows, and resuming cuts back to the checkpoint anyway
+        OnInterrupt::Keep => {
+            drop(writer);
+            match resume_blocker(options) {
+                None => format!("Interrupted - {} was left as it is, to finish with --resume", temp_name),
+                Some(reason) => format!("Interrupted - {} was left half written, and can't be resumed ({})", temp_name, reason),
+            }
+        },
+        action => {
+            drop(writer);
+            fs::remove_file(&temp_name)?;
+            remove_checkpoint(&checkpoint_name(name))?;
+            match finish_blocker(options) {
+                Some(reason) if action == OnInterrupt::Finish => {
+                    format!("Interrupted - {} can't be finished part way ({}), so it was deleted", name, reason)
+                },
+                _ => format!("Interrupted - {} was deleted", name),
+            }
+        },
+    };
+    Err(GeneratorError::Interrupted(message))
+}
+
+// Puts the rows that were actually written in the header, which still promises all of them
+// The seed goes too, since it makes the whole file rather than the rows that got written
+fn fix_count(path: &str, rows: u32, count: u32, seed: Option<u64>, options: &Options) -> error::Result<()> {
+    let header = |rows: u32, seed: Option<u64>| -> error::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        match options.format {
+            OutputFormat::Text => write_text_header(&mut bytes, &format!("Count: {}", rows), rows, seed, options)?,
+            OutputFormat::Matrix => {
+                write_text_header(&mut bytes, &format!("Matrix: {} x {}", rows, options.columns), rows, seed, options)?;
+            },
+            // Binary output is only allowed for types with a tag, so there is one
+            OutputFormat::Binary => write_header(&mut bytes, options.data_type.binary_tag().unwrap(), rows, options.endianness)?,
+            // A CSV header is just the column names, which haven't changed
+            _ => {},
+        }
+        Ok(bytes)
+    };
+    let (old, new) = (header(count, seed)?, header(rows, None)?);
+    if old == new {
+        return Ok(());
+    }
+    // A binary header is always the same size, so the new one goes straight over the old one
+    if old.len() == new.len() {
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        return Ok(file.write_all(&new)?);
+    }
+    // Otherwise the rows have to move, so they're copied in behind the new header next door
+    // and swapped in
+    let temp = temp_name(path);
+    let mut input = File::open(path)?;
+    input.seek(SeekFrom::Start(old.len() as u64))?;
+    let mut output = BufWriter::new(File::create(&temp)?);
+    output.write_all(&new)?;
+    io::copy(&mut input, &mut output)?;
+    output.into_inner().map_err(|e| e.into_error())?;
+    Ok(fs::rename(&temp, path)?)
+}
+
+// Seconds since `since`, starting it again for the next step
+fn lap(since: &mut Instant) -> f64 {
+    let seconds = since.elapsed().as_secs_f64();
+    *since = Instant::now();
+    seconds
+}
+
+// The values go straight into the mapped file, which is already its final size
+// Checkpoints are off, since the pages reach the disk in whatever order the OS likes
+fn write_mapped(mut file: MappedFile, options: &Options) -> error::Result<Option<Stats>> {
+    remove_checkpoint(&checkpoint_name(&options.filename))?;
+    let rng = &mut make_rng(options.seed);
+    let total = options.count as u64 * options.values_per_row() as u64;
+    let source = Progress::new(value_source(rng, options)?, total, options.progress);
+    let mut collector = Collector::new(options);
+    let mut rest = file.bytes();
+    write_body(&mut rest, source, options.count, options.seed, options, &mut collector, &mut Blanks::new(options), &mut Checkpoints::off())?;
+    // Anything left over would mean binary_len and write_binary disagree about the size
+    if !rest.is_empty() {
+        return Err(invalid_data(format!("{} bytes of the mapped file were never written", rest.len())));
+    }
+    file.finish()?;
+    publish(&options.filename, options.if_exists != IfExists::Abort)?;
+    if options.checksum {
+        write_checksum(&options.filename)?;
+    }
+    Ok(collector.stats())
+}
+
+/// Same as write_data, but into any writer instead of options.filename - a socket, a pipe,
+/// or a Vec<u8> in memory. Only a fresh file written front to back can go this way, so
+/// appending and checkpoints don't apply, and databases and shards are refused
+pub fn write_to(writer: impl Write + Send + 'static, options: &Options) -> error::Result<Option<Stats>> {
+    options.validate()?;
+    if options.format == OutputFormat::Sqlite || options.shards > 1 || options.resume || options.mmap || writes_blob_files(options) {
+        return Err(invalid_input("SQLite, shards, raw blobs, --resume and --mmap need files of their own, so they can't go to a writer"));
+    }
+    let mut writer = BufWriter::with_capacity(options.write_buffer_size(), OutputFile::new(Box::new(writer), options));
+    let rng = &mut make_rng(options.seed);
+    let total = options.count as u64 * options.values_per_row() as u64;
+    let values = Throttle::new(value_source(rng, options)?, options.rate);
+    let source = Progress::new(values, total, options.progress);
+    let mut collector = Collector::new(options);
+    let blanks = &mut Blanks::new(options);
+    write_body(&mut writer, source, options.count, options.seed, options, &mut collector, blanks, &mut Checkpoints::off())?;
+    writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+    Ok(collector.stats())
+}
+
+// One generator run dealt out across the shards in order, each written as a file of its own
+// The seed is only recorded in the manifest, since it regenerates the whole dataset, not one part
+fn write_shards(options: &Options) -> error::Result<Option<Stats>> {
+    // Check them all first, so an abort never leaves a partial set behind
+    if options.if_exists == IfExists::Abort {
+        if let Some(taken) = output_files(&options.filename, options.shards).iter().find(|name| fs::metadata(name).is_ok()) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists (choose overwrite to replace the set)", taken),
+            ).into());
+        }
+    }
+    for name in output_files(&options.filename, options.shards) {
+        remove_stale_temp(&name)?;
+    }
+    let rng = &mut make_rng(options.seed);
+    let values_per_row = options.values_per_row() as usize;
+    let total = options.count as u64 * values_per_row as u64;
+    let values = Throttle::new(value_source(rng, options)?, options.rate);
+    let mut source = Progress::new(values, total, options.progress);
+    // One collector for every shard, so the stats describe the whole dataset,
+    // and one set of blanks, so the same values don't go missing in every shard
+    let mut collector = Collector::new(options);
+    let mut blanks = Blanks::new(options);
+    for (part, rows) in (1..=options.shards).zip(shard_rows(options.count, options.shards)) {
+        let name = shard_name(&options.filename, part);
+        let file = Box::new(File::create(temp_name(&name))?);
+        let mut writer = BufWriter::with_capacity(options.write_buffer_size(), OutputFile::new(file, options));
+        // by_ref() lets take() borrow the source, so the next shard carries on where this one stopped
+        let shard = source.by_ref().take(rows as usize * values_per_row);
+        write_body(&mut writer, shard, rows, None, options, &mut collector, &mut blanks, &mut Checkpoints::off())?;
+        writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+        publish(&name, options.if_exists != IfExists::Abort)?;
+        if options.checksum {
+            write_checksum(&name)?;
+        }
+    }
+    write_shard_manifest(options)?;
+    Ok(collector.stats())
+}
+
+// A database is opened and filled through SQLite rather than written byte by byte,
+// so it gets the generator and progress from here and does the rest itself
+fn write_database(options: &Options) -> error::Result<Option<Stats>> {
+    let rng = &mut make_rng(options.seed);
+    let total = options.count as u64 * options.values_per_row() as u64;
+    let source = Progress::new(value_source(rng, options)?, total, options.progress);
+    let mut collector = Collector::new(options);
+    write_table(source, options, &mut collector)?;
+    if options.checksum {
+        write_checksum(&options.filename)?;
+    }
+    Ok(collector.stats())
+}
+
+// Writes the whole file - headers, values and any appended stats - in the chosen format
+// Kept apart from write_data so the benchmark can time writing on its own
+// The state that carries on from one file to the next (stats, missing values, checkpoints)
+// comes in from outside, which is one argument more than clippy likes
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write_body(
+    writer: &mut (impl Write + Send),
+    mut source: impl Iterator<Item = Value>,
+    count: u32,
+    seed: Option<u64>,
+    options: &Options,
+    collector: &mut Collector,
+    blanks: &mut Blanks,
+    checkpoints: &mut Checkpoints,
+) -> error::Result<()> {
+    if let Some(graph) = options.graph {
+        return write_graph(writer, source, count, seed, graph, options, collector);
+    }
+    // Parquet has a column per field, so records and plain values are written the same way
+    if options.format == OutputFormat::Parquet {
+        return write_parquet(writer, source, count, options, collector);
+    }
+    if !options.schema.is_empty() {
+        return write_records(writer, source, count, options, blanks);
+    }
+    match options.format {
+        OutputFormat::Text => {
+            // A resumed file already has its headers from the first run
+            if !checkpoints.resuming() {
+                write_text_header(writer, &format!("Count: {}", count), count, seed, options)?;
+            }
+            // One value per line gets the prefix and suffix on each, and any other delimiter
+            // puts them all on one line, which gets them at its ends instead
+            let layout = &options.line_layout;
+            let one_per_line = layout.is_one_per_line();
+            if !one_per_line {
+                write!(writer, "{}", layout.prefix)?;
+            }
+            // zip() numbers the values, carrying on from wherever a resumed file left off
+            for (i, value) in (checkpoints.start_value()..).zip(source) {  // Nicer than C-style for loops
+                if one_per_line {
+                    write!(writer, "{}", layout.prefix)?;
+                } else if i > 0 {
+                    write!(writer, "{}", layout.delimiter)?;
+                }
+                let value = write_value(writer, value, options)?;
+                collector.push(value);
+                if one_per_line {
+                    writeln!(writer, "{}", layout.suffix)?;
+                }
+                checkpoints.value_written(writer, i + 1)?;
+            }
+            if !one_per_line {
+                writeln!(writer, "{}", layout.suffix)?;
+            }
+            if options.append_stats {
+                write_stats_comment(writer, collector)?;
+            }
+        },
+        OutputFormat::Csv => {
+            if options.csv_header && !checkpoints.resuming() {
+                // Builds "col1,col2,..." - collect() glues the iterator into a Vec for join()
+                let names: Vec<String> = (1..=options.columns).map(|i| format!("col{}", i)).collect();
+                writeln!(writer, "{}", names.join(&csv_delimiter(options).to_string()))?;
+            }
+            write_rows(writer, &mut source, count, options, collector, blanks, checkpoints)?;
+            if options.append_stats {
+                write_stats_comment(writer, collector)?;
+            }
+        },
+        OutputFormat::Matrix => {
+            // Rows x columns up front, so a reader can allocate the whole matrix before reading it
+            if !checkpoints.resuming() {
+                write_text_header(writer, &format!("Matrix: {} x {}", count, options.columns), count, seed, options)?;
+            }
+            write_rows(writer, &mut source, count, options, collector, blanks, checkpoints)?;
+            if options.append_stats {
+                write_stats_comment(writer, collector)?;
+            }
+        },
+        // No header or comments - every line has to be nothing but fields
+        OutputFormat::FixedWidth => {
+            for _ in 0..count {
+                for value in source.by_ref().take(options.columns as usize) {
+                    collector.push(write_fixed(writer, value, options)?);
+                }
+                writeln!(writer)?;
+            }
+        },
+        OutputFormat::Json => {
+            // Numbers are valid JSON as-is, so we can write it by hand without a JSON library
+            writeln!(writer, "{{")?; // {{ is how you print a literal { with write!
+            writeln!(writer, "  \"count\": {},", count)?;
+            writeln!(writer, "  \"type\": \"{}\",", options.data_type.name())?;
+            if let Some(seed) = seed {
+                writeln!(writer, "  \"seed\": {},", seed)?;
+            }
+            write!(writer, "  \"values\": [")?;
+            for (i, value) in source.enumerate() {
+                // JSON doesn't allow trailing commas, so the separator goes before each value
+                if i > 0 {
+                    write!(writer, ",")?;
+                }
+                write!(writer, "\n    ")?;
+                if !blanks.write_missing(writer, options)? {
+                    collector.push(write_value(writer, value, options)?);
+                }
+            }
+            if count > 0 {
+                write!(writer, "\n  ")?;
+            }
+            write!(writer, "]")?;
+            // The stats go in as one more field, so the file is still a single valid object
+            if let Some(stats) = collector.stats().filter(|_| options.append_stats) {
+                writeln!(writer, ",")?;
+                writeln!(writer, "  \"stats\": {{")?;
+                writeln!(writer, "    \"min\": {},", stats.min)?;
+                writeln!(writer, "    \"max\": {},", stats.max)?;
+                writeln!(writer, "    \"mean\": {:.3},", stats.mean)?;
+                writeln!(writer, "    \"median\": {:.3},", stats.median)?;
+                writeln!(writer, "    \"std_dev\": {:.3}", stats.std_dev)?;
+                write!(writer, "  }}")?;
+            }
+            writeln!(writer)?;
+            writeln!(writer, "}}")?;
+        },
+        OutputFormat::Binary => write_binary(writer, source, count, options, collector, checkpoints)?,
+        OutputFormat::Code(language) => write_code(writer, source, count, seed, language, options, collector)?,
+        // Options::validate only lets JSON lines through with a schema, which was handled above,
+        // and Parquet went its own way before the match
+        OutputFormat::JsonLines => unreachable!("JSON lines output needs a schema"),
+        OutputFormat::Parquet => unreachable!("Parquet is written by write_parquet"),
+        OutputFormat::Sqlite => unreachable!("SQLite databases are filled by write_database"),
+    }
+    Ok(())
+}
+
+// Writes `count` records, one per line, pulling one value per field off the source
+// CSV files always get the header line, since naming the fields is what a schema is for
+fn write_records(
+    writer: &mut impl Write,
+    mut source: impl Iterator<Item = Value>,
+    count: u32,
+    options: &Options,
+    blanks: &mut Blanks,
+) -> error::Result<()> {
+    // Each field is written with its own type's settings, e.g. quotes for strings in JSON
+    let fields = field_options(options);
+    let json = options.format == OutputFormat::JsonLines;
+    let fixed = options.format == OutputFormat::FixedWidth;
+    if !json && !fixed {
+        let names: Vec<&str> = options.schema.iter().map(|field| field.name.as_str()).collect();
+        writeln!(writer, "{}", names.join(&csv_delimiter(options).to_string()))?;
+    }
+    for _ in 0..count {
+        if json {
+            write!(writer, "{{")?;
+        }
+        // zip() walks the schema and the values side by side, stopping at the shorter one
+        let row = options.schema.iter().zip(&fields).zip(source.by_ref().take(fields.len()));
+        for (i, ((field, field_options), value)) in row.enumerate() {
+            // Fields sit side by side, so there's no separator or name to write
+            if fixed {
+                write_fixed(writer, value, field_options)?;
+                continue;
+            }
+            if i > 0 {
+                if json {
+                    write!(writer, ", ")?;
+                } else {
+                    write!(writer, "{}", csv_delimiter(options))?;
+                }
+            }
+            if json {
+                write!(writer, "\"{}\": ", field.name)?;
+            }
+            if !blanks.write_missing(writer, options)? {
+                write_value(writer, value, field_options)?;
+            }
+        }
+        if json {
+            write!(writer, "}}")?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+// One edge per line, "u v" or "u v w", under # lines that most graph tools skip as comments
+// Only the weights go into the stats - the node numbers are just labels
+fn write_graph(
+    writer: &mut impl Write,
+    mut source: impl Iterator<Item = Value>,
+    count: u32,
+    seed: Option<u64>,
+    graph: Graph,
+    options: &Options,
+    collector: &mut Collector,
+) -> error::Result<()> {
+    writeln!(writer, "# Nodes: {}", graph.nodes)?;
+    writeln!(writer, "# Edges: {}", count)?;
+    if let Some(seed) = seed {
+        writeln!(writer, "# Seed: {}", seed)?;
+    }
+    for _ in 0..count {
+        let edge = source.by_ref().take(options.values_per_row() as usize);
+        for (i, value) in edge.enumerate() {
+            if i > 0 {
+                write!(writer, " ")?;
+            }
+            match value {
+                // Node numbers are labels, so they stay plain whatever the number style
+                Value::Unsigned(node) if i < 2 => write!(writer, "{}", node)?,
+                _ => collector.push(write_value(writer, value, options)?),
+            }
+        }
+        writeln!(writer)?;
+    }
+    if options.append_stats {
+        write_stats_comment(writer, collector)?;
+    }
+    Ok(())
+}
+
+// Writes one value padded out to the field width, going through a buffer first since the
+// padding depends on how long the value turns out to be
+fn write_fixed(writer: &mut impl Write, value: Value, options: &Options) -> error::Result<f64> {
+    let mut text = Vec::new();
+    let written = write_value(&mut text, value, options)?;
+    // write_value only writes valid UTF-8, so this can't fail
+    let text = String::from_utf8(text).unwrap();
+    write!(writer, "{}", options.field_width.pad(&text)?)?;
+    Ok(written)
+}
+
+// A comma inside the numbers would split them across columns, so CSV switches to semicolons
+// then, the same way spreadsheets do in countries that write 1,5
+pub(crate) fn csv_delimiter(options: &Options) -> char {
+    if options.number_format.uses(',') { ';' } else { ',' }
+}
+
+// The header of a text or matrix file: `first` is its Count: or Matrix: line
+fn write_text_header(writer: &mut impl Write, first: &str, count: u32, seed: Option<u64>, options: &Options) -> error::Result<()> {
+    match &options.header_style {
+        HeaderStyle::Plain => {
+            writeln!(writer, "{}", first)?;
+            write_extra_headers(writer, seed, options)?;
+        },
+        HeaderStyle::Comment => {
+            // Written out plainly first, then copied over a line at a time with the # added
+            let mut lines = Vec::new();
+            writeln!(lines, "{}", first)?;
+            write_extra_headers(&mut lines, seed, options)?;
+            for line in String::from_utf8_lossy(&lines).lines() {
+                writeln!(writer, "# {}", line)?;
+            }
+        },
+        HeaderStyle::Omit => {},
+        HeaderStyle::Template(template) => writeln!(writer, "{}", HeaderStyle::fill(template, count, seed, options))?,
+    }
+    Ok(())
+}
+
+// The optional header lines shared by text and matrix files
+fn write_extra_headers(writer: &mut impl Write, seed: Option<u64>, options: &Options) -> error::Result<()> {
+    // Recording the seed means anyone can regenerate the exact same file later
+    if let Some(seed) = seed {
+        writeln!(writer, "Seed: {}", seed)?;
+    }
+    // Numbers are easy to tell apart by looking at them, but a string like "1234"
+    // isn't (and neither is a timestamp written as seconds, or a 1 that means true), so those files say what they hold
+    if options.data_type.needs_type_line() {
+        writeln!(writer, "Type: {}", options.data_type.name())?;
+    }
+    Ok(())
+}
+
+// Writes `count` rows of options.columns values each - CSV and matrix files are laid out the same way
+fn write_rows(
+    writer: &mut impl Write,
+    source: &mut impl Iterator<Item = Value>,
+    count: u32,
+    options: &Options,
+    collector: &mut Collector,
+    blanks: &mut Blanks,
+    checkpoints: &mut Checkpoints,
+) -> error::Result<()> {
+    let separator = if options.format == OutputFormat::Matrix { options.separator } else { csv_delimiter(options) };
+    for row in checkpoints.start()..count {
+        // take() pulls one row's worth of values off the source
+        for (column, value) in source.by_ref().take(options.columns as usize).enumerate() {
+            if column > 0 {
+                write!(writer, "{}", separator)?;
+            }
+            // A missing value leaves the stats alone, the same as a NULL in SQL's AVG()
+            if !blanks.write_missing(writer, options)? {
+                collector.push(write_value(writer, value, options)?);
+            }
+        }
+        writeln!(writer)?;
+        checkpoints.row_written(writer, row + 1)?;
+    }
+    Ok(())
+}
+
+// Cuts a file back to its last checkpoint, dropping anything written after it (like a
+// half-finished row), and opens it to carry on writing at the end
+fn open_at_checkpoint(filename: &str, checkpoint: &Checkpoint) -> error::Result<File> {
+    let mut file = OpenOptions::new().write(true).open(filename).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => io::Error::new(e.kind(), format!("{} is gone, so there's nothing to resume", filename)),
+        _ => e,
+    })?;
+    if file.metadata()?.len() < checkpoint.bytes {
+        return Err(invalid_data(format!("{} is shorter than its checkpoint says, so it can't be resumed", filename)));
+    }
+    // set_len() is ftruncate() in C
+    file.set_len(checkpoint.bytes)?;
+    file.seek(SeekFrom::End(0))?;
+    Ok(file)
+}
+
+// Where the bytes end up - straight into the file, or squeezed through gzip on the way
+// An enum instead of Box<dyn Write> so we can still call finish() on the gzip side
+// --framed wraps the file itself, outside gzip, so the frames hold the compressed bytes
+pub(crate) enum OutputFile {
+    Plain(Framed<Box<dyn Write + Send>>),
+    Gzip(GzEncoder<Framed<Box<dyn Write + Send>>>),
+}
+
+impl OutputFile {
+    pub(crate) fn new(file: Box<dyn Write + Send>, options: &Options) -> OutputFile {
+        let file = Framed::new(file, options.framed);
+        if options.compress {
+            OutputFile::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            OutputFile::Plain(file)
+        }
+    }
+
+    // gzip puts a checksum at the very end, which only gets written here, and so does the
+    // frames' end marker. Dropping them would write those too, but would throw away any error
+    // The final flush matters for stdout, which keeps its own buffer
+    pub(crate) fn finish(self) -> error::Result<()> {
+        match self {
+            OutputFile::Plain(file) => file.finish()?,
+            OutputFile::Gzip(encoder) => encoder.finish()?.finish()?,
+        };
+        Ok(())
+    }
+}
+
+// Implementing Write means everything else can write to an OutputFile without caring which kind it is
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputFile::Plain(file) => file.write(buf),
+            OutputFile::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputFile::Plain(file) => file.flush(),
+            OutputFile::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+// Loads the file we're about to add to and checks the new values can sit alongside the old ones
+// Text and CSV files don't record their exact type, so there we only check numbers vs strings
+fn existing_values(options: &Options) -> error::Result<Vec<Value>> {
+    let data = load_file(&options.filename)?;
+    if data.format != options.format {
+        return Err(invalid_data(format!(
+            "Can't append {} output to a {} file", options.format.name(), data.format.name()
+        )));
+    }
+    // UUIDs and addresses are recognised for exactly what they are in every format
+    let exact_type = matches!(data.format, OutputFormat::Json | OutputFormat::Binary)
+        || data.data_type.is_identifier()
+        || options.data_type.is_identifier();
+    let same_kind = data.data_type.is_text() == options.data_type.is_text()
+        && data.data_type.is_bytes() == options.data_type.is_bytes()
+        && data.data_type.is_timestamp() == options.data_type.is_timestamp()
+        && data.data_type.is_boolean() == options.data_type.is_boolean()
+        && data.data_type.is_integer() == options.data_type.is_integer();
+    if (exact_type && data.data_type != options.data_type) || (!exact_type && !same_kind) {
+        return Err(invalid_data(format!(
+            "Can't append {} values to a file of {} values", options.data_type.name(), data.data_type.name()
+        )));
+    }
+    if options.unique && !data.values.is_empty() {
+        return Err(invalid_input("Unique values can't be guaranteed when appending to a file that has values"));
+    }
+    // A matrix says how wide it is, so the new rows have to match exactly
+    if data.columns.is_some_and(|columns| columns != options.columns) {
+        return Err(invalid_data(format!(
+            "Existing matrix has {} columns, not {}", data.columns.unwrap_or_default(), options.columns
+        )));
+    }
+    if data.values.len() % options.columns as usize != 0 {
+        return Err(invalid_data(format!(
+            "Existing file doesn't split into rows of {} columns", options.columns
+        )));
+    }
+    if options.data_type.is_text() {
+        return Ok(data.strings.into_iter().map(Value::Text).collect());
+    }
+    if options.data_type.is_uuid() {
+        // load_file already checked every one, so parsing them again can't fail
+        return Ok(data.strings.iter().map(|text| Value::Uuid(parse_uuid(text).unwrap())).collect());
+    }
+    if options.data_type.is_ip() {
+        return Ok(data.strings.iter().map(|text| Value::Ip(text.parse().unwrap())).collect());
+    }
+    if options.data_type.is_bytes() {
+        return Ok(data.strings.iter().map(|text| Value::Bytes(parse_hex(text).unwrap())).collect());
+    }
+    let (lowest, highest) = options.data_type.limits();
+    data.values.iter().map(|&num| {
+        if num < lowest || num > highest {
+            return Err(invalid_data(format!("Existing value {} doesn't fit in {}", num, options.data_type.name())));
+        }
+        // The range check above keeps these casts from wrapping
+        Ok(match options.data_type {
+            DataType::Integer => Value::Int(num as i32),
+            DataType::Short => Value::Short(num as i16),
+            DataType::Long => Value::Long(num as i64),
+            DataType::Unsigned => Value::Unsigned(num as u32),
+            DataType::Float => Value::Float(num as f32),
+            DataType::Double => Value::Double(num),
+            DataType::Timestamp => Value::Timestamp(num as i64),
+            DataType::Boolean => Value::Bool(num != 0.0),
+            DataType::String | DataType::Bytes | DataType::Uuid | DataType::Ipv4 | DataType::Ipv6 => {
+                unreachable!("strings, blobs and identifiers were handled above")
+            },
+            DataType::FirstName | DataType::LastName | DataType::Email | DataType::Phone => {
+                unreachable!("names, emails and phone numbers were handled above")
+            },
+        })
+    }).collect()
+}
+
+// Wraps the value source and prints how far along we are every whole percent
+// Sitting between the generator and the writer means every output format gets it for free
+pub(crate) struct Progress<I> {
+    inner: I,
+    enabled: bool,
+    done: u64,
+    total: u64,
+    last_percent: u64,
+}
+
+impl<I> Progress<I> {
+    pub(crate) fn new(inner: I, total: u64, enabled: bool) -> Self {
+        // Nothing to report for an empty file, and it saves a divide by zero below
+        Progress { inner, enabled: enabled && total > 0, done: 0, total, last_percent: 0 }
+    }
+}
+
+impl<I: Iterator<Item = Value>> Iterator for Progress<I> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        let next = self.inner.next();
+        if self.enabled && next.is_some() {
+            self.done += 1;
+            let percent = self.done * 100 / self.total;
+            // Only redraw when the number changes - printing on every value would be slower than generating
+            if percent != self.last_percent {
+                self.last_percent = percent;
+                // \r jumps back to the start of the line so the percentage updates in place
+                eprint!("\rProgress: {:3}%", percent);
+                if self.done == self.total {
+                    eprintln!();
+                }
+            }
+        }
+        next
+    }
+}
+
+// Writes a single value with no newline, so the caller decides the layout
+// Returns the value exactly as written so stats match what's in the file
+pub(crate) fn write_value(writer: &mut impl Write, value: Value, options: &Options) -> error::Result<f64> {
+    check_finite(&value)?;
+    // Stats still want the number itself, whatever base it's written in
+    if let Some(digits) = radix_text(&value, options.int_format) {
+        write!(writer, "{}", digits)?;
+        return Ok(value.as_f64());
+    }
+    let decimals = options.decimals();
+    // Plain numbers skip format! and its String (see numtext.rs), which makes the same text faster
+    if options.number_format.is_plain() {
+        let written = match value {
+            Value::Short(num) => write_integer(writer, num).map(|_| Some(num as f64)),
+            Value::Int(num) => write_integer(writer, num).map(|_| Some(num as f64)),
+            Value::Long(num) => write_integer(writer, num).map(|_| Some(num as f64)),
+            Value::Unsigned(num) => write_integer(writer, num).map(|_| Some(num as f64)),
+            Value::Float(num) if !options.scientific => write_decimals(writer, num as f64, decimals),
+            Value::Double(num) if !options.scientific => write_decimals(writer, num, decimals),
+            _ => Ok(None),
+        };
+        // None is a float that has to be rounded the slow way below
+        if let Some(written) = written? {
+            return Ok(written);
+        }
+    }
+    // Floats are rounded to a fixed number of decimals by the {:.*} format,
+    // and {:.*e} does the same in scientific notation (like %.3e in C)
+    // Only actual quantities get the number style - epoch seconds and 1/0 booleans are codes
+    let is_number = !matches!(value, Value::Timestamp(_) | Value::Bool(_) | Value::Text(_) | Value::Uuid(_) | Value::Ip(_) | Value::Bytes(_));
+    let text = match value {
+        Value::Int(num) => num.to_string(),
+        Value::Short(num) => num.to_string(),
+        Value::Long(num) => num.to_string(),
+        Value::Unsigned(num) => num.to_string(),
+        Value::Float(num) if options.scientific => format!("{:.*e}", decimals, num),
+        Value::Double(num) if options.scientific => format!("{:.*e}", decimals, num),
+        Value::Float(num) => format!("{:.*}", decimals, num),
+        Value::Double(num) => format!("{:.*}", decimals, num),
+        Value::Timestamp(seconds) if options.time_format == TimeFormat::Epoch => seconds.to_string(),
+        Value::Bool(flag) if options.bool_format == BoolFormat::Digits => (flag as u8).to_string(),
+        Value::Bool(flag) => {
+            write!(writer, "{}", flag)?;
+            // Stats count true as 1, so the mean comes out as the share of trues
+            return Ok(flag as u8 as f64);
+        },
+        Value::Timestamp(seconds) => {
+            // A date has dashes and colons in it, so JSON needs it in quotes like a string
+            if options.format.is_json() {
+                write!(writer, "\"{}\"", format_iso(seconds))?;
+            } else {
+                write!(writer, "{}", format_iso(seconds))?;
+            }
+            // Stats work on the seconds, same as for epoch output
+            return Ok(seconds as f64);
+        },
+        Value::Text(text) => {
+            // Only letters and digits, so JSON needs the quotes but nothing escaped
+            if options.format.is_json() {
+                write!(writer, "\"{}\"", text)?;
+            } else {
+                write!(writer, "{}", text)?;
+            }
+            // Strings feed their length into the stats
+            return Ok(text.len() as f64);
+        },
+        Value::Uuid(bits) => {
+            // Hex and hyphens, so it's quoted like a string in JSON and bare everywhere else
+            if options.format.is_json() {
+                write!(writer, "\"{}\"", format_uuid(bits))?;
+            } else {
+                write!(writer, "{}", format_uuid(bits))?;
+            }
+            return Ok(UUID_LENGTH as f64);
+        },
+        Value::Bytes(blob) => {
+            // Hex is letters and digits, so JSON quotes it like a string
+            if options.format.is_json() {
+                write!(writer, "\"{}\"", format_hex(&blob))?;
+            } else {
+                write!(writer, "{}", format_hex(&blob))?;
+            }
+            // Blobs feed their size in bytes into the stats
+            return Ok(blob.len() as f64);
+        },
+        Value::Ip(address) => {
+            // Dots or colons, so JSON quotes it too
+            let text = address.to_string();
+            if options.format.is_json() {
+                write!(writer, "\"{}\"", text)?;
+            } else {
+                write!(writer, "{}", text)?;
+            }
+            return Ok(text.len() as f64);
+        },
+    };
+    if is_number && !options.number_format.is_plain() {
+        write!(writer, "{}", options.number_format.apply(&text))?;
+    } else {
+        write!(writer, "{}", text)?;
+    }
+    // Going through the printed text avoids f32 -> f64 noise like 0.100000001
+    Ok(text.parse().unwrap())
+}
+
+// An integer in hex or binary, or None for decimal (and anything that isn't an integer type)
+// Negative numbers come out as their two's complement bits at the type's own width, the way
+// %X prints a negative int in C - so -1 as an i16 is 0xFFFF, as an i32 0xFFFFFFFF
+fn radix_text(value: &Value, int_format: IntFormat) -> Option<String> {
+    // Casting to the unsigned type of the same size first keeps the bits and drops the sign
+    let bits = match *value {
+        Value::Short(num) => num as u16 as u64,
+        Value::Int(num) => num as u32 as u64,
+        Value::Unsigned(num) => num as u64,
+        Value::Long(num) => num as u64,
+        _ => return None,
+    };
+    match int_format {
+        IntFormat::Decimal => None,
+        IntFormat::Hex => Some(format!("0x{:X}", bits)),
+        IntFormat::Binary => Some(format!("0b{:b}", bits)),
+    }
+}
+
+// The last line of defence: validation and the generator keep every float finite, but if
+// one ever slipped through, a clean error beats "NaN" or "inf" in a file that other programs
+// will choke on (most parsers and every JSON one reject them)
+pub(crate) fn check_finite(value: &Value) -> error::Result<()> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(invalid_data(format!("Refusing to write {:?}: NaN and infinity are never valid output", value)))
+    }
+}
+
+// Binary layout (everything little-endian):
+//   byte 0      type tag (0 = i32, 1 = f32, 2 = i16, 3 = i64, 4 = u32, 5 = f64, 6 = timestamp as i64,
+//               7 = boolean as one byte, 8 = UUID as 16 bytes, most significant first,
+//               9 = IPv4 and 10 = IPv6 as 4 or 16 bytes in network order)
+//   bytes 1-4   element count as u32
+//   then        count values, each DataType::size() bytes
+// Like fwrite() on an int array in C, but we pick the byte order explicitly
+// so the file reads back the same on any machine
+fn write_binary(
+    writer: &mut impl Write,
+    source: impl Iterator<Item = Value>,
+    count: u32,
+    options: &Options,
+    collector: &mut Collector,
+    checkpoints: &mut Checkpoints,
+) -> error::Result<()> {
+    let tag = options.data_type.binary_tag()
+        .ok_or_else(|| unsupported_format("Binary output only supports number types"))?;
+    let order = options.endianness;
+    if !checkpoints.resuming() {
+        write_header(writer, tag, count, order)?;
+    }
+    for (i, value) in (checkpoints.start_value()..).zip(source) {
+        check_finite(&value)?;
+        // Each arm writes a different sized array, so we write inside the match
+        let value = match value {
+            Value::Int(num) => {
+                writer.write_all(&order.arrange(num.to_le_bytes()))?;
+                num as f64
+            },
+            Value::Short(num) => {
+                writer.write_all(&order.arrange(num.to_le_bytes()))?;
+                num as f64
+            },
+            Value::Long(num) | Value::Timestamp(num) => {
+                writer.write_all(&order.arrange(num.to_le_bytes()))?;
+                num as f64
+            },
+            Value::Unsigned(num) => {
+                writer.write_all(&order.arrange(num.to_le_bytes()))?;
+                num as f64
+            },
+            Value::Float(num) => {
+                writer.write_all(&order.arrange(num.to_le_bytes()))?;
+                // Display prints the shortest text that round-trips, so parsing it back avoids f32 -> f64 noise
+                num.to_string().parse().unwrap()
+            },
+            Value::Double(num) => {
+                writer.write_all(&order.arrange(num.to_le_bytes()))?;
+                num
+            },
+            Value::Bool(flag) => {
+                writer.write_all(&[flag as u8])?;
+                flag as u8 as f64
+            },
+            // Always big-endian, whatever the numbers use, so the bytes are in the same order as the text
+            Value::Uuid(bits) => {
+                writer.write_all(&bits.to_be_bytes())?;
+                UUID_LENGTH as f64
+            },
+            // Addresses go in network byte order (most significant first), as they do on the wire
+            Value::Ip(address) => {
+                match address {
+                    IpAddr::V4(address) => writer.write_all(&address.octets())?,
+                    IpAddr::V6(address) => writer.write_all(&address.octets())?,
+                }
+                address.to_string().len() as f64
+            },
+            // binary_tag() already refused strings above
+            Value::Text(_) => unreachable!("strings can't be written as binary"),
+            Value::Bytes(_) => unreachable!("blobs are written as files of their own"),
+        };
+        collector.push(value);
+        checkpoints.value_written(writer, i + 1)?;
+    }
+    Ok(())
+}
+
+// Text and CSV files get the summary as # comment lines at the end, which the reader skips
+fn write_stats_comment(writer: &mut impl Write, collector: &Collector) -> error::Result<()> {
+    if let Some(stats) = collector.stats() {
+        writeln!(writer, "# min: {}", stats.min)?;
+        writeln!(writer, "# max: {}", stats.max)?;
+        writeln!(writer, "# mean: {:.3}", stats.mean)?;
+        writeln!(writer, "# median: {:.3}", stats.median)?;
+        writeln!(writer, "# std_dev: {:.3}", stats.std_dev)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn number_styles() {
+        let european = NumberFormat { decimal: ',', thousands: Some('.') };
+        assert_eq!(european.apply("-1234567.891"), "-1.234.567,891");
+        assert_eq!(european.apply("123"), "123");
+        assert_eq!(european.apply("1234"), "1.234");
+        assert_eq!(european.apply("0.5"), "0,5");
+        // Scientific notation keeps its decimal comma but isn't grouped
+        assert_eq!(european.apply("1234.5e3"), "1234,5e3");
+        let swiss = NumberFormat { decimal: '.', thousands: Some('\'') };
+        assert_eq!(swiss.apply("-100000"), "-100'000");
+        assert!(NumberFormat { decimal: ',', thousands: Some(',') }.validate().is_err());
+        assert!(NumberFormat { decimal: ';', thousands: None }.validate().is_err());
+    }
+
+    #[test]
+    fn fixed_width_padding() {
+        let right = FieldWidth { width: 6, fill: ' ', align: Align::Right };
+        assert_eq!(right.pad("42").unwrap(), "    42");
+        let left = FieldWidth { align: Align::Left, fill: '.', ..right };
+        assert_eq!(left.pad("-4.5").unwrap(), "-4.5..");
+        // Zeros go after the sign so the number still reads the same
+        let zeros = FieldWidth { fill: '0', ..right };
+        assert_eq!(zeros.pad("-42").unwrap(), "-00042");
+        assert_eq!(zeros.pad("0x3FF").unwrap(), "0x03FF");
+        assert_eq!(zeros.pad("123456").unwrap(), "123456");
+        assert!(zeros.pad("1234567").is_err());
+        assert!(FieldWidth { width: 0, ..right }.validate().is_err());
+        assert!(FieldWidth { fill: '\n', ..right }.validate().is_err());
+    }
+
+    #[test]
+    fn hex_and_binary_integers() {
+        assert_eq!(radix_text(&Value::Int(1023), IntFormat::Hex).unwrap(), "0x3FF");
+        assert_eq!(radix_text(&Value::Unsigned(10), IntFormat::Binary).unwrap(), "0b1010");
+        // Negative numbers are two's complement at the type's own width, like %X in C
+        assert_eq!(radix_text(&Value::Short(-1), IntFormat::Hex).unwrap(), "0xFFFF");
+        assert_eq!(radix_text(&Value::Int(-2), IntFormat::Hex).unwrap(), "0xFFFFFFFE");
+        assert_eq!(radix_text(&Value::Long(-1), IntFormat::Binary).unwrap(), format!("0b{}", "1".repeat(64)));
+        assert_eq!(radix_text(&Value::Int(5), IntFormat::Decimal), None);
+        assert_eq!(radix_text(&Value::Timestamp(5), IntFormat::Hex), None);
+    }
+
+    #[test]
+    fn missing_values_take_their_share() {
+        let mut options = crate::generator::tests::options(DataType::Integer, 1.0, 9.0, crate::Distribution::Uniform);
+        (options.count, options.columns, options.format, options.csv_header) = (1000, 2, OutputFormat::Csv, false);
+        (options.missing, options.missing_token, options.seed) = (10.0, MissingToken::Null, Some(7));
+        options.validate().unwrap();
+        let mut out = Vec::new();
+        let rng = &mut make_rng(options.seed);
+        let source = value_source(rng, &options).unwrap();
+        let (mut collector, mut blanks) = (Collector::new(&options), Blanks::new(&options));
+        write_body(&mut out, source, options.count, None, &options, &mut collector, &mut blanks, &mut Checkpoints::off()).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let nulls = text.lines().flat_map(|line| line.split(',')).filter(|field| *field == "NULL").count();
+        // About 200 of the 2000 values - the chance of landing outside this is tiny
+        assert!((120..280).contains(&nulls), "{} missing", nulls);
+        assert_eq!(text.lines().count(), 1000);
+        assert_eq!(MissingToken::Empty.text(true), "null");
+        options.format = OutputFormat::Text;
+        assert!(options.validate().is_err());
+    }
+
+    #[test]
+    fn headers_can_be_left_out_commented_or_templated() {
+        let mut options = crate::generator::tests::options(DataType::Integer, 1.0, 9.0, crate::Distribution::Uniform);
+        options.seed = Some(5);
+        let header = |options: &Options| {
+            let mut out = Vec::new();
+            write_text_header(&mut out, "Count: 3", 3, options.seed, options).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        options.header_style = HeaderStyle::Comment;
+        assert_eq!(header(&options), "# Count: 3\n# Seed: 5\n");
+        options.header_style = HeaderStyle::Omit;
+        assert_eq!(header(&options), "");
+        options.header_style = HeaderStyle::Template(String::from("n={count} {type} seed={seed}"));
+        assert_eq!(header(&options), "n=3 integer seed=5\n");
+        options.validate().unwrap();
+        options.header_style = HeaderStyle::Template(String::from("{cuont}"));
+        assert!(options.validate().is_err());
+        // A commented header still reads back, and the # lines after the values are left alone
+        let uncommented = crate::reader::uncomment_header(b"# Count: 1\n# Seed: 5\n7\n# min: 7\n".to_vec());
+        assert_eq!(uncommented, b"Count: 1\nSeed: 5\n7\n# min: 7\n");
+    }
+
+    #[test]
+    fn values_can_be_laid_out_as_a_list() {
+        let mut options = crate::generator::tests::options(DataType::Integer, 1.0, 9.0, crate::Distribution::Uniform);
+        (options.count, options.header_style) = (3, HeaderStyle::Omit);
+        let text = |options: &Options| {
+            let mut out = Vec::new();
+            let source = [1, 2, 3].into_iter().map(Value::Int);
+            write_body(&mut out, source, 3, None, options, &mut Collector::new(options), &mut Blanks::new(options), &mut Checkpoints::off()).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        // Any other delimiter puts the prefix and suffix around the whole list
+        options.line_layout = LineLayout { delimiter: String::from(", "), prefix: String::from("int data[] = {"), suffix: String::from("};") };
+        assert_eq!(text(&options), "int data[] = {1, 2, 3};\n");
+        options.line_layout = LineLayout { delimiter: String::from("\n"), prefix: String::from("  "), suffix: String::from(",") };
+        assert_eq!(text(&options), "  1,\n  2,\n  3,\n");
+        options.validate().unwrap();
+        options.format = OutputFormat::Csv;
+        assert!(options.validate().is_err());
+    }
+    #[test]
+    fn stopped_files_get_the_count_they_really_have() {
+        let mut options = crate::generator::tests::options(DataType::Integer, 1.0, 9.0, crate::Distribution::Uniform);
+        let path = std::env::temp_dir().join(format!("ruststf_test_{}_stopped", std::process::id()));
+        (options.count, options.seed, options.filename) = (10, Some(5), path.to_string_lossy().into_owned());
+        // What Ctrl-C leaves after 4 rows: the full file's header, then just those rows
+        write_data(&options).unwrap();
+        let text = fs::read_to_string(&options.filename).unwrap();
+        let rows: Vec<&str> = text.lines().skip(2).take(4).collect();
+        fs::write(&options.filename, format!("Count: 10\nSeed: 5\n{}\n", rows.join("\n"))).unwrap();
+        fix_count(&options.filename, 4, 10, Some(5), &options).unwrap();
+        assert_eq!(fs::read_to_string(&options.filename).unwrap(), format!("Count: 4\n{}\n", rows.join("\n")));
+
+        // Binary headers are all the same size, so the count is written over in place
+        options.format = OutputFormat::Binary;
+        write_data(&options).unwrap();
+        let bytes = fs::read(&options.filename).unwrap();
+        fs::write(&options.filename, &bytes[..bytes.len() - 6 * 4]).unwrap();
+        fix_count(&options.filename, 4, 10, Some(5), &options).unwrap();
+        assert_eq!(crate::binary::read_header(&fs::read(&options.filename).unwrap()).unwrap().count, 4);
+        fs::remove_file(&options.filename).unwrap();
+    }
+}